@@ -1,25 +1,73 @@
 # Evaluator-specific error messages
 
 error-evaluating-expression = Памылка пры вылічэнні выразу
+error-evaluating-line = Памылка пры вылічэнні выразу: {$details}
 cannot-compute-average-empty = Немагчыма вылічыць сярэдняе значэнне пустой гісторыі
 no-previous-result = Няма папярэдняга выніку ў гісторыі
+mixed-units = Значэнні ў {$unit} нельга скласці са значэннем у {$other}
+line-not-above = Радок {$line} не знаходзіцца вышэй за гэты
+line-no-result = У радка {$line} няма выніку
+no-line-context = '{$name}' спасылаецца на радкі дакумента
+no-line-above = Над гэтым радком няма радкоў
+section-empty = У гэтым раздзеле няма значэнняў для вылічэння сярэдняга
+circular-reference = Цыклічная спасылка паміж радкамі {$lines}
+forward-reference = '{$name}' вызначана ніжэй, у радку {$line}
+undefined-reference = '{$name}' не вызначана ні ў адным радку
 failed-parse-percentage = Не атрымалася разабраць працэнтны вынік: {$error}
 invalid-percentage-operator = Недапушчальны аператар працэнта: {$op}
-failed-evaluate-expression = Не атрымалася вылічыць выраз: {$error}
 input-validation-error = Памылка праверкі ўводу: {$error}
 
 # Clipboard operations
 clipboard-copy-failed = Не атрымалася скапіяваць у буфер абмену: {$error}
 clipboard-not-available = Буфер абмену недаступны: {$error}
 
-# Алгебра адзінак
-unit-algebra-parse-left = Не атрымалася разабраць левы аперанд
-unit-algebra-parse-right = Не атрымалася разабраць правы аперанд
-unit-algebra-unsupported-op = Непадтрымліваемая аперацыя
-unit-algebra-not-expression = Не з'яўляецца выразам алгебры адзінак
+# Памылкі разбору і вылічэння
+unexpected-character = Нечаканы сімвал '{$char}'
+unexpected-token = Нечаканае '{$token}'
+unexpected-end = Нечаканы канец выразу
+ambiguous-decimal-comma = '{$number}' можа быць адным лікам або двума; раздзяляйце значэнні знакам ';'
+ambiguous-currency-list = '{$text}' можа быць адной сумай або спісам з валютай толькі ў апошнім значэнні; пішыце дробы праз '{$decimal}' або ўкажыце валюту для кожнага значэння
+missing-conversion-target = Пасля '{$keyword}' чакаецца адзінка вымярэння
+unknown-identifier = Невядомае імя '{$name}'
+unknown-unit = Невядомая адзінка '{$unit}'
+unknown-function = Невядомая функцыя '{$name}'
+function-arity = Няправільная колькасць аргументаў для {$name}
+calculus-variable = {$name} чакае імя зменнай другім аргументам
+calculus-single-value = {$name} чакае выраз з адным значэннем, а не спіс
+calculus-diverges = {$name} не даў канчатковага значэння
+function-too-deep = Функцыя {$name} выклікае сябе занадта глыбока
+not-enough-values = {$name} патрабуе не менш за {$count} значэнняў
+list-length-mismatch = Спісы маюць розную даўжыню ({$left} і {$right})
+range-too-large = Дыяпазон мае больш за {$max} значэнняў
+percentile-out-of-range = Працэнтыль павінен быць ад 0 да 100
+irr-no-solution = Няма стаўкі, пры якой гэтыя грашовыя патокі акупляюцца
+no-payment-periods = Тэрмін крэдыту павінен быць больш за нуль перыядаў
+interest-rate = Чакалася працэнтная стаўка, напрыклад 5%, а не {$value}
+interest-term = Чакаўся тэрмін у гадах або месяцах, а не {$value}
+incompatible-units = Нельга пераўтварыць {$from} у {$to}
+temperature-sum = Нельга складваць два значэнні тэмпературы; запішыце змяненне тэмпературы як «{$unit} difference»
+temperature-product = Нельга множыць або дзяліць значэнне тэмпературы ў {$unit} на адзінку; выкарыстоўвайце кельвіны або «{$unit} difference»
+integer-required = '{$op}' патрабуе цэлых лікаў
+integer-overflow = Вынік '{$op}' перапаўняе 128-бітны цэлы лік
+literal-overflow = '{$literal}' не змяшчаецца ў 128-бітны цэлы лік
+negative-shift = Нельга зрушыць на адмоўную колькасць з '{$op}'
+integer-unit = '{$op}' патрабуе лікаў без адзінак, а не {$unit}
+division-by-zero = Дзяленне на нуль
+too-large-to-spell = Занадта вялікі лік для запісу словамі
+invalid-roman-numeral = Несапраўдны рымскі лік '{$numeral}'
+roman-out-of-range = Рымскія лікі патрабуюць цэлага ліку ад 1 да {$max}
+solve-no-solution = Ніводнае значэнне {$var} не з'яўляецца рашэннем гэтага ўраўнення
+solve-any-value = Любое значэнне {$var} з'яўляецца рашэннем гэтага ўраўнення
+solve-units = Ніводная адзінка {$var} не дае абодвум бакам ураўнення аднолькавыя адзінкі
 
-# Адлюстраванне даты і часу
-datetime-render-pair = Лакальны {$local}\nUTC   {$utc}
+# Даты і час
+missing-time-zone = Чакаўся гадзінны пояс пасля '{$keyword}'
+unknown-time-zone = Невядомы гадзінны пояс '{$zone}'
+invalid-date = Недапушчальная дата
+date-expected = Чакалася дата, а не {$value}
+date-needs-duration = Дату можна зрушыць толькі на працягласць, а не на {$value}
+date-arithmetic = Нельга выкарыстоўваць '{$op}' з датай
+date-variable = Нельга захаваць дату ў {$name}
 
 # Папярэджанні па змянных
 variable-tracking-update-failed = Папярэджанне: не атрымалася абнавіць адсочванне змянных па радку: {$error}
@@ -20,3 +20,8 @@ currency-parse-json = Не атрымалася разабраць адказ JS
 # CLI
 cli-table-expression = Выраз
 cli-table-result = Вынік
+cli-schedule-year = Год
+cli-schedule-payment = Плацёж
+cli-schedule-principal = Асноўны доўг
+cli-schedule-interest = Працэнты
+cli-schedule-balance = Рэшта
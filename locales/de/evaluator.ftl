@@ -1,25 +1,73 @@
 # Evaluator-specific error messages
 
 error-evaluating-expression = Fehler beim Auswerten des Ausdrucks
+error-evaluating-line = Fehler beim Auswerten des Ausdrucks: {$details}
 cannot-compute-average-empty = Durchschnitt kann nicht aus leerem Verlauf berechnet werden
 no-previous-result = Kein vorheriges Ergebnis im Verlauf
+mixed-units = Werte in {$unit} lassen sich nicht mit einem Wert in {$other} addieren
+line-not-above = Zeile {$line} steht nicht über dieser
+line-no-result = Zeile {$line} hat kein Ergebnis
+no-line-context = '{$name}' bezieht sich auf Zeilen eines Dokuments
+no-line-above = Über dieser Zeile steht keine Zeile
+section-empty = Dieser Abschnitt enthält keine Werte für einen Durchschnitt
+circular-reference = Zirkelbezug zwischen den Zeilen {$lines}
+forward-reference = '{$name}' wird weiter unten definiert, in Zeile {$line}
+undefined-reference = '{$name}' ist in keiner Zeile definiert
 failed-parse-percentage = Prozentuales Ergebnis konnte nicht geparst werden: {$error}
 invalid-percentage-operator = Ungültiger Prozentoperator: {$op}
-failed-evaluate-expression = Ausdruck konnte nicht ausgewertet werden: {$error}
 input-validation-error = Eingabevalidierungsfehler: {$error}
 
 # Clipboard operations
 clipboard-copy-failed = Kopieren in Zwischenablage fehlgeschlagen: {$error}
 clipboard-not-available = Zwischenablage nicht verfügbar: {$error}
 
-# Einheiten-Algebra
-unit-algebra-parse-left = Linken Operanden konnte nicht geparst werden
-unit-algebra-parse-right = Rechten Operanden konnte nicht geparst werden
-unit-algebra-unsupported-op = Nicht unterstützte Operation
-unit-algebra-not-expression = Kein Einheiten-Algebra-Ausdruck
+# Parser- und Auswertungsfehler
+unexpected-character = Unerwartetes Zeichen '{$char}'
+unexpected-token = Unerwartetes '{$token}'
+unexpected-end = Unerwartetes Ende des Ausdrucks
+ambiguous-decimal-comma = '{$number}' kann eine Zahl oder zwei sein; trennen Sie Werte mit ';'
+ambiguous-currency-list = '{$text}' kann ein Betrag sein oder eine Liste, in der nur der letzte Wert eine Währung hat; schreiben Sie Dezimalstellen mit '{$decimal}' oder geben Sie jedem Wert eine Währung
+missing-conversion-target = Einheit nach '{$keyword}' erwartet
+unknown-identifier = Unbekannter Name '{$name}'
+unknown-unit = Unbekannte Einheit '{$unit}'
+unknown-function = Unbekannte Funktion '{$name}'
+function-arity = Falsche Anzahl von Argumenten für {$name}
+calculus-variable = {$name} erwartet einen Variablennamen als zweites Argument
+calculus-single-value = {$name} erwartet einen Ausdruck mit einem einzelnen Wert, keine Liste
+calculus-diverges = {$name} hat keinen endlichen Wert ergeben
+function-too-deep = Funktion {$name} ruft sich zu tief selbst auf
+not-enough-values = {$name} benötigt mindestens {$count} Werte
+list-length-mismatch = Listen haben unterschiedliche Längen ({$left} und {$right})
+range-too-large = Bereich hat mehr als {$max} Werte
+percentile-out-of-range = Perzentil muss zwischen 0 und 100 liegen
+irr-no-solution = Kein Zinssatz gleicht diese Zahlungsströme aus
+no-payment-periods = Ein Kredit braucht eine Laufzeit von mehr als null Perioden
+interest-rate = Zinssatz wie 5% erwartet, nicht {$value}
+interest-term = Laufzeit in Jahren oder Monaten erwartet, nicht {$value}
+incompatible-units = {$from} kann nicht in {$to} umgerechnet werden
+temperature-sum = Zwei Temperaturwerte können nicht addiert werden; schreiben Sie eine Temperaturänderung als „{$unit} difference“
+temperature-product = Ein Temperaturwert in {$unit} kann nicht mit einer Einheit multipliziert oder dividiert werden; verwenden Sie Kelvin oder „{$unit} difference“
+integer-required = '{$op}' erfordert ganze Zahlen
+integer-overflow = Ergebnis von '{$op}' überläuft eine 128-Bit-Ganzzahl
+literal-overflow = '{$literal}' passt nicht in eine 128-Bit-Ganzzahl
+negative-shift = Mit '{$op}' kann nicht um einen negativen Betrag verschoben werden
+integer-unit = '{$op}' braucht Zahlen ohne Einheit, nicht {$unit}
+division-by-zero = Division durch null
+too-large-to-spell = Zu groß, um in Worten geschrieben zu werden
+invalid-roman-numeral = Ungültige römische Zahl '{$numeral}'
+roman-out-of-range = Römische Zahlen brauchen eine ganze Zahl von 1 bis {$max}
+solve-no-solution = Kein Wert von {$var} löst diese Gleichung
+solve-any-value = Jeder Wert von {$var} löst diese Gleichung
+solve-units = Keine Einheit von {$var} gibt beiden Seiten der Gleichung dieselben Einheiten
 
-# Datums-/Zeitdarstellung
-datetime-render-pair = Lokal {$local}\nUTC   {$utc}
+# Datum und Uhrzeit
+missing-time-zone = Zeitzone nach '{$keyword}' erwartet
+unknown-time-zone = Unbekannte Zeitzone '{$zone}'
+invalid-date = Kein gültiges Datum
+date-expected = Datum erwartet, nicht {$value}
+date-needs-duration = Ein Datum lässt sich nur um eine Dauer verschieben, nicht um {$value}
+date-arithmetic = '{$op}' ist mit einem Datum nicht möglich
+date-variable = Ein Datum kann nicht in {$name} gespeichert werden
 
 # Variablen-Warnungen
 variable-tracking-update-failed = Warnung: Zeilenbezogenes Variablen-Tracking konnte nicht aktualisiert werden: {$error}
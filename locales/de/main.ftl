@@ -20,3 +20,8 @@ currency-parse-json = JSON-Antwort konnte nicht geparst werden
 # CLI
 cli-table-expression = Ausdruck
 cli-table-result = Ergebnis
+cli-schedule-year = Jahr
+cli-schedule-payment = Zahlung
+cli-schedule-principal = Tilgung
+cli-schedule-interest = Zinsen
+cli-schedule-balance = Restschuld
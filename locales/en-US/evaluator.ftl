@@ -1,25 +1,73 @@
 # Evaluator-specific error messages
 
 error-evaluating-expression = Error evaluating expression
+error-evaluating-line = Error evaluating expression: {$details}
 cannot-compute-average-empty = Cannot compute average of empty history
 no-previous-result = No previous result in history
+mixed-units = Values in {$unit} can't be added to a value in {$other}
+line-not-above = Line {$line} is not above this one
+line-no-result = Line {$line} has no result
+no-line-context = '{$name}' refers to lines of a document
+no-line-above = There is no line above this one
+section-empty = No values in this section to average
+circular-reference = Circular reference between lines {$lines}
+forward-reference = '{$name}' is defined further down, on line {$line}
+undefined-reference = '{$name}' isn't defined on any line
 failed-parse-percentage = Failed to parse percentage result: {$error}
 invalid-percentage-operator = Invalid percentage operator: {$op}
-failed-evaluate-expression = Failed to evaluate expression: {$error}
 input-validation-error = Input validation error: {$error}
 
 # Clipboard operations
 clipboard-copy-failed = Failed to copy to clipboard: {$error}
 clipboard-not-available = Clipboard not available: {$error}
 
-# Unit algebra errors
-unit-algebra-parse-left = Failed to parse left operand
-unit-algebra-parse-right = Failed to parse right operand
-unit-algebra-unsupported-op = Unsupported operation
-unit-algebra-not-expression = Not a unit algebra expression
+# Parser and evaluation errors
+unexpected-character = Unexpected character '{$char}'
+unexpected-token = Unexpected '{$token}'
+unexpected-end = Unexpected end of expression
+ambiguous-decimal-comma = '{$number}' could be one number or two; separate values with ';'
+ambiguous-currency-list = '{$text}' could be one amount or a list with the currency on the last value only; write decimals with '{$decimal}' or give every value a currency
+missing-conversion-target = Expected a unit after '{$keyword}'
+unknown-identifier = Unknown name '{$name}'
+unknown-unit = Unknown unit '{$unit}'
+unknown-function = Unknown function '{$name}'
+function-arity = Wrong number of arguments for {$name}
+calculus-variable = {$name} needs a variable name as its second argument
+calculus-single-value = {$name} needs an expression with a single value, not a list
+calculus-diverges = {$name} did not give a finite value
+function-too-deep = Function {$name} calls itself too deeply
+not-enough-values = {$name} needs at least {$count} values
+list-length-mismatch = Lists have different lengths ({$left} and {$right})
+range-too-large = Range has more than {$max} values
+percentile-out-of-range = Percentile must be between 0 and 100
+irr-no-solution = No interest rate makes these cash flows break even
+no-payment-periods = A loan needs a term of more than zero periods
+interest-rate = Expected an interest rate such as 5%, not {$value}
+interest-term = Expected a term in years or months, not {$value}
+incompatible-units = Cannot convert {$from} to {$to}
+temperature-sum = Cannot add two temperature readings; write a change in temperature as "{$unit} difference"
+temperature-product = Cannot multiply or divide a {$unit} reading by a unit; use kelvin or "{$unit} difference"
+integer-required = '{$op}' needs whole numbers
+integer-overflow = Result of '{$op}' overflows a 128-bit integer
+literal-overflow = '{$literal}' doesn't fit in a 128-bit integer
+negative-shift = Cannot shift by a negative amount with '{$op}'
+integer-unit = '{$op}' needs plain numbers, not {$unit}
+division-by-zero = Division by zero
+too-large-to-spell = Too large to spell out in words
+invalid-roman-numeral = Invalid Roman numeral '{$numeral}'
+roman-out-of-range = Roman numerals need a whole number from 1 to {$max}
+solve-no-solution = No value of {$var} solves this equation
+solve-any-value = Every value of {$var} solves this equation
+solve-units = No unit of {$var} gives both sides of the equation the same units
 
-# Datetime rendering
-datetime-render-pair = Local {$local}\nUTC   {$utc}
+# Dates and times
+missing-time-zone = Expected a time zone after '{$keyword}'
+unknown-time-zone = Unknown time zone '{$zone}'
+invalid-date = Not a valid date
+date-expected = Expected a date, not {$value}
+date-needs-duration = A date can only be moved by a duration, not {$value}
+date-arithmetic = Cannot use '{$op}' with a date
+date-variable = Cannot store a date in {$name}
 
 # Variable tracking warnings
 variable-tracking-update-failed = Warning: Failed to update line variable tracking: {$error}
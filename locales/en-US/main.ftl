@@ -20,3 +20,8 @@ currency-parse-json = Failed to parse JSON response
 # CLI tables/headings
 cli-table-expression = Expression
 cli-table-result = Result
+cli-schedule-year = Year
+cli-schedule-payment = Payment
+cli-schedule-principal = Principal
+cli-schedule-interest = Interest
+cli-schedule-balance = Balance
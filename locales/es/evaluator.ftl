@@ -1,25 +1,73 @@
 # Mensajes de error del evaluador
 
 error-evaluating-expression = Error al evaluar la expresión
+error-evaluating-line = Error al evaluar la expresión: {$details}
 cannot-compute-average-empty = No se puede calcular el promedio de un historial vacío
 no-previous-result = No hay resultado anterior en el historial
+mixed-units = Los valores en {$unit} no se pueden sumar a un valor en {$other}
+line-not-above = La línea {$line} no está encima de esta
+line-no-result = La línea {$line} no tiene resultado
+no-line-context = '{$name}' se refiere a líneas de un documento
+no-line-above = No hay ninguna línea encima de esta
+section-empty = No hay valores en esta sección para promediar
+circular-reference = Referencia circular entre las líneas {$lines}
+forward-reference = '{$name}' se define más abajo, en la línea {$line}
+undefined-reference = '{$name}' no está definido en ninguna línea
 failed-parse-percentage = Error al analizar el resultado de porcentaje: {$error}
 invalid-percentage-operator = Operador de porcentaje inválido: {$op}
-failed-evaluate-expression = Error al evaluar la expresión: {$error}
 input-validation-error = Error de validación de entrada: {$error}
 
 # Operaciones del portapapeles
 clipboard-copy-failed = Error al copiar al portapapeles: {$error}
 clipboard-not-available = Portapapeles no disponible: {$error}
 
-# Álgebra de unidades
-unit-algebra-parse-left = No se pudo analizar el operando izquierdo
-unit-algebra-parse-right = No se pudo analizar el operando derecho
-unit-algebra-unsupported-op = Operación no soportada
-unit-algebra-not-expression = No es una expresión de álgebra de unidades
+# Errores de análisis y evaluación
+unexpected-character = Carácter inesperado '{$char}'
+unexpected-token = '{$token}' inesperado
+unexpected-end = Fin de expresión inesperado
+ambiguous-decimal-comma = '{$number}' puede ser un número o dos; separa los valores con ';'
+ambiguous-currency-list = '{$text}' puede ser un importe o una lista con la moneda solo en el último valor; escribe los decimales con '{$decimal}' o pon la moneda en cada valor
+missing-conversion-target = Se esperaba una unidad después de '{$keyword}'
+unknown-identifier = Nombre desconocido '{$name}'
+unknown-unit = Unidad desconocida '{$unit}'
+unknown-function = Función desconocida '{$name}'
+function-arity = Número incorrecto de argumentos para {$name}
+calculus-variable = {$name} necesita un nombre de variable como segundo argumento
+calculus-single-value = {$name} necesita una expresión con un solo valor, no una lista
+calculus-diverges = {$name} no dio un valor finito
+function-too-deep = La función {$name} se llama a sí misma demasiadas veces
+not-enough-values = {$name} necesita al menos {$count} valores
+list-length-mismatch = Las listas tienen longitudes distintas ({$left} y {$right})
+range-too-large = El rango tiene más de {$max} valores
+percentile-out-of-range = El percentil debe estar entre 0 y 100
+irr-no-solution = Ningún tipo de interés equilibra estos flujos de caja
+no-payment-periods = Un préstamo necesita un plazo de más de cero períodos
+interest-rate = Se esperaba una tasa de interés como 5%, no {$value}
+interest-term = Se esperaba un plazo en años o meses, no {$value}
+incompatible-units = No se puede convertir {$from} a {$to}
+temperature-sum = No se pueden sumar dos lecturas de temperatura; escribe un cambio de temperatura como "{$unit} difference"
+temperature-product = No se puede multiplicar ni dividir una lectura en {$unit} por una unidad; usa kelvin o "{$unit} difference"
+integer-required = '{$op}' requiere números enteros
+integer-overflow = El resultado de '{$op}' desborda un entero de 128 bits
+literal-overflow = '{$literal}' no cabe en un entero de 128 bits
+negative-shift = No se puede desplazar una cantidad negativa con '{$op}'
+integer-unit = '{$op}' necesita números sin unidades, no {$unit}
+division-by-zero = División por cero
+too-large-to-spell = Demasiado grande para escribirlo en palabras
+invalid-roman-numeral = Número romano no válido '{$numeral}'
+roman-out-of-range = Los números romanos requieren un entero entre 1 y {$max}
+solve-no-solution = Ningún valor de {$var} resuelve esta ecuación
+solve-any-value = Cualquier valor de {$var} resuelve esta ecuación
+solve-units = Ninguna unidad de {$var} da las mismas unidades a los dos lados de la ecuación
 
-# Formato de fecha/hora
-datetime-render-pair = Local {$local}\nUTC   {$utc}
+# Fechas y horas
+missing-time-zone = Se esperaba una zona horaria después de '{$keyword}'
+unknown-time-zone = Zona horaria desconocida '{$zone}'
+invalid-date = No es una fecha válida
+date-expected = Se esperaba una fecha, no {$value}
+date-needs-duration = Una fecha solo se puede mover una duración, no {$value}
+date-arithmetic = No se puede usar '{$op}' con una fecha
+date-variable = No se puede guardar una fecha en {$name}
 
 # Advertencias de variables
 variable-tracking-update-failed = Advertencia: No se pudo actualizar el seguimiento de variables por línea: {$error}
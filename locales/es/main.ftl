@@ -20,3 +20,8 @@ currency-parse-json = Error al analizar la respuesta JSON
 # CLI
 cli-table-expression = Expresión
 cli-table-result = Resultado
+cli-schedule-year = Año
+cli-schedule-payment = Pago
+cli-schedule-principal = Capital
+cli-schedule-interest = Intereses
+cli-schedule-balance = Saldo
@@ -1,25 +1,73 @@
 # Evaluator-specific error messages
 
 error-evaluating-expression = Erreur lors de l'évaluation de l'expression
+error-evaluating-line = Erreur lors de l'évaluation de l'expression : {$details}
 cannot-compute-average-empty = Impossible de calculer la moyenne d'un historique vide
 no-previous-result = Aucun résultat précédent dans l'historique
+mixed-units = Les valeurs en {$unit} ne peuvent pas être additionnées à une valeur en {$other}
+line-not-above = La ligne {$line} n'est pas au-dessus de celle-ci
+line-no-result = La ligne {$line} n'a pas de résultat
+no-line-context = '{$name}' fait référence aux lignes d'un document
+no-line-above = Il n'y a aucune ligne au-dessus de celle-ci
+section-empty = Aucune valeur dans cette section pour calculer la moyenne
+circular-reference = Référence circulaire entre les lignes {$lines}
+forward-reference = '{$name}' est défini plus bas, à la ligne {$line}
+undefined-reference = '{$name}' n'est défini sur aucune ligne
 failed-parse-percentage = Échec de l'analyse du résultat en pourcentage : {$error}
 invalid-percentage-operator = Opérateur de pourcentage invalide : {$op}
-failed-evaluate-expression = Échec de l'évaluation de l'expression : {$error}
 input-validation-error = Erreur de validation de l'entrée : {$error}
 
 # Clipboard operations
 clipboard-copy-failed = Échec de la copie dans le presse-papiers : {$error}
 clipboard-not-available = Presse-papiers non disponible : {$error}
 
-# Algèbre des unités
-unit-algebra-parse-left = Impossible d'analyser l'opérande gauche
-unit-algebra-parse-right = Impossible d'analyser l'opérande droite
-unit-algebra-unsupported-op = Opération non prise en charge
-unit-algebra-not-expression = Pas une expression d'algèbre d'unités
+# Erreurs d'analyse et d'évaluation
+unexpected-character = Caractère inattendu '{$char}'
+unexpected-token = '{$token}' inattendu
+unexpected-end = Fin d'expression inattendue
+ambiguous-decimal-comma = '{$number}' peut être un nombre ou deux ; séparez les valeurs par ';'
+ambiguous-currency-list = '{$text}' peut être un montant ou une liste dont seule la dernière valeur a une devise ; écrivez les décimales avec '{$decimal}' ou donnez une devise à chaque valeur
+missing-conversion-target = Unité attendue après '{$keyword}'
+unknown-identifier = Nom inconnu '{$name}'
+unknown-unit = Unité inconnue '{$unit}'
+unknown-function = Fonction inconnue '{$name}'
+function-arity = Nombre d'arguments incorrect pour {$name}
+calculus-variable = {$name} attend un nom de variable en deuxième argument
+calculus-single-value = {$name} attend une expression à valeur unique, pas une liste
+calculus-diverges = {$name} n'a pas donné de valeur finie
+function-too-deep = La fonction {$name} s'appelle elle-même trop profondément
+not-enough-values = {$name} nécessite au moins {$count} valeurs
+list-length-mismatch = Les listes ont des longueurs différentes ({$left} et {$right})
+range-too-large = La plage contient plus de {$max} valeurs
+percentile-out-of-range = Le centile doit être compris entre 0 et 100
+irr-no-solution = Aucun taux ne rend ces flux de trésorerie à l'équilibre
+no-payment-periods = Un prêt nécessite une durée de plus de zéro période
+interest-rate = Taux d'intérêt attendu, comme 5 %, pas {$value}
+interest-term = Durée attendue en années ou en mois, pas {$value}
+incompatible-units = Impossible de convertir {$from} en {$to}
+temperature-sum = Impossible d'additionner deux relevés de température ; écrivez une variation comme « {$unit} difference »
+temperature-product = Impossible de multiplier ou diviser un relevé en {$unit} par une unité ; utilisez le kelvin ou « {$unit} difference »
+integer-required = '{$op}' nécessite des nombres entiers
+integer-overflow = Le résultat de '{$op}' dépasse un entier de 128 bits
+literal-overflow = '{$literal}' ne tient pas dans un entier de 128 bits
+negative-shift = Impossible de décaler d'une quantité négative avec '{$op}'
+integer-unit = '{$op}' nécessite des nombres sans unité, pas {$unit}
+division-by-zero = Division par zéro
+too-large-to-spell = Trop grand pour être écrit en toutes lettres
+invalid-roman-numeral = Chiffre romain invalide '{$numeral}'
+roman-out-of-range = Les chiffres romains exigent un entier de 1 à {$max}
+solve-no-solution = Aucune valeur de {$var} ne résout cette équation
+solve-any-value = Toute valeur de {$var} résout cette équation
+solve-units = Aucune unité de {$var} ne donne les mêmes unités aux deux membres de l'équation
 
-# Affichage date/heure
-datetime-render-pair = Local {$local}\nUTC   {$utc}
+# Dates et heures
+missing-time-zone = Fuseau horaire attendu après '{$keyword}'
+unknown-time-zone = Fuseau horaire inconnu '{$zone}'
+invalid-date = Date invalide
+date-expected = Une date est attendue, pas {$value}
+date-needs-duration = Une date ne peut être décalée que d'une durée, pas de {$value}
+date-arithmetic = Impossible d'utiliser '{$op}' avec une date
+date-variable = Impossible de stocker une date dans {$name}
 
 # Avertissements de variables
 variable-tracking-update-failed = Avertissement : échec de la mise à jour du suivi des variables par ligne : {$error}
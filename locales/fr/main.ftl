@@ -20,3 +20,8 @@ currency-parse-json = Échec de l'analyse de la réponse JSON
 # CLI
 cli-table-expression = Expression
 cli-table-result = Résultat
+cli-schedule-year = Année
+cli-schedule-payment = Paiement
+cli-schedule-principal = Capital
+cli-schedule-interest = Intérêts
+cli-schedule-balance = Solde
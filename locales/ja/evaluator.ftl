@@ -1,25 +1,73 @@
 # Evaluator-specific error messages
 
 error-evaluating-expression = 式の評価中にエラーが発生しました
+error-evaluating-line = 式の評価中にエラーが発生しました: {$details}
 cannot-compute-average-empty = 空の履歴の平均を計算できません
 no-previous-result = 履歴に前の結果がありません
+mixed-units = {$unit} の値と {$other} の値は合算できません
+line-not-above = {$line} 行目はこの行より上にありません
+line-no-result = {$line} 行目に結果がありません
+no-line-context = '{$name}' はドキュメントの行を参照します
+no-line-above = この行より上に行がありません
+section-empty = このセクションには平均する値がありません
+circular-reference = 行 {$lines} の間で循環参照があります
+forward-reference = '{$name}' は下の {$line} 行目で定義されています
+undefined-reference = '{$name}' はどの行でも定義されていません
 failed-parse-percentage = パーセンテージ結果の解析に失敗しました: {$error}
 invalid-percentage-operator = 無効なパーセンテージ演算子: {$op}
-failed-evaluate-expression = 式の評価に失敗しました: {$error}
 input-validation-error = 入力検証エラー: {$error}
 
 # Clipboard operations
 clipboard-copy-failed = クリップボードへのコピーに失敗しました: {$error}
 clipboard-not-available = クリップボードが利用できません: {$error}
 
-# 単位の代数
-unit-algebra-parse-left = 左オペランドを解析できませんでした
-unit-algebra-parse-right = 右オペランドを解析できませんでした
-unit-algebra-unsupported-op = サポートされていない演算
-unit-algebra-not-expression = 単位代数の式ではありません
+# 構文解析と評価のエラー
+unexpected-character = 予期しない文字 '{$char}'
+unexpected-token = 予期しない '{$token}'
+unexpected-end = 式が途中で終わっています
+ambiguous-decimal-comma = '{$number}' は1つの数か2つの数か曖昧です。値は ';' で区切ってください
+ambiguous-currency-list = '{$text}' は1つの金額か、最後の値だけに通貨が付いたリストか曖昧です。小数は '{$decimal}' で書くか、すべての値に通貨を付けてください
+missing-conversion-target = '{$keyword}' の後に単位が必要です
+unknown-identifier = 不明な名前 '{$name}'
+unknown-unit = 不明な単位 '{$unit}'
+unknown-function = 不明な関数 '{$name}'
+function-arity = {$name} の引数の数が正しくありません
+calculus-variable = {$name} の2番目の引数には変数名が必要です
+calculus-single-value = {$name} にはリストではなく単一の値を持つ式が必要です
+calculus-diverges = {$name} は有限の値になりませんでした
+function-too-deep = 関数 {$name} の再帰呼び出しが深すぎます
+not-enough-values = {$name} には少なくとも {$count} 個の値が必要です
+list-length-mismatch = リストの長さが異なります（{$left} と {$right}）
+range-too-large = 範囲の値が {$max} 個を超えています
+percentile-out-of-range = パーセンタイルは 0 から 100 の間で指定してください
+irr-no-solution = このキャッシュフローが均衡する利率はありません
+no-payment-periods = ローンの期間は0期間より長くする必要があります
+interest-rate = 5% のような利率が必要です（{$value} ではなく）
+interest-term = 年または月単位の期間が必要です（{$value} ではなく）
+incompatible-units = {$from} を {$to} に変換できません
+temperature-sum = 2つの温度の値は足せません。温度の変化は「{$unit} difference」と書いてください
+temperature-product = {$unit} の温度の値を単位と掛け算・割り算できません。ケルビンか「{$unit} difference」を使ってください
+integer-required = '{$op}' には整数が必要です
+integer-overflow = '{$op}' の結果が128ビット整数をオーバーフローしました
+literal-overflow = '{$literal}' は128ビット整数に収まりません
+negative-shift = '{$op}' で負の量だけシフトすることはできません
+integer-unit = '{$op}' には単位のない数値が必要です（{$unit} ではなく）
+division-by-zero = ゼロによる除算
+too-large-to-spell = 大きすぎて言葉で表せません
+invalid-roman-numeral = 無効なローマ数字 '{$numeral}'
+roman-out-of-range = ローマ数字には 1 から {$max} までの整数が必要です
+solve-no-solution = この方程式を満たす {$var} の値はありません
+solve-any-value = {$var} のどの値でもこの方程式を満たします
+solve-units = {$var} のどの単位でも方程式の両辺の単位がそろいません
 
-# 日時表示
-datetime-render-pair = ローカル {$local}\nUTC   {$utc}
+# 日付と時刻
+missing-time-zone = '{$keyword}' の後にタイムゾーンが必要です
+unknown-time-zone = 不明なタイムゾーン '{$zone}'
+invalid-date = 有効な日付ではありません
+date-expected = 日付が必要です ({$value} ではなく)
+date-needs-duration = 日付は期間でのみ移動できます ({$value} は不可)
+date-arithmetic = 日付に '{$op}' は使えません
+date-variable = {$name} に日付は保存できません
 
 # 変数警告
 variable-tracking-update-failed = 警告: 行ごとの変数追跡を更新できませんでした: {$error}
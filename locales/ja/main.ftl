@@ -20,3 +20,8 @@ currency-parse-json = JSON レスポンスの解析に失敗しました
 # CLI
 cli-table-expression = 式
 cli-table-result = 結果
+cli-schedule-year = 年
+cli-schedule-payment = 支払額
+cli-schedule-principal = 元金
+cli-schedule-interest = 利息
+cli-schedule-balance = 残高
@@ -1,25 +1,73 @@
 # Evaluator-specific error messages
 
 error-evaluating-expression = Ошибка при вычислении выражения
+error-evaluating-line = Ошибка при вычислении выражения: {$details}
 cannot-compute-average-empty = Невозможно вычислить среднее значение пустой истории
 no-previous-result = Нет предыдущего результата в истории
+mixed-units = Значения в {$unit} нельзя сложить со значением в {$other}
+line-not-above = Строка {$line} не находится выше этой
+line-no-result = У строки {$line} нет результата
+no-line-context = '{$name}' ссылается на строки документа
+no-line-above = Над этой строкой нет строк
+section-empty = В этом разделе нет значений для вычисления среднего
+circular-reference = Циклическая ссылка между строками {$lines}
+forward-reference = '{$name}' определено ниже, в строке {$line}
+undefined-reference = '{$name}' не определено ни в одной строке
 failed-parse-percentage = Не удалось разобрать процентный результат: {$error}
 invalid-percentage-operator = Недопустимый оператор процента: {$op}
-failed-evaluate-expression = Не удалось вычислить выражение: {$error}
 input-validation-error = Ошибка проверки ввода: {$error}
 
 # Clipboard operations
 clipboard-copy-failed = Не удалось скопировать в буфер обмена: {$error}
 clipboard-not-available = Буфер обмена недоступен: {$error}
 
-# Алгебра единиц
-unit-algebra-parse-left = Не удалось разобрать левый операнд
-unit-algebra-parse-right = Не удалось разобрать правый операнд
-unit-algebra-unsupported-op = Неподдерживаемая операция
-unit-algebra-not-expression = Не выражение алгебры единиц
+# Ошибки разбора и вычисления
+unexpected-character = Неожиданный символ '{$char}'
+unexpected-token = Неожиданное '{$token}'
+unexpected-end = Неожиданный конец выражения
+ambiguous-decimal-comma = '{$number}' может быть одним числом или двумя; разделяйте значения знаком ';'
+ambiguous-currency-list = '{$text}' может быть одной суммой или списком с валютой только у последнего значения; пишите дроби через '{$decimal}' или укажите валюту у каждого значения
+missing-conversion-target = После '{$keyword}' ожидается единица измерения
+unknown-identifier = Неизвестное имя '{$name}'
+unknown-unit = Неизвестная единица '{$unit}'
+unknown-function = Неизвестная функция '{$name}'
+function-arity = Неверное число аргументов для {$name}
+calculus-variable = {$name} ожидает имя переменной вторым аргументом
+calculus-single-value = {$name} ожидает выражение с одним значением, а не список
+calculus-diverges = {$name} не дал конечного значения
+function-too-deep = Функция {$name} вызывает себя слишком глубоко
+not-enough-values = {$name} требует не менее {$count} значений
+list-length-mismatch = Списки имеют разную длину ({$left} и {$right})
+range-too-large = Диапазон содержит более {$max} значений
+percentile-out-of-range = Процентиль должен быть от 0 до 100
+irr-no-solution = Нет ставки, при которой эти денежные потоки окупаются
+no-payment-periods = Срок кредита должен быть больше нуля периодов
+interest-rate = Ожидалась процентная ставка, например 5%, а не {$value}
+interest-term = Ожидался срок в годах или месяцах, а не {$value}
+incompatible-units = Нельзя преобразовать {$from} в {$to}
+temperature-sum = Нельзя складывать два значения температуры; запишите изменение температуры как «{$unit} difference»
+temperature-product = Нельзя умножать или делить значение температуры в {$unit} на единицу; используйте кельвины или «{$unit} difference»
+integer-required = '{$op}' требует целых чисел
+integer-overflow = Результат '{$op}' переполняет 128-битное целое
+literal-overflow = '{$literal}' не помещается в 128-битное целое
+negative-shift = Нельзя сдвинуть на отрицательное число с '{$op}'
+integer-unit = '{$op}' требует чисел без единиц, а не {$unit}
+division-by-zero = Деление на ноль
+too-large-to-spell = Слишком большое число для записи словами
+invalid-roman-numeral = Недопустимое римское число '{$numeral}'
+roman-out-of-range = Римские числа требуют целого числа от 1 до {$max}
+solve-no-solution = Ни одно значение {$var} не является решением этого уравнения
+solve-any-value = Любое значение {$var} является решением этого уравнения
+solve-units = Ни одна единица {$var} не даёт обеим частям уравнения одинаковые единицы
 
-# Отображение даты/времени
-datetime-render-pair = Локально {$local}\nUTC   {$utc}
+# Даты и время
+missing-time-zone = Ожидался часовой пояс после '{$keyword}'
+unknown-time-zone = Неизвестный часовой пояс '{$zone}'
+invalid-date = Недопустимая дата
+date-expected = Ожидалась дата, а не {$value}
+date-needs-duration = Дату можно сдвинуть только на длительность, а не на {$value}
+date-arithmetic = Нельзя использовать '{$op}' с датой
+date-variable = Нельзя сохранить дату в {$name}
 
 # Предупреждения переменных
 variable-tracking-update-failed = Предупреждение: не удалось обновить отслеживание переменных по строке: {$error}
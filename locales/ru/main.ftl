@@ -20,3 +20,8 @@ currency-parse-json = Не удалось разобрать JSON-ответ
 # CLI
 cli-table-expression = Выражение
 cli-table-result = Результат
+cli-schedule-year = Год
+cli-schedule-payment = Платёж
+cli-schedule-principal = Основной долг
+cli-schedule-interest = Проценты
+cli-schedule-balance = Остаток
@@ -1,25 +1,73 @@
 # 求值器特定错误消息
 
 error-evaluating-expression = 求值表达式时出错
+error-evaluating-line = 求值表达式时出错：{$details}
 cannot-compute-average-empty = 无法计算空历史记录的平均值
 no-previous-result = 历史记录中没有先前的结果
+mixed-units = 以 {$unit} 为单位的值无法与 {$other} 的值相加
+line-not-above = 第 {$line} 行不在此行之上
+line-no-result = 第 {$line} 行没有结果
+no-line-context = '{$name}' 指的是文档中的行
+no-line-above = 此行之上没有行
+section-empty = 此部分没有可求平均值的数值
+circular-reference = 第 {$lines} 行之间存在循环引用
+forward-reference = '{$name}' 在下方第 {$line} 行定义
+undefined-reference = '{$name}' 未在任何行中定义
 failed-parse-percentage = 解析百分比结果失败: {$error}
 invalid-percentage-operator = 无效的百分比运算符: {$op}
-failed-evaluate-expression = 求值表达式失败: {$error}
 input-validation-error = 输入验证错误: {$error}
 
 # 剪贴板操作
 clipboard-copy-failed = 复制到剪贴板失败: {$error}
 clipboard-not-available = 剪贴板不可用: {$error}
 
-# 单位代数
-unit-algebra-parse-left = 无法解析左操作数
-unit-algebra-parse-right = 无法解析右操作数
-unit-algebra-unsupported-op = 不支持的运算
-unit-algebra-not-expression = 不是单位代数表达式
+# 解析与求值错误
+unexpected-character = 意外的字符 '{$char}'
+unexpected-token = 意外的 '{$token}'
+unexpected-end = 表达式意外结束
+ambiguous-decimal-comma = '{$number}' 可能是一个数也可能是两个数；请用 ';' 分隔数值
+ambiguous-currency-list = '{$text}' 可能是一个金额，也可能是只有最后一个值带货币的列表；请用 '{$decimal}' 书写小数，或为每个值加上货币
+missing-conversion-target = '{$keyword}' 之后需要单位
+unknown-identifier = 未知名称 '{$name}'
+unknown-unit = 未知单位 '{$unit}'
+unknown-function = 未知函数 '{$name}'
+function-arity = {$name} 的参数数量错误
+calculus-variable = {$name} 的第二个参数必须是变量名
+calculus-single-value = {$name} 需要单个值的表达式，而不是列表
+calculus-diverges = {$name} 没有得到有限的值
+function-too-deep = 函数 {$name} 递归调用过深
+not-enough-values = {$name} 至少需要 {$count} 个值
+list-length-mismatch = 列表长度不同（{$left} 和 {$right}）
+range-too-large = 范围包含超过 {$max} 个值
+percentile-out-of-range = 百分位数必须在 0 到 100 之间
+irr-no-solution = 没有利率能使这些现金流达到平衡
+no-payment-periods = 贷款期限必须大于零期
+interest-rate = 应为利率（如 5%），而不是 {$value}
+interest-term = 应为以年或月计的期限，而不是 {$value}
+incompatible-units = 无法将 {$from} 转换为 {$to}
+temperature-sum = 不能将两个温度读数相加；温度变化请写作“{$unit} difference”
+temperature-product = 不能将 {$unit} 温度读数与单位相乘或相除；请使用开尔文或“{$unit} difference”
+integer-required = '{$op}' 需要整数
+integer-overflow = '{$op}' 的结果超出了 128 位整数范围
+literal-overflow = '{$literal}' 超出了 128 位整数范围
+negative-shift = '{$op}' 不能移动负数位
+integer-unit = '{$op}' 需要不带单位的数字，而不是 {$unit}
+division-by-zero = 除以零
+too-large-to-spell = 数字太大，无法用文字表示
+invalid-roman-numeral = 无效的罗马数字 '{$numeral}'
+roman-out-of-range = 罗马数字需要 1 到 {$max} 之间的整数
+solve-no-solution = 没有 {$var} 的值能满足此方程
+solve-any-value = {$var} 取任何值都满足此方程
+solve-units = {$var} 取任何单位都无法使方程两边单位一致
 
-# 日期时间显示
-datetime-render-pair = 本地 {$local}\nUTC   {$utc}
+# 日期和时间
+missing-time-zone = '{$keyword}' 后应为时区
+unknown-time-zone = 未知时区 '{$zone}'
+invalid-date = 不是有效日期
+date-expected = 应为日期，而不是 {$value}
+date-needs-duration = 日期只能按时长移动，不能按 {$value}
+date-arithmetic = 不能对日期使用 '{$op}'
+date-variable = 不能将日期存入 {$name}
 
 # 变量警告
 variable-tracking-update-failed = 警告：无法更新行变量跟踪：{$error}
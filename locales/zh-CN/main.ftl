@@ -20,3 +20,8 @@ currency-parse-json = 解析 JSON 响应失败
 # CLI
 cli-table-expression = 表达式
 cli-table-result = 结果
+cli-schedule-year = 年
+cli-schedule-payment = 还款额
+cli-schedule-principal = 本金
+cli-schedule-interest = 利息
+cli-schedule-balance = 余额
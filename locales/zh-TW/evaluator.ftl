@@ -1,25 +1,73 @@
 # Evaluator-specific error messages
 
 error-evaluating-expression = 評估表達式時發生錯誤
+error-evaluating-line = 評估表達式時發生錯誤：{$details}
 cannot-compute-average-empty = 無法計算空歷史記錄的平均值
 no-previous-result = 歷史記錄中沒有先前的結果
+mixed-units = 以 {$unit} 為單位的值無法與 {$other} 的值相加
+line-not-above = 第 {$line} 行不在此行之上
+line-no-result = 第 {$line} 行沒有結果
+no-line-context = '{$name}' 指的是文件中的行
+no-line-above = 此行之上沒有行
+section-empty = 此區段沒有可求平均值的數值
+circular-reference = 第 {$lines} 行之間存在循環參照
+forward-reference = '{$name}' 在下方第 {$line} 行定義
+undefined-reference = '{$name}' 未在任何行中定義
 failed-parse-percentage = 無法解析百分比結果：{$error}
 invalid-percentage-operator = 無效的百分比運算符：{$op}
-failed-evaluate-expression = 無法評估表達式：{$error}
 input-validation-error = 輸入驗證錯誤：{$error}
 
 # Clipboard operations
 clipboard-copy-failed = 複製到剪貼簿失敗：{$error}
 clipboard-not-available = 剪貼簿不可用：{$error}
 
-# 單位代數
-unit-algebra-parse-left = 無法解析左運算元
-unit-algebra-parse-right = 無法解析右運算元
-unit-algebra-unsupported-op = 不支援的運算
-unit-algebra-not-expression = 不是單位代數表達式
+# 解析與求值錯誤
+unexpected-character = 意外的字元 '{$char}'
+unexpected-token = 意外的 '{$token}'
+unexpected-end = 表達式意外結束
+ambiguous-decimal-comma = '{$number}' 可能是一個數也可能是兩個數；請用 ';' 分隔數值
+ambiguous-currency-list = '{$text}' 可能是一個金額，也可能是只有最後一個值帶貨幣的清單；請用 '{$decimal}' 書寫小數，或為每個值加上貨幣
+missing-conversion-target = '{$keyword}' 之後需要單位
+unknown-identifier = 未知名稱 '{$name}'
+unknown-unit = 未知單位 '{$unit}'
+unknown-function = 未知函式 '{$name}'
+function-arity = {$name} 的參數數量錯誤
+calculus-variable = {$name} 的第二個參數必須是變數名稱
+calculus-single-value = {$name} 需要單一值的運算式，而不是清單
+calculus-diverges = {$name} 沒有得到有限的值
+function-too-deep = 函式 {$name} 遞迴呼叫過深
+not-enough-values = {$name} 至少需要 {$count} 個值
+list-length-mismatch = 列表長度不同（{$left} 和 {$right}）
+range-too-large = 範圍包含超過 {$max} 個值
+percentile-out-of-range = 百分位數必須介於 0 到 100 之間
+irr-no-solution = 沒有利率能使這些現金流達到平衡
+no-payment-periods = 貸款期限必須大於零期
+interest-rate = 應為利率（如 5%），而不是 {$value}
+interest-term = 應為以年或月計的期限，而不是 {$value}
+incompatible-units = 無法將 {$from} 轉換為 {$to}
+temperature-sum = 不能將兩個溫度讀數相加；溫度變化請寫作「{$unit} difference」
+temperature-product = 不能將 {$unit} 溫度讀數與單位相乘或相除；請使用克耳文或「{$unit} difference」
+integer-required = '{$op}' 需要整數
+integer-overflow = '{$op}' 的結果超出了 128 位元整數範圍
+literal-overflow = '{$literal}' 超出了 128 位元整數範圍
+negative-shift = '{$op}' 不能移動負數位
+integer-unit = '{$op}' 需要不帶單位的數字，而不是 {$unit}
+division-by-zero = 除以零
+too-large-to-spell = 數字太大，無法用文字表示
+invalid-roman-numeral = 無效的羅馬數字 '{$numeral}'
+roman-out-of-range = 羅馬數字需要 1 到 {$max} 之間的整數
+solve-no-solution = 沒有 {$var} 的值能滿足此方程式
+solve-any-value = {$var} 取任何值都滿足此方程式
+solve-units = {$var} 取任何單位都無法使方程式兩邊單位一致
 
-# 日期時間顯示
-datetime-render-pair = 本地 {$local}\nUTC   {$utc}
+# 日期和時間
+missing-time-zone = '{$keyword}' 後應為時區
+unknown-time-zone = 未知時區 '{$zone}'
+invalid-date = 不是有效日期
+date-expected = 應為日期，而不是 {$value}
+date-needs-duration = 日期只能按時長移動，不能按 {$value}
+date-arithmetic = 不能對日期使用 '{$op}'
+date-variable = 不能將日期存入 {$name}
 
 # 變數警告
 variable-tracking-update-failed = 警告：無法更新行變數追蹤：{$error}
@@ -20,3 +20,8 @@ currency-parse-json = 解析 JSON 回應失敗
 # CLI
 cli-table-expression = 運算式
 cli-table-result = 結果
+cli-schedule-year = 年
+cli-schedule-payment = 還款額
+cli-schedule-principal = 本金
+cli-schedule-interest = 利息
+cli-schedule-balance = 餘額
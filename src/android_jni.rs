@@ -102,25 +102,22 @@ pub extern "system" fn Java_com_numby_NumbyWrapper_evaluate<'local>(
         Err(_) => return create_error_result(&mut env, "Failed to initialize registry"),
     };
 
-    match registry.evaluate(&input_str, context) {
-        Some((result_str, _)) => {
-            // Parse result_str, e.g., "3.11 miles" -> value=3.11, formatted="3.11 miles", unit="miles"
-            let parts: Vec<&str> = result_str.split_whitespace().collect();
-            let value = parts
-                .first()
-                .and_then(|s| crate::conversions::parse_number_with_scale(s))
-                .unwrap_or(0.0);
+    match registry.evaluate_with_value(&input_str, context) {
+        Ok((result_str, value, unit)) => {
+            // e.g. "3.11 miles" -> value=3.11, formatted="3.11 miles", unit="miles".
+            // The formatted text may use separators, exponents, `±` or words,
+            // so the value and unit come from the evaluator, never the text.
+            let value = value.map_or(0.0, |v| v.to_f64());
 
             let formatted = env
                 .new_string(&result_str)
                 .unwrap_or_else(|_| JObject::null().into());
 
-            let unit = if parts.len() > 1 {
-                let unit_str = parts[1..].join(" ");
-                env.new_string(&unit_str)
-                    .unwrap_or_else(|_| JObject::null().into())
-            } else {
-                JObject::null().into()
+            let unit = match unit {
+                Some(unit_str) => env
+                    .new_string(&unit_str)
+                    .unwrap_or_else(|_| JObject::null().into()),
+                None => JObject::null().into(),
             };
 
             env.new_object(
@@ -135,7 +132,7 @@ pub extern "system" fn Java_com_numby_NumbyWrapper_evaluate<'local>(
             )
             .unwrap_or_else(|_| JObject::null())
         }
-        None => create_error_result(&mut env, "Evaluation failed"),
+        Err(e) => create_error_result(&mut env, e.message()),
     }
 }
 
@@ -203,7 +200,7 @@ pub extern "system" fn Java_com_numby_NumbyWrapper_loadConfig(
                 return -1;
             }
 
-            match serde_json::from_str::<Config>(&contents) {
+            match crate::config::parse_config(&contents) {
                 Ok(config) => {
                     let context = unsafe { &mut *(ctx as *mut AppState) };
                     context.length_units = config.length_units;
@@ -216,6 +213,8 @@ pub extern "system" fn Java_com_numby_NumbyWrapper_loadConfig(
                     context.data_units = config.data_units;
                     context.speed_units = config.speed_units;
                     context.rates = config.currencies;
+                    context.display = config.display;
+                    context.constants = config.constants;
                     // Store the config path for later use (currency rate saving)
                     context.config_override_path = Some(path_str.clone());
                     // Set global config path override for load_config() calls
@@ -323,7 +322,7 @@ pub extern "system" fn Java_com_numby_NumbyWrapper_clearHistory(
     }
 }
 
-/// Clear variables
+/// Clear variables, lists and user-defined functions
 #[no_mangle]
 pub extern "system" fn Java_com_numby_NumbyWrapper_clearVariables(
     _env: JNIEnv,
@@ -335,12 +334,20 @@ pub extern "system" fn Java_com_numby_NumbyWrapper_clearVariables(
     }
 
     let context = unsafe { &mut *(ctx as *mut AppState) };
-    match context.variables.write() {
-        Ok(mut variables) => {
+    match (
+        context.variables.write(),
+        context.uncertainties.write(),
+        context.lists.write(),
+        context.functions.write(),
+    ) {
+        (Ok(mut variables), Ok(mut uncertainties), Ok(mut lists), Ok(mut functions)) => {
             variables.clear();
+            uncertainties.clear();
+            lists.clear();
+            functions.clear();
             0
         }
-        Err(_) => -1,
+        _ => -1,
     }
 }
 
@@ -1,5 +1,7 @@
 use nu_ansi_term::Color;
 
+use crate::evaluator::agents::amortization_schedule;
+use crate::evaluator::lexer;
 use crate::models::AppState;
 
 /// Evaluate an expression and print the result.
@@ -13,25 +15,34 @@ pub fn evaluate_expression(
     // Allow users to pass "\n" in a single-arg invocation; normalize to real newlines
     let normalized = expression.replace("\\n", "\n");
 
-    // Collect evaluated lines
-    let mut rows: Vec<(String, Option<String>)> = Vec::new();
-    for line in normalized.lines() {
+    // Collect evaluated lines; failed lines keep the reason they failed
+    let mut rows: Vec<(String, Result<String, String>)> = Vec::new();
+    for (index, line) in normalized.lines().enumerate() {
         let trimmed = line.trim();
+        // Each line is a line of the document, for `line 3` and sections
+        if let Ok(mut current_line) = state.current_line.write() {
+            *current_line = Some(index);
+        }
+        if lexer::section_header(trimmed).is_some() {
+            let _ = registry.evaluate(trimmed, state);
+            continue;
+        }
         if trimmed.is_empty() || trimmed.starts_with("//") || trimmed.starts_with("#") {
             continue;
         }
         let result = match registry.evaluate(trimmed, state) {
-            Some((r, _)) => Some(r),
-            None => {
-                eprintln!(
-                    "{}",
-                    Color::Red.paint(crate::fl!("error-evaluating-expression"))
-                );
-                None
+            Ok((r, _)) => Ok(r),
+            Err(e) => {
+                let message = crate::fl!("error-evaluating-line", "details" => e.message());
+                eprintln!("{}", Color::Red.paint(&message));
+                Err(message)
             }
         };
         rows.push((trimmed.to_string(), result));
     }
+    if let Ok(mut current_line) = state.current_line.write() {
+        *current_line = None;
+    }
 
     if rows.is_empty() {
         eprintln!(
@@ -43,54 +54,51 @@ pub fn evaluate_expression(
 
     match format.to_lowercase().as_str() {
         "markdown" | "md" => print_markdown(&rows),
-        "table" | "box" => print_table(&rows),
+        "table" | "box" => {
+            print_table(&rows);
+            print_schedules(&rows, state, registry.config());
+        }
         "pretty" | "share" => print_pretty(&rows),
         _ => print_plain(&rows),
     }
 }
 
-fn print_plain(rows: &[(String, Option<String>)]) {
+fn print_plain(rows: &[(String, Result<String, String>)]) {
     for (_expr, res) in rows {
-        if let Some(r) = res {
+        // Errors were already reported on stderr while evaluating
+        if let Ok(r) = res {
             println!("{}", Color::Green.paint(r));
-        } else {
-            eprintln!(
-                "{}",
-                Color::Red.paint(crate::fl!("error-evaluating-expression"))
-            );
         }
     }
 }
 
 /// Plain but shareable: include expression alongside result.
-fn print_pretty(rows: &[(String, Option<String>)]) {
+fn print_pretty(rows: &[(String, Result<String, String>)]) {
     let expr_width = rows.iter().map(|(e, _)| e.len()).max().unwrap_or(0).max(6);
     let arrow = Color::Magenta.paint("⇒");
 
     for (expr, res) in rows {
         match res {
-            Some(r) => println!(
+            Ok(r) => println!(
                 "{} {} {}",
                 Color::Cyan.paint(format!("{:expr_width$}", expr, expr_width = expr_width)),
                 arrow,
                 Color::Green.paint(r)
             ),
-            None => println!(
+            Err(e) => println!(
                 "{} {} {}",
                 Color::Cyan.paint(format!("{:expr_width$}", expr, expr_width = expr_width)),
                 arrow,
-                Color::Red.paint(crate::fl!("error-evaluating-expression"))
+                Color::Red.paint(e)
             ),
         }
     }
 }
 
-fn print_markdown(rows: &[(String, Option<String>)]) {
+fn print_markdown(rows: &[(String, Result<String, String>)]) {
     println!("{}\n", crate::fl!("markdown-results-heading"));
     for (expr, res) in rows {
-        let result = res
-            .clone()
-            .unwrap_or_else(|| crate::fl!("error-evaluating-expression"));
+        let result = res.clone().unwrap_or_else(|e| e);
         println!(
             "{}",
             crate::fl!("markdown-results-row", "expr" => expr, "result" => &result)
@@ -98,57 +106,95 @@ fn print_markdown(rows: &[(String, Option<String>)]) {
     }
 }
 
-fn print_table(rows: &[(String, Option<String>)]) {
-    let expr_width = rows.iter().map(|(e, _)| e.len()).max().unwrap_or(0).max(10);
-    let res_width = rows
-        .iter()
-        .map(|(_, r)| r.as_ref().map(|s| s.len()).unwrap_or(5))
-        .max()
-        .unwrap_or(5)
-        .max(6);
-
-    let top = format!(
-        "╭─{:─<expr$}─┬─{:─<res$}─╮",
-        "",
-        "",
-        expr = expr_width,
-        res = res_width
-    );
-    let mid = format!(
-        "├─{:─<expr$}─┼─{:─<res$}─┤",
-        "",
-        "",
-        expr = expr_width,
-        res = res_width
-    );
-    let bot = format!(
-        "╰─{:─<expr$}─┴─{:─<res$}─╯",
-        "",
-        "",
-        expr = expr_width,
-        res = res_width
-    );
-
-    println!("{}", top);
-    println!(
-        "│ {:expr_width$} │ {:res_width$} │",
+fn print_table(rows: &[(String, Result<String, String>)]) {
+    let headers = [
         crate::fl!("cli-table-expression"),
         crate::fl!("cli-table-result"),
-        expr_width = expr_width,
-        res_width = res_width
-    );
-    println!("{}", mid);
+    ];
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(expr, res)| vec![expr.clone(), res.clone().unwrap_or_else(|e| e)])
+        .collect();
+    print_box(&headers, &cells);
+}
+
+/// Print the yearly amortization schedule of each loan line, e.g.
+/// "mortgage 300k at 6.5% over 30 years".
+fn print_schedules(
+    rows: &[(String, Result<String, String>)],
+    state: &AppState,
+    config: &crate::config::Config,
+) {
     for (expr, res) in rows {
-        let result = res
-            .clone()
-            .unwrap_or_else(|| crate::fl!("error-evaluating-expression"));
-        println!(
-            "│ {:expr_width$} │ {:res_width$} │",
-            expr,
-            result,
-            expr_width = expr_width,
-            res_width = res_width
-        );
+        if res.is_err() {
+            continue;
+        }
+        let Some((schedule, unit)) = amortization_schedule(expr, state, config) else {
+            continue;
+        };
+        match unit {
+            Some(unit) => println!("\n{} ({})", expr, unit),
+            None => println!("\n{}", expr),
+        }
+        let headers = [
+            crate::fl!("cli-schedule-year"),
+            crate::fl!("cli-schedule-payment"),
+            crate::fl!("cli-schedule-principal"),
+            crate::fl!("cli-schedule-interest"),
+            crate::fl!("cli-schedule-balance"),
+        ];
+        let cells: Vec<Vec<String>> = schedule
+            .iter()
+            .map(|year| {
+                vec![
+                    year.year.to_string(),
+                    format!("{:.2}", year.payment),
+                    format!("{:.2}", year.principal),
+                    format!("{:.2}", year.interest),
+                    format!("{:.2}", year.balance.max(0.0)),
+                ]
+            })
+            .collect();
+        print_box(&headers, &cells);
+    }
+}
+
+/// Print `rows` in a box-drawn table under `headers`.
+fn print_box(headers: &[String], rows: &[Vec<String>]) {
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(col, header)| {
+            rows.iter()
+                .map(|row| row[col].chars().count())
+                .chain(std::iter::once(header.chars().count()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let rule = |left: &str, join: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+        format!(
+            "{}─{}─{}",
+            left,
+            segments.join(&format!("─{}─", join)),
+            right
+        )
+    };
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect();
+        format!("│ {} │", padded.join(" │ "))
+    };
+
+    println!("{}", rule("╭", "┬", "╮"));
+    println!("{}", line(headers));
+    println!("{}", rule("├", "┼", "┤"));
+    for row in rows {
+        println!("{}", line(row));
     }
-    println!("{}", bot);
+    println!("{}", rule("╰", "┴", "╯"));
 }
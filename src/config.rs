@@ -10,6 +10,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::RwLock;
 
+use crate::prettify::DisplayFormat;
+
 /// Global config path override (used on Android/iOS)
 static CONFIG_PATH_OVERRIDE: RwLock<Option<String>> = RwLock::new(None);
 
@@ -54,6 +56,10 @@ pub struct Config {
     pub scales: HashMap<String, f64>,
     pub functions: HashMap<String, String>,
     pub custom_units: HashMap<String, HashMap<String, f64>>,
+    /// Named constants such as `c` or `G`, with the unit their value is in.
+    /// Names are case-sensitive; variables of the same name take precedence.
+    #[serde(default = "create_constants")]
+    pub constants: HashMap<String, Constant>,
     /// City name aliases to IANA time zones (lowercase keys).
     #[serde(default = "create_city_aliases")]
     pub city_aliases: HashMap<String, String>,
@@ -63,8 +69,25 @@ pub struct Config {
     /// Preferred date-only format (iso|long|short) shared by CLI and TUI.
     #[serde(default = "default_date_format")]
     pub date_format: String,
+    /// Numeric backend (float|decimal|fraction). `decimal` keeps money sums
+    /// exact; `fraction` keeps exact rationals and shows results as fractions.
+    #[serde(default = "default_number_mode")]
+    pub number_mode: String,
+    /// Where `sum`, `average` and `prev` on a line of a document take their
+    /// values from (document|session). `document` uses the lines above it,
+    /// top-down; `session` uses every result evaluated this session.
+    #[serde(default = "default_history_mode")]
+    pub history_mode: String,
+    /// How numbers in results are shown: precision, grouping, suffixes,
+    /// scientific notation and currency overrides.
+    #[serde(default)]
+    pub display: DisplayFormat,
     #[serde(default)]
     pub locale: Option<String>,
+    /// Locale whose decimal separator and digit grouping numbers are typed
+    /// and shown with, e.g. "de" for `3,5`. Defaults to the interface locale.
+    #[serde(default)]
+    pub number_locale: Option<String>,
     #[serde(default = "default_padding_left")]
     pub padding_left: u16,
     #[serde(default = "default_padding_right")]
@@ -83,6 +106,14 @@ pub struct Config {
     pub default_timezone: Option<String>,
 }
 
+/// A named constant: `value` in `unit`, or a plain number if `unit` is `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Constant {
+    pub value: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
 fn default_padding_left() -> u16 {
     2
 }
@@ -133,8 +164,21 @@ fn create_length_units() -> HashMap<String, f64> {
             ("inches", 0.0254),
             ("yard", 0.9144),
             ("yards", 0.9144),
+            ("yd", 0.9144),
             ("mile", 1609.344),
             ("miles", 1609.344),
+            ("mi", 1609.344),
+            ("au", 149_597_870_700.0),
+            ("astronomical unit", 149_597_870_700.0),
+            ("astronomical units", 149_597_870_700.0),
+            ("ly", 9_460_730_472_580_800.0),
+            ("lightyear", 9_460_730_472_580_800.0),
+            ("lightyears", 9_460_730_472_580_800.0),
+            ("light year", 9_460_730_472_580_800.0),
+            ("light years", 9_460_730_472_580_800.0),
+            ("pc", 3.085_677_581_491_367e16),
+            ("parsec", 3.085_677_581_491_367e16),
+            ("parsecs", 3.085_677_581_491_367e16),
         ],
     );
     map
@@ -149,21 +193,28 @@ fn create_time_units() -> HashMap<String, f64> {
             ("sec", 1.0),
             ("second", 1.0),
             ("seconds", 1.0),
+            ("secs", 1.0),
             ("min", 60.0),
             ("minute", 60.0),
             ("minutes", 60.0),
+            ("mins", 60.0),
             ("h", 3600.0),
             ("hr", 3600.0),
             ("hour", 3600.0),
             ("hours", 3600.0),
+            ("hrs", 3600.0),
             ("day", 86400.0),
             ("days", 86400.0),
             ("week", 604800.0),
             ("weeks", 604800.0),
+            ("wk", 604800.0),
+            ("wks", 604800.0),
             ("month", 2592000.0),
             ("months", 2592000.0),
             ("year", 31536000.0),
             ("years", 31536000.0),
+            ("yr", 31536000.0),
+            ("yrs", 31536000.0),
         ],
     );
     map
@@ -181,6 +232,8 @@ fn create_temperature_units() -> HashMap<String, String> {
             ("celsius", "celsius"),
             ("f", "fahrenheit"),
             ("fahrenheit", "fahrenheit"),
+            ("r", "rankine"),
+            ("rankine", "rankine"),
         ],
     );
     map
@@ -196,10 +249,12 @@ fn create_area_units() -> HashMap<String, f64> {
             ("square meters", 1.0),
             ("hectare", 10000.0),
             ("hectares", 10000.0),
+            ("ha", 10000.0),
             ("are", 100.0),
             ("ares", 100.0),
             ("acre", 4046.86),
             ("acres", 4046.86),
+            ("ac", 4046.86),
         ],
     );
     map
@@ -221,10 +276,13 @@ fn create_volume_units() -> HashMap<String, f64> {
             ("ml", 0.000001),
             ("pint", 0.000473176),
             ("pints", 0.000473176),
+            ("pt", 0.000473176),
             ("quart", 0.000946353),
             ("quarts", 0.000946353),
+            ("qt", 0.000946353),
             ("gallon", 0.00378541),
             ("gallons", 0.00378541),
+            ("gal", 0.00378541),
             ("teaspoon", 4.92892e-6),
             ("teaspoons", 4.92892e-6),
             ("tsp", 4.92892e-6),
@@ -275,8 +333,10 @@ fn create_angular_units() -> HashMap<String, f64> {
         &[
             ("radian", 1.0),
             ("radians", 1.0),
+            ("rad", 1.0),
             ("degree", 0.0174533),
             ("degrees", 0.0174533),
+            ("deg", 0.0174533),
             ("°", 0.0174533),
         ],
     );
@@ -447,12 +507,106 @@ fn create_custom_units() -> HashMap<String, HashMap<String, f64>> {
             ("calorie", 4.184),
             ("calories", 4.184),
             ("cal", 4.184),
+            ("wh", 3600.0),
+            ("kwh", 3600000.0),
+            ("kilowatt hour", 3600000.0),
+            ("kilowatt hours", 3600000.0),
         ],
     );
     custom_units.insert("energy".to_string(), energy_units);
+
+    let mut power_units = HashMap::new();
+    insert_numeric_units(
+        &mut power_units,
+        &[
+            ("W", 1.0),
+            ("watt", 1.0),
+            ("watts", 1.0),
+            ("kw", 1000.0),
+            ("kilowatt", 1000.0),
+            ("kilowatts", 1000.0),
+            ("MW", 1_000_000.0),
+            ("megawatt", 1_000_000.0),
+            ("megawatts", 1_000_000.0),
+            ("hp", 745.699_872),
+            ("horsepower", 745.699_872),
+        ],
+    );
+    custom_units.insert("power".to_string(), power_units);
+
+    let mut force_units = HashMap::new();
+    insert_numeric_units(
+        &mut force_units,
+        &[
+            ("N", 1.0),
+            ("newton", 1.0),
+            ("newtons", 1.0),
+            ("kN", 1000.0),
+            ("kilonewton", 1000.0),
+            ("kilonewtons", 1000.0),
+            ("lbf", 4.448_221_615),
+        ],
+    );
+    custom_units.insert("force".to_string(), force_units);
+
+    let mut pressure_units = HashMap::new();
+    insert_numeric_units(
+        &mut pressure_units,
+        &[
+            ("pa", 1.0),
+            ("pascal", 1.0),
+            ("pascals", 1.0),
+            ("kpa", 1000.0),
+            ("bar", 100_000.0),
+            ("atm", 101_325.0),
+            ("psi", 6_894.757_293),
+        ],
+    );
+    custom_units.insert("pressure".to_string(), pressure_units);
     custom_units
 }
 
+fn create_constants() -> HashMap<String, Constant> {
+    let constants: &[(&[&str], f64, Option<&str>)] = &[
+        (&["c", "speed_of_light"], 299_792_458.0, Some("m/s")),
+        (&["g", "gravity"], 9.806_65, Some("m/s²")),
+        (
+            &["G", "gravitational_constant"],
+            6.674_30e-11,
+            Some("m³/kg·s²"),
+        ),
+        (&["h", "planck"], 6.626_070_15e-34, Some("J·s")),
+        (&["hbar"], 1.054_571_817e-34, Some("J·s")),
+        (&["NA", "avogadro"], 6.022_140_76e23, None),
+        (&["kB", "boltzmann"], 1.380_649e-23, Some("J/K")),
+        (&["me", "electron_mass"], 9.109_383_701_5e-31, Some("kg")),
+        (&["mp", "proton_mass"], 1.672_621_923_69e-27, Some("kg")),
+        (&["earth_mass"], 5.972_2e24, Some("kg")),
+        (&["earth_radius"], 6_371_000.0, Some("m")),
+        (&["sun_mass", "solar_mass"], 1.988_47e30, Some("kg")),
+        (&["AU"], 149_597_870_700.0, Some("m")),
+        (&["lightyear"], 9_460_730_472_580_800.0, Some("m")),
+        (&["parsec"], 3.085_677_581_491_367e16, Some("m")),
+        (&["phi", "golden_ratio"], 1.618_033_988_749_895, None),
+        (&["tau"], std::f64::consts::TAU, None),
+    ];
+    let mut map = HashMap::new();
+    for (names, value, unit) in constants {
+        let constant = Constant {
+            value: *value,
+            unit: unit.map(str::to_string),
+        };
+        for name in *names {
+            map.insert(name.to_string(), constant.clone());
+            // Multi-word names can also be written with spaces: golden ratio
+            if name.contains('_') {
+                map.insert(name.replace('_', " "), constant.clone());
+            }
+        }
+    }
+    map
+}
+
 fn create_city_aliases() -> HashMap<String, String> {
     let mut map = HashMap::new();
     for tz in TZ_VARIANTS.iter() {
@@ -473,6 +627,14 @@ fn default_date_format() -> String {
     "iso".to_string()
 }
 
+fn default_number_mode() -> String {
+    "float".to_string()
+}
+
+fn default_history_mode() -> String {
+    "document".to_string()
+}
+
 impl Default for Config {
     fn default() -> Self {
         Config {
@@ -490,10 +652,15 @@ impl Default for Config {
             scales: create_scales(),
             functions: create_functions(),
             custom_units: create_custom_units(),
+            constants: create_constants(),
             city_aliases: create_city_aliases(),
             time_format: default_time_format(),
             date_format: default_date_format(),
+            number_mode: default_number_mode(),
+            history_mode: default_history_mode(),
+            display: DisplayFormat::default(),
             locale: None,
+            number_locale: None,
             padding_left: default_padding_left(),
             padding_right: default_padding_right(),
             padding_top: default_padding_top(),
@@ -505,6 +672,61 @@ impl Default for Config {
     }
 }
 
+impl Config {
+    /// The config with the built-in defaults under its own entries, so units,
+    /// currencies, constants and other entries added since a saved config
+    /// was written still work. Entries in the config win over the defaults.
+    fn with_defaults(mut self) -> Config {
+        let defaults = Config::default();
+        fill(&mut self.length_units, defaults.length_units);
+        fill(&mut self.time_units, defaults.time_units);
+        fill(&mut self.temperature_units, defaults.temperature_units);
+        fill(&mut self.area_units, defaults.area_units);
+        fill(&mut self.volume_units, defaults.volume_units);
+        fill(&mut self.weight_units, defaults.weight_units);
+        fill(&mut self.angular_units, defaults.angular_units);
+        fill(&mut self.data_units, defaults.data_units);
+        fill(&mut self.speed_units, defaults.speed_units);
+        fill(&mut self.currencies, defaults.currencies);
+        fill(&mut self.operators, defaults.operators);
+        fill(&mut self.scales, defaults.scales);
+        fill(&mut self.functions, defaults.functions);
+        fill(&mut self.constants, defaults.constants);
+        fill(&mut self.city_aliases, defaults.city_aliases);
+        for (category, units) in defaults.custom_units {
+            fill(self.custom_units.entry(category).or_default(), units);
+        }
+        self
+    }
+}
+
+/// Add the entries of `defaults` that `map` doesn't have.
+fn fill<V>(map: &mut HashMap<String, V>, defaults: HashMap<String, V>) {
+    for (key, value) in defaults {
+        map.entry(key).or_insert(value);
+    }
+}
+
+/// Parse a saved config, filling in the built-in entries it lacks.
+///
+/// # Errors
+///
+/// Returns error if `content` is not a valid config.
+///
+/// # Examples
+///
+/// ```
+/// use numby::config::{parse_config, Config};
+///
+/// let mut saved = serde_json::to_value(Config::default()).unwrap();
+/// saved["length_units"] = serde_json::json!({ "meter": 1.0 });
+/// let config = parse_config(&saved.to_string()).unwrap();
+/// assert!(config.length_units.contains_key("mi"));
+/// ```
+pub fn parse_config(content: &str) -> serde_json::Result<Config> {
+    serde_json::from_str::<Config>(content).map(Config::with_defaults)
+}
+
 /// Parse a currency rate string in "CURRENCY:RATE" format.
 ///
 /// # Examples
@@ -548,7 +770,7 @@ pub fn load_config() -> Config {
     // Check for override path first (used on Android/iOS)
     if let Some(override_path) = get_config_path_override() {
         if let Ok(content) = fs::read_to_string(&override_path) {
-            if let Ok(config) = serde_json::from_str::<Config>(&content) {
+            if let Ok(config) = parse_config(&content) {
                 return config;
             }
         }
@@ -556,13 +778,13 @@ pub fn load_config() -> Config {
 
     let config_path = get_config_path();
     if let Ok(content) = fs::read_to_string(&config_path) {
-        if let Ok(config) = serde_json::from_str::<Config>(&content) {
+        if let Ok(config) = parse_config(&content) {
             return config;
         }
     }
     // Try ./config.json
     if let Ok(content) = fs::read_to_string("./config.json") {
-        if let Ok(config) = serde_json::from_str::<Config>(&content) {
+        if let Ok(config) = parse_config(&content) {
             return config;
         }
     }
@@ -675,7 +897,7 @@ pub fn update_currency_rates_at_path(
     let current_date = Utc::now().format("%Y-%m-%d").to_string();
 
     let mut config = if let Ok(content) = fs::read_to_string(path) {
-        serde_json::from_str(&content).unwrap_or_else(|_| Config::default())
+        parse_config(&content).unwrap_or_else(|_| Config::default())
     } else {
         Config::default()
     };
@@ -716,3 +938,31 @@ pub fn save_config_to_path(path: &Path, config: &Config) -> anyhow::Result<()> {
     fs::write(path, json)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_config_gets_new_defaults() {
+        // A config saved before `mi`, the derived unit categories and the
+        // constants table existed, with a rate of its own
+        let mut saved = serde_json::to_value(Config::default()).unwrap();
+        saved["length_units"] = serde_json::json!({ "meter": 1.0, "foot": 0.3048 });
+        saved["custom_units"] = serde_json::json!({ "energy": { "joule": 1.0, "cal": 4.2 } });
+        saved["currencies"] = serde_json::json!({ "USD": 1.0, "EUR": 0.5 });
+        let saved = saved.as_object_mut().unwrap();
+        for key in ["constants", "number_mode", "history_mode", "display"] {
+            saved.remove(key);
+        }
+
+        let config = parse_config(&serde_json::to_string(saved).unwrap()).unwrap();
+        assert!(config.length_units.contains_key("mi"));
+        assert!(config.custom_units["power"].contains_key("kw"));
+        assert!(config.custom_units["energy"].contains_key("kwh"));
+        assert!(config.constants.contains_key("c"));
+        assert_eq!(config.custom_units["energy"]["cal"], 4.2);
+        assert_eq!(config.currencies["EUR"], 0.5);
+        assert!(config.currencies.contains_key("GBP"));
+    }
+}
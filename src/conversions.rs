@@ -3,6 +3,7 @@
 //! This module provides conversion between different units of measurement
 //! including length, temperature, and currencies.
 
+use crate::evaluator::{Number, NumberMode};
 use crate::models::{Rates, Units};
 
 /// Map currency symbols to their ISO currency codes.
@@ -22,7 +23,7 @@ use crate::models::{Rates, Units};
 /// assert_eq!(symbol_to_currency_code("€"), Some("EUR"));
 /// assert_eq!(symbol_to_currency_code("unknown"), None);
 /// ```
-fn symbol_to_currency_code(symbol: &str) -> Option<&str> {
+pub(crate) fn symbol_to_currency_code(symbol: &str) -> Option<&'static str> {
     match symbol {
         "$" => Some("USD"),
         "€" => Some("EUR"),
@@ -49,6 +50,56 @@ fn symbol_to_currency_code(symbol: &str) -> Option<&str> {
     }
 }
 
+/// The symbol a currency is written with, the reverse of
+/// [`symbol_to_currency_code`] for currencies whose symbol is their own.
+pub(crate) fn currency_symbol(code: &str) -> Option<&'static str> {
+    match code.to_uppercase().as_str() {
+        "USD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "JPY" => Some("¥"),
+        "INR" => Some("₹"),
+        "CNY" => Some("￥"),
+        "RUB" => Some("₽"),
+        "KRW" => Some("₩"),
+        "ILS" => Some("₪"),
+        "NGN" => Some("₦"),
+        "PKR" => Some("₨"),
+        "PHP" => Some("₱"),
+        "THB" => Some("฿"),
+        "CRC" => Some("₡"),
+        "UAH" => Some("₴"),
+        "GHS" => Some("₵"),
+        "KZT" => Some("₸"),
+        "TRY" => Some("₺"),
+        "AZN" => Some("₼"),
+        "GEL" => Some("₾"),
+        _ => None,
+    }
+}
+
+/// Map spoken currency words to ISO codes ("dollars" -> "USD").
+pub(crate) fn currency_word_to_code(word: &str) -> Option<&'static str> {
+    match word {
+        "dollar" | "dollars" => Some("USD"),
+        "euro" | "euros" => Some("EUR"),
+        "pound" | "pounds" => Some("GBP"),
+        "yen" => Some("JPY"),
+        "yuan" | "rmb" => Some("CNY"),
+        "rupee" | "rupees" => Some("INR"),
+        "ruble" | "rubles" => Some("RUB"),
+        "won" => Some("KRW"),
+        "franc" | "francs" => Some("CHF"),
+        "peso" | "pesos" => Some("MXN"),
+        "krona" => Some("SEK"),
+        "krone" => Some("NOK"),
+        "lira" => Some("TRY"),
+        "bitcoin" | "btc" => Some("BTC"),
+        "ethereum" | "eth" => Some("ETH"),
+        _ => None,
+    }
+}
+
 /// Normalize currency input by converting symbols to codes
 /// Examples: "100$" -> "100 USD", "$100" -> "100 USD", "€50" -> "50 EUR"
 fn normalize_currency_input(input: &str) -> String {
@@ -78,9 +129,15 @@ fn normalize_currency_input(input: &str) -> String {
     input.to_string()
 }
 
+#[allow(unused)]
 pub fn parse_number_with_scale(num_str: &str) -> Option<f64> {
+    parse_number_with_scale_in(num_str, NumberMode::Float).map(Number::to_f64)
+}
+
+/// Parse a number with an optional scale suffix (k, M, G, B, T) in the given mode.
+pub fn parse_number_with_scale_in(num_str: &str, mode: NumberMode) -> Option<Number> {
     // Try direct parse first
-    if let Ok(num) = num_str.parse::<f64>() {
+    if let Some(num) = Number::parse(num_str, mode) {
         return Some(num);
     }
 
@@ -96,8 +153,8 @@ pub fn parse_number_with_scale(num_str: &str) -> Option<f64> {
         };
 
         let num_part = &num_str[..num_str.len() - 1];
-        if let Ok(num) = num_part.parse::<f64>() {
-            return Some(num * multiplier);
+        if let Some(num) = Number::parse(num_part, mode) {
+            return Some(num * Number::from_f64(multiplier, mode));
         }
     }
 
@@ -130,17 +187,25 @@ pub fn parse_number_with_scale(num_str: &str) -> Option<f64> {
 /// let value = result.unwrap();
 /// assert!((value - 3.106).abs() < 0.01); // 5 km ≈ 3.106 miles
 /// ```
+#[allow(unused)]
 pub fn evaluate_generic_conversion(left: &str, right: &str, units: &Units) -> Option<f64> {
+    convert_generic(left, right, units, NumberMode::Float).map(Number::to_f64)
+}
+
+/// Convert between generic units, keeping the value exact in decimal mode.
+pub fn convert_generic(left: &str, right: &str, units: &Units, mode: NumberMode) -> Option<Number> {
     // Simple: assume left is number + unit, right is unit
     let left_parts: Vec<&str> = left.split_whitespace().collect();
     if left_parts.len() == 2 {
         let num_str = left_parts[0];
         let unit1 = left_parts[1];
         let unit2 = right;
-        if let Some(num) = parse_number_with_scale(num_str) {
+        if let Some(num) = parse_number_with_scale_in(num_str, mode) {
             if let Some(conv1) = units.get(&unit1.to_lowercase()) {
                 if let Some(conv2) = units.get(&unit2.to_lowercase()) {
-                    return Some(num * conv1 / conv2);
+                    return Some(
+                        num * Number::from_f64(*conv1, mode) / Number::from_f64(*conv2, mode),
+                    );
                 }
             }
         }
@@ -149,7 +214,7 @@ pub fn evaluate_generic_conversion(left: &str, right: &str, units: &Units) -> Op
     None
 }
 
-/// Convert between temperature units (Celsius, Fahrenheit, Kelvin).
+/// Convert between temperature units (Celsius, Fahrenheit, Kelvin, Rankine).
 ///
 /// # Arguments
 ///
@@ -173,6 +238,7 @@ pub fn evaluate_generic_conversion(left: &str, right: &str, units: &Units) -> Op
 /// let result = evaluate_temperature_conversion("0 kelvin", "celsius");
 /// assert_eq!(result, Some(-273.15));
 /// ```
+#[allow(unused)]
 pub fn evaluate_temperature_conversion(left: &str, right: &str) -> Option<f64> {
     // Assume left is number + unit
     let left_parts: Vec<&str> = left.split_whitespace().collect();
@@ -188,12 +254,13 @@ pub fn evaluate_temperature_conversion(left: &str, right: &str) -> Option<f64> {
     None
 }
 
-fn convert_temperature(val: f64, from: &str, to: &str) -> Option<f64> {
+pub(crate) fn convert_temperature(val: f64, from: &str, to: &str) -> Option<f64> {
     // Convert to celsius first
     let celsius = match from {
         "celsius" => val,
         "fahrenheit" => (val - 32.0) * 5.0 / 9.0,
         "kelvin" => val - 273.15,
+        "rankine" => (val - 491.67) * 5.0 / 9.0,
         _ => return None,
     };
     // Convert from celsius to target
@@ -201,6 +268,7 @@ fn convert_temperature(val: f64, from: &str, to: &str) -> Option<f64> {
         "celsius" => Some(celsius),
         "fahrenheit" => Some(celsius * 9.0 / 5.0 + 32.0),
         "kelvin" => Some(celsius + 273.15),
+        "rankine" => Some((celsius + 273.15) * 9.0 / 5.0),
         _ => None,
     }
 }
@@ -229,7 +297,18 @@ fn convert_temperature(val: f64, from: &str, to: &str) -> Option<f64> {
 /// assert!(result.is_some());
 /// assert_eq!(result.unwrap(), 85.0); // 100 USD = 85 EUR
 /// ```
+#[allow(unused)]
 pub fn evaluate_currency_conversion(left: &str, right: &str, rates: &Rates) -> Option<f64> {
+    convert_currency(left, right, rates, NumberMode::Float).map(Number::to_f64)
+}
+
+/// Convert between currencies, keeping the amount exact in decimal mode.
+pub fn convert_currency(
+    left: &str,
+    right: &str,
+    rates: &Rates,
+    mode: NumberMode,
+) -> Option<Number> {
     // Normalize inputs to handle currency symbols
     let left_normalized = normalize_currency_input(left);
     let right_normalized = normalize_currency_input(right);
@@ -242,7 +321,7 @@ pub fn evaluate_currency_conversion(left: &str, right: &str, rates: &Rates) -> O
         let num_str = left_parts[0];
         let curr1 = left_parts[1];
         let curr2 = right_normalized.trim();
-        if let Some(num) = parse_number_with_scale(num_str) {
+        if let Some(num) = parse_number_with_scale_in(num_str, mode) {
             if let Some(rate1) = rates.get(&curr1.to_uppercase()) {
                 if let Some(rate2) = rates.get(&curr2.to_uppercase()) {
                     // Formula: Convert from source currency to target currency
@@ -251,7 +330,9 @@ pub fn evaluate_currency_conversion(left: &str, right: &str, rates: &Rates) -> O
                     // source -> USD: amount / rate1 (e.g., 850 USD / 1 = 850 USD)
                     // USD -> target: usd_amount * rate2 (e.g., 850 * 154 = 130,900 JPY)
                     // Combined: amount * rate2 / rate1
-                    return Some(num * rate2 / rate1);
+                    return Some(
+                        num * Number::from_f64(*rate2, mode) / Number::from_f64(*rate1, mode),
+                    );
                 }
             }
         }
@@ -263,7 +344,7 @@ pub fn evaluate_currency_conversion(left: &str, right: &str, rates: &Rates) -> O
         let curr2 = right_normalized.trim();
         // Only apply this if the target is actually a valid currency
         if rates.contains_key(&curr2.to_uppercase()) {
-            if let Some(num) = parse_number_with_scale(left_parts[0]) {
+            if let Some(num) = parse_number_with_scale_in(left_parts[0], mode) {
                 // Just return the number - it's being "converted" to the target currency
                 // This allows expressions like "100 + 400 to USD" to work
                 return Some(num);
@@ -171,13 +171,9 @@ pub fn are_rates_stale(stored_date: &str) -> bool {
         None => return true, // If we can't parse, consider it stale
     };
 
-    // Handle API date errors - allow up to 7 days difference in either direction
+    // Handle API date errors - dates ahead of today (API clock skew) are never stale
     let tolerance_days = 7;
-    let day_difference = if today_days > stored_days {
-        today_days - stored_days
-    } else {
-        stored_days - today_days
-    };
+    let day_difference = today_days.saturating_sub(stored_days);
 
     // Consider stale only if more than tolerance_days behind
     day_difference > tolerance_days as u64
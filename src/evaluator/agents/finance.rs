@@ -0,0 +1,94 @@
+use crate::config::Config;
+use crate::evaluator::agents::{any_token, evaluate_if, PRIORITY_FINANCE};
+use crate::evaluator::ast::{Expr, ExprKind, InterestPlan};
+use crate::evaluator::finance::{self, ScheduleYear};
+use crate::evaluator::lexer::TokenKind;
+use crate::evaluator::{evaluate, parse, Diagnostic, EvalContext};
+use crate::models::{Agent, AgentOutput, AppState};
+
+/// Words that start or join an interest phrase.
+const FINANCE_WORDS: &[&str] = &["mortgage", "loan", "at"];
+
+/// Loan and compound interest phrases.
+///
+/// Supports:
+/// - loan payments: "mortgage 300k at 6.5% over 30 years" (paid monthly)
+/// - compound growth: "$5000 at 4% for 10 years compounded monthly"
+///
+/// The phrases are parsed like any other expression and results keep their
+/// currency, so they convert, add up and can be assigned like any other
+/// value (`payment = mortgage 300k at 6.5% over 30 years in EUR`). `pmt`,
+/// `fv`, `npv` and `irr` are built-in functions handled by the math agent.
+pub struct FinanceAgent;
+
+impl Agent for FinanceAgent {
+    fn name(&self) -> &'static str {
+        "finance"
+    }
+
+    fn priority(&self) -> i32 {
+        PRIORITY_FINANCE
+    }
+
+    fn can_handle(&self, input: &str, _state: &AppState) -> bool {
+        any_token(input, |kind| match kind {
+            TokenKind::Ident(word) => FINANCE_WORDS.contains(&word.to_lowercase().as_str()),
+            _ => false,
+        })
+    }
+
+    fn process(
+        &self,
+        input: &str,
+        state: &mut AppState,
+        config: &Config,
+    ) -> Result<Option<AgentOutput>, Diagnostic> {
+        evaluate_if(input, state, config, |expr| {
+            !expr.is_definition() && expr.any(&|kind| matches!(kind, ExprKind::Interest { .. }))
+        })
+    }
+}
+
+/// The loan phrase a line works out, possibly converted or assigned.
+fn loan_phrase(expr: &Expr) -> Option<&Expr> {
+    match &expr.kind {
+        ExprKind::Interest {
+            plan: InterestPlan::Loan,
+            ..
+        } => Some(expr),
+        ExprKind::Convert { expr, .. } | ExprKind::Assign { value: expr, .. } => loan_phrase(expr),
+        _ => None,
+    }
+}
+
+/// Yearly amortization schedule of a loan phrase such as
+/// "mortgage 300k at 6.5% over 30 years", with the loan's unit. `None` if
+/// `input` is not a loan phrase or its amounts do not evaluate.
+pub fn amortization_schedule(
+    input: &str,
+    state: &AppState,
+    config: &Config,
+) -> Option<(Vec<ScheduleYear>, Option<String>)> {
+    let mut vars_guard = state.variables.write().ok()?;
+    let history_guard = state.history.read().ok()?;
+    let ctx = EvalContext::new(&mut vars_guard, &history_guard, state, config);
+
+    let expr = parse(input, &ctx).ok()?;
+    let ExprKind::Interest {
+        principal,
+        rate,
+        term,
+        ..
+    } = &loan_phrase(&expr)?.kind
+    else {
+        return None;
+    };
+    let (rate, months) = ctx.interest_terms(rate, term).ok()?;
+    let principal = evaluate(principal, &ctx).ok()?;
+    let months = months.round();
+    if principal.items.is_some() || months < 1.0 {
+        return None;
+    }
+    let schedule = finance::amortization(principal.value.to_f64(), rate, months as usize);
+    Some((schedule, principal.unit))
+}
@@ -1,9 +1,14 @@
-use crate::evaluator::agents::PRIORITY_HISTORY;
-use crate::models::{Agent, AppState};
+use crate::evaluator::agents::{lock_error, PRIORITY_HISTORY};
+use crate::evaluator::{Diagnostic, EvalContext};
+use crate::models::{Agent, AgentOutput, AppState};
 
 pub struct HistoryAgent;
 
 impl Agent for HistoryAgent {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+
     fn priority(&self) -> i32 {
         PRIORITY_HISTORY
     }
@@ -17,47 +22,18 @@ impl Agent for HistoryAgent {
         &self,
         input: &str,
         state: &mut AppState,
-        _config: &crate::config::Config,
-    ) -> Option<(String, bool, Option<f64>, Option<String>)> {
+        config: &crate::config::Config,
+    ) -> Result<Option<AgentOutput>, Diagnostic> {
         let trimmed = input.trim();
-        let history_guard = state
-            .history
-            .read()
-            .expect("Failed to acquire read lock on history");
-        let unit = crate::evaluator::core::all_same_unit(&history_guard);
-        match trimmed {
-            "sum" | "total" => {
-                let sum = history_guard.iter().map(|h| h.value).sum::<f64>();
-                let formatted = if let Some(ref u) = unit {
-                    format!("{} {}", sum, u)
-                } else {
-                    format!("{}", sum)
-                };
-                Some((formatted, true, Some(sum), unit.clone()))
-            }
-            "average" | "avg" => {
-                if history_guard.is_empty() {
-                    None
-                } else {
-                    let avg = history_guard.iter().map(|h| h.value).sum::<f64>()
-                        / history_guard.len() as f64;
-                    let formatted = if let Some(ref u) = unit {
-                        format!("{} {}", avg, u)
-                    } else {
-                        format!("{}", avg)
-                    };
-                    Some((formatted, true, Some(avg), unit.clone()))
-                }
-            }
-            "prev" => history_guard.last().map(|h| {
-                let formatted = if let Some(ref u) = h.unit {
-                    format!("{} {}", h.value, u)
-                } else {
-                    format!("{}", h.value)
-                };
-                (formatted, true, Some(h.value), h.unit.clone())
-            }),
-            _ => None,
-        }
+        let mut vars_guard = state.variables.write().map_err(lock_error)?;
+        let history_guard = state.history.read().map_err(lock_error)?;
+        let ctx = EvalContext::new(&mut vars_guard, &history_guard, state, config);
+        let result = match trimmed {
+            "sum" | "total" | "average" | "avg" => ctx.history_aggregate(trimmed)?,
+            "prev" => ctx.previous()?,
+            _ => return Ok(None),
+        };
+        let formatted = ctx.format(&result);
+        Ok(Some((formatted, true, Some(result.value), result.unit)))
     }
 }
@@ -1,22 +1,80 @@
+mod finance;
 mod history;
 mod math;
 mod percentage;
+mod roman;
+mod solve;
 mod unit;
 mod variable;
 mod datetime;
 
+pub use finance::{amortization_schedule, FinanceAgent};
 pub use history::HistoryAgent;
 pub use math::MathAgent;
 pub use percentage::PercentageAgent;
+pub use roman::RomanAgent;
+pub use solve::SolveAgent;
 pub use unit::UnitAgent;
 pub use variable::VariableAgent;
 pub use datetime::DateTimeAgent;
 
+use crate::config::Config;
+use crate::evaluator::ast::Expr;
+use crate::evaluator::lexer::{tokenize, TokenKind};
+use crate::evaluator::{evaluate, parse, Diagnostic, EvalContext, EvaluatorError};
+use crate::models::{AgentOutput, AppState};
+
 /// Agent priority constants. Lower priority values run first.
 /// The order is designed to process high-specificity agents before fallback math evaluation.
 pub const PRIORITY_HISTORY: i32 = 10;
+/// Runs before the roman and variable agents, which would otherwise claim
+/// the `=` of `solve 2x + 3 = 11 for x` and of `r = solve ... for r`.
+pub const PRIORITY_SOLVE: i32 = 12;
+/// Runs before the unit agent, which would otherwise claim `to roman` as a
+/// conversion.
+pub const PRIORITY_ROMAN: i32 = 15;
 pub const PRIORITY_VARIABLE: i32 = 20;
+pub const PRIORITY_FINANCE: i32 = 25;
 pub const PRIORITY_PERCENTAGE: i32 = 30;
 pub const PRIORITY_DATETIME: i32 = 35;
 pub const PRIORITY_UNIT: i32 = 40;
 pub const PRIORITY_MATH: i32 = 50;
+
+/// Cheap pre-check for `can_handle`: true if any token of `input` matches.
+fn any_token(input: &str, pred: impl Fn(&TokenKind) -> bool) -> bool {
+    tokenize(input)
+        .map(|tokens| tokens.iter().any(|t| pred(&t.kind)))
+        .unwrap_or(false)
+}
+
+/// Error for a poisoned state lock.
+fn lock_error(e: impl std::fmt::Display) -> Diagnostic {
+    Diagnostic::new(EvaluatorError::LockError(e.to_string()))
+}
+
+/// Parse `input` and evaluate it if `accepts` the expression tree.
+///
+/// Declines (`Ok(None)`) trees it doesn't accept; parse and evaluation
+/// errors are returned so the caller can report them.
+fn evaluate_if(
+    input: &str,
+    state: &AppState,
+    config: &Config,
+    accepts: impl Fn(&Expr) -> bool,
+) -> Result<Option<AgentOutput>, Diagnostic> {
+    let mut vars_guard = state.variables.write().map_err(lock_error)?;
+    let history_guard = state.history.read().map_err(lock_error)?;
+    let ctx = EvalContext::new(&mut vars_guard, &history_guard, state, config);
+
+    let expr = parse(input, &ctx)?;
+    if !accepts(&expr) {
+        return Ok(None);
+    }
+    let result = evaluate(&expr, &ctx)?;
+    let formatted = ctx.format(&result);
+    // Lists and dates are shown but not added to history
+    if result.items.is_some() || result.moment.is_some() {
+        return Ok(Some((formatted, false, None, None)));
+    }
+    Ok(Some((formatted, true, Some(result.value), result.unit)))
+}
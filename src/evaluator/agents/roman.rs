@@ -0,0 +1,132 @@
+use crate::evaluator::agents::{any_token, evaluate_if, PRIORITY_ROMAN};
+use crate::evaluator::ast::ExprKind;
+use crate::evaluator::lexer::TokenKind;
+use crate::evaluator::roman::is_roman_word;
+use crate::evaluator::Diagnostic;
+use crate::models::{Agent, AgentOutput, AppState};
+
+/// Roman numerals in expressions (`MCMXCIV + 6`) and the `to roman` target.
+///
+/// Numerals are read while evaluating the expression tree, so errors point
+/// at the numeral as written. Words of two or more uppercase `IVXLCDM`
+/// letters that name nothing else are numerals; single letters such as `C`
+/// only with `to number` or `to roman`. Assignments are left to the
+/// variable agent, which reads numerals the same way.
+pub struct RomanAgent;
+
+impl Agent for RomanAgent {
+    fn name(&self) -> &'static str {
+        "roman"
+    }
+
+    fn priority(&self) -> i32 {
+        PRIORITY_ROMAN
+    }
+
+    fn can_handle(&self, input: &str, _state: &AppState) -> bool {
+        any_token(input, |kind| match kind {
+            TokenKind::Ident(word) => {
+                (word.len() > 1 && is_roman_word(word)) || word.eq_ignore_ascii_case("roman")
+            }
+            _ => false,
+        })
+    }
+
+    fn process(
+        &self,
+        input: &str,
+        state: &mut AppState,
+        config: &crate::config::Config,
+    ) -> Result<Option<AgentOutput>, Diagnostic> {
+        evaluate_if(input, state, config, |expr| {
+            !expr.is_definition()
+                && expr.any(&|kind| match kind {
+                    ExprKind::Ident(name) => name.len() > 1 && is_roman_word(name),
+                    ExprKind::Convert { targets, .. } => {
+                        matches!(targets.as_slice(), [target] if target == "roman")
+                    }
+                    _ => false,
+                })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::evaluator::Number;
+
+    fn run(input: &str) -> Result<Option<AgentOutput>, Diagnostic> {
+        let config = Config::default();
+        let mut state = AppState::builder(&config).build();
+        RomanAgent.process(input, &mut state, &config)
+    }
+
+    #[test]
+    fn test_roman_expressions() {
+        let (out, add_hist, raw, _) = run("MCMXCIV + 6").unwrap().unwrap();
+        assert_eq!(raw.unwrap().to_f64(), 2000.0);
+        assert!(add_hist, "got {}", out);
+
+        let (out, _, raw, _) = run("2025 to roman").unwrap().unwrap();
+        assert_eq!(out, "MMXXV");
+        assert_eq!(raw.unwrap().to_f64(), 2025.0);
+
+        let (out, _, _, _) = run("XIV * 2 in roman numerals").unwrap().unwrap();
+        assert_eq!(out, "XXVIII");
+
+        assert!(run("IIII + 1").is_err());
+        assert!(run("5000 to roman").is_err());
+        assert!(run("2.5 to roman").is_err());
+    }
+
+    #[test]
+    fn test_single_letters_only_when_asked_for() {
+        let config = Config::default();
+        let mut state = AppState::builder(&config).build();
+        let mut show = |input: &str| {
+            crate::evaluator::AgentRegistry::new(&config)
+                .unwrap()
+                .evaluate(input, &mut state)
+        };
+        assert_eq!(show("C to number").unwrap().0, "100");
+        assert_eq!(show("MCM to number").unwrap().0, "1900");
+        assert_eq!(show("V to roman").unwrap().0, "V");
+        assert!(show("C + 1").is_err());
+
+        // Errors point at the input as written
+        let err = show("10 delta C").unwrap_err();
+        assert!(!err.to_string().contains("100"), "{}", err);
+        let err = show("IIII + 1").unwrap_err();
+        assert_eq!(err.span.map(|s| (s.start, s.end)), Some((0, 4)));
+    }
+
+    #[test]
+    fn test_leaves_other_names_alone() {
+        let config = Config::default();
+        let mut state = AppState::builder(&config).build();
+        state
+            .variables
+            .write()
+            .unwrap()
+            .insert("XL".to_string(), (Number::from(3i64), None));
+        assert!(!RomanAgent.can_handle("20 C to F", &state));
+        assert!(!RomanAgent.can_handle("mix + 1", &state));
+        let (out, _, _, _) = RomanAgent
+            .process("XL + 1", &mut state, &config)
+            .unwrap()
+            .unwrap();
+        assert_eq!(out, "4.00");
+
+        // Assignments are the variable agent's, which reads numerals too
+        assert!(RomanAgent
+            .process("year = MMXXV", &mut state, &config)
+            .unwrap()
+            .is_none());
+        let registry = crate::evaluator::AgentRegistry::new(&config).unwrap();
+        registry.evaluate("year = MMXXV", &mut state).unwrap();
+        let year = state.variables.read().unwrap()["year"].0;
+        assert_eq!(year.to_f64(), 2025.0);
+    }
+}
@@ -0,0 +1,717 @@
+use crate::evaluator::agents::{lock_error, variable::track_line, PRIORITY_SOLVE};
+use crate::evaluator::ast::{BinaryOp, Expr, ExprKind, UnaryOp};
+use crate::evaluator::lexer::{tokenize, Span, Token, TokenKind};
+use crate::evaluator::{
+    evaluate, parse, Diagnostic, EvalContext, EvalResult, EvaluatorError, Number, StateEvent,
+};
+use crate::models::{Agent, AgentOutput, AppState};
+
+/// Highest power of the unknown a polynomial equation is read up to.
+const MAX_DEGREE: usize = 12;
+
+/// Relative size below which a residual or coefficient counts as zero.
+const TOLERANCE: f64 = 1e-9;
+
+/// Equations in one unknown: `solve 2x + 3 = 11 for x`.
+///
+/// Linear and quadratic equations are solved exactly and other polynomials
+/// for all their real roots. Equations with units give the unknown the unit
+/// that makes both sides agree (`solve 2 x = 10 km for x` is 5 km). Anything else, such as equations calling
+/// functions or using document variables (`solve pmt(r, 360, 300000) =
+/// 1800 for r`), is solved numerically for the root nearest the unknown's
+/// current value, or zero. Written as an assignment (`r = solve ...`) the
+/// solution is stored like any other variable.
+pub struct SolveAgent;
+
+/// A `solve <lhs> = <rhs> for <unknown>` line.
+struct Equation<'t> {
+    /// Variable the solution is assigned to, if any.
+    target: Option<&'t str>,
+    unknown: &'t str,
+    /// Tokens of the equation itself, between `solve` and `for`.
+    tokens: &'t [Token],
+    /// Index of the `=` in `tokens`.
+    equals: usize,
+}
+
+impl Agent for SolveAgent {
+    fn name(&self) -> &'static str {
+        "solve"
+    }
+
+    fn priority(&self) -> i32 {
+        PRIORITY_SOLVE
+    }
+
+    fn can_handle(&self, input: &str, _state: &AppState) -> bool {
+        tokenize(input).is_ok_and(|tokens| equation(&tokens).is_some())
+    }
+
+    fn process(
+        &self,
+        input: &str,
+        state: &mut AppState,
+        config: &crate::config::Config,
+    ) -> Result<Option<AgentOutput>, Diagnostic> {
+        let Ok(tokens) = tokenize(input) else {
+            return Ok(None);
+        };
+        let Some(equation) = equation(&tokens) else {
+            return Ok(None);
+        };
+
+        // Solve against a copy of the variables so the unknown can be bound freely
+        let mut variables = state.variables.read().map_err(lock_error)?.clone();
+        let guess = variables
+            .get(equation.unknown)
+            .map_or(0.0, |(value, _)| value.to_f64());
+        let history_guard = state.history.read().map_err(lock_error)?;
+        let mut ctx = EvalContext::new(&mut variables, &history_guard, state, config);
+
+        let (roots, unit) = solve(input, &equation, &mut ctx, guess)?;
+        let mode = ctx.number_mode;
+        let mut values: Vec<EvalResult> = roots
+            .into_iter()
+            .map(|root| EvalResult::new(Number::from_f64(root, mode), unit.clone()))
+            .collect();
+        let result = if values.len() == 1 {
+            values.remove(0)
+        } else {
+            EvalResult::list(values)
+        };
+        let formatted = ctx.format(&result);
+        drop(ctx);
+        drop(history_guard);
+
+        if let Some(name) = equation.target {
+            // Block variable assignments in display-only mode
+            if !state.is_display_only {
+                store(state, input, name, &result)?;
+            }
+        }
+        // Lists are shown but not added to history
+        if result.items.is_some() {
+            return Ok(Some((formatted, false, None, None)));
+        }
+        Ok(Some((
+            formatted,
+            true,
+            Some(result.value),
+            result.unit.clone(),
+        )))
+    }
+}
+
+/// The equation of `solve <lhs> = <rhs> for <unknown>`, or of `name = solve
+/// ...`. The words are matched case-insensitively; the equation must have
+/// exactly one `=` and something on both sides of it.
+fn equation(tokens: &[Token]) -> Option<Equation<'_>> {
+    let is_word = |token: &Token, word: &str| match &token.kind {
+        TokenKind::Ident(w) => w.eq_ignore_ascii_case(word),
+        _ => false,
+    };
+    let (target, start) = match tokens {
+        [name, equals, solve, ..]
+            if equals.kind == TokenKind::Equals && is_word(solve, "solve") =>
+        {
+            let TokenKind::Ident(name) = &name.kind else {
+                return None;
+            };
+            (Some(name.as_str()), 3)
+        }
+        [solve, ..] if is_word(solve, "solve") => (None, 1),
+        _ => return None,
+    };
+    let [.., keyword, unknown] = tokens else {
+        return None;
+    };
+    let TokenKind::Ident(unknown) = &unknown.kind else {
+        return None;
+    };
+    if !is_word(keyword, "for") {
+        return None;
+    }
+
+    let tokens = tokens.get(start..tokens.len() - 2)?;
+    let mut equals = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.kind == TokenKind::Equals)
+        .map(|(i, _)| i);
+    let (Some(equals), None) = (equals.next(), equals.next()) else {
+        return None;
+    };
+    (equals > 0 && equals + 1 < tokens.len()).then_some(Equation {
+        target,
+        unknown,
+        tokens,
+        equals,
+    })
+}
+
+/// Real solutions of `equation`, in ascending order, with the unit of the
+/// unknown.
+fn solve(
+    input: &str,
+    equation: &Equation,
+    ctx: &mut EvalContext,
+    guess: f64,
+) -> Result<(Vec<f64>, Option<String>), Diagnostic> {
+    let unknown = equation.unknown;
+    let tokens = equation.tokens;
+
+    // Write `2x` as `2*x`, which would otherwise read as a unit
+    let inserted: Vec<usize> = tokens
+        .windows(2)
+        .filter(|pair| {
+            matches!(pair[0].kind, TokenKind::Number(_))
+                && matches!(&pair[1].kind, TokenKind::Ident(w) if w == unknown)
+        })
+        .map(|pair| pair[1].span.start)
+        .collect();
+    let mut line = input.to_string();
+    for &pos in inserted.iter().rev() {
+        line.insert(pos, '*');
+    }
+    let forward = |pos: usize| pos + inserted.iter().filter(|&&i| i <= pos).count();
+    let back = |pos: usize| {
+        pos - inserted
+            .iter()
+            .enumerate()
+            .filter(|(k, &i)| i + k < pos)
+            .count()
+    };
+    let located = |e: Diagnostic| Diagnostic {
+        span: e.span.map(|s| Span::new(back(s.start), back(s.end))),
+        ..e
+    };
+
+    // Each side is parsed from the line with the rest blanked, so spans
+    // point into the line as written
+    let side = |from: usize, to: usize| {
+        let (from, to) = (forward(from), forward(to));
+        let text: String = line
+            .char_indices()
+            .map(|(i, c)| match i >= from && i < to {
+                true => c.to_string(),
+                false => " ".repeat(c.len_utf8()),
+            })
+            .collect();
+        parse(&text, ctx).map_err(located)
+    };
+    let equals = &tokens[equation.equals];
+    let lhs = side(tokens[0].span.start, equals.span.start)?;
+    let rhs = side(equals.span.end, tokens[tokens.len() - 1].span.end)?;
+
+    // The unknown shadows whatever the document calls by that name
+    ctx.variables.remove(unknown);
+    ctx.lists.remove(unknown);
+    ctx.user_functions.remove(unknown);
+    ctx.uncertainties.remove(unknown);
+
+    let no_solution = || {
+        Diagnostic::new(EvaluatorError::EvaluationError(
+            crate::fl!("solve-no-solution", "var" => unknown),
+        ))
+    };
+    let unit = unknown_unit(&lhs, &rhs, unknown, ctx, guess)?;
+    let polynomials = match unit {
+        // Polynomials have unitless coefficients
+        Some(_) => None,
+        None => polynomial(&lhs, unknown, ctx).zip(polynomial(&rhs, unknown, ctx)),
+    };
+    if let Some((left, right)) = polynomials {
+        let scale = left
+            .iter()
+            .chain(&right)
+            .fold(0.0f64, |m, c| m.max(c.abs()));
+        let mut coefficients = add(&left, &right, -1.0);
+        while coefficients
+            .last()
+            .is_some_and(|c| c.abs() <= TOLERANCE * scale)
+        {
+            coefficients.pop();
+        }
+        let roots = match coefficients.as_slice() {
+            [] => {
+                return Err(Diagnostic::new(EvaluatorError::EvaluationError(
+                    crate::fl!("solve-any-value", "var" => unknown),
+                )))
+            }
+            [_] => Vec::new(),
+            [c, b] => vec![-c / b],
+            _ => polynomial_roots(&coefficients),
+        };
+        return match tidy(roots) {
+            roots if roots.is_empty() => Err(no_solution()),
+            roots => Ok((roots, None)),
+        };
+    }
+
+    let difference = binary(BinaryOp::Sub, lhs, rhs);
+    let mode = ctx.number_mode;
+    let mut f = |x: f64| {
+        ctx.variables.insert(
+            unknown.to_string(),
+            (Number::from_f64(x, mode), unit.clone()),
+        );
+        evaluate(&difference, ctx)
+    };
+
+    // Report errors that don't depend on the value tried, like unknown
+    // names or incompatible units
+    let mut tried = [guess, 1.0, -1.0, 0.5].into_iter().map(&mut f);
+    if let Some(Err(e)) = tried.next() {
+        if let Some(e) = tried.all(|r| r.is_err()).then_some(e) {
+            return Err(located(e));
+        }
+    }
+
+    let value = |result: Result<EvalResult, Diagnostic>| {
+        result
+            .ok()
+            .filter(|r| r.items.is_none())
+            .map(|r| r.value.to_f64())
+            .filter(|v| v.is_finite())
+    };
+    let roots = tidy(numeric_roots(|x| value(f(x)), guess));
+    if roots.is_empty() {
+        return Err(no_solution());
+    }
+    Ok((roots, unit))
+}
+
+fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+    let span = lhs.span.to(rhs.span);
+    Expr {
+        kind: ExprKind::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+        span,
+    }
+}
+
+/// Unit the unknown needs for both sides of the equation to be in the same
+/// units: none for plain numbers, or one side's unit over the other's, like
+/// `km` in `2 x = 10 km`. Equations that don't evaluate at any of the values
+/// tried are left to the solver to report.
+fn unknown_unit(
+    lhs: &Expr,
+    rhs: &Expr,
+    unknown: &str,
+    ctx: &mut EvalContext,
+    guess: f64,
+) -> Result<Option<String>, Diagnostic> {
+    let mode = ctx.number_mode;
+    let ratios = [
+        binary(BinaryOp::Div, rhs.clone(), lhs.clone()),
+        binary(BinaryOp::Div, lhs.clone(), rhs.clone()),
+    ];
+    // Unit of a ratio with the unknown in `unit`, at the first value tried
+    // where it evaluates
+    let mut ratio_unit = |ratio: &Expr, unit: &Option<String>| {
+        [guess, 1.0, 2.0, 0.5].into_iter().find_map(|x| {
+            ctx.variables.insert(
+                unknown.to_string(),
+                (Number::from_f64(x, mode), unit.clone()),
+            );
+            evaluate(ratio, ctx)
+                .ok()
+                .filter(|result| result.items.is_none())
+                .map(|result| result.unit)
+        })
+    };
+    let Some(Some(over)) = ratio_unit(&ratios[0], &None) else {
+        return Ok(None);
+    };
+    let under = ratio_unit(&ratios[1], &None).flatten();
+    for unit in [Some(over), under].into_iter().flatten().map(Some) {
+        if ratio_unit(&ratios[0], &unit) == Some(None) {
+            return Ok(unit);
+        }
+    }
+    Err(Diagnostic::new(EvaluatorError::EvaluationError(
+        crate::fl!("solve-units", "var" => unknown),
+    )))
+}
+
+/// Coefficients, constant term first, of `expr` as a polynomial in
+/// `unknown`, if it is one: built from sums, products, negation, division
+/// by a constant and whole powers, with unitless constants.
+fn polynomial(expr: &Expr, unknown: &str, ctx: &EvalContext) -> Option<Vec<f64>> {
+    let mentions = expr.any(&|kind| matches!(kind, ExprKind::Ident(name) if name == unknown));
+    if !mentions {
+        let result = evaluate(expr, ctx).ok()?;
+        let value = result.value.to_f64();
+        return (result.unit.is_none() && result.items.is_none() && value.is_finite())
+            .then(|| vec![value]);
+    }
+    let coefficients = match &expr.kind {
+        ExprKind::Ident(_) => vec![0.0, 1.0],
+        ExprKind::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => add(&[], &polynomial(operand, unknown, ctx)?, -1.0),
+        ExprKind::Binary { op, lhs, rhs } => {
+            let left = polynomial(lhs, unknown, ctx)?;
+            let right = polynomial(rhs, unknown, ctx)?;
+            match (op, right.as_slice()) {
+                (BinaryOp::Add, _) => add(&left, &right, 1.0),
+                (BinaryOp::Sub, _) => add(&left, &right, -1.0),
+                (BinaryOp::Mul, _) => multiply(&left, &right),
+                (BinaryOp::Div, [divisor]) if *divisor != 0.0 => {
+                    left.iter().map(|c| c / divisor).collect()
+                }
+                (BinaryOp::Pow, [power])
+                    if power.fract() == 0.0 && (0.0..=MAX_DEGREE as f64).contains(power) =>
+                {
+                    (0..*power as usize).fold(vec![1.0], |acc, _| multiply(&acc, &left))
+                }
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    (coefficients.len() <= MAX_DEGREE + 1).then_some(coefficients)
+}
+
+/// `a + sign * b`, coefficient-wise.
+fn add(a: &[f64], b: &[f64], sign: f64) -> Vec<f64> {
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0.0) + sign * b.get(i).unwrap_or(&0.0))
+        .collect()
+}
+
+fn multiply(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut product = vec![0.0; (a.len() + b.len()).saturating_sub(1)];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+    product
+}
+
+/// Value at `x` of the polynomial with `coefficients`, constant term first.
+fn horner(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c)
+}
+
+/// Real roots of `a x² + b x + c`, avoiding cancellation in the formula.
+fn quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant.abs() <= TOLERANCE * (b * b).max((4.0 * a * c).abs()) {
+        return vec![-b / (2.0 * a)];
+    }
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return vec![0.0];
+    }
+    vec![q / a, c / q]
+}
+
+/// Real roots of the polynomial with `coefficients`, constant term first
+/// and the last one non-zero. Between neighbouring roots of the derivative
+/// the polynomial is monotonic, so each such interval, and the ones out to
+/// the bound on the roots, holds at most one root, found by bisection.
+fn polynomial_roots(coefficients: &[f64]) -> Vec<f64> {
+    match coefficients {
+        [] | [_] => return Vec::new(),
+        [c, b] => return vec![-c / b],
+        [c, b, a] => return quadratic(*a, *b, *c),
+        _ => {}
+    }
+    let derivative: Vec<f64> = coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| c * i as f64)
+        .collect();
+    let (lead, rest) = coefficients.split_last().unwrap_or((&1.0, &[]));
+    // Cauchy's bound: every root is within it of zero
+    let bound = 1.0 + rest.iter().fold(0.0f64, |m, c| m.max((c / lead).abs()));
+
+    let mut points = vec![-bound];
+    points.extend(polynomial_roots(&derivative));
+    points.push(bound);
+    points.sort_by(f64::total_cmp);
+    let mut p = |x: f64| Some(horner(coefficients, x));
+    // Largest the terms can add up to at `x`, the scale of rounding error
+    let magnitudes: Vec<f64> = coefficients.iter().map(|c| c.abs()).collect();
+
+    let mut roots = Vec::new();
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let (fa, fb) = (horner(coefficients, a), horner(coefficients, b));
+        if fa != 0.0 && fb != 0.0 && fa.signum() != fb.signum() {
+            roots.extend(bisect(&mut p, a, b, fa, fb));
+        }
+    }
+    // Roots the polynomial touches without crossing, like 1 in `(x - 1)²`
+    roots.extend(
+        points
+            .iter()
+            .filter(|&&x| horner(coefficients, x).abs() <= TOLERANCE * horner(&magnitudes, x.abs()))
+            .copied(),
+    );
+    roots
+}
+
+/// Roots of `f` bracketed by a sign change between neighbouring points of a
+/// logarithmic grid and refined by bisection, or found by Newton's method
+/// from the grid points if none is bracketed. Only the root nearest `guess`
+/// is kept.
+fn numeric_roots(mut f: impl FnMut(f64) -> Option<f64>, guess: f64) -> Vec<f64> {
+    let mut grid = vec![0.0, guess];
+    for exponent in -6..=9 {
+        for step in [1.0, 2.0, 5.0] {
+            let x = step * 10f64.powi(exponent);
+            grid.extend([x, -x]);
+        }
+    }
+    grid.sort_by(f64::total_cmp);
+    grid.dedup();
+    let samples: Vec<(f64, f64)> = grid.into_iter().filter_map(|x| Some((x, f(x)?))).collect();
+
+    let mut roots: Vec<f64> = samples
+        .iter()
+        .filter(|(_, y)| *y == 0.0)
+        .map(|(x, _)| *x)
+        .collect();
+    for pair in samples.windows(2) {
+        let ((a, fa), (b, fb)) = (pair[0], pair[1]);
+        if fa != 0.0 && fb != 0.0 && fa.signum() != fb.signum() {
+            roots.extend(bisect(&mut f, a, b, fa, fb));
+        }
+    }
+    if roots.is_empty() {
+        roots.extend(samples.iter().find_map(|&(x, _)| newton(&mut f, x)));
+    }
+    roots
+        .into_iter()
+        .min_by(|a, b| (a - guess).abs().total_cmp(&(b - guess).abs()))
+        .into_iter()
+        .collect()
+}
+
+/// Root of `f` between `a` and `b`, where it changes sign.
+fn bisect(
+    f: &mut impl FnMut(f64) -> Option<f64>,
+    mut a: f64,
+    mut b: f64,
+    mut fa: f64,
+    fb: f64,
+) -> Option<f64> {
+    let scale = fa.abs().max(fb.abs()).max(1.0);
+    for _ in 0..200 {
+        let mid = (a + b) / 2.0;
+        if mid <= a || mid >= b {
+            break;
+        }
+        let fm = f(mid)?;
+        if fm == 0.0 {
+            return Some(mid);
+        }
+        if fm.signum() == fa.signum() {
+            (a, fa) = (mid, fm);
+        } else {
+            b = mid;
+        }
+    }
+    // A sign change across a pole, as in `1/x`, is no root
+    let root = (a + b) / 2.0;
+    (f(root)?.abs() <= TOLERANCE * scale).then_some(root)
+}
+
+/// Root of `f` by Newton's method from `x`, with a numeric derivative.
+fn newton(f: &mut impl FnMut(f64) -> Option<f64>, mut x: f64) -> Option<f64> {
+    let scale = f(x)?.abs().max(1.0);
+    for _ in 0..100 {
+        let fx = f(x)?;
+        if fx == 0.0 {
+            return Some(x);
+        }
+        let h = 1e-7 * x.abs().max(1.0);
+        let slope = (f(x + h)? - f(x - h)?) / (2.0 * h);
+        if slope == 0.0 || !slope.is_finite() {
+            return None;
+        }
+        let step = fx / slope;
+        x -= step;
+        if step.abs() <= 1e-12 * x.abs().max(1.0) {
+            return (f(x)?.abs() <= TOLERANCE * scale).then_some(x);
+        }
+    }
+    None
+}
+
+/// Sort `roots`, snap them to whole numbers they are within rounding error
+/// of and drop duplicates.
+fn tidy(roots: Vec<f64>) -> Vec<f64> {
+    let mut roots: Vec<f64> = roots
+        .into_iter()
+        .filter(|r| r.is_finite())
+        .map(|r| {
+            let whole = r.round();
+            match (r - whole).abs() <= TOLERANCE * r.abs().max(1.0) {
+                // `+ 0.0` turns `-0` into `0`
+                true => whole + 0.0,
+                false => r,
+            }
+        })
+        .collect();
+    roots.sort_by(f64::total_cmp);
+    roots.dedup_by(|a, b| (*a - *b).abs() <= TOLERANCE * a.abs().max(1.0));
+    roots
+}
+
+/// Store the solution as the variable `name`, or a list of them.
+fn store(state: &AppState, input: &str, name: &str, result: &EvalResult) -> Result<(), Diagnostic> {
+    let mut vars_guard = state.variables.write().map_err(lock_error)?;
+    track_line(state, input, name, &mut vars_guard);
+    // A name is either a variable, a list or a function, never more than one
+    if let Ok(mut functions) = state.functions.write() {
+        functions.remove(name);
+    }
+    if let Ok(mut uncertainties) = state.uncertainties.write() {
+        uncertainties.remove(name);
+    }
+    let mut lists = state.lists.write().map_err(lock_error)?;
+    match &result.items {
+        Some(items) => {
+            vars_guard.remove(name);
+            let items = items.iter().map(|i| (i.value, i.unit.clone())).collect();
+            lists.insert(name.to_string(), items);
+        }
+        None => {
+            lists.remove(name);
+            vars_guard.insert(name.to_string(), (result.value, result.unit.clone()));
+        }
+    }
+    drop(lists);
+    drop(vars_guard);
+    state.publish_event(StateEvent::VariableChanged(name.to_string()));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn run(input: &str, state: &mut AppState) -> Result<Option<AgentOutput>, Diagnostic> {
+        SolveAgent.process(input, state, &Config::default())
+    }
+
+    fn solve_value(input: &str) -> f64 {
+        let config = Config::default();
+        let mut state = AppState::builder(&config).build();
+        let (_, _, value, _) = run(input, &mut state).unwrap().unwrap();
+        value.unwrap().to_f64()
+    }
+
+    #[test]
+    fn test_grammar() {
+        let config = Config::default();
+        let state = AppState::builder(&config).build();
+        assert!(SolveAgent.can_handle("solve 2x + 3 = 11 for x", &state));
+        assert!(SolveAgent.can_handle("Solve x^2 = 2 FOR x", &state));
+        assert!(SolveAgent.can_handle("r = solve pmt(r, 360, 300000) = 1800 for r", &state));
+        assert!(!SolveAgent.can_handle("x = 5", &state));
+        assert!(!SolveAgent.can_handle("solve = 5", &state));
+        assert!(!SolveAgent.can_handle("solve 2x + 3 for x", &state));
+        assert!(!SolveAgent.can_handle("solve x = 1 = 2 for x", &state));
+        assert!(!SolveAgent.can_handle("solve = 11 for x", &state));
+    }
+
+    #[test]
+    fn test_polynomials() {
+        assert_eq!(solve_value("solve 2x + 3 = 11 for x"), 4.0);
+        assert_eq!(solve_value("solve (x - 1) / 4 = 2 for x"), 9.0);
+        assert_eq!(solve_value("solve 3 * (y + 2) = y for y"), -3.0);
+
+        let config = Config::default();
+        let mut state = AppState::builder(&config).build();
+        let (out, add_hist, _, _) = run("solve x^2 - x = 6 for x", &mut state).unwrap().unwrap();
+        assert!(out.contains("-2") && out.contains('3'), "got {}", out);
+        assert!(!add_hist);
+
+        let (out, _, _, _) = run("solve x^3 - 6x^2 + 11x = 6 for x", &mut state)
+            .unwrap()
+            .unwrap();
+        assert!(
+            out.contains('1') && out.contains('2') && out.contains('3'),
+            "got {}",
+            out
+        );
+
+        assert!(run("solve x^2 = -1 for x", &mut state).is_err());
+        assert!(run("solve x + 1 = x for x", &mut state).is_err());
+        assert!(run("solve 2x = x + x for x", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_numeric() {
+        let rate = solve_value("solve pmt(r, 360, 300000) = 1800 for r");
+        assert!((rate - 0.0050058).abs() < 1e-6, "got {}", rate);
+
+        let root = solve_value("solve sqrt(x) + x = 6 for x");
+        assert!((root - 4.0).abs() < 1e-9, "got {}", root);
+
+        let root = solve_value("solve 2^x = 10 for x");
+        assert!((root - 10f64.log2()).abs() < 1e-9, "got {}", root);
+    }
+
+    #[test]
+    fn test_document_variables_and_assignment() {
+        let config = Config::default();
+        let mut state = AppState::builder(&config).build();
+        state
+            .variables
+            .write()
+            .unwrap()
+            .insert("price".to_string(), (Number::from(20i64), None));
+        let (_, add_hist, value, _) = run("n = solve price * n = 100 for n", &mut state)
+            .unwrap()
+            .unwrap();
+        assert!(add_hist);
+        assert_eq!(value.unwrap().to_f64(), 5.0);
+        assert_eq!(state.variables.read().unwrap()["n"].0.to_f64(), 5.0);
+
+        // A stored value doesn't get in the way of solving for the name again
+        let (_, _, value, _) = run("solve n + 1 = 3 for n", &mut state).unwrap().unwrap();
+        assert_eq!(value.unwrap().to_f64(), 2.0);
+        assert_eq!(state.variables.read().unwrap()["n"].0.to_f64(), 5.0);
+
+        // Solutions keep the unit that makes both sides agree, and are stored with it
+        let (out, _, value, unit) = run("d = solve 2 x = 10 km for x", &mut state)
+            .unwrap()
+            .unwrap();
+        assert_eq!(out, "5.00 km");
+        assert_eq!(value.unwrap().to_f64(), 5.0);
+        assert_eq!(unit.as_deref(), Some("km"));
+        assert_eq!(
+            state.variables.read().unwrap()["d"].1.as_deref(),
+            Some("km")
+        );
+        let (out, _, _, _) = run("solve 10 km / t = 5 km/h for t", &mut state)
+            .unwrap()
+            .unwrap();
+        assert_eq!(out, "2.00 h");
+        let (out, _, _, _) = run("solve x km = 10 km for x", &mut state)
+            .unwrap()
+            .unwrap();
+        assert_eq!(out, "10.00");
+        let err = run("solve x^2 = 16 m for x", &mut state).unwrap_err();
+        assert!(err.to_string().contains("same units"), "{}", err);
+
+        // Spans point into the line as written, before `2x` became `2*x`
+        let err = run("solve 2x + foo(x) = 1 for x", &mut state).unwrap_err();
+        assert_eq!(err.span.map(|s| s.start), Some(11));
+    }
+}
@@ -1,17 +1,23 @@
-use crate::evaluator::agents::PRIORITY_VARIABLE;
-use crate::evaluator::{preprocess_input, EvalContext};
-use crate::models::{Agent, AppState};
-use crate::prettify::prettify_number;
+use crate::evaluator::agents::{any_token, lock_error, PRIORITY_VARIABLE};
+use crate::evaluator::ast::ExprKind;
+use crate::evaluator::lexer::TokenKind;
+use crate::evaluator::{evaluate, parse, Diagnostic, EvalContext, EvaluatorError, Number};
+use crate::models::{Agent, AgentOutput, AppState, UserFunction};
+use std::collections::HashMap;
 
 pub struct VariableAgent;
 
 impl Agent for VariableAgent {
+    fn name(&self) -> &'static str {
+        "variable"
+    }
+
     fn priority(&self) -> i32 {
         PRIORITY_VARIABLE
     }
 
     fn can_handle(&self, input: &str, _state: &AppState) -> bool {
-        input.contains('=')
+        any_token(input, |kind| *kind == TokenKind::Equals)
     }
 
     fn process(
@@ -19,162 +25,196 @@ impl Agent for VariableAgent {
         input: &str,
         state: &mut AppState,
         config: &crate::config::Config,
-    ) -> Option<(String, bool, Option<f64>, Option<String>)> {
-        // Get original input to extract the original val_expr (before variable substitution)
-        // If not available, fall back to using the preprocessed input
-        let original_input = state.original_input.read().ok()?.clone();
-        let original_parts: Vec<&str> = if let Some(ref orig) = original_input {
-            orig.split('=').collect()
-        } else {
-            vec![]
-        };
-
-        let parts: Vec<&str> = input.split('=').collect();
-        if parts.len() == 2 {
-            let var = parts[0].trim();
-            let val_expr = parts[1].trim();
-
-            // Validate variable name: not empty, valid identifier, starts with letter/underscore
-            if var.is_empty()
-                || val_expr.is_empty()
-                || !var.chars().all(|c| c.is_alphanumeric() || c == '_')
-                || !var
-                    .chars()
-                    .next()
-                    .map(|c| c.is_alphabetic() || c == '_')
-                    .unwrap_or(false)
-            {
-                return None;
+    ) -> Result<Option<AgentOutput>, Diagnostic> {
+        let mut vars_guard = state.variables.write().map_err(lock_error)?;
+        let history_guard = state.history.read().map_err(lock_error)?;
+        let ctx = EvalContext::new(&mut vars_guard, &history_guard, state, config);
+
+        let expr = parse(input, &ctx)?;
+        if let ExprKind::Define { name, params, body } = &expr.kind {
+            let signature = format!("{}({})", name, params.join(", "));
+            // Block function definitions in display-only mode
+            if state.is_display_only {
+                return Ok(Some((signature, false, None, None)));
             }
-            // Use original val_expr if available, otherwise use preprocessed
-            let original_val_expr = if original_parts.len() == 2 {
-                original_parts[1].trim()
-            } else {
-                val_expr
-            };
 
-            let mut vars_guard = state.variables.write().ok()?;
-            let history_guard = state.history.read().ok()?;
-
-            let preprocessed = preprocess_input(val_expr, &vars_guard, config);
-
-            let mut ctx = EvalContext {
-                variables: &mut vars_guard,
-                history: &history_guard,
-                length_units: &config.length_units,
-                time_units: &config.time_units,
-                temperature_units: &config.temperature_units,
-                area_units: &config.area_units,
-                volume_units: &config.volume_units,
-                weight_units: &config.weight_units,
-                angular_units: &config.angular_units,
-                data_units: &config.data_units,
-                speed_units: &config.speed_units,
-                rates: &config.currencies,
-                custom_units: &config.custom_units,
+            track_line(state, input, name, &mut vars_guard);
+            vars_guard.remove(name);
+            if let Ok(mut uncertainties) = state.uncertainties.write() {
+                uncertainties.remove(name);
+            }
+            if let Ok(mut lists) = state.lists.write() {
+                lists.remove(name);
+            }
+            let function = UserFunction {
+                params: params.clone(),
+                body: (**body).clone(),
             };
+            state
+                .functions
+                .write()
+                .map_err(lock_error)?
+                .insert(name.clone(), function);
 
-            // Pass original expression for unit tracking
-            if let Ok(eval_result) = crate::evaluator::evaluate_expr_with_original(
-                &preprocessed,
-                &mut ctx,
-                Some(original_val_expr),
-            ) {
-                // Block variable assignments in display-only mode
-                if state.is_display_only {
-                    // Format the result for display but don't store it
-                    let formatted = prettify_number(eval_result.value);
-                    let unit_clone = eval_result.unit.clone();
-                    let val_str = if let Some(unit) = unit_clone.as_ref() {
-                        format!("{} {}", formatted, unit)
-                    } else {
-                        formatted
-                    };
-                    return Some((val_str, true, Some(eval_result.value), unit_clone));
-                }
-
-                // Check if we're evaluating a specific line (TUI mode)
-                if let Ok(current_line_guard) = state.current_line.read() {
-                    if let Some(line_idx) = *current_line_guard {
-                        drop(current_line_guard);
-
-                        // Check if this line previously created a different variable
-                        match state.line_variables.write() {
-                            Ok(mut line_vars) => {
-                                if let Some(old_var) = line_vars.get(&line_idx) {
-                                    // If the variable name changed, delete the old variable
-                                    if old_var != var {
-                                        vars_guard.remove(old_var);
-                                    }
-                                }
-                                // Track that this line now creates this variable
-                                line_vars.insert(line_idx, var.to_string());
-                            }
-                            Err(e) => {
-                                eprintln!("{}", crate::fl!(
-                                    "variable-tracking-update-failed",
-                                    "error" => &e.to_string()
-                                ));
-                            }
-                        }
-
-                        // Store the evaluated content for this line
-                        // Store the ORIGINAL user input for change detection,
-                        // not the preprocessed string (which already replaced variables).
-                        let original_line = state
-                            .original_input
-                            .read()
-                            .ok()
-                            .and_then(|o| o.clone())
-                            .unwrap_or_else(|| input.to_string());
-
-                        match state.line_content.write() {
-                            Ok(mut line_content) => {
-                                line_content.insert(line_idx, original_line);
-                            }
-                            Err(e) => {
-                                eprintln!(
-                                    "{}",
-                                    crate::fl!(
-                                        "variable-content-update-failed",
-                                        "error" => &e.to_string()
-                                    )
-                                );
-                            }
-                        }
-
-                        // Note: Don't publish VariableDeleted yet - wait until new var is inserted
-                        // to avoid rendering with incomplete state
-                    }
-                }
+            drop(vars_guard);
+            drop(history_guard);
+            state.publish_event(crate::evaluator::StateEvent::VariableChanged(name.clone()));
 
-                // Insert directly since we already have the lock
-                vars_guard.insert(
-                    var.to_string(),
-                    (eval_result.value, eval_result.unit.clone()),
-                );
+            return Ok(Some((signature, false, None, None)));
+        }
+        if let ExprKind::Assign { name: var, value } = &expr.kind {
+            let var = var.as_str();
+            let eval_result = evaluate(value, &ctx)?;
+            if eval_result.moment.is_some() {
+                return Err(Diagnostic::new(EvaluatorError::EvaluationError(
+                    crate::fl!("date-variable", "name" => var),
+                ))
+                .at(value.span));
+            }
+            let formatted = ctx.format(&eval_result);
+            // Block variable assignments in display-only mode
+            if state.is_display_only {
+                // Format the result for display but don't store it
+                return Ok(Some((
+                    formatted,
+                    true,
+                    Some(eval_result.value),
+                    eval_result.unit,
+                )));
+            }
 
-                // Drop locks before calling methods that might need them
+            track_line(state, input, var, &mut vars_guard);
+            // A name is either a variable, a list or a function, never more than one
+            if let Ok(mut functions) = state.functions.write() {
+                functions.remove(var);
+            }
+            if let Ok(mut uncertainties) = state.uncertainties.write() {
+                match eval_result.uncertainty {
+                    Some(error) if eval_result.items.is_none() => {
+                        uncertainties.insert(var.to_string(), error)
+                    }
+                    _ => uncertainties.remove(var),
+                };
+            }
+            if let Some(items) = &eval_result.items {
+                let items = items.iter().map(|i| (i.value, i.unit.clone())).collect();
+                vars_guard.remove(var);
+                state
+                    .lists
+                    .write()
+                    .map_err(lock_error)?
+                    .insert(var.to_string(), items);
                 drop(vars_guard);
                 drop(history_guard);
-
-                // Publish VariableChanged event which will invalidate entire cache
-                // This happens AFTER both delete and insert are complete
                 state.publish_event(crate::evaluator::StateEvent::VariableChanged(
                     var.to_string(),
                 ));
+                return Ok(Some((formatted, false, None, None)));
+            }
+            if let Ok(mut lists) = state.lists.write() {
+                lists.remove(var);
+            }
 
-                // Format the result string for display
-                let formatted = prettify_number(eval_result.value);
-                let unit_clone = eval_result.unit.clone();
-                let val_str = if let Some(unit) = unit_clone.as_ref() {
-                    format!("{} {}", formatted, unit)
-                } else {
-                    formatted
-                };
-                return Some((val_str, true, Some(eval_result.value), unit_clone));
+            // Insert directly since we already have the lock
+            vars_guard.insert(
+                var.to_string(),
+                (eval_result.value, eval_result.unit.clone()),
+            );
+
+            // Drop locks before calling methods that might need them
+            drop(vars_guard);
+            drop(history_guard);
+
+            // Publish VariableChanged event which will invalidate entire cache
+            // This happens AFTER both delete and insert are complete
+            state.publish_event(crate::evaluator::StateEvent::VariableChanged(
+                var.to_string(),
+            ));
+
+            return Ok(Some((
+                formatted,
+                true,
+                Some(eval_result.value),
+                eval_result.unit,
+            )));
+        }
+        Ok(None)
+    }
+}
+
+/// Record that the line being evaluated in the TUI defines `name`, dropping
+/// whatever variable, list or function the line defined before it was edited.
+pub(super) fn track_line(
+    state: &AppState,
+    input: &str,
+    name: &str,
+    vars_guard: &mut HashMap<String, (Number, Option<String>)>,
+) {
+    // Check if we're evaluating a specific line (TUI mode)
+    let line_idx = match state.current_line.read() {
+        Ok(current_line_guard) => match *current_line_guard {
+            Some(line_idx) => line_idx,
+            None => return,
+        },
+        Err(_) => return,
+    };
+
+    // Check if this line previously created a different variable, list or function
+    match state.line_variables.write() {
+        Ok(mut line_vars) => {
+            if let Some(old_var) = line_vars.get(&line_idx) {
+                // If the name changed, delete the old definition
+                if old_var != name {
+                    vars_guard.remove(old_var);
+                    if let Ok(mut uncertainties) = state.uncertainties.write() {
+                        uncertainties.remove(old_var);
+                    }
+                    if let Ok(mut functions) = state.functions.write() {
+                        functions.remove(old_var);
+                    }
+                    if let Ok(mut lists) = state.lists.write() {
+                        lists.remove(old_var);
+                    }
+                }
             }
+            // Track that this line now creates this name
+            line_vars.insert(line_idx, name.to_string());
+        }
+        Err(e) => {
+            eprintln!(
+                "{}",
+                crate::fl!(
+                    "variable-tracking-update-failed",
+                    "error" => &e.to_string()
+                )
+            );
+        }
+    }
+
+    // Store the evaluated content for this line
+    // Store the ORIGINAL user input (with comments) for change detection
+    let original_line = state
+        .original_input
+        .read()
+        .ok()
+        .and_then(|o| o.clone())
+        .unwrap_or_else(|| input.to_string());
+
+    match state.line_content.write() {
+        Ok(mut line_content) => {
+            line_content.insert(line_idx, original_line);
+        }
+        Err(e) => {
+            eprintln!(
+                "{}",
+                crate::fl!(
+                    "variable-content-update-failed",
+                    "error" => &e.to_string()
+                )
+            );
         }
-        None
     }
+
+    // Note: Don't publish VariableDeleted yet - wait until new var is inserted
+    // to avoid rendering with incomplete state
 }
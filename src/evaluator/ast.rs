@@ -0,0 +1,2025 @@
+//! Expression tree and parser.
+//!
+//! [`parse`] turns one line of input into an [`Expr`] using a Pratt parser
+//! over the tokens from [`crate::evaluator::lexer`]. Every node carries the
+//! [`Span`] of the source text it came from.
+//!
+//! Precedence, loosest first:
+//!
+//! | Level | Syntax |
+//! |-------|--------|
+//! | conversion | `x to unit`, `x in unit`, `x into unit`, `x as unit` |
+//! | percent-of | `10% of x`, `20% off x`, `15% on x`, `x is what % of y` (take the rest of the expression) |
+//! | interest | `x at 4% for 10 years`, `mortgage x at 6.5% over 30 years` |
+//! | bitwise or | `\|` |
+//! | bitwise xor | `xor` |
+//! | bitwise and | `&` |
+//! | shift | `<<`, `>>` |
+//! | range | `1..100` |
+//! | sum | `+`, `-`, operator words (`plus`, `minus`, `and`, ...) |
+//! | product | `*`, `/`, `%` (modulo), `mod` |
+//! | uncertainty | `±`, `+/-` (`5.0 ± 0.1 m * 2.0 ± 0.05 m`) |
+//! | prefix | unary `-`, `not`, bare function calls (`sqrt 16`) |
+//! | power | `^` (right-associative) |
+//! | postfix | `%` (percent), scales (`2k`) and units (`5 km`) |
+//!
+//! `% change from x to y`, `days between x and y` and `mortgage x at r over
+//! t` are whole expressions of their own. Durations before `ago`, `later`, `from`, `after` and
+//! `before` move a point in time (`3 days from today`), parsed like `today
+//! + 3 days`, and `in` after a point in time names a time zone.
+
+use chrono::Weekday;
+
+use crate::evaluator::calendar::weekday;
+use crate::evaluator::finance::compounding_periods;
+use crate::evaluator::lexer::{tokenize_with, LexError, Span, Token, TokenKind};
+use crate::i18n::NumberStyle;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// Numeric literal as plain decimal text, parsed according to the number mode.
+    Number(String),
+    /// Variable, constant or history keyword.
+    Ident(String),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// `10%`. On its own it is the fraction `0.1`; as the right operand of
+    /// `+ - * /` it applies to the left operand (`200 + 10%` is `220`).
+    Percent(Box<Expr>),
+    /// `10% of 200`
+    Of {
+        percent: Box<Expr>,
+        base: Box<Expr>,
+    },
+    /// `20% off 150`, `50 is what % of 200` and the other phrases of [`PercentOp`].
+    PercentOp {
+        op: PercentOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// A value with a unit attached: `5 km`, `$100`.
+    Quantity {
+        value: Box<Expr>,
+        unit: String,
+        /// Where the unit was written, for error reporting.
+        unit_span: Span,
+    },
+    /// Mixed-unit quantity written as a sum of quantities: `5 ft 3 in`.
+    Compound(Vec<Expr>),
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// `expr to unit`, or `expr to ft and in` with several targets for a
+    /// compound result.
+    Convert {
+        expr: Box<Expr>,
+        targets: Vec<String>,
+    },
+    /// `[1, 2, 3]`, or `1, 2, 3` as a whole line or assigned value.
+    List(Vec<Expr>),
+    /// `1..100`: the integers from one bound to the other, both included.
+    Range {
+        from: Box<Expr>,
+        to: Box<Expr>,
+    },
+    /// `name = expr`
+    Assign {
+        name: String,
+        value: Box<Expr>,
+    },
+    /// `name(params) = body`, a function defined in the document.
+    Define {
+        name: String,
+        params: Vec<String>,
+        body: Box<Expr>,
+    },
+    /// A point in time: `now`, `next monday`, `2025-01-31`.
+    Date(DateWord),
+    /// `now in Tokyo`: a point in time shown in another time zone.
+    InZone {
+        expr: Box<Expr>,
+        zone: String,
+        /// Where the zone was written, for error reporting.
+        zone_span: Span,
+    },
+    /// `days between 2025-01-01 and 2025-01-31`
+    DaysBetween {
+        from: Box<Expr>,
+        to: Box<Expr>,
+    },
+    /// `mortgage 300k at 6.5% over 30 years` or `$5000 at 4% for 10 years
+    /// compounded monthly`: a yearly `rate` on `principal` over `term`.
+    Interest {
+        principal: Box<Expr>,
+        rate: Box<Expr>,
+        term: Box<Expr>,
+        plan: InterestPlan,
+    },
+}
+
+/// What an interest phrase works out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterestPlan {
+    /// The monthly payment that pays off a loan
+    Loan,
+    /// The balance after interest compounded `per_year` times a year, or
+    /// continuously if `None`
+    Compound { per_year: Option<f64> },
+}
+
+/// Words and literals naming a point in time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateWord {
+    Now,
+    /// `time`, as in `time in Tokyo`: the time of day with its offset
+    Time,
+    Today,
+    Tomorrow,
+    Yesterday,
+    /// `next monday` (`direction` 1), `last friday` (-1), `this sunday` (0)
+    Weekday {
+        day: Weekday,
+        direction: i8,
+    },
+    /// `2025-01-31` or `2025-01-31 13:20:00`
+    Literal(String),
+}
+
+/// Percentage phrases relating a left and a right operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PercentOp {
+    /// `20% off 150`: the right operand less the percentage
+    Off,
+    /// `15% on 200`: the right operand plus the percentage
+    On,
+    /// `50 is what % of 200`, `50 as a % of 200`
+    WhatPercent,
+    /// `% change from 80 to 100`
+    Change,
+    /// `5% of what is 20`: the whole the percentage is taken from
+    OfWhat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    /// Bitwise complement
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    /// Value with an uncertainty: `5.0 ± 0.1`
+    PlusMinus,
+}
+
+impl BinaryOp {
+    /// Left and right binding power.
+    fn binding_power(self) -> (u8, u8) {
+        match self {
+            BinaryOp::BitOr => (BP_BIT_OR, BP_BIT_OR + 1),
+            BinaryOp::BitXor => (BP_BIT_XOR, BP_BIT_XOR + 1),
+            BinaryOp::BitAnd => (BP_BIT_AND, BP_BIT_AND + 1),
+            BinaryOp::Shl | BinaryOp::Shr => (BP_SHIFT, BP_SHIFT + 1),
+            BinaryOp::Add | BinaryOp::Sub => (BP_SUM, BP_SUM + 1),
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => (BP_PRODUCT, BP_PRODUCT + 1),
+            BinaryOp::PlusMinus => (BP_UNCERTAINTY, BP_UNCERTAINTY + 1),
+            BinaryOp::Pow => (BP_POWER, BP_POWER - 1),
+        }
+    }
+
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "+" => Some(BinaryOp::Add),
+            "-" => Some(BinaryOp::Sub),
+            "*" => Some(BinaryOp::Mul),
+            "/" => Some(BinaryOp::Div),
+            "%" => Some(BinaryOp::Rem),
+            "^" => Some(BinaryOp::Pow),
+            "&" => Some(BinaryOp::BitAnd),
+            "|" => Some(BinaryOp::BitOr),
+            "xor" => Some(BinaryOp::BitXor),
+            "<<" => Some(BinaryOp::Shl),
+            ">>" => Some(BinaryOp::Shr),
+            "±" => Some(BinaryOp::PlusMinus),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Pow => "^",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "xor",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::PlusMinus => "±",
+        }
+    }
+}
+
+impl Expr {
+    fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// True if this node or any node below it matches `pred`.
+    pub fn any(&self, pred: &impl Fn(&ExprKind) -> bool) -> bool {
+        if pred(&self.kind) {
+            return true;
+        }
+        match &self.kind {
+            ExprKind::Number(_) | ExprKind::Ident(_) => false,
+            ExprKind::Unary { operand, .. } => operand.any(pred),
+            ExprKind::Binary { lhs, rhs, .. } => lhs.any(pred) || rhs.any(pred),
+            ExprKind::Percent(inner) => inner.any(pred),
+            ExprKind::Of { percent, base } => percent.any(pred) || base.any(pred),
+            ExprKind::PercentOp { lhs, rhs, .. } => lhs.any(pred) || rhs.any(pred),
+            ExprKind::Quantity { value, .. } => value.any(pred),
+            ExprKind::Compound(parts) => parts.iter().any(|p| p.any(pred)),
+            ExprKind::Call { args, .. } => args.iter().any(|a| a.any(pred)),
+            ExprKind::Convert { expr, .. } => expr.any(pred),
+            ExprKind::List(items) => items.iter().any(|i| i.any(pred)),
+            ExprKind::Range { from, to } => from.any(pred) || to.any(pred),
+            ExprKind::Assign { value, .. } => value.any(pred),
+            ExprKind::Define { body, .. } => body.any(pred),
+            ExprKind::Date(_) => false,
+            ExprKind::InZone { expr, .. } => expr.any(pred),
+            ExprKind::DaysBetween { from, to } => from.any(pred) || to.any(pred),
+            ExprKind::Interest {
+                principal,
+                rate,
+                term,
+                ..
+            } => principal.any(pred) || rate.any(pred) || term.any(pred),
+        }
+    }
+
+    /// True if the expression is a point in time: a date, or a date moved
+    /// by a duration.
+    pub fn is_moment(&self) -> bool {
+        match &self.kind {
+            ExprKind::Date(_) | ExprKind::InZone { .. } => true,
+            ExprKind::Binary {
+                op: BinaryOp::Add | BinaryOp::Sub,
+                lhs,
+                ..
+            } => lhs.is_moment(),
+            _ => false,
+        }
+    }
+
+    /// True for lines that define a name: `x = 5` or `f(x) = x^2`.
+    pub fn is_definition(&self) -> bool {
+        matches!(self.kind, ExprKind::Assign { .. } | ExprKind::Define { .. })
+    }
+}
+
+/// Names the parser has to tell apart. Implemented by the evaluation context,
+/// which knows the configured units, scales, operator words and functions.
+pub trait Vocabulary {
+    /// Canonical spelling of a unit (currency words become ISO codes), or
+    /// `None` if `name` is not a known unit.
+    fn unit(&self, name: &str) -> Option<String>;
+    /// Multiplier for a scale word such as `k` or `million`.
+    fn scale(&self, name: &str) -> Option<f64>;
+    /// Operator symbol for an operator word or phrase such as `divided by`.
+    fn operator(&self, phrase: &str) -> Option<&str>;
+    fn is_function(&self, name: &str) -> bool;
+    /// True if `name` is a named constant such as `golden ratio`.
+    fn is_constant(&self, name: &str) -> bool;
+    /// True if the unit `unit`, as [`unit`](Self::unit) spells it, is a currency.
+    fn is_currency(&self, unit: &str) -> bool;
+    /// How numbers are written: decimal point or decimal comma.
+    fn number_style(&self) -> NumberStyle;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl From<LexError> for ParseError {
+    fn from(err: LexError) -> Self {
+        Self {
+            message: err.message,
+            span: err.span,
+        }
+    }
+}
+
+const BP_CONVERT: u8 = 1;
+const BP_OF: u8 = 2;
+const BP_BIT_OR: u8 = 3;
+const BP_BIT_XOR: u8 = 5;
+const BP_BIT_AND: u8 = 7;
+const BP_SHIFT: u8 = 8;
+const BP_RANGE: u8 = 9;
+const BP_SUM: u8 = 10;
+const BP_PRODUCT: u8 = 20;
+const BP_UNCERTAINTY: u8 = 25;
+const BP_PREFIX: u8 = 30;
+const BP_POWER: u8 = 40;
+const BP_POSTFIX: u8 = 50;
+
+const CONVERSION_KEYWORDS: &[&str] = &["to", "in", "into", "as"];
+
+/// Words joining the parts of an interest phrase.
+const INTEREST_WORDS: &[&str] = &["at", "for", "over", "compounded"];
+
+/// Longest run of words tried when matching multi-word units ("miles per hour").
+const MAX_UNIT_WORDS: usize = 4;
+
+/// True if `word` introduces a unit conversion.
+pub fn is_conversion_keyword(word: &str) -> bool {
+    CONVERSION_KEYWORDS.contains(&word.to_lowercase().as_str())
+}
+
+/// Value of an English number word (`one` .. `ninety`).
+fn word_number(word: &str) -> Option<u32> {
+    let value = match word.to_lowercase().as_str() {
+        "zero" => 0,
+        "one" => 1,
+        "two" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "seven" => 7,
+        "eight" => 8,
+        "nine" => 9,
+        "ten" => 10,
+        "eleven" => 11,
+        "twelve" => 12,
+        "thirteen" => 13,
+        "fourteen" => 14,
+        "fifteen" => 15,
+        "sixteen" => 16,
+        "seventeen" => 17,
+        "eighteen" => 18,
+        "nineteen" => 19,
+        "twenty" => 20,
+        "thirty" => 30,
+        "forty" => 40,
+        "fifty" => 50,
+        "sixty" => 60,
+        "seventy" => 70,
+        "eighty" => 80,
+        "ninety" => 90,
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Parse one line of input into an expression tree.
+pub fn parse(input: &str, vocab: &dyn Vocabulary) -> Result<Expr, ParseError> {
+    let tokens = tokenize_with(input, vocab.number_style())?;
+    let mut parser = Parser {
+        source: input,
+        tokens,
+        pos: 0,
+        vocab,
+        defining: None,
+    };
+    parser.parse_line()
+}
+
+fn percent_op(op: PercentOp, lhs: Expr, rhs: Expr) -> Expr {
+    let span = lhs.span.to(rhs.span);
+    Expr::new(
+        ExprKind::PercentOp {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+        span,
+    )
+}
+
+enum Infix {
+    Binary(BinaryOp),
+    /// `ago`, `later`, or `from`, `after` and `before` a point in time
+    Relative {
+        subtract: bool,
+        from_now: bool,
+    },
+    Range,
+    Percent,
+    Of,
+    /// `at` a rate of interest
+    Interest,
+    PercentOp(PercentOp),
+    Convert,
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    vocab: &'a dyn Vocabulary,
+    /// Name of the function whose body is being parsed, callable from it.
+    defining: Option<String>,
+}
+
+impl Parser<'_> {
+    fn parse_line(&mut self) -> Result<Expr, ParseError> {
+        if self.tokens.is_empty() {
+            return Err(self.unexpected_end());
+        }
+
+        let expr = match (self.peek_kind(0), self.peek_kind(1)) {
+            (Some(TokenKind::Ident(name)), Some(TokenKind::Equals)) => {
+                let name = name.clone();
+                let start = self.tokens[0].span;
+                self.pos = 2;
+                let value = self.parse_items()?;
+                let span = start.to(value.span);
+                Expr::new(
+                    ExprKind::Assign {
+                        name,
+                        value: Box::new(value),
+                    },
+                    span,
+                )
+            }
+            (Some(TokenKind::Ident(name)), Some(TokenKind::LParen)) => {
+                match self.definition_params() {
+                    Some((params, width)) => {
+                        let name = name.clone();
+                        let start = self.tokens[0].span;
+                        self.pos = width;
+                        self.defining = Some(name.clone());
+                        let body = self.parse_items()?;
+                        let span = start.to(body.span);
+                        Expr::new(
+                            ExprKind::Define {
+                                name,
+                                params,
+                                body: Box::new(body),
+                            },
+                            span,
+                        )
+                    }
+                    None => self.parse_items()?,
+                }
+            }
+            _ => self.parse_items()?,
+        };
+
+        match self.tokens.get(self.pos) {
+            Some(token) => Err(self.unexpected(token)),
+            None => Ok(expr),
+        }
+    }
+
+    /// Parameter names of a `name(a, b) =` head at the start of the line and
+    /// the number of tokens it takes, or `None` if the line is not a definition.
+    fn definition_params(&self) -> Option<(Vec<String>, usize)> {
+        let mut params = Vec::new();
+        let mut pos = 2;
+        if self.peek_kind(pos)? != &TokenKind::RParen {
+            loop {
+                match self.peek_kind(pos)? {
+                    TokenKind::Ident(param) if !params.contains(param) => {
+                        params.push(param.clone())
+                    }
+                    _ => return None,
+                }
+                match self.peek_kind(pos + 1)? {
+                    TokenKind::Comma => pos += 2,
+                    TokenKind::RParen => break,
+                    _ => return None,
+                }
+            }
+            pos += 1;
+        }
+        (self.peek_kind(pos + 1)? == &TokenKind::Equals).then_some((params, pos + 2))
+    }
+
+    /// An expression, or a comma-separated list of them (`12, 15, 9`).
+    fn parse_items(&mut self) -> Result<Expr, ParseError> {
+        let first = self.parse_expr(0)?;
+        if !matches!(self.peek_kind(0), Some(TokenKind::Comma)) {
+            return Ok(first);
+        }
+        let mut items = vec![first];
+        while matches!(self.peek_kind(0), Some(TokenKind::Comma)) {
+            self.pos += 1;
+            items.push(self.parse_expr(0)?);
+        }
+        let span = items[0].span.to(items[items.len() - 1].span);
+        // `12,50 EUR` with a decimal point: one amount, or `12` and `50 EUR`
+        let has_unit = |item: &Expr| matches!(item.kind, ExprKind::Quantity { .. });
+        if let [rest @ .., last] = items.as_slice() {
+            let currency_last = match &last.kind {
+                ExprKind::Quantity { unit, .. } => self.vocab.is_currency(unit),
+                _ => false,
+            };
+            if currency_last && !rest.iter().any(has_unit) {
+                return Err(ParseError {
+                    message: crate::fl!(
+                        "ambiguous-currency-list",
+                        "text" => &self.source[span.start..span.end],
+                        "decimal" => self.vocab.number_style().decimal.to_string()
+                    ),
+                    span,
+                });
+            }
+        }
+        Ok(Expr::new(ExprKind::List(items), span))
+    }
+
+    fn parse_expr(&mut self, min_bp: u8) -> Result<Expr, ParseError> {
+        let mut lhs = self.parse_prefix()?;
+
+        while let Some((infix, width)) = self.peek_infix() {
+            match infix {
+                Infix::Convert => {
+                    if BP_CONVERT < min_bp {
+                        break;
+                    }
+                    let keyword = self.tokens[self.pos].clone();
+                    self.pos += width;
+                    if lhs.is_moment() {
+                        let (zone, zone_span) = self.parse_zone(&keyword)?;
+                        let span = lhs.span.to(zone_span);
+                        lhs = Expr::new(
+                            ExprKind::InZone {
+                                expr: Box::new(lhs),
+                                zone,
+                                zone_span,
+                            },
+                            span,
+                        );
+                        continue;
+                    }
+                    let (targets, target_span) = self.parse_targets(&keyword)?;
+                    let span = lhs.span.to(target_span);
+                    lhs = Expr::new(
+                        ExprKind::Convert {
+                            expr: Box::new(lhs),
+                            targets,
+                        },
+                        span,
+                    );
+                }
+                Infix::Of => {
+                    if BP_OF < min_bp {
+                        break;
+                    }
+                    self.pos += width;
+                    // 5% of what is 20
+                    if self.is_word(self.pos, "what") && self.is_word(self.pos + 1, "is") {
+                        self.pos += 2;
+                        let rhs = self.parse_expr(BP_OF + 1)?;
+                        lhs = percent_op(PercentOp::OfWhat, lhs, rhs);
+                        continue;
+                    }
+                    let base = self.parse_expr(BP_OF + 1)?;
+                    let span = lhs.span.to(base.span);
+                    lhs = Expr::new(
+                        ExprKind::Of {
+                            percent: Box::new(lhs),
+                            base: Box::new(base),
+                        },
+                        span,
+                    );
+                }
+                Infix::Interest => {
+                    if BP_OF < min_bp {
+                        break;
+                    }
+                    self.pos += width;
+                    let plan = InterestPlan::Compound {
+                        per_year: Some(1.0),
+                    };
+                    lhs = self.parse_interest(lhs, plan)?;
+                }
+                Infix::PercentOp(op) => {
+                    // `off` and `on` only follow a percentage
+                    let needs_percent = matches!(op, PercentOp::Off | PercentOp::On);
+                    if BP_OF < min_bp
+                        || (needs_percent && !matches!(lhs.kind, ExprKind::Percent(_)))
+                    {
+                        break;
+                    }
+                    self.pos += width;
+                    let rhs = self.parse_expr(BP_OF + 1)?;
+                    lhs = percent_op(op, lhs, rhs);
+                }
+                Infix::Relative { subtract, from_now } => {
+                    if BP_OF < min_bp {
+                        break;
+                    }
+                    let keyword = self.tokens[self.pos].span;
+                    self.pos += width;
+                    // `3 days ago` is `now - 3 days`, `3 days from today` is `today + 3 days`
+                    let base = if from_now {
+                        Expr::new(ExprKind::Date(DateWord::Now), keyword)
+                    } else {
+                        self.parse_expr(BP_OF + 1)?
+                    };
+                    let op = if subtract {
+                        BinaryOp::Sub
+                    } else {
+                        BinaryOp::Add
+                    };
+                    let span = lhs.span.to(base.span);
+                    lhs = Expr::new(
+                        ExprKind::Binary {
+                            op,
+                            lhs: Box::new(base),
+                            rhs: Box::new(lhs),
+                        },
+                        span,
+                    );
+                }
+                Infix::Percent => {
+                    if BP_POSTFIX < min_bp {
+                        break;
+                    }
+                    let span = lhs.span.to(self.tokens[self.pos].span);
+                    self.pos += width;
+                    lhs = Expr::new(ExprKind::Percent(Box::new(lhs)), span);
+                }
+                Infix::Range => {
+                    if BP_RANGE < min_bp {
+                        break;
+                    }
+                    self.pos += width;
+                    let to = self.parse_expr(BP_RANGE + 1)?;
+                    let span = lhs.span.to(to.span);
+                    lhs = Expr::new(
+                        ExprKind::Range {
+                            from: Box::new(lhs),
+                            to: Box::new(to),
+                        },
+                        span,
+                    );
+                }
+                Infix::Binary(op) => {
+                    let (left_bp, right_bp) = op.binding_power();
+                    if left_bp < min_bp {
+                        break;
+                    }
+                    self.pos += width;
+                    let rhs = self.parse_expr(right_bp)?;
+                    let span = lhs.span.to(rhs.span);
+                    lhs = Expr::new(
+                        ExprKind::Binary {
+                            op,
+                            lhs: Box::new(lhs),
+                            rhs: Box::new(rhs),
+                        },
+                        span,
+                    );
+                }
+            }
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expr, ParseError> {
+        let Some(token) = self.tokens.get(self.pos).cloned() else {
+            return Err(self.unexpected_end());
+        };
+
+        match &token.kind {
+            TokenKind::Number(text) => {
+                self.pos += 1;
+                let number = Expr::new(ExprKind::Number(text.clone()), token.span);
+                let number = self.parse_mixed_number(number);
+                let quantity = self.parse_number_suffix(number)?;
+                self.parse_compound_quantity(quantity)
+            }
+            TokenKind::Date(text) => {
+                self.pos += 1;
+                Ok(Expr::new(
+                    ExprKind::Date(DateWord::Literal(text.clone())),
+                    token.span,
+                ))
+            }
+            TokenKind::Currency(code) => {
+                // Prefix symbol: $100, €1.5k
+                self.pos += 1;
+                let value = self.parse_expr(BP_POSTFIX)?;
+                let mut span = token.span.to(value.span);
+                let mut unit = code.to_string();
+                // A price per unit: $10/kg, €2 per liter
+                if let Some((per_unit, width)) = self.price_unit(code) {
+                    span = span.to(self.tokens[self.pos + width - 1].span);
+                    unit = per_unit;
+                    self.pos += width;
+                }
+                Ok(Expr::new(
+                    ExprKind::Quantity {
+                        value: Box::new(value),
+                        unit,
+                        unit_span: token.span,
+                    },
+                    span,
+                ))
+            }
+            TokenKind::Percent
+                if self.is_word(self.pos + 1, "change") && self.is_word(self.pos + 2, "from") =>
+            {
+                // % change from 80 to 100
+                self.pos += 3;
+                let from = self.parse_expr(BP_CONVERT + 1)?;
+                self.expect_word("to")?;
+                let to = self.parse_expr(BP_CONVERT + 1)?;
+                let mut change = percent_op(PercentOp::Change, from, to);
+                change.span = token.span.to(change.span);
+                Ok(change)
+            }
+            TokenKind::Minus => {
+                self.pos += 1;
+                let operand = self.parse_expr(BP_PREFIX)?;
+                let span = token.span.to(operand.span);
+                Ok(Expr::new(
+                    ExprKind::Unary {
+                        op: UnaryOp::Neg,
+                        operand: Box::new(operand),
+                    },
+                    span,
+                ))
+            }
+            TokenKind::Plus => {
+                self.pos += 1;
+                self.parse_expr(BP_PREFIX)
+            }
+            TokenKind::LParen => {
+                self.pos += 1;
+                let mut inner = self.parse_expr(0)?;
+                match self.tokens.get(self.pos) {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        span,
+                    }) => {
+                        inner.span = token.span.to(*span);
+                        self.pos += 1;
+                    }
+                    Some(other) => return Err(self.unexpected(other)),
+                    None => return Err(self.unexpected_end()),
+                }
+                self.parse_known_unit_suffix(inner)
+            }
+            TokenKind::LBracket => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                if !matches!(self.peek_kind(0), Some(TokenKind::RBracket)) {
+                    loop {
+                        items.push(self.parse_expr(0)?);
+                        if matches!(self.peek_kind(0), Some(TokenKind::Comma)) {
+                            self.pos += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                let end = match self.tokens.get(self.pos) {
+                    Some(Token {
+                        kind: TokenKind::RBracket,
+                        span,
+                    }) => *span,
+                    Some(other) => return Err(self.unexpected(other)),
+                    None => return Err(self.unexpected_end()),
+                };
+                self.pos += 1;
+                Ok(Expr::new(ExprKind::List(items), token.span.to(end)))
+            }
+            TokenKind::Ident(name)
+                if name.eq_ignore_ascii_case("not") && self.starts_operand(self.pos + 1) =>
+            {
+                self.pos += 1;
+                let operand = self.parse_expr(BP_PREFIX)?;
+                let span = token.span.to(operand.span);
+                Ok(Expr::new(
+                    ExprKind::Unary {
+                        op: UnaryOp::Not,
+                        operand: Box::new(operand),
+                    },
+                    span,
+                ))
+            }
+            TokenKind::Ident(name)
+                if (name.eq_ignore_ascii_case("mortgage") || name.eq_ignore_ascii_case("loan"))
+                    && (self.starts_operand(self.pos + 1) || self.is_word(self.pos + 1, "of")) =>
+            {
+                // mortgage 300k at 6.5% over 30 years, loan of 12k at 5% for 2 years
+                self.pos += 1;
+                if self.is_word(self.pos, "of") {
+                    self.pos += 1;
+                }
+                let principal = self.parse_expr(BP_OF + 1)?;
+                self.expect_word("at")?;
+                let mut loan = self.parse_interest(principal, InterestPlan::Loan)?;
+                loan.span = token.span.to(loan.span);
+                Ok(loan)
+            }
+            TokenKind::Ident(name) => {
+                if let Some(date) = self.parse_date(&token)? {
+                    return Ok(date);
+                }
+                if let Some(reference) = self.parse_line_reference(&token) {
+                    return self.parse_known_unit_suffix(reference);
+                }
+                if let Some((constant, width)) = self.constant_phrase(self.pos) {
+                    let span = token.span.to(self.tokens[self.pos + width - 1].span);
+                    self.pos += width;
+                    let ident = Expr::new(ExprKind::Ident(constant), span);
+                    return self.parse_known_unit_suffix(ident);
+                }
+                if let Some(value) = word_number(name) {
+                    self.pos += 1;
+                    let number = Expr::new(ExprKind::Number(value.to_string()), token.span);
+                    return self.parse_number_suffix(number);
+                }
+                // `f(3)` is a call even with no `f`, so it's reported as unknown
+                let call_follows = self.tokens.get(self.pos + 1).is_some_and(|next| {
+                    next.kind == TokenKind::LParen && next.span.start == token.span.end
+                });
+                if self.vocab.is_function(name)
+                    || self.defining.as_ref() == Some(name)
+                    || call_follows
+                {
+                    if let Some(call) = self.parse_call(&token, name)? {
+                        return Ok(call);
+                    }
+                }
+                self.pos += 1;
+                let ident = Expr::new(ExprKind::Ident(name.clone()), token.span);
+                self.parse_known_unit_suffix(ident)
+            }
+            _ => Err(self.unexpected(&token)),
+        }
+    }
+
+    /// Parse a word naming a point in time (`today`, `next monday`), `time in
+    /// Tokyo` or `days between a and b`. Returns `None` (without consuming
+    /// anything) for other words.
+    fn parse_date(&mut self, token: &Token) -> Result<Option<Expr>, ParseError> {
+        let TokenKind::Ident(word) = &token.kind else {
+            return Ok(None);
+        };
+        if let Some((date, width)) = self.date_word(self.pos) {
+            let span = token.span.to(self.tokens[self.pos + width - 1].span);
+            self.pos += width;
+            return Ok(Some(Expr::new(ExprKind::Date(date), span)));
+        }
+
+        let place_follows = ["in", "at", "for"]
+            .iter()
+            .any(|keyword| self.is_word(self.pos + 1, keyword))
+            && matches!(self.peek_kind(2), Some(TokenKind::Ident(_)));
+        if word.eq_ignore_ascii_case("time") && place_follows {
+            let keyword = self.tokens[self.pos + 1].clone();
+            self.pos += 2;
+            let (zone, zone_span) = self.parse_zone(&keyword)?;
+            let time = Expr::new(ExprKind::Date(DateWord::Time), token.span);
+            return Ok(Some(Expr::new(
+                ExprKind::InZone {
+                    expr: Box::new(time),
+                    zone,
+                    zone_span,
+                },
+                token.span.to(zone_span),
+            )));
+        }
+
+        if word.eq_ignore_ascii_case("days") && self.is_word(self.pos + 1, "between") {
+            self.pos += 2;
+            // `and` is also an operator word, so the first date stops before it
+            let from = self.parse_expr(BP_SUM + 1)?;
+            self.expect_word("and")?;
+            let to = self.parse_expr(BP_CONVERT + 1)?;
+            let span = token.span.to(to.span);
+            return Ok(Some(Expr::new(
+                ExprKind::DaysBetween {
+                    from: Box::new(from),
+                    to: Box::new(to),
+                },
+                span,
+            )));
+        }
+        Ok(None)
+    }
+
+    /// The rest of an interest phrase after `at`: the rate, `for` or `over`
+    /// and the term, then for compound interest an optional `compounded
+    /// monthly` (yearly if left out).
+    fn parse_interest(&mut self, principal: Expr, plan: InterestPlan) -> Result<Expr, ParseError> {
+        let rate = self.parse_expr(BP_OF + 1)?;
+        if self.is_word(self.pos, "over") {
+            self.pos += 1;
+        } else {
+            self.expect_word("for")?;
+        }
+        let term = self.parse_expr(BP_OF + 1)?;
+        let mut span = principal.span.to(term.span);
+        let mut plan = plan;
+        if matches!(plan, InterestPlan::Compound { .. }) && self.is_word(self.pos, "compounded") {
+            let per_year = match self.tokens.get(self.pos + 1) {
+                Some(Token {
+                    kind: TokenKind::Ident(word),
+                    span: word_span,
+                }) => match compounding_periods(&word.to_lowercase()) {
+                    Some(per_year) => {
+                        span = span.to(*word_span);
+                        per_year
+                    }
+                    None => return Err(self.unexpected(&self.tokens[self.pos + 1])),
+                },
+                Some(other) => return Err(self.unexpected(other)),
+                None => return Err(self.unexpected_end()),
+            };
+            self.pos += 2;
+            plan = InterestPlan::Compound { per_year };
+        }
+        Ok(Expr::new(
+            ExprKind::Interest {
+                principal: Box::new(principal),
+                rate: Box::new(rate),
+                term: Box::new(term),
+                plan,
+            },
+            span,
+        ))
+    }
+
+    /// Point in time named at `pos`, with the number of tokens it spans.
+    fn date_word(&self, pos: usize) -> Option<(DateWord, usize)> {
+        let word = match self.tokens.get(pos).map(|t| &t.kind) {
+            Some(TokenKind::Ident(word)) => word.to_lowercase(),
+            Some(TokenKind::Date(text)) => return Some((DateWord::Literal(text.clone()), 1)),
+            _ => return None,
+        };
+        let date = match word.as_str() {
+            "now" => DateWord::Now,
+            "today" => DateWord::Today,
+            "tomorrow" => DateWord::Tomorrow,
+            "yesterday" => DateWord::Yesterday,
+            "next" | "last" | "this" => {
+                let day = match self.tokens.get(pos + 1).map(|t| &t.kind) {
+                    Some(TokenKind::Ident(day)) => weekday(day)?,
+                    _ => return None,
+                };
+                let direction = match word.as_str() {
+                    "next" => 1,
+                    "last" => -1,
+                    _ => 0,
+                };
+                return Some((DateWord::Weekday { day, direction }, 2));
+            }
+            _ => return None,
+        };
+        Some((date, 1))
+    }
+
+    /// Time zone name after `in`: an abbreviation, a city of one or more
+    /// words or an IANA name (`Europe/Berlin`), up to the end of the line.
+    fn parse_zone(&mut self, keyword: &Token) -> Result<(String, Span), ParseError> {
+        let start = self.pos;
+        loop {
+            match self.peek_kind(0) {
+                Some(TokenKind::Ident(_)) => self.pos += 1,
+                Some(TokenKind::Slash)
+                    if self.pos > start
+                        && matches!(self.peek_kind(1), Some(TokenKind::Ident(_))) =>
+                {
+                    self.pos += 2
+                }
+                _ => break,
+            }
+        }
+        if self.pos == start {
+            return match self.tokens.get(self.pos) {
+                Some(other) => Err(self.unexpected(other)),
+                None => Err(ParseError {
+                    message: crate::fl!(
+                        "missing-time-zone",
+                        "keyword" => &self.source[keyword.span.start..keyword.span.end]
+                    ),
+                    span: keyword.span,
+                }),
+            };
+        }
+        let span = self.tokens[start].span.to(self.tokens[self.pos - 1].span);
+        Ok((self.source[span.start..span.end].to_string(), span))
+    }
+
+    /// `ago` and `later` after a duration, or `from`, `after` and `before`
+    /// followed by a point in time, with the number of tokens they span.
+    fn relative_phrase(&self, pos: usize) -> Option<(Infix, usize)> {
+        let relative = |subtract, from_now| Some((Infix::Relative { subtract, from_now }, 1));
+        let date_follows = self.date_word(pos + 1).is_some();
+        if self.is_word(pos, "ago") {
+            relative(true, true)
+        } else if self.is_word(pos, "later") {
+            relative(false, true)
+        } else if (self.is_word(pos, "from") || self.is_word(pos, "after")) && date_follows {
+            relative(false, false)
+        } else if self.is_word(pos, "before") && date_follows {
+            relative(true, false)
+        } else {
+            None
+        }
+    }
+
+    /// Parse `line 3` as the reference `line3`, and `prev line`, `grand total`
+    /// and `section avg`, as single identifiers spanning both words.
+    fn parse_line_reference(&mut self, token: &Token) -> Option<Expr> {
+        let name = match self.tokens.get(self.pos + 1) {
+            Some(Token {
+                kind: TokenKind::Number(digits),
+                ..
+            }) if self.is_word(self.pos, "line") && digits.bytes().all(|b| b.is_ascii_digit()) => {
+                format!("line{digits}")
+            }
+            Some(_) if self.is_word(self.pos, "prev") && self.is_word(self.pos + 1, "line") => {
+                "prev line".to_string()
+            }
+            Some(_) if self.is_word(self.pos, "grand") && self.is_word(self.pos + 1, "total") => {
+                "grand total".to_string()
+            }
+            Some(_)
+                if self.is_word(self.pos, "section")
+                    && (self.is_word(self.pos + 1, "avg")
+                        || self.is_word(self.pos + 1, "average")) =>
+            {
+                "section avg".to_string()
+            }
+            _ => return None,
+        };
+        let span = token.span.to(self.tokens[self.pos + 1].span);
+        self.pos += 2;
+        Some(Expr::new(ExprKind::Ident(name), span))
+    }
+
+    /// Parse `name(args...)` or a bare `name arg`. Returns `None` (without
+    /// consuming anything) if `name` is not followed by an argument.
+    fn parse_call(&mut self, token: &Token, name: &str) -> Result<Option<Expr>, ParseError> {
+        match self.peek_kind(1) {
+            Some(TokenKind::LParen) => {
+                self.pos += 2;
+                let mut args = Vec::new();
+                if !matches!(self.peek_kind(0), Some(TokenKind::RParen)) {
+                    loop {
+                        args.push(self.parse_expr(0)?);
+                        if matches!(self.peek_kind(0), Some(TokenKind::Comma)) {
+                            self.pos += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                let end = match self.tokens.get(self.pos) {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        span,
+                    }) => *span,
+                    Some(other) => return Err(self.unexpected(other)),
+                    None => return Err(self.unexpected_end()),
+                };
+                self.pos += 1;
+                Ok(Some(Expr::new(
+                    ExprKind::Call {
+                        name: name.to_string(),
+                        args,
+                    },
+                    token.span.to(end),
+                )))
+            }
+            Some(_) if self.starts_operand(self.pos + 1) => {
+                self.pos += 1;
+                let arg = self.parse_expr(BP_PREFIX)?;
+                let span = token.span.to(arg.span);
+                Ok(Some(Expr::new(
+                    ExprKind::Call {
+                        name: name.to_string(),
+                        args: vec![arg],
+                    },
+                    span,
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Fractions written without spaces around the slash bind like a single
+    /// number: `2 3/4` is the mixed number `2 + 3/4`, and `3/8 in` is
+    /// `(3/8) in` rather than `3 / (8 in)`.
+    fn parse_mixed_number(&mut self, whole: Expr) -> Expr {
+        if self.is_fraction(self.pos) && self.is_integer(self.pos - 1) {
+            let fraction = self.fraction(self.pos);
+            let span = whole.span.to(fraction.span);
+            self.pos += 3;
+            return Expr::new(
+                ExprKind::Binary {
+                    op: BinaryOp::Add,
+                    lhs: Box::new(whole),
+                    rhs: Box::new(fraction),
+                },
+                span,
+            );
+        }
+        let unit_follows = matches!(self.peek_kind(2), Some(TokenKind::Ident(_)))
+            && (!self.is_reserved_word(self.pos + 2) || self.is_inch(self.pos + 2))
+            && self.match_unit(self.pos + 2).is_some();
+        if self.pos > 0 && self.is_fraction(self.pos - 1) && unit_follows {
+            let fraction = self.fraction(self.pos - 1);
+            self.pos += 2;
+            return fraction;
+        }
+        whole
+    }
+
+    fn is_integer(&self, pos: usize) -> bool {
+        matches!(
+            self.tokens.get(pos).map(|t| &t.kind),
+            Some(TokenKind::Number(text)) if text.chars().all(|c| c.is_ascii_digit())
+        )
+    }
+
+    /// True if `pos` starts an integer fraction written as `3/4`.
+    fn is_fraction(&self, pos: usize) -> bool {
+        self.is_integer(pos)
+            && self.is_integer(pos + 2)
+            && self.tokens.get(pos + 1).map(|t| &t.kind) == Some(&TokenKind::Slash)
+            && self.tokens[pos].span.end == self.tokens[pos + 1].span.start
+            && self.tokens[pos + 1].span.end == self.tokens[pos + 2].span.start
+    }
+
+    /// The fraction starting at `pos`; see [`Self::is_fraction`].
+    fn fraction(&self, pos: usize) -> Expr {
+        let number = |token: &Token| match &token.kind {
+            TokenKind::Number(text) => Expr::new(ExprKind::Number(text.clone()), token.span),
+            _ => unreachable!("checked by is_fraction"),
+        };
+        let (num, den) = (&self.tokens[pos], &self.tokens[pos + 2]);
+        Expr::new(
+            ExprKind::Binary {
+                op: BinaryOp::Div,
+                lhs: Box::new(number(num)),
+                rhs: Box::new(number(den)),
+            },
+            num.span.to(den.span),
+        )
+    }
+
+    /// Scales and units written after a number: `2k`, `5 km`, `100$`, `1k USD`.
+    fn parse_number_suffix(&mut self, mut expr: Expr) -> Result<Expr, ParseError> {
+        if let Some(TokenKind::Ident(word)) = self.peek_kind(0) {
+            if let Some(factor) = self.vocab.scale(word) {
+                let span = self.tokens[self.pos].span;
+                self.pos += 1;
+                let factor = Expr::new(ExprKind::Number(factor.to_string()), span);
+                let full = expr.span.to(span);
+                expr = Expr::new(
+                    ExprKind::Binary {
+                        op: BinaryOp::Mul,
+                        lhs: Box::new(expr),
+                        rhs: Box::new(factor),
+                    },
+                    full,
+                );
+            }
+        }
+
+        match self.peek_kind(0) {
+            Some(TokenKind::Currency(code)) => {
+                // Price units: 3 $/kg
+                let (unit, width) = self
+                    .compound_unit(self.pos, code)
+                    .unwrap_or_else(|| (code.to_string(), 1));
+                Ok(self.attach_unit(expr, unit, width))
+            }
+            Some(TokenKind::Ident(word)) => {
+                if self.is_reserved_word(self.pos) && !self.is_inch(self.pos) {
+                    return Ok(expr);
+                }
+                // A constant after a number multiplies it: `2 electron mass`
+                let found = self
+                    .match_unit(self.pos)
+                    .or_else(|| self.constant_phrase(self.pos));
+                let (unit, width) = match found {
+                    Some(found) => found,
+                    // Unknown word after a number is reported as an unknown unit
+                    None => (word.clone(), 1),
+                };
+                Ok(self.attach_unit(expr, unit, width))
+            }
+            _ => Ok(expr),
+        }
+    }
+
+    /// Further `number unit` parts of a mixed-unit quantity (`5 ft 3 in`,
+    /// `1h 30min`). Anything else after the first quantity is left alone.
+    fn parse_compound_quantity(&mut self, first: Expr) -> Result<Expr, ParseError> {
+        if !matches!(first.kind, ExprKind::Quantity { .. }) {
+            return Ok(first);
+        }
+        let mut parts = vec![first];
+        while let Some(Token {
+            kind: TokenKind::Number(text),
+            span,
+        }) = self.tokens.get(self.pos).cloned()
+        {
+            let start = self.pos;
+            self.pos += 1;
+            let number = self.parse_mixed_number(Expr::new(ExprKind::Number(text), span));
+            let unit = match self.peek_kind(0) {
+                Some(TokenKind::Ident(_))
+                    if !self.is_reserved_word(self.pos) || self.is_inch(self.pos) =>
+                {
+                    self.match_unit(self.pos)
+                }
+                _ => None,
+            };
+            let Some((unit, width)) = unit else {
+                self.pos = start;
+                break;
+            };
+            parts.push(self.attach_unit(number, unit, width));
+        }
+
+        if parts.len() == 1 {
+            return Ok(parts.remove(0));
+        }
+        let span = parts[0].span.to(parts[parts.len() - 1].span);
+        Ok(Expr::new(ExprKind::Compound(parts), span))
+    }
+
+    /// Units after identifiers and parentheses (`pi radians`, `(2 + 3) m`).
+    /// Unlike after numbers, unknown words are left for the caller.
+    fn parse_known_unit_suffix(&mut self, expr: Expr) -> Result<Expr, ParseError> {
+        if let Some(TokenKind::Ident(_)) = self.peek_kind(0) {
+            if self.is_reserved_word(self.pos) && !self.is_inch(self.pos) {
+                return Ok(expr);
+            }
+            if let Some((unit, width)) = self.match_unit(self.pos) {
+                return Ok(self.attach_unit(expr, unit, width));
+            }
+        }
+        Ok(expr)
+    }
+
+    fn attach_unit(&mut self, expr: Expr, unit: String, width: usize) -> Expr {
+        let unit_span = self.tokens[self.pos].span.to(self.tokens[self.pos + width - 1].span);
+        let span = expr.span.to(unit_span);
+        self.pos += width;
+        Expr::new(
+            ExprKind::Quantity {
+                value: Box::new(expr),
+                unit,
+                unit_span,
+            },
+            span,
+        )
+    }
+
+    /// Conversion targets: one unit, or several for a compound result
+    /// (`to ft and in`, `to h min s`).
+    fn parse_targets(&mut self, keyword: &Token) -> Result<(Vec<String>, Span), ParseError> {
+        let (first, mut span) = self.parse_target(keyword)?;
+        let mut targets = vec![first];
+        if !matches!(self.tokens[self.pos - 1].kind, TokenKind::Ident(_)) {
+            return Ok((targets, span));
+        }
+        loop {
+            let and = matches!(
+                self.peek_kind(0),
+                Some(TokenKind::Ident(word)) if word.eq_ignore_ascii_case("and")
+            );
+            let at = self.pos + usize::from(and);
+            // `in` without `and` is another conversion unless it ends the line
+            if self.is_reserved_word(at) && !and && !self.is_inch(at) {
+                break;
+            }
+            let Some((unit, width)) = self.match_unit(at) else {
+                break;
+            };
+            span = span.to(self.tokens[at + width - 1].span);
+            self.pos = at + width;
+            targets.push(unit);
+        }
+        Ok((targets, span))
+    }
+
+    /// Target of a conversion: a unit (possibly multi-word), currency symbol
+    /// or precision (`4 dp`).
+    fn parse_target(&mut self, keyword: &Token) -> Result<(String, Span), ParseError> {
+        match self.tokens.get(self.pos).cloned() {
+            Some(Token {
+                kind: TokenKind::Currency(code),
+                span,
+            }) => {
+                self.pos += 1;
+                Ok((code.to_string(), span))
+            }
+            // `in roman numerals`
+            Some(Token {
+                kind: TokenKind::Ident(word),
+                span,
+            }) if word.eq_ignore_ascii_case("roman")
+                && (self.is_word(self.pos + 1, "numerals")
+                    || self.is_word(self.pos + 1, "numeral")) =>
+            {
+                let span = span.to(self.tokens[self.pos + 1].span);
+                self.pos += 2;
+                Ok(("roman".to_string(), span))
+            }
+            Some(Token {
+                kind: TokenKind::Ident(word),
+                span,
+            }) => {
+                let (unit, width) = self.match_unit(self.pos).unwrap_or((word, 1));
+                let span = span.to(self.tokens[self.pos + width - 1].span);
+                self.pos += width;
+                Ok((unit, span))
+            }
+            // Precision of the result: `to 4 dp`, `to 3 sf`
+            Some(Token {
+                kind: TokenKind::Number(digits),
+                span,
+            }) if self.is_word(self.pos + 1, "dp") || self.is_word(self.pos + 1, "sf") => {
+                let kind = if self.is_word(self.pos + 1, "dp") {
+                    "dp"
+                } else {
+                    "sf"
+                };
+                let target = format!("{} {}", digits, kind);
+                let span = span.to(self.tokens[self.pos + 1].span);
+                self.pos += 2;
+                Ok((target, span))
+            }
+            Some(other) => Err(self.unexpected(&other)),
+            None => Err(ParseError {
+                message: crate::fl!(
+                    "missing-conversion-target",
+                    "keyword" => &self.source[keyword.span.start..keyword.span.end]
+                ),
+                span: keyword.span,
+            }),
+        }
+    }
+
+    /// Longest unit name starting at `pos`: a single word, a compound unit
+    /// written without spaces (`km/h`, `m^2`), or several words (`miles per hour`).
+    fn match_unit(&self, pos: usize) -> Option<(String, usize)> {
+        let mut best = None;
+
+        let mut words: Vec<&str> = Vec::new();
+        for offset in 0..MAX_UNIT_WORDS {
+            match self.tokens.get(pos + offset).map(|t| &t.kind) {
+                Some(TokenKind::Ident(word)) => words.push(word),
+                _ => break,
+            }
+            if let Some(unit) = self.vocab.unit(&words.join(" ")) {
+                best = Some((unit, words.len()));
+            }
+        }
+
+        if let Some(TokenKind::Ident(first)) = self.tokens.get(pos).map(|t| &t.kind) {
+            if let Some(compound) = self.compound_unit(pos, first) {
+                best = Some(compound);
+            }
+        }
+
+        best
+    }
+
+    /// Constant named by two or more words at `pos` (`electron mass`), with
+    /// the number of tokens it spans.
+    fn constant_phrase(&self, pos: usize) -> Option<(String, usize)> {
+        let mut best = None;
+        let mut words: Vec<&str> = Vec::new();
+        for offset in 0..MAX_UNIT_WORDS {
+            match self.tokens.get(pos + offset).map(|t| &t.kind) {
+                Some(TokenKind::Ident(word)) => words.push(word),
+                _ => break,
+            }
+            let phrase = words.join(" ").to_lowercase();
+            if words.len() > 1 && self.vocab.is_constant(&phrase) {
+                best = Some((phrase, words.len()));
+            }
+        }
+        best
+    }
+
+    /// Unit after a price in `currency`: `/kg` written without spaces, or
+    /// `per` and a unit. Returns the price's unit (`USD/kg`) and the number
+    /// of tokens the suffix spans.
+    fn price_unit(&self, currency: &str) -> Option<(String, usize)> {
+        let adjacent_slash = matches!(self.peek_kind(0), Some(TokenKind::Slash))
+            && self.pos > 0
+            && self.tokens[self.pos - 1].span.end == self.tokens[self.pos].span.start
+            && self
+                .tokens
+                .get(self.pos + 1)
+                .is_some_and(|next| self.tokens[self.pos].span.end == next.span.start);
+        if !adjacent_slash && !self.is_word(self.pos, "per") {
+            return None;
+        }
+        let (per, width) = self.match_unit(self.pos + 1)?;
+        let unit = self.vocab.unit(&format!("{}/{}", currency, per))?;
+        Some((unit, width + 1))
+    }
+
+    /// Compound unit continuing the unit `first` at `pos` with `/unit` and
+    /// `^n` parts written without spaces (`km/h`, `m/s^2`, `USD/kg`).
+    fn compound_unit(&self, pos: usize, first: &str) -> Option<(String, usize)> {
+        let mut text = first.to_string();
+        let mut best = None;
+        let mut next = pos + 1;
+        while let (Some(op), Some(operand)) = (self.tokens.get(next), self.tokens.get(next + 1)) {
+            let adjacent = self.tokens[next - 1].span.end == op.span.start
+                && op.span.end == operand.span.start;
+            match (&op.kind, &operand.kind) {
+                (TokenKind::Slash, TokenKind::Ident(word)) if adjacent => {
+                    text.push('/');
+                    text.push_str(word);
+                }
+                (TokenKind::Caret, TokenKind::Number(exp)) if adjacent => {
+                    text.push('^');
+                    text.push_str(exp);
+                }
+                _ => break,
+            }
+            next += 2;
+            if let Some(unit) = self.vocab.unit(&text) {
+                best = Some((unit, next - pos));
+            }
+        }
+        best
+    }
+
+    /// Operator word or phrase at `pos`, with the number of tokens it spans.
+    fn operator_at(&self, pos: usize) -> Option<(&str, usize)> {
+        let first = match self.tokens.get(pos).map(|t| &t.kind) {
+            Some(TokenKind::Ident(word)) => word,
+            _ => return None,
+        };
+        if let Some(TokenKind::Ident(second)) = self.tokens.get(pos + 1).map(|t| &t.kind) {
+            if let Some(op) = self.vocab.operator(&format!("{} {}", first, second)) {
+                return Some((op, 2));
+            }
+        }
+        self.vocab.operator(first).map(|op| (op, 1))
+    }
+
+    /// Keywords and operator words that end an operand.
+    fn is_reserved_word(&self, pos: usize) -> bool {
+        match self.tokens.get(pos).map(|t| &t.kind) {
+            Some(TokenKind::Ident(word)) => {
+                is_conversion_keyword(word)
+                    || word.eq_ignore_ascii_case("of")
+                    || word.eq_ignore_ascii_case("per")
+                    || INTEREST_WORDS.iter().any(|w| word.eq_ignore_ascii_case(w))
+                    || self.relative_phrase(pos).is_some()
+                    || self.percent_phrase(pos).is_some()
+                    || word.eq_ignore_ascii_case("xor")
+                    || self.operator_at(pos).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    /// `in` right after a value is the inch unit when nothing that could be
+    /// a conversion target follows it (`5 in`, `5 in + 2 cm`, `5 in to cm`).
+    fn is_inch(&self, pos: usize) -> bool {
+        let is_in = matches!(
+            self.tokens.get(pos).map(|t| &t.kind),
+            Some(TokenKind::Ident(word)) if word.eq_ignore_ascii_case("in")
+        );
+        if !is_in || self.vocab.unit("in").is_none() {
+            return false;
+        }
+        match self.tokens.get(pos + 1).map(|t| &t.kind) {
+            None => true,
+            Some(TokenKind::Ident(_)) => self.is_reserved_word(pos + 1),
+            Some(kind) => !matches!(
+                kind,
+                TokenKind::Number(_) | TokenKind::Currency(_) | TokenKind::LParen
+            ),
+        }
+    }
+
+    fn starts_operand(&self, pos: usize) -> bool {
+        match self.tokens.get(pos).map(|t| &t.kind) {
+            Some(TokenKind::Number(_))
+            | Some(TokenKind::LParen)
+            | Some(TokenKind::LBracket)
+            | Some(TokenKind::Currency(_))
+            | Some(TokenKind::Date(_)) => true,
+            Some(TokenKind::Ident(_)) => !self.is_reserved_word(pos),
+            _ => false,
+        }
+    }
+
+    fn peek_infix(&self) -> Option<(Infix, usize)> {
+        let kind = self.peek_kind(0)?;
+        let infix = match kind {
+            TokenKind::Plus => Infix::Binary(BinaryOp::Add),
+            TokenKind::Minus => Infix::Binary(BinaryOp::Sub),
+            TokenKind::Star => Infix::Binary(BinaryOp::Mul),
+            TokenKind::Slash => Infix::Binary(BinaryOp::Div),
+            TokenKind::Caret => Infix::Binary(BinaryOp::Pow),
+            TokenKind::Amp => Infix::Binary(BinaryOp::BitAnd),
+            TokenKind::Pipe => Infix::Binary(BinaryOp::BitOr),
+            TokenKind::Shl => Infix::Binary(BinaryOp::Shl),
+            TokenKind::Shr => Infix::Binary(BinaryOp::Shr),
+            TokenKind::PlusMinus => Infix::Binary(BinaryOp::PlusMinus),
+            TokenKind::DotDot => Infix::Range,
+            TokenKind::Percent => {
+                // `%` followed by an operand is modulo, otherwise it is a percentage
+                if self.starts_operand(self.pos + 1) {
+                    Infix::Binary(BinaryOp::Rem)
+                } else {
+                    Infix::Percent
+                }
+            }
+            TokenKind::Ident(word) => {
+                if let Some((op, width)) = self.percent_phrase(self.pos) {
+                    return Some((Infix::PercentOp(op), width));
+                }
+                if let Some(relative) = self.relative_phrase(self.pos) {
+                    return Some(relative);
+                }
+                if is_conversion_keyword(word) {
+                    Infix::Convert
+                } else if word.eq_ignore_ascii_case("of") {
+                    Infix::Of
+                } else if word.eq_ignore_ascii_case("at") {
+                    Infix::Interest
+                } else if word.eq_ignore_ascii_case("xor") {
+                    Infix::Binary(BinaryOp::BitXor)
+                } else {
+                    let (symbol, width) = self.operator_at(self.pos)?;
+                    return BinaryOp::from_symbol(symbol).map(|op| (Infix::Binary(op), width));
+                }
+            }
+            _ => return None,
+        };
+        Some((infix, 1))
+    }
+
+    /// Percentage phrase starting at `pos`, with the number of tokens it
+    /// spans: `off`, `on`, `is what % of`, `as a % of`.
+    fn percent_phrase(&self, pos: usize) -> Option<(PercentOp, usize)> {
+        let percent_of = |at: usize| {
+            matches!(
+                self.tokens.get(at).map(|t| &t.kind),
+                Some(TokenKind::Percent)
+            ) && self.is_word(at + 1, "of")
+        };
+        if self.is_word(pos, "off") {
+            Some((PercentOp::Off, 1))
+        } else if self.is_word(pos, "on") {
+            Some((PercentOp::On, 1))
+        } else if self.is_word(pos, "is") && self.is_word(pos + 1, "what") && percent_of(pos + 2) {
+            Some((PercentOp::WhatPercent, 4))
+        } else if self.is_word(pos, "as") && percent_of(pos + 1) {
+            Some((PercentOp::WhatPercent, 3))
+        } else if self.is_word(pos, "as") && self.is_word(pos + 1, "a") && percent_of(pos + 2) {
+            Some((PercentOp::WhatPercent, 4))
+        } else {
+            None
+        }
+    }
+
+    /// Consume `word`, or report what is there instead.
+    fn expect_word(&mut self, word: &str) -> Result<(), ParseError> {
+        match self.tokens.get(self.pos) {
+            Some(_) if self.is_word(self.pos, word) => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(other) => Err(self.unexpected(other)),
+            None => Err(self.unexpected_end()),
+        }
+    }
+
+    /// True if the token at `pos` is `word`, ignoring case.
+    fn is_word(&self, pos: usize, word: &str) -> bool {
+        matches!(
+            self.tokens.get(pos).map(|t| &t.kind),
+            Some(TokenKind::Ident(w)) if w.eq_ignore_ascii_case(word)
+        )
+    }
+
+    fn peek_kind(&self, offset: usize) -> Option<&TokenKind> {
+        self.tokens.get(self.pos + offset).map(|t| &t.kind)
+    }
+
+    fn unexpected(&self, token: &Token) -> ParseError {
+        ParseError {
+            message: crate::fl!(
+                "unexpected-token",
+                "token" => &self.source[token.span.start..token.span.end]
+            ),
+            span: token.span,
+        }
+    }
+
+    fn unexpected_end(&self) -> ParseError {
+        let end = self.source.trim_end().len();
+        ParseError {
+            message: crate::fl!("unexpected-end"),
+            span: Span::new(end, end),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestVocabulary;
+
+    impl Vocabulary for TestVocabulary {
+        fn unit(&self, name: &str) -> Option<String> {
+            match name {
+                "m" | "km" | "in" | "cm" | "ft" | "h" | "min" | "s" | "km/h" | "m/s"
+                | "miles per hour" | "radians" => Some(name.to_string()),
+                "USD" | "EUR" | "USD/km" => Some(name.to_string()),
+                "dollars" => Some("USD".to_string()),
+                _ => None,
+            }
+        }
+
+        fn scale(&self, name: &str) -> Option<f64> {
+            match name {
+                "k" => Some(1000.0),
+                "M" => Some(1_000_000.0),
+                _ => None,
+            }
+        }
+
+        fn operator(&self, phrase: &str) -> Option<&str> {
+            match phrase.to_lowercase().as_str() {
+                "plus" | "and" => Some("+"),
+                "minus" => Some("-"),
+                "divided by" => Some("/"),
+                "mod" => Some("%"),
+                _ => None,
+            }
+        }
+
+        fn is_function(&self, name: &str) -> bool {
+            matches!(name, "sin" | "sqrt" | "max")
+        }
+
+        fn is_constant(&self, name: &str) -> bool {
+            matches!(name, "golden ratio" | "speed of light")
+        }
+
+        fn is_currency(&self, unit: &str) -> bool {
+            matches!(unit, "USD" | "EUR")
+        }
+
+        fn number_style(&self) -> NumberStyle {
+            NumberStyle::POINT
+        }
+    }
+
+    fn parse_ok(input: &str) -> Expr {
+        parse(input, &TestVocabulary).unwrap_or_else(|e| panic!("{}: {}", input, e.message))
+    }
+
+    /// Compact s-expression rendering so tests can assert tree shape.
+    fn sexp(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Number(n) => n.clone(),
+            ExprKind::Ident(name) => name.clone(),
+            ExprKind::Unary { op, operand } => {
+                let op = match op {
+                    UnaryOp::Neg => "neg",
+                    UnaryOp::Not => "not",
+                };
+                format!("({} {})", op, sexp(operand))
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let op = match op {
+                    BinaryOp::Rem => "mod",
+                    op => op.symbol(),
+                };
+                format!("({} {} {})", op, sexp(lhs), sexp(rhs))
+            }
+            ExprKind::Percent(inner) => format!("(% {})", sexp(inner)),
+            ExprKind::Of { percent, base } => format!("(of {} {})", sexp(percent), sexp(base)),
+            ExprKind::PercentOp { op, lhs, rhs } => {
+                let op = match op {
+                    PercentOp::Off => "off",
+                    PercentOp::On => "on",
+                    PercentOp::WhatPercent => "what%",
+                    PercentOp::Change => "change",
+                    PercentOp::OfWhat => "of-what",
+                };
+                format!("({} {} {})", op, sexp(lhs), sexp(rhs))
+            }
+            ExprKind::Quantity { value, unit, .. } => format!("({} {})", sexp(value), unit),
+            ExprKind::Call { name, args } => {
+                let args: Vec<String> = args.iter().map(sexp).collect();
+                format!("({} {})", name, args.join(" "))
+            }
+            ExprKind::Compound(parts) => {
+                let parts: Vec<String> = parts.iter().map(sexp).collect();
+                format!("(compound {})", parts.join(" "))
+            }
+            ExprKind::Convert { expr, targets } => {
+                format!("(to {} {})", sexp(expr), targets.join(" "))
+            }
+            ExprKind::List(items) => {
+                let items: Vec<String> = items.iter().map(sexp).collect();
+                format!("[{}]", items.join(" "))
+            }
+            ExprKind::Range { from, to } => format!("(.. {} {})", sexp(from), sexp(to)),
+            ExprKind::Assign { name, value } => format!("(= {} {})", name, sexp(value)),
+            ExprKind::Define { name, params, body } => {
+                format!("(= ({} {}) {})", name, params.join(" "), sexp(body))
+            }
+            ExprKind::Date(DateWord::Literal(text)) => text.clone(),
+            ExprKind::Date(DateWord::Weekday { day, direction }) => {
+                format!("(weekday {} {})", day, direction)
+            }
+            ExprKind::Date(word) => format!("{:?}", word).to_lowercase(),
+            ExprKind::InZone { expr, zone, .. } => format!("(in {} {})", sexp(expr), zone),
+            ExprKind::DaysBetween { from, to } => {
+                format!("(days-between {} {})", sexp(from), sexp(to))
+            }
+            ExprKind::Interest {
+                principal,
+                rate,
+                term,
+                plan,
+            } => {
+                let plan = match plan {
+                    InterestPlan::Loan => "loan".to_string(),
+                    InterestPlan::Compound { per_year: Some(n) } => format!("compound {}", n),
+                    InterestPlan::Compound { per_year: None } => "compound".to_string(),
+                };
+                format!(
+                    "({} {} {} {})",
+                    plan,
+                    sexp(principal),
+                    sexp(rate),
+                    sexp(term)
+                )
+            }
+        }
+    }
+
+    fn tree(input: &str) -> String {
+        sexp(&parse_ok(input))
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(tree("2 + 3 * 4"), "(+ 2 (* 3 4))");
+        assert_eq!(tree("2 ^ 3 ^ 2"), "(^ 2 (^ 3 2))");
+        assert_eq!(tree("-5 ^ 2"), "(neg (^ 5 2))");
+        assert_eq!(tree("2 ^ -1"), "(^ 2 (neg 1))");
+        assert_eq!(tree("(1 + 2) * 3"), "(* (+ 1 2) 3)");
+        assert_eq!(tree("5.0 ± 0.1 m"), "(± 5.0 (0.1 m))");
+        assert_eq!(tree("1 + 5 ± 0.1 * 2"), "(+ 1 (* (± 5 0.1) 2))");
+        assert_eq!(
+            tree("5.0 ± 0.1 m * 2.0 ± 0.05 m"),
+            "(* (± 5.0 (0.1 m)) (± 2.0 (0.05 m)))"
+        );
+        assert_eq!(tree("-5 ± 1"), "(± (neg 5) 1)");
+    }
+
+    #[test]
+    fn test_bitwise_precedence() {
+        assert_eq!(tree("1 | 2 xor 3 & 4"), "(| 1 (xor 2 (& 3 4)))");
+        assert_eq!(tree("1 << 2 + 3"), "(<< 1 (+ 2 3))");
+        assert_eq!(tree("0xF0 >> 4 & 0xF"), "(& (>> 0xF0 4) 0xF)");
+        assert_eq!(tree("not 0 & 0xFF"), "(& (not 0) 0xFF)");
+        assert_eq!(tree("5 xor 3 to hex"), "(to (xor 5 3) hex)");
+    }
+
+    #[test]
+    fn test_percent_versus_modulo() {
+        assert_eq!(tree("10 % 3"), "(mod 10 3)");
+        assert_eq!(tree("100 + 10%"), "(+ 100 (% 10))");
+        assert_eq!(tree("50% of 200 + 50"), "(of (% 50) (+ 200 50))");
+        assert_eq!(tree("((100 + 50%) + 10%)"), "(+ (+ 100 (% 50)) (% 10))");
+        assert_eq!(tree("10 mod 3"), "(mod 10 3)");
+    }
+
+    #[test]
+    fn test_percentage_phrases() {
+        assert_eq!(tree("50 is what % of 200"), "(what% 50 200)");
+        assert_eq!(tree("50 as a % of 200"), "(what% 50 200)");
+        assert_eq!(tree("50 m as % of 1 km"), "(what% (50 m) (1 km))");
+        assert_eq!(tree("% change from 80 to 100"), "(change 80 100)");
+        assert_eq!(tree("20% off 150 + 50"), "(off (% 20) (+ 150 50))");
+        assert_eq!(tree("15% on 200"), "(on (% 15) 200)");
+        assert_eq!(tree("5% of what is 20"), "(of-what (% 5) 20)");
+        assert_eq!(tree("50 as a % of 200 to hex"), "(to (what% 50 200) hex)");
+        assert!(parse("% change from 80", &TestVocabulary).is_err());
+        assert!(parse("5 off 10", &TestVocabulary).is_err());
+    }
+
+    #[test]
+    fn test_conversion_binds_loosest() {
+        assert_eq!(tree("10 + 5 m in cm"), "(to (+ 10 (5 m)) cm)");
+        assert_eq!(tree("sum to USD + 100"), "(+ (to sum USD) 100)");
+        assert_eq!(tree("5 in to cm"), "(to (5 in) cm)");
+        assert_eq!(tree("5 in"), "(5 in)");
+        assert_eq!(tree("1 m/s to km/h"), "(to (1 m/s) km/h)");
+        assert_eq!(tree("60 miles per hour"), "(60 miles per hour)");
+        assert_eq!(tree("(5 m to km) to m"), "(to (to (5 m) km) m)");
+    }
+
+    #[test]
+    fn test_compound_quantities_and_targets() {
+        assert_eq!(tree("5 ft 3 in"), "(compound (5 ft) (3 in))");
+        assert_eq!(tree("1h 30min * 2"), "(* (compound (1 h) (30 min)) 2)");
+        assert_eq!(tree("5 ft 3 in to cm"), "(to (compound (5 ft) (3 in)) cm)");
+        assert_eq!(tree("1.8 m to ft and in"), "(to (1.8 m) ft in)");
+        assert_eq!(tree("5000 s to h min s"), "(to (5000 s) h min s)");
+        assert_eq!(tree("5 m to cm in ft"), "(to (to (5 m) cm) ft)");
+        assert_eq!(tree("5 ft + 3"), "(+ (5 ft) 3)");
+    }
+
+    #[test]
+    fn test_mixed_numbers() {
+        assert_eq!(tree("2 3/4"), "(+ 2 (/ 3 4))");
+        assert_eq!(tree("2 3/4 m * 2"), "(* ((+ 2 (/ 3 4)) m) 2)");
+        assert_eq!(tree("-1 1/2"), "(neg (+ 1 (/ 1 2)))");
+        assert_eq!(tree("5 ft 3 1/2 in"), "(compound (5 ft) ((+ 3 (/ 1 2)) in))");
+        assert_eq!(tree("3/8 in to cm"), "(to ((/ 3 8) in) cm)");
+        assert_eq!(tree("1/3^2"), "(/ 1 (^ 3 2))");
+        assert_eq!(tree("3 / 8 m"), "(/ 3 (8 m))");
+        assert!(parse("2 3 / 4", &TestVocabulary).is_err());
+        assert!(parse("2.5 3/4", &TestVocabulary).is_err());
+    }
+
+    #[test]
+    fn test_scales_and_currency() {
+        assert_eq!(tree("2k"), "(* 2 1000)");
+        assert_eq!(tree("1M USD"), "((* 1 1000000) USD)");
+        assert_eq!(tree("$100 to EUR"), "(to (100 USD) EUR)");
+        assert_eq!(tree("$ 100 to EUR"), "(to (100 USD) EUR)");
+        assert_eq!(tree("100$"), "(100 USD)");
+        assert_eq!(tree("100 dollars"), "(100 USD)");
+        assert_eq!(tree("$2/km * 5 km"), "(* (2 USD/km) (5 km))");
+        assert_eq!(tree("$2 per km"), "(2 USD/km)");
+        assert_eq!(tree("$10/2"), "(/ (10 USD) 2)");
+    }
+
+    #[test]
+    fn test_words_functions_and_assignment() {
+        assert_eq!(tree("ten plus five"), "(+ 10 5)");
+        assert_eq!(tree("8 divided by 2"), "(/ 8 2)");
+        assert_eq!(tree("sqrt 16 + 1"), "(+ (sqrt 16) 1)");
+        assert_eq!(tree("max(1, 2, 3)"), "(max 1 2 3)");
+        // Parentheses right after an unknown name are a call to it
+        assert_eq!(tree("f(3) + 1"), "(+ (f 3) 1)");
+        assert_eq!(tree("x = 5 m to km"), "(= x (to (5 m) km))");
+        assert_eq!(tree("pi radians"), "(pi radians)");
+        assert_eq!(tree("Golden Ratio * 2"), "(* golden ratio 2)");
+        assert_eq!(tree("3 speed of light"), "(3 speed of light)");
+    }
+
+    #[test]
+    fn test_line_references() {
+        assert_eq!(tree("line 3 * 2"), "(* line3 2)");
+        assert_eq!(tree("line2 + above"), "(+ line2 above)");
+        assert_eq!(tree("prev line km"), "(prev line km)");
+        assert_eq!(tree("grand total * 2"), "(* grand total 2)");
+        assert_eq!(tree("section average to km"), "(to section avg km)");
+        // Lines are referred to by `line N`: `$2` is two dollars
+        assert_eq!(tree("$2 * 3"), "(* (2 USD) 3)");
+    }
+
+    #[test]
+    fn test_date_phrases() {
+        assert_eq!(tree("2025-01-31 + 3 h"), "(+ 2025-01-31 (3 h))");
+        assert_eq!(tree("3 h from next monday"), "(+ (weekday Mon 1) (3 h))");
+        assert_eq!(tree("2 min ago"), "(- now (2 min))");
+        assert_eq!(
+            tree("90 s before today in UTC"),
+            "(in (- today (90 s)) UTC)"
+        );
+        assert_eq!(tree("now in America/New_York"), "(in now America/New_York)");
+        assert_eq!(tree("time in new york"), "(in time new york)");
+        assert_eq!(
+            tree("days between today and 2025-12-25"),
+            "(days-between today 2025-12-25)"
+        );
+        // Without a date after it, `from` is not a date phrase
+        assert_eq!(tree("5 km in m"), "(to (5 km) m)");
+    }
+
+    #[test]
+    fn test_interest_phrases() {
+        assert_eq!(
+            tree("mortgage $300k at 6.5% over 30 years in EUR"),
+            "(to (loan ((* 300 1000) USD) (% 6.5) (30 years)) EUR)"
+        );
+        assert_eq!(
+            tree("loan of 12k at 5% for 2 years"),
+            "(loan (* 12 1000) (% 5) (2 years))"
+        );
+        assert_eq!(
+            tree("$5000 at 4% for 10 years compounded monthly to EUR"),
+            "(to (compound 12 (5000 USD) (% 4) (10 years)) EUR)"
+        );
+        assert_eq!(
+            tree("2 * 100 at 3% over 6 months compounded continuously"),
+            "(compound (* 2 100) (% 3) (6 months))"
+        );
+        assert_eq!(
+            tree("payment = mortgage 200k at 5% over 25 years"),
+            "(= payment (loan (* 200 1000) (% 5) (25 years)))"
+        );
+        // Without a phrase after it, `loan` is a name
+        assert_eq!(tree("loan * 2"), "(* loan 2)");
+        assert!(parse("$5000 at 4% for 10 years compounded never", &TestVocabulary).is_err());
+        assert!(parse("mortgage 300k at 6.5%", &TestVocabulary).is_err());
+    }
+
+    #[test]
+    fn test_function_definitions() {
+        assert_eq!(
+            tree("tax(amount) = amount * 0.08"),
+            "(= (tax amount) (* amount 0.08))"
+        );
+        assert_eq!(tree("area(w, h) = w * h"), "(= (area w h) (* w h))");
+        assert_eq!(tree("answer() = 42"), "(= (answer ) 42)");
+        assert_eq!(tree("fact(n) = n * fact(n - 1)"), "(= (fact n) (* n (fact (- n 1))))");
+        // Not a definition head: calls and expressions keep their meaning
+        assert_eq!(tree("max(1, 2)"), "(max 1 2)");
+        assert!(parse("f(x, x) = x", &TestVocabulary).is_err());
+        assert!(parse("f(2) = 4", &TestVocabulary).is_err());
+    }
+
+    #[test]
+    fn test_lists_and_ranges() {
+        assert_eq!(tree("[1, 2 + 3, 4 m]"), "[1 (+ 2 3) (4 m)]");
+        assert_eq!(tree("[]"), "[]");
+        assert_eq!(tree("prices = 12, 15, 9"), "(= prices [12 15 9])");
+        assert_eq!(tree("1..n + 1"), "(.. 1 (+ n 1))");
+        assert_eq!(tree("sqrt [1, 4] to m"), "(to (sqrt [1 4]) m)");
+        assert_eq!(tree("max(1..3, 7)"), "(max (.. 1 3) 7)");
+        assert_eq!(tree("[5 km, 3 miles] to km"), "(to [(5 km) (3 miles)] km)");
+    }
+
+    #[test]
+    fn test_spans_point_into_source() {
+        let expr = parse_ok("1 + 20 km");
+        assert_eq!(expr.span, Span::new(0, 9));
+        if let ExprKind::Binary { rhs, .. } = expr.kind {
+            assert_eq!(rhs.span, Span::new(4, 9));
+        } else {
+            panic!("expected binary expression");
+        }
+    }
+
+    #[test]
+    fn test_errors_carry_location() {
+        let err = parse("2 +", &TestVocabulary).unwrap_err();
+        assert_eq!(err.span, Span::new(3, 3));
+        let err = parse("2 + )", &TestVocabulary).unwrap_err();
+        assert_eq!(err.span, Span::new(4, 5));
+        let err = parse("invalid expression", &TestVocabulary).unwrap_err();
+        assert_eq!(err.span, Span::new(8, 18));
+        assert!(parse("", &TestVocabulary).is_err());
+    }
+}
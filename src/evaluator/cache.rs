@@ -1,18 +1,19 @@
 use super::events::{EventSubscriber, StateEvent};
 #[cfg(feature = "desktop")]
 use ratatui::text::Span;
+#[cfg(feature = "desktop")]
 use std::collections::HashMap;
 use std::sync::RwLock;
 
+#[cfg(feature = "desktop")]
 const MAX_CACHE_SIZE: usize = 1000;
 
 pub struct CacheManager {
-    display: RwLock<HashMap<String, Option<String>>>,
     #[cfg(feature = "desktop")]
     highlight: RwLock<HashMap<String, Vec<Span<'static>>>>,
-    display_access: RwLock<HashMap<String, u64>>,
     #[cfg(feature = "desktop")]
     highlight_access: RwLock<HashMap<String, u64>>,
+    #[cfg(feature = "desktop")]
     counter: RwLock<u64>,
     generation: RwLock<u64>,
 }
@@ -20,12 +21,11 @@ pub struct CacheManager {
 impl CacheManager {
     pub fn new() -> Self {
         Self {
-            display: RwLock::new(HashMap::new()),
             #[cfg(feature = "desktop")]
             highlight: RwLock::new(HashMap::new()),
-            display_access: RwLock::new(HashMap::new()),
             #[cfg(feature = "desktop")]
             highlight_access: RwLock::new(HashMap::new()),
+            #[cfg(feature = "desktop")]
             counter: RwLock::new(0),
             generation: RwLock::new(0),
         }
@@ -39,20 +39,6 @@ impl CacheManager {
             .unwrap_or_else(|_| panic!("Failed to read cache generation"))
     }
 
-    fn evict_lru_display(&self) {
-        if let (Ok(mut cache), Ok(mut access)) = (self.display.write(), self.display_access.write())
-        {
-            if cache.len() >= MAX_CACHE_SIZE {
-                // Find LRU entry
-                if let Some((lru_key, _)) = access.iter().min_by_key(|(_, &v)| v) {
-                    let lru_key = lru_key.clone();
-                    cache.remove(&lru_key);
-                    access.remove(&lru_key);
-                }
-            }
-        }
-    }
-
     #[cfg(feature = "desktop")]
     fn evict_lru_highlight(&self) {
         if let (Ok(mut cache), Ok(mut access)) =
@@ -69,34 +55,6 @@ impl CacheManager {
         }
     }
 
-    pub fn get_display(&self, key: &str) -> Option<Option<String>> {
-        let result = self.display.read().ok()?.get(key).cloned();
-        if result.is_some() {
-            // Update access time
-            if let (Ok(mut counter), Ok(mut access)) =
-                (self.counter.write(), self.display_access.write())
-            {
-                *counter += 1;
-                access.insert(key.to_string(), *counter);
-            }
-        }
-        result
-    }
-
-    pub fn set_display(&self, key: String, value: Option<String>) {
-        self.evict_lru_display();
-
-        if let (Ok(mut cache), Ok(mut counter), Ok(mut access)) = (
-            self.display.write(),
-            self.counter.write(),
-            self.display_access.write(),
-        ) {
-            *counter += 1;
-            cache.insert(key.clone(), value);
-            access.insert(key, *counter);
-        }
-    }
-
     #[cfg(feature = "desktop")]
     pub fn get_highlight(&self, key: &str) -> Option<Vec<Span<'static>>> {
         let result = self.highlight.read().ok()?.get(key).cloned();
@@ -128,16 +86,10 @@ impl CacheManager {
     }
 
     pub fn invalidate_all(&self) {
-        if let Ok(mut cache) = self.display.write() {
-            cache.clear();
-        }
         #[cfg(feature = "desktop")]
         if let Ok(mut cache) = self.highlight.write() {
             cache.clear();
         }
-        if let Ok(mut access) = self.display_access.write() {
-            access.clear();
-        }
         #[cfg(feature = "desktop")]
         if let Ok(mut access) = self.highlight_access.write() {
             access.clear();
@@ -147,17 +99,12 @@ impl CacheManager {
         }
     }
 
+    #[cfg_attr(not(feature = "desktop"), allow(unused_variables))]
     fn invalidate_prefix(&self, prefix: &str) {
-        if let Ok(mut cache) = self.display.write() {
-            cache.retain(|k: &String, _| !k.starts_with(prefix));
-        }
         #[cfg(feature = "desktop")]
         if let Ok(mut cache) = self.highlight.write() {
             cache.retain(|k: &String, _| !k.starts_with(prefix));
         }
-        if let Ok(mut access) = self.display_access.write() {
-            access.retain(|k: &String, _| !k.starts_with(prefix));
-        }
         #[cfg(feature = "desktop")]
         if let Ok(mut access) = self.highlight_access.write() {
             access.retain(|k: &String, _| !k.starts_with(prefix));
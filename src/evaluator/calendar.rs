@@ -0,0 +1,285 @@
+//! Points in time for date phrases: `now`, `next monday`, `2025-01-31 + 30 days`.
+//!
+//! The parser turns the phrases into [`DateWord`] nodes; this module gives
+//! them a time, moves them by durations and formats them.
+
+use std::collections::HashMap;
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, Offset,
+    TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+use crate::evaluator::ast::DateWord;
+
+/// Time zone abbreviations and the zones they stand for.
+const ZONE_ABBREVIATIONS: &[(&str, &str)] = &[
+    ("utc", "UTC"),
+    ("gmt", "UTC"),
+    ("est", "America/New_York"),
+    ("edt", "America/New_York"),
+    ("cst", "America/Chicago"),
+    ("cdt", "America/Chicago"),
+    ("mst", "America/Denver"),
+    ("mdt", "America/Denver"),
+    ("pst", "America/Los_Angeles"),
+    ("pdt", "America/Los_Angeles"),
+    ("bst", "Europe/London"),
+    ("cet", "Europe/Paris"),
+    ("cest", "Europe/Paris"),
+    ("eet", "Europe/Athens"),
+    ("eest", "Europe/Athens"),
+    ("ist", "Asia/Kolkata"),
+    ("jst", "Asia/Tokyo"),
+    ("kst", "Asia/Seoul"),
+    ("aest", "Australia/Sydney"),
+    ("aedt", "Australia/Sydney"),
+    ("acst", "Australia/Adelaide"),
+    ("acdt", "Australia/Adelaide"),
+    ("awst", "Australia/Perth"),
+];
+
+/// How a point in time is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MomentStyle {
+    /// The date alone, in the configured date format: `2025-01-31`
+    Date,
+    /// Date and time in short: `Jan 31, 13:20`
+    Compact,
+    /// Date, time and offset in the configured time format, as for `time in Tokyo`
+    Clock,
+}
+
+/// A point in time, with the offset it is shown in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Moment {
+    pub at: DateTime<FixedOffset>,
+    pub style: MomentStyle,
+}
+
+impl Moment {
+    /// The moment `word` names, in `zone` or the local time zone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use numby::evaluator::ast::DateWord;
+    /// use numby::evaluator::calendar::{Moment, MomentStyle};
+    ///
+    /// let moment = Moment::named(&DateWord::Literal("2025-01-31".to_string()), None).unwrap();
+    /// assert_eq!(moment.style, MomentStyle::Date);
+    /// assert_eq!(moment.format("iso", "iso"), "2025-01-31");
+    /// ```
+    pub fn named(word: &DateWord, zone: Option<Tz>) -> Option<Self> {
+        let now = in_zone(Utc::now().naive_utc(), zone);
+        let date = |at| Some(Self::new(at, MomentStyle::Date));
+        match word {
+            DateWord::Now => Some(Self::new(now, MomentStyle::Compact)),
+            DateWord::Time => Some(Self::new(now, MomentStyle::Clock)),
+            DateWord::Today => date(now),
+            DateWord::Tomorrow => date(now + Duration::days(1)),
+            DateWord::Yesterday => date(now - Duration::days(1)),
+            DateWord::Weekday { day, direction } => {
+                date(now + Duration::days(weekday_offset(now.weekday(), *day, *direction)))
+            }
+            DateWord::Literal(text) => {
+                let (naive, style) = parse_literal(text)?;
+                let offset = match zone {
+                    Some(zone) => zone.offset_from_local_datetime(&naive).single()?.fix(),
+                    None => Local.offset_from_local_datetime(&naive).single()?,
+                };
+                Some(Self::new(
+                    offset.from_local_datetime(&naive).single()?,
+                    style,
+                ))
+            }
+        }
+    }
+
+    fn new(at: DateTime<FixedOffset>, style: MomentStyle) -> Self {
+        Self { at, style }
+    }
+
+    /// The same instant in `zone`.
+    pub fn in_zone(self, zone: Tz) -> Self {
+        Self::new(in_zone(self.at.naive_utc(), Some(zone)), self.style)
+    }
+
+    /// Moved by whole calendar months; the day is clamped to the month's
+    /// length, so Jan 31 plus a month is Feb 28 or 29.
+    pub fn add_months(self, months: i64) -> Option<Self> {
+        let magnitude = Months::new(u32::try_from(months.unsigned_abs()).ok()?);
+        let at = if months >= 0 {
+            self.at.checked_add_months(magnitude)?
+        } else {
+            self.at.checked_sub_months(magnitude)?
+        };
+        Some(Self::new(at, self.style))
+    }
+
+    /// Moved by `seconds`. A date moved by part of a day gets a time.
+    pub fn add_seconds(self, seconds: f64) -> Option<Self> {
+        let millis = (seconds * 1000.0).round();
+        if !millis.is_finite() || millis.abs() > i64::MAX as f64 {
+            return None;
+        }
+        let at = self
+            .at
+            .checked_add_signed(Duration::milliseconds(millis as i64))?;
+        let style = match self.style {
+            MomentStyle::Date if seconds % 86_400.0 != 0.0 => MomentStyle::Compact,
+            style => style,
+        };
+        Some(Self::new(at, style))
+    }
+
+    /// Days from `self` to `other`: whole days between two dates, or the
+    /// exact time between two instants.
+    pub fn days_until(self, other: Moment) -> f64 {
+        if self.style == MomentStyle::Date && other.style == MomentStyle::Date {
+            return (other.at.date_naive() - self.at.date_naive()).num_days() as f64;
+        }
+        (other.at - self.at).num_milliseconds() as f64 / 86_400_000.0
+    }
+
+    /// Display text in the `date_format` and `time_format` keys of the config.
+    pub fn format(&self, date_format: &str, time_format: &str) -> String {
+        match self.style {
+            MomentStyle::Date => {
+                let pattern = match date_format {
+                    "long" => "%A, %Y-%m-%d",
+                    "short" => "%m/%d/%y",
+                    _ => "%Y-%m-%d",
+                };
+                self.at.date_naive().format(pattern).to_string()
+            }
+            MomentStyle::Compact => self.at.format("%b %d, %H:%M").to_string(),
+            MomentStyle::Clock => {
+                let pattern = match time_format {
+                    "long" => "%A, %Y-%m-%d %H:%M %:z",
+                    "short" => "%m/%d %H:%M %Z",
+                    "time" => "%H:%M %:z",
+                    "12h" => "%Y-%m-%d %I:%M %p %:z",
+                    _ => "%Y-%m-%d %H:%M %:z",
+                };
+                self.at.format(pattern).to_string()
+            }
+        }
+    }
+}
+
+/// The time zone called `name`: an abbreviation (`PST`), a city
+/// (`Tokyo`, `new york`) or an IANA name in any case (`europe/berlin`).
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::calendar::resolve_zone;
+/// use std::collections::HashMap;
+///
+/// let cities = HashMap::from([("tokyo".to_string(), "Asia/Tokyo".to_string())]);
+/// assert_eq!(resolve_zone("Tokyo", &cities), Some(chrono_tz::Asia::Tokyo));
+/// assert_eq!(resolve_zone("europe/berlin", &cities), Some(chrono_tz::Europe::Berlin));
+/// assert_eq!(resolve_zone("Atlantis", &cities), None);
+/// ```
+pub fn resolve_zone(name: &str, city_aliases: &HashMap<String, String>) -> Option<Tz> {
+    let lower = name.to_lowercase();
+    let alias = ZONE_ABBREVIATIONS
+        .iter()
+        .find(|(abbr, _)| *abbr == lower)
+        .map(|(_, zone)| *zone)
+        .or_else(|| city_aliases.get(&lower).map(String::as_str));
+    if let Some(zone) = alias.and_then(|zone| zone.parse().ok()) {
+        return Some(zone);
+    }
+    if let Ok(zone) = name.parse() {
+        return Some(zone);
+    }
+    // Title-case each path segment (europe/berlin -> Europe/Berlin)
+    let title = lower
+        .split('/')
+        .map(|segment| {
+            let mut chars = segment.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    title.parse().or_else(|_| name.to_uppercase().parse()).ok()
+}
+
+/// Weekday for an English day name.
+pub fn weekday(name: &str) -> Option<Weekday> {
+    match name.to_lowercase().as_str() {
+        "monday" => Some(Weekday::Mon),
+        "tuesday" => Some(Weekday::Tue),
+        "wednesday" => Some(Weekday::Wed),
+        "thursday" => Some(Weekday::Thu),
+        "friday" => Some(Weekday::Fri),
+        "saturday" => Some(Weekday::Sat),
+        "sunday" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// `2025-01-31` as a date, or `2025-01-31 13:20:00` as a date and time.
+fn parse_literal(text: &str) -> Option<(NaiveDateTime, MomentStyle)> {
+    if let Ok(at) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S") {
+        return Some((at, MomentStyle::Compact));
+    }
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").ok()?;
+    Some((date.and_hms_opt(0, 0, 0)?, MomentStyle::Date))
+}
+
+/// A UTC time in `zone`, or in the local time zone.
+fn in_zone(utc: NaiveDateTime, zone: Option<Tz>) -> DateTime<FixedOffset> {
+    let offset = match zone {
+        Some(zone) => zone.offset_from_utc_datetime(&utc).fix(),
+        None => *Local.from_utc_datetime(&utc).offset(),
+    };
+    DateTime::<Utc>::from_naive_utc_and_offset(utc, Utc).with_timezone(&offset)
+}
+
+/// Days from `current` to the `target` weekday: the next one after today
+/// (`direction` 1), the last one before today (-1), or this week's, today
+/// included (0).
+fn weekday_offset(current: Weekday, target: Weekday, direction: i8) -> i64 {
+    let delta = target.num_days_from_monday() as i64 - current.num_days_from_monday() as i64;
+    match direction {
+        1 if delta <= 0 => delta + 7,
+        -1 if delta >= 0 => delta - 7,
+        0 if delta < 0 => delta + 7,
+        _ => delta,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weekday_offset() {
+        assert_eq!(weekday_offset(Weekday::Wed, Weekday::Mon, 1), 5);
+        assert_eq!(weekday_offset(Weekday::Wed, Weekday::Wed, 1), 7);
+        assert_eq!(weekday_offset(Weekday::Wed, Weekday::Fri, -1), -5);
+        assert_eq!(weekday_offset(Weekday::Wed, Weekday::Wed, 0), 0);
+        assert_eq!(weekday_offset(Weekday::Wed, Weekday::Tue, 0), 6);
+    }
+
+    #[test]
+    fn test_calendar_arithmetic() {
+        let date = |text: &str| Moment::named(&DateWord::Literal(text.to_string()), None).unwrap();
+        let end_of_january = date("2024-01-31");
+        let month_later = end_of_january.add_months(1).unwrap();
+        assert_eq!(month_later.format("iso", "iso"), "2024-02-29");
+        assert_eq!(end_of_january.days_until(date("2024-03-01")), 30.0);
+
+        let later = end_of_january.add_seconds(5400.0).unwrap();
+        assert_eq!(later.style, MomentStyle::Compact);
+        assert_eq!(later.format("iso", "iso"), "Jan 31, 01:30");
+        assert!(Moment::named(&DateWord::Literal("2024-02-30".to_string()), None).is_none());
+    }
+}
@@ -1,547 +1,2854 @@
-use lazy_static::lazy_static;
-use regex::Regex;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fmt;
 
-use crate::conversions::{
-    evaluate_currency_conversion, evaluate_generic_conversion, evaluate_temperature_conversion,
+use chrono_tz::Tz;
+
+use crate::config::{Config, Constant};
+use crate::conversions::{convert_temperature, currency_symbol, currency_word_to_code};
+use crate::evaluator::ast::{
+    self, BinaryOp, Expr, ExprKind, InterestPlan, PercentOp, UnaryOp, Vocabulary,
 };
-use crate::evaluator::{EvaluatorError, Result};
-use crate::models::{HistoryEntry, Rates, TempUnits, Units};
-use crate::parser::{apply_function_parsing, apply_replacements, parse_percentage_op, preprocess_percentage_parens};
-use crate::prettify::prettify_number;
+use crate::evaluator::calendar::{resolve_zone, Moment};
+use crate::evaluator::finance;
+use crate::evaluator::lexer::Span;
+use crate::evaluator::roman::{is_roman_word, parse_roman, to_roman, MAX_ROMAN};
+use crate::evaluator::units::{Conversion, Factor, Temperature, Unit, UnitRegistry};
+use crate::evaluator::{Diagnostic, EvaluatorError, Number, NumberMode, Result};
+use crate::i18n::NumberStyle;
+use crate::models::{AppState, HistoryEntry, LineKind, UserFunction};
+use crate::prettify::{format_number, localize_number, DisplayFormat, Notation};
+use crate::spell::{can_spell, spell_amount, spell_number};
 
 #[derive(Debug, Clone)]
 pub struct EvalResult {
-    pub value: f64,
+    pub value: Number,
     pub unit: Option<String>,
+    /// Breakdown for a compound conversion target (`5 ft 10.87 in`), largest
+    /// unit first. `value` and `unit` then hold the total in the first unit.
+    pub parts: Vec<(Number, String)>,
+    /// Base for showing an integer value exactly instead of prettified.
+    pub radix: Option<Radix>,
+    /// Items of a list value (`[1, 2, 3]`, `1..10`). `value` and `unit` are
+    /// unused for lists.
+    pub items: Option<Vec<EvalResult>>,
+    /// Show the value as a percentage: `0.089` as `8.90%`.
+    pub percent: bool,
+    /// Notation asked for with a target such as `to sci` or `to 4 dp`.
+    pub notation: Option<Notation>,
+    /// Spell the value out in words (`to words`).
+    pub words: bool,
+    /// Standard uncertainty of the value (`5.0 ± 0.1 m`), in the same unit.
+    pub uncertainty: Option<f64>,
+    /// A point in time (`today + 3 days`). `value` and `unit` are unused.
+    pub moment: Option<Moment>,
 }
 
-pub struct EvalContext<'a> {
-    pub variables: &'a mut HashMap<String, (f64, Option<String>)>,
-    pub history: &'a [HistoryEntry],
-    pub length_units: &'a Units,
-    pub time_units: &'a Units,
-    pub temperature_units: &'a TempUnits,
-    pub area_units: &'a Units,
-    pub volume_units: &'a Units,
-    pub weight_units: &'a Units,
-    pub angular_units: &'a Units,
-    pub data_units: &'a Units,
-    pub speed_units: &'a Units,
-    pub rates: &'a Rates,
-    pub custom_units: &'a HashMap<String, HashMap<String, f64>>,
-}
-
-lazy_static! {
-    static ref PI_RE: Regex = Regex::new(r"\bpi\b").expect("Invalid regex pattern for pi constant");
-    static ref E_RE: Regex = Regex::new(r"\be\b").expect("Invalid regex pattern for e constant");
-    static ref PI_UPPER_RE: Regex =
-        Regex::new(r"\bPI\b").expect("Invalid regex pattern for PI constant");
-    static ref E_UPPER_RE: Regex =
-        Regex::new(r"\bE\b").expect("Invalid regex pattern for E constant");
-    static ref PERCENT_OF_RE: Regex = Regex::new(r"(\d+(?:\.\d+)?)%\s*of\s*(.+)")
-        .expect("Invalid regex pattern for percent-of expression");
-    static ref PERCENT_OP_RE: Regex = Regex::new(r"(\d+(?:\.\d+)?)\s*([+\-*/])\s*(\d+(?:\.\d+)?)%")
-        .expect("Invalid regex pattern for percent-operation expression");
-    static ref FUNC_RE: Regex =
-        Regex::new(r"(\w+)\s+(\d+(?:\.\d+)?)").expect("Invalid regex pattern for function parsing");
-    static ref HISTORY_TOKEN_RE: Regex = Regex::new(r"\b(sum|total|average|avg|prev)\b")
-        .expect("Invalid regex pattern for history tokens");
-}
-
-/// Returns Some(unit) if all history entries share the same non-empty unit, else None.
-pub(crate) fn all_same_unit(history: &[HistoryEntry]) -> Option<String> {
-    let mut unit: Option<String> = None;
-    for entry in history {
-        if let Some(u) = &entry.unit {
-            if let Some(existing) = &unit {
-                if existing != u {
-                    return None;
-                }
-            } else {
-                unit = Some(u.clone());
-            }
-        } else {
-            // Mixed unitless and unitful -> treat as unitless
-            return None;
+/// Base an integer result is shown in. Set by radix literals, bitwise
+/// operators and `to hex`-style targets; such results skip the k/M suffixes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Radix {
+    Decimal,
+    Hex,
+    Binary,
+    Octal,
+    /// `MMXXV`, for whole numbers from 1 to 3999
+    Roman,
+}
+
+impl Radix {
+    /// Radix for a conversion target word such as `hex` or `binary`.
+    fn from_target(word: &str) -> Option<Self> {
+        match word.to_lowercase().as_str() {
+            "hex" | "hexadecimal" => Some(Radix::Hex),
+            "binary" | "bin" => Some(Radix::Binary),
+            "octal" => Some(Radix::Octal),
+            "decimal" => Some(Radix::Decimal),
+            "roman" => Some(Radix::Roman),
+            _ => None,
+        }
+    }
+
+    /// `255` as `0xFF`, `0b11111111`, `0o377`, `CCLV` or `255`. Values
+    /// Roman numerals cannot write are shown in decimal.
+    fn format(self, value: i128) -> String {
+        let sign = if value < 0 { "-" } else { "" };
+        let abs = value.unsigned_abs();
+        match self {
+            Radix::Decimal => value.to_string(),
+            Radix::Hex => format!("{}0x{:X}", sign, abs),
+            Radix::Binary => format!("{}0b{:b}", sign, abs),
+            Radix::Octal => format!("{}0o{:o}", sign, abs),
+            Radix::Roman => u32::try_from(value)
+                .ok()
+                .and_then(to_roman)
+                .unwrap_or_else(|| value.to_string()),
+        }
+    }
+}
+
+impl EvalResult {
+    pub(crate) fn new(value: Number, unit: Option<String>) -> Self {
+        Self {
+            value,
+            unit,
+            parts: Vec::new(),
+            radix: None,
+            items: None,
+            percent: false,
+            notation: None,
+            words: false,
+            uncertainty: None,
+            moment: None,
         }
     }
-    unit
+
+    fn moment(moment: Moment) -> Self {
+        Self {
+            moment: Some(moment),
+            ..Self::unitless(Number::from(0i64))
+        }
+    }
+
+    pub(crate) fn list(items: Vec<EvalResult>) -> Self {
+        Self {
+            items: Some(items),
+            ..Self::unitless(Number::from(0i64))
+        }
+    }
+
+    /// The items of a list, or the result itself for a single value.
+    fn into_items(self) -> Vec<EvalResult> {
+        match self.items {
+            Some(items) => items,
+            None => vec![self],
+        }
+    }
+
+    /// Apply `f` to each item of a list, or to the value itself.
+    fn map_items(
+        self,
+        mut f: impl FnMut(EvalResult) -> Located<EvalResult>,
+    ) -> Located<EvalResult> {
+        match self.items {
+            Some(items) => Ok(Self::list(
+                items.into_iter().map(&mut f).collect::<Located<Vec<_>>>()?,
+            )),
+            None => f(self),
+        }
+    }
+
+    /// Show the result in `radix` unless a radix is already set.
+    fn with_radix(mut self, radix: Option<Radix>) -> Self {
+        self.radix = self.radix.or(radix);
+        self
+    }
+
+    fn with_uncertainty(mut self, uncertainty: Option<f64>) -> Self {
+        self.uncertainty = uncertainty;
+        self
+    }
+
+    fn unitless(value: Number) -> Self {
+        Self::new(value, None)
+    }
 }
 
-fn history_token_value(keyword: &str, history: &[HistoryEntry]) -> Result<f64> {
-    match keyword {
-        "sum" | "total" => Ok(history.iter().map(|h| h.value).sum::<f64>()),
-        "average" | "avg" => {
-            if history.is_empty() {
-                Err(EvaluatorError::InvalidExpression(crate::fl!(
-                    "cannot-compute-average-empty"
-                )))
+impl EvalResult {
+    /// Display text for the result in `display`, with the currency overrides
+    /// for units that `is_currency` says are currencies.
+    fn format(&self, display: &DisplayFormat, is_currency: &dyn Fn(&str) -> bool) -> String {
+        if let Some(moment) = &self.moment {
+            return moment.format("iso", "iso");
+        }
+        if let Some(items) = &self.items {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| item.format(display, is_currency))
+                .collect();
+            // `[1,5; 2]` rather than the ambiguous `[1,5, 2]` with decimal commas
+            let separator = if display.decimal_separator == Some(',') {
+                "; "
             } else {
-                Ok(history.iter().map(|h| h.value).sum::<f64>() / history.len() as f64)
+                ", "
+            };
+            return format!("[{}]", items.join(separator));
+        }
+        let is_currency = self.unit.as_deref().is_some_and(is_currency);
+        let display = display.for_value(is_currency).with_notation(self.notation);
+        if self.words {
+            if let Some(words) = self.spell(is_currency) {
+                return words;
             }
         }
-        "prev" => history
-            .last()
-            .map(|h| h.value)
-            .ok_or_else(|| EvaluatorError::InvalidExpression(crate::fl!("no-previous-result"))),
-        _ => Err(EvaluatorError::InvalidExpression(
-            "Unknown history keyword".to_string(),
-        )),
-    }
-}
-
-/// Replace inline history keywords so they can be used in larger expressions
-/// (e.g., `sum + 100`, `avg to USD`).
-fn replace_history_tokens(expr: &str, history: &[HistoryEntry]) -> Result<String> {
-    let err: RefCell<Option<EvaluatorError>> = RefCell::new(None);
-
-    let replaced = HISTORY_TOKEN_RE
-        .replace_all(expr, |caps: &regex::Captures| {
-            match history_token_value(caps.get(1).unwrap().as_str(), history) {
-                Ok(val) => val.to_string(),
-                Err(e) => {
-                    *err.borrow_mut() = Some(e);
-                    String::new()
+        if let Some(((last, last_unit), whole)) = self.parts.split_last() {
+            let whole_display = display.whole();
+            let mut text = String::new();
+            for (value, unit) in whole {
+                text.push_str(&format!(
+                    "{} {} ",
+                    format_number(value.to_f64(), &whole_display),
+                    unit
+                ));
+            }
+            // Leftovers that are whole up to rounding error print without decimals
+            let rounded = last.to_f64().round();
+            let last = if (last.to_f64() - rounded).abs() < 1e-9 {
+                format_number(rounded + 0.0, &whole_display)
+            } else {
+                format_value(*last, &display, self.notation)
+            };
+            text.push_str(&format!("{} {}", last, last_unit));
+            return text;
+        }
+        let formatted = match (self.radix, self.value.as_integer()) {
+            (Some(radix), Some(integer)) => radix.format(integer),
+            _ if self.percent => {
+                format!("{}%", format_number(self.value.to_f64() * 100.0, &display))
+            }
+            _ => format_value(self.value, &display, self.notation),
+        };
+        let formatted = match self.uncertainty {
+            Some(error) => format!("{} ± {}", formatted, format_number(error, &display)),
+            None => formatted,
+        };
+        let symbol = self
+            .unit
+            .as_deref()
+            .filter(|_| is_currency && display.currency_symbols)
+            .and_then(currency_symbol);
+        match (symbol, &self.unit) {
+            (Some(symbol), _) if display.symbol_first == Some(true) => {
+                match formatted.strip_prefix('-') {
+                    Some(amount) => format!("-{}{}", symbol, amount),
+                    None => format!("{}{}", symbol, formatted),
                 }
             }
-        })
-        .to_string();
+            (Some(symbol), _) => format!("{} {}", formatted, symbol),
+            (None, Some(unit)) => format!("{} {}", formatted, unit),
+            (None, None) => formatted,
+        }
+    }
 
-    if let Some(e) = err.into_inner() {
-        Err(e)
-    } else {
-        Ok(replaced)
+    /// The value in words in the interface language; currency amounts are
+    /// written cheque-style with the cents as a fraction.
+    fn spell(&self, is_currency: bool) -> Option<String> {
+        let locale = crate::i18n::get_locale().to_string();
+        let value = self.value.to_f64();
+        match &self.unit {
+            Some(unit) if is_currency => spell_amount(value, unit, &locale),
+            Some(unit) => spell_number(value, &locale).map(|words| format!("{} {}", words, unit)),
+            None => spell_number(value, &locale),
+        }
     }
 }
 
-fn parse_conversion_result(val: String) -> EvalResult {
-    let parts: Vec<&str> = val.split_whitespace().collect();
-    let value = parts
-        .first()
-        .and_then(|v| crate::conversions::parse_number_with_scale(v))
-        .unwrap_or(0.0);
-    let unit = if parts.len() > 1 {
-        Some(parts[1].to_string())
-    } else {
-        None
-    };
-    EvalResult { value, unit }
+impl fmt::Display for EvalResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.format(&DisplayFormat::default(), &|_| false))
+    }
 }
 
-/// Evaluate an expression
-pub fn evaluate_expr(expr: &str, ctx: &mut EvalContext) -> Result<EvalResult> {
-    evaluate_expr_with_original(expr, ctx, None)
+/// Display text for a value: fractions as mixed numbers (`2 3/4`) unless
+/// the line asks for a notation (`1/3 to 4 dp`), everything else formatted
+/// as `display` says. Decimals shown to the line's `N dp` keep their exact
+/// digits.
+fn format_value(value: Number, display: &DisplayFormat, notation: Option<Notation>) -> String {
+    if let Some(Notation::Decimals(decimals)) = notation {
+        if let Some(text) = value.to_fixed(decimals) {
+            if !display.uses_exponent(value.to_f64().abs()) {
+                return localize_number(&text, display);
+            }
+        }
+    }
+    match value {
+        Number::Fraction(fraction) if fraction.denominator() != 1 && notation.is_none() => {
+            fraction.mixed()
+        }
+        _ => format_number(value.to_f64(), display),
+    }
 }
 
-/// Evaluate an expression with optional original expression for unit tracking
-///
-/// # Arguments
-/// * `expr` - The preprocessed expression to evaluate
-/// * `ctx` - The evaluation context
-/// * `original_expr` - Optional original expression (before variable substitution) for unit tracking
-pub fn evaluate_expr_with_original(
-    expr: &str,
-    ctx: &mut EvalContext,
-    original_expr: Option<&str>,
-) -> Result<EvalResult> {
-    // Preprocessing is now handled elsewhere before calling this function
-    // This function receives already preprocessed input
-    let mut expr_str = expr.to_string();
-
-    // Pre-process percentage expressions in parentheses first
-    expr_str = preprocess_percentage_parens(expr_str);
-
-    // Track unit from variables that are actually used in the ORIGINAL expression
-    // (before preprocessing replaced variable names with values)
-    let expr_for_unit_check = original_expr.unwrap_or(expr);
-    let mut has_unit = false;
-    let mut result_unit = None;
-    for (var_name, (_val, unit)) in ctx.variables.iter() {
-        if unit.is_some() && expr_for_unit_check.contains(var_name) {
-            has_unit = true;
-            result_unit = unit.clone();
-            break;
-        }
-    }
-
-    // Special commands
-    let trimmed = expr_str.trim();
-    if trimmed == "sum" || trimmed == "total" {
-        let value = ctx.history.iter().map(|h| h.value).sum::<f64>();
-        let unit = all_same_unit(ctx.history);
-        return Ok(EvalResult { value, unit });
-    }
-    if trimmed == "average" || trimmed == "avg" {
-        if ctx.history.is_empty() {
-            return Err(EvaluatorError::InvalidExpression(crate::fl!(
-                "cannot-compute-average-empty"
-            )));
+pub struct EvalContext<'a> {
+    pub variables: &'a mut HashMap<String, (Number, Option<String>)>,
+    pub history: &'a [HistoryEntry],
+    pub units: UnitRegistry<'a>,
+    pub operators: &'a HashMap<String, String>,
+    pub scales: &'a HashMap<String, f64>,
+    pub functions: &'a HashMap<String, String>,
+    /// Functions defined in the document, shadowing built-ins of the same name.
+    pub user_functions: HashMap<String, UserFunction>,
+    /// List variables (`prices = 12, 15, 9`).
+    pub lists: HashMap<String, Vec<(Number, Option<String>)>>,
+    /// Uncertainties of variables measured with one (`l = 5.0 ± 0.1 m`).
+    pub uncertainties: HashMap<String, f64>,
+    /// Named constants (`c`, `G`, `phi`), shadowed by variables.
+    pub constants: &'a HashMap<String, Constant>,
+    /// Values of the document's lines by index, for `line 3` and `above`.
+    pub line_results: HashMap<usize, HistoryEntry>,
+    /// Index of the line being evaluated, if it is part of a document.
+    pub current_line: Option<usize>,
+    /// Section headers and total lines of the document by index.
+    pub line_kinds: HashMap<usize, LineKind>,
+    /// Values of the lines above in a document, top-down, which `sum`,
+    /// `average` and `prev` use instead of `history` unless the history
+    /// mode is `session`.
+    document_history: Option<Vec<HistoryEntry>>,
+    pub number_mode: NumberMode,
+    pub number_style: NumberStyle,
+    pub display: &'a DisplayFormat,
+    /// Time zone of `now` and `today`: the configured default, or local time if unset.
+    pub time_zone: Option<Tz>,
+    pub city_aliases: &'a HashMap<String, String>,
+    /// `date_format` and `time_format` keys dates and times are shown in.
+    pub date_format: &'a str,
+    pub time_format: &'a str,
+    /// Parameter bindings of the user function calls in progress, innermost last.
+    scopes: RefCell<Vec<HashMap<String, EvalResult>>>,
+}
+
+/// Built-in functions. Aliases from `config.functions` resolve to these names.
+const FUNCTIONS: &[&str] = &[
+    "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh", "tanh", "asinh", "acosh", "atanh",
+    "sqrt", "cbrt", "exp", "ln", "log", "log10", "log2", "abs", "round", "ceil", "floor", "int",
+    "trunc", "sign", "min", "max", "sum", "mean", "median", "mode", "stdev", "variance", "count",
+    "percentile", "pmt", "fv", "npv", "irr", "integrate", "derive", "prod",
+];
+
+/// Built-ins that reduce all their arguments, with lists flattened, to one value.
+const AGGREGATES: &[&str] = &[
+    "sum", "mean", "median", "mode", "stdev", "variance", "min", "max", "count", "percentile",
+];
+
+/// Time-value-of-money built-ins.
+const FINANCIAL: &[&str] = &["pmt", "fv", "npv", "irr"];
+
+/// Built-ins over an expression in a bound variable, named by the second
+/// argument: `integrate(x^2, x, 0, 3)`, `derive(sin(x), x, pi/4)` and
+/// `prod(k, k, 1, 5)`. `sum` is one too when written that way.
+const CALCULUS: &[&str] = &["integrate", "derive", "prod"];
+
+/// Subintervals an integral is split into before refining adaptively, so
+/// a periodic integrand can't fool the first estimate.
+const INTEGRAL_PIECES: usize = 16;
+
+/// Most times an integral's subintervals are halved.
+const MAX_INTEGRAL_DEPTH: u32 = 20;
+
+/// Most values a range may produce, so `1..1e12` fails instead of exhausting memory.
+const MAX_RANGE_LEN: u128 = 100_000;
+
+/// Nesting limit for user function calls, so `f(x) = f(x)` fails instead of
+/// overflowing the stack.
+const MAX_CALL_DEPTH: usize = 16;
+
+/// Conversion targets that turn the value into a fraction (`0.375 to fraction`).
+const FRACTION_TARGETS: &[&str] = &["fraction", "fractions"];
+
+/// Conversion targets that spell the value out in words.
+const WORDS_TARGETS: &[&str] = &["words", "word"];
+
+/// Conversion target that shows the value as a plain decimal number.
+const NUMBER_TARGET: &str = "number";
+
+/// True for the targets that read a bare numeral: `to number`, `to roman`.
+fn is_numeral_target(targets: &[String]) -> bool {
+    matches!(targets, [target] if target.eq_ignore_ascii_case(NUMBER_TARGET)
+        || Radix::from_target(target) == Some(Radix::Roman))
+}
+
+impl<'a> EvalContext<'a> {
+    /// Build a context from the state's unit tables and rates and the
+    /// config's operator words, scales and function aliases.
+    pub fn new(
+        variables: &'a mut HashMap<String, (Number, Option<String>)>,
+        history: &'a [HistoryEntry],
+        state: &'a AppState,
+        config: &'a Config,
+    ) -> Self {
+        let line_results = state
+            .line_results
+            .read()
+            .map(|line_results| line_results.clone())
+            .unwrap_or_default();
+        let current_line = state.current_line.read().ok().and_then(|line| *line);
+        let line_kinds = state
+            .line_kinds
+            .read()
+            .map(|line_kinds| line_kinds.clone())
+            .unwrap_or_default();
+        let document_history = current_line
+            .filter(|_| config.history_mode != "session")
+            .map(|current| {
+                (0..current)
+                    .filter(|index| !line_kinds.contains_key(index))
+                    .filter_map(|index| line_results.get(&index).cloned())
+                    .collect()
+            });
+        Self {
+            variables,
+            history,
+            units: UnitRegistry::new(state, config),
+            operators: &config.operators,
+            scales: &config.scales,
+            functions: &config.functions,
+            user_functions: state
+                .functions
+                .read()
+                .map(|functions| functions.clone())
+                .unwrap_or_default(),
+            lists: state
+                .lists
+                .read()
+                .map(|lists| lists.clone())
+                .unwrap_or_default(),
+            uncertainties: state
+                .uncertainties
+                .read()
+                .map(|uncertainties| uncertainties.clone())
+                .unwrap_or_default(),
+            constants: &state.constants,
+            line_results,
+            current_line,
+            line_kinds,
+            document_history,
+            number_mode: NumberMode::from_config(&config.number_mode),
+            number_style: crate::i18n::number_style(config.number_locale.as_deref()),
+            display: &state.display,
+            time_zone: config
+                .default_timezone
+                .as_deref()
+                .and_then(|zone| resolve_zone(zone, &config.city_aliases)),
+            city_aliases: &config.city_aliases,
+            date_format: &state.date_format,
+            time_format: &state.time_format,
+            scopes: RefCell::new(Vec::new()),
         }
-        let value = ctx.history.iter().map(|h| h.value).sum::<f64>() / ctx.history.len() as f64;
-        let unit = all_same_unit(ctx.history);
-        return Ok(EvalResult { value, unit });
     }
-    if trimmed == "prev" {
-        return ctx
-            .history
-            .last()
-            .map(|h| EvalResult {
-                value: h.value,
-                unit: h.unit.clone(),
-            })
-            .ok_or_else(|| EvaluatorError::InvalidExpression(crate::fl!("no-previous-result")));
-    }
-
-    // Allow history tokens inside larger expressions (e.g., "sum + 100")
-    expr_str = replace_history_tokens(&expr_str, ctx.history)?;
-
-    expr_str = apply_replacements(expr_str);
-    expr_str = apply_function_parsing(expr_str);
-    if let Some(result_str) = parse_percentage_op(&expr_str) {
-        let value = result_str
-            .split_whitespace()
-            .next()
-            .unwrap_or(&result_str)
-            .parse::<f64>()
-            .map_err(|e| {
-                EvaluatorError::ParseError(
-                    crate::fl!("failed-parse-percentage", "error" => &e.to_string()),
-                )
-            })?;
-        return Ok(EvalResult { value, unit: None });
-    }
-
-    // Percentage expressions: "X% of Y"
-    if let Some(caps) = PERCENT_OF_RE.captures(&expr_str) {
-        if let (Some(percent_str), Some(base_str)) = (caps.get(1), caps.get(2)) {
-            if let Ok(percent) = percent_str.as_str().parse::<f64>() {
-                if let Ok(base_result) = evaluate_expr(base_str.as_str(), ctx) {
-                    let result = percent / 100.0 * base_result.value;
-                    return Ok(EvalResult {
-                        value: result,
-                        unit: base_result.unit.clone(),
-                    });
-                }
-            }
+
+    /// Display text for `result` in the configured number format.
+    pub fn format(&self, result: &EvalResult) -> String {
+        if let Some(moment) = &result.moment {
+            return moment.format(self.date_format, self.time_format);
         }
+        let is_currency = |unit: &str| {
+            self.units
+                .parse(unit)
+                .is_some_and(|unit| self.units.is_currency(&unit))
+        };
+        result.format(&self.display.localized(self.number_style), &is_currency)
     }
 
-    // Percentage operations: "X + Y%" or "X - Y%" etc.
-    if let Some(caps) = PERCENT_OP_RE.captures(&expr_str) {
-        if let (Some(base_str), Some(op), Some(percent_str)) =
-            (caps.get(1), caps.get(2), caps.get(3))
-        {
-            if let (Ok(base), Ok(percent)) = (
-                base_str.as_str().parse::<f64>(),
-                percent_str.as_str().parse::<f64>(),
-            ) {
-                let percent_decimal = percent / 100.0;
-                let result = match op.as_str() {
-                    "+" => base + (base * percent_decimal),
-                    "-" => base - (base * percent_decimal),
-                    "*" => base * percent_decimal,
-                    "/" => base / percent_decimal,
-                    _ => {
-                        return Err(EvaluatorError::InvalidExpression(
-                            crate::fl!("invalid-percentage-operator", "op" => op.as_str()),
-                        ))
-                    }
+    fn parse_unit(&self, name: &str) -> Result<Unit> {
+        self.units.parse(name).ok_or_else(|| unknown_unit(name))
+    }
+
+    /// Resolve a conversion target. Currency words that are also other units
+    /// (`pounds`) are read as currencies when converting from a currency, and
+    /// a change in temperature converts to a change on the target scale.
+    fn resolve_target(&self, name: &str, source: &Unit) -> Option<(Unit, String)> {
+        if self.units.is_currency(source) {
+            if let Some(code) = self.units.currency(name) {
+                let display = if currency_word_to_code(&name.to_lowercase()).is_some() {
+                    code.to_string()
+                } else {
+                    name.to_string()
                 };
-                return Ok(EvalResult {
-                    value: result,
-                    unit: None,
-                });
-            }
-        }
-    }
-
-    // Unit conversion (supports trailing math, e.g., "sum to USD + 100")
-    let conversion_keyword = expr_str
-        .find(" in ")
-        .map(|pos| (" in ", pos))
-        .or_else(|| expr_str.find(" to ").map(|pos| (" to ", pos)));
-    if let Some((kw, pos)) = conversion_keyword {
-        let left = expr_str[..pos].trim();
-        let right_raw = expr_str[pos + kw.len()..].trim();
-
-        // Split right side into target unit and optional trailing expression
-        let mut right_iter = right_raw.split_whitespace();
-        let target_unit = right_iter.next().unwrap_or("");
-        let trailing_expr: String = right_iter.collect::<Vec<&str>>().join(" ");
-        let has_trailing = !trailing_expr.trim().is_empty();
-        let right_for_conversion = if target_unit.is_empty() {
-            right_raw
-        } else {
-            target_unit
+                return Some((self.units.parse(code)?, display));
+            }
+        }
+        let target = self.units.parse(name)?;
+        if let (Some(Temperature::Difference(_)), Some(Temperature::Reading(_))) = (
+            self.units.temperature(source),
+            self.units.temperature(&target),
+        ) {
+            let name = format!("{} difference", name);
+            return Some((self.units.parse(&name)?, name));
+        }
+        Some((target, self.unit(name)?))
+    }
+
+    /// Convert `value` from one unit to another, returning the converted value
+    /// and the display name of the target unit.
+    fn convert(&self, value: Number, from: &str, to: &str) -> Result<(Number, String)> {
+        let mode = self.number_mode;
+        let source = self.parse_unit(from)?;
+        let (target, display) = self
+            .resolve_target(to, &source)
+            .ok_or_else(|| unknown_unit(to))?;
+
+        let converted = match self.units.conversion(&source, &target) {
+            Some(Conversion::Scale(factor)) => Some(self.scale(value, factor)),
+            Some(Conversion::Temperature(a, b)) => {
+                convert_temperature(value.to_f64(), a, b).map(|v| Number::from_f64(v, mode))
+            }
+            None => None,
         };
 
-        let try_conversion = |source: &str| -> Option<EvalResult> {
-            evaluate_unit_conversion(
-                source,
-                right_for_conversion,
-                ctx.length_units,
-                ctx.time_units,
-                ctx.temperature_units,
-                ctx.area_units,
-                ctx.volume_units,
-                ctx.weight_units,
-                ctx.angular_units,
-                ctx.data_units,
-                ctx.speed_units,
-                ctx.rates,
-                ctx.custom_units,
+        converted.map(|v| (v, display)).ok_or_else(|| {
+            EvaluatorError::EvaluationError(
+                crate::fl!("incompatible-units", "from" => from, "to" => to),
             )
-            .map(parse_conversion_result)
-        };
+        })
+    }
+
+    fn scale(&self, value: Number, factor: Factor) -> Number {
+        if factor == Factor::ONE {
+            return value;
+        }
+        let mode = self.number_mode;
+        value * Number::from_f64(factor.num, mode) / Number::from_f64(factor.den, mode)
+    }
 
-        // First, try direct conversion (e.g., "100 USD" to "EUR")
-        if let Some(converted) = try_conversion(left) {
-            if has_trailing {
-                let base = if let Some(unit) = &converted.unit {
-                    format!("{} {}", converted.value, unit)
+    /// Sum or difference of two temperatures. A change moves a reading
+    /// (`20 C + 9 ΔF` is `25 C`) and two readings differ by a change (`20 C -
+    /// 50 F` is `10 C difference`), but readings can't be added. `None` when
+    /// the operands aren't both temperatures.
+    fn add_temperatures<'r>(
+        &self,
+        left: &'r EvalResult,
+        right: &'r EvalResult,
+        subtract: bool,
+    ) -> Result<Option<EvalResult>> {
+        let temperature = |unit: Option<&'r str>| {
+            let unit = unit?;
+            Some((unit, self.units.temperature(&self.units.parse(unit)?)?))
+        };
+        let (Some((a, left_kind)), Some((b, right_kind))) = (
+            temperature(left.unit.as_deref()),
+            temperature(right.unit.as_deref()),
+        ) else {
+            return Ok(None);
+        };
+        let in_unit = |value: Number, from: &str, to: &str| -> Result<Number> {
+            if from == to {
+                return Ok(value);
+            }
+            Ok(self.convert(value, from, to)?.0)
+        };
+        let result = match (left_kind, right_kind) {
+            (Temperature::Reading(_), Temperature::Reading(_)) if subtract => EvalResult::new(
+                left.value - in_unit(right.value, b, a)?,
+                Some(format!("{} difference", a)),
+            ),
+            (Temperature::Reading(_), Temperature::Reading(_)) => {
+                return Err(EvaluatorError::EvaluationError(
+                    crate::fl!("temperature-sum", "unit" => a),
+                ))
+            }
+            (Temperature::Reading(_), Temperature::Difference(_)) => {
+                let change = in_unit(right.value, b, &format!("{} difference", a))?;
+                let value = if subtract {
+                    left.value - change
                 } else {
-                    converted.value.to_string()
+                    left.value + change
                 };
-                let combined = format!("{} {}", base, trailing_expr);
-                return evaluate_expr(&combined, ctx);
+                EvalResult::new(value, Some(a.to_string()))
+            }
+            (Temperature::Difference(_), Temperature::Reading(_)) if !subtract => {
+                let change = in_unit(left.value, a, &format!("{} difference", b))?;
+                EvalResult::new(change + right.value, Some(b.to_string()))
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(result))
+    }
+
+    /// `moment` moved by `duration` (`today + 3 days`). Months and years
+    /// move by calendar months; other units by their length in seconds.
+    fn shift_moment(&self, moment: Moment, duration: &EvalResult, back: bool) -> Result<Moment> {
+        let needs_duration = || {
+            EvaluatorError::EvaluationError(crate::fl!(
+                "date-needs-duration",
+                "value" => duration.to_string()
+            ))
+        };
+        let unit = duration.unit.as_deref().ok_or_else(needs_duration)?;
+        let amount = if back {
+            -duration.value
+        } else {
+            duration.value
+        };
+        let moved = match (calendar_months(unit), amount.as_integer()) {
+            (Some(months), Some(count)) => i64::try_from(count * months)
+                .ok()
+                .and_then(|n| moment.add_months(n)),
+            _ => {
+                let (seconds, _) = self
+                    .convert(amount, unit, "s")
+                    .map_err(|_| needs_duration())?;
+                moment.add_seconds(seconds.to_f64())
+            }
+        };
+        moved.ok_or_else(|| EvaluatorError::EvaluationError(crate::fl!("invalid-date")))
+    }
+
+    /// Yearly rate and term in months of an interest phrase such as
+    /// `at 6.5% over 30 years`.
+    pub(crate) fn interest_terms(&self, rate: &Expr, term: &Expr) -> Located<(f64, f64)> {
+        let rate_result = evaluate(rate, self)?;
+        if rate_result.unit.is_some() || rate_result.items.is_some() {
+            return Err(Diagnostic::new(EvaluatorError::EvaluationError(crate::fl!(
+                "interest-rate",
+                "value" => self.format(&rate_result)
+            )))
+            .at(rate.span));
+        }
+        let term_result = evaluate(term, self)?;
+        let needs_term = || {
+            Diagnostic::new(EvaluatorError::EvaluationError(crate::fl!(
+                "interest-term",
+                "value" => self.format(&term_result)
+            )))
+            .at(term.span)
+        };
+        let unit = term_result.unit.as_deref().ok_or_else(needs_term)?;
+        let months = match calendar_months(unit) {
+            Some(months) => term_result.value.to_f64() * months as f64,
+            None => {
+                let (years, _) = self
+                    .convert(term_result.value, unit, "years")
+                    .map_err(|_| needs_term())?;
+                years.to_f64() * 12.0
             }
-            return Ok(converted);
+        };
+        Ok((rate_result.value.to_f64(), months))
+    }
+
+    /// Results `sum`, `average` and `prev` draw on: the lines above in a
+    /// document, or the session's history.
+    fn history_entries(&self) -> &[HistoryEntry] {
+        self.document_history.as_deref().unwrap_or(self.history)
+    }
+
+    /// `sum`/`total` or `average`/`avg` of the history.
+    pub(crate) fn history_aggregate(&self, keyword: &str) -> Result<EvalResult> {
+        let history = self.history_entries();
+        let average = matches!(keyword, "average" | "avg");
+        let (total, unit) = self.total(history, average)?;
+        if !average {
+            return Ok(EvalResult::new(total, unit));
         }
+        if history.is_empty() {
+            return Err(EvaluatorError::InvalidExpression(crate::fl!(
+                "cannot-compute-average-empty"
+            )));
+        }
+        let count = Number::from(history.len() as i64);
+        Ok(EvalResult::new(total / count, unit))
+    }
 
-        // If direct conversion failed, try evaluating left side first (e.g., "150 USD * 5" to "JPY")
-        if let Ok(left_result) = evaluate_expr(left, ctx) {
-            let left_with_unit = if let Some(unit) = left_result.unit.clone() {
-                format!("{} {}", left_result.value, unit)
-            } else {
-                left_result.value.to_string()
-            };
+    /// `prev`: the latest result in the history.
+    pub(crate) fn previous(&self) -> Result<EvalResult> {
+        self.history_entries()
+            .last()
+            .map(|h| EvalResult::new(h.value, h.unit.clone()))
+            .ok_or_else(|| EvaluatorError::InvalidExpression(crate::fl!("no-previous-result")))
+    }
 
-            if let Some(converted) = try_conversion(&left_with_unit) {
-                if has_trailing {
-                    let base = if let Some(unit) = &converted.unit {
-                        format!("{} {}", converted.value, unit)
-                    } else {
-                        converted.value.to_string()
-                    };
-                    let combined = format!("{} {}", base, trailing_expr);
-                    return evaluate_expr(&combined, ctx);
+    /// Sum of `entries` in the unit most of them are in. Other units are
+    /// converted to it, currencies at the current rates, and plain numbers
+    /// count in it as they do in `5 km + 3`; a unit that doesn't convert,
+    /// like `USD` among lengths, is an error. Temperatures add up as they do
+    /// with `+`, so two readings are an error too, unless the total is for
+    /// an `average`: readings then convert to one scale like other units.
+    fn total(&self, entries: &[HistoryEntry], average: bool) -> Result<(Number, Option<String>)> {
+        let unit = dominant_unit(entries);
+        let mut total = EvalResult::new(Number::from(0i64), unit.clone());
+        for (index, entry) in entries.iter().enumerate() {
+            let item = EvalResult::new(entry.value, entry.unit.clone());
+            if index > 0 && !average {
+                if let Some(sum) = self.add_temperatures(&total, &item, false)? {
+                    total = sum;
+                    continue;
                 }
-                return Ok(converted);
-            }
-        }
-    }
-
-    // Try to parse and handle unit algebra for multiplication/division
-    // Check if expression contains units and operations
-    let result_from_algebra = try_evaluate_with_unit_algebra(&expr_str, ctx);
-    if result_from_algebra.is_ok() {
-        return result_from_algebra;
-    }
-
-    // Fall back to original unit extraction logic for simple cases
-    let mut num_expr = expr_str.clone();
-    let mut found_unit = None;
-    let words: Vec<&str> = expr_str.split_whitespace().collect();
-    for word in words {
-        let lower = word.to_lowercase();
-        let upper = word.to_uppercase();
-        if ctx.length_units.get(&lower).is_some()
-            || ctx.time_units.get(&lower).is_some()
-            || ctx.temperature_units.get(&lower).is_some()
-            || ctx.area_units.get(&lower).is_some()
-            || ctx.volume_units.get(&lower).is_some()
-            || ctx.weight_units.get(&lower).is_some()
-            || ctx.angular_units.get(&lower).is_some()
-            || ctx.data_units.get(&lower).is_some()
-            || ctx.speed_units.get(&lower).is_some()
-            || ctx.rates.get(&upper).is_some()
-            || ctx.custom_units.values().any(|u| u.contains_key(&lower))
-        {
-            num_expr = num_expr.replace(word, "");
-            found_unit = Some(word);
-        }
-    }
-    // Functions
-    num_expr = FUNC_RE.replace_all(&num_expr, "$1($2)").to_string();
-
-    num_expr = num_expr.replace(" ", "").trim().to_string();
-    // Eval
-    if let Ok(val) = num_expr.parse::<f64>() {
-        let unit = if has_unit && result_unit.is_some() {
-            result_unit.clone()
-        } else {
-            found_unit.map(|u| u.to_string())
-        };
-        Ok(EvalResult { value: val, unit })
-    } else {
-        let mut ns = fasteval2::EmptyNamespace;
-        match fasteval2::ez_eval(&num_expr, &mut ns) {
-            Ok(val) => {
-                let unit = if has_unit && result_unit.is_some() {
-                    result_unit.clone()
-                } else {
-                    found_unit.map(|u| u.to_string())
-                };
-                Ok(EvalResult { value: val, unit })
             }
-            Err(e) => Err(EvaluatorError::EvaluationError(
-                crate::fl!("failed-evaluate-expression", "error" => &e.to_string()),
-            )),
+            let value = match (&total.unit, &entry.unit) {
+                (Some(to), Some(from)) if from != to => {
+                    let (value, _) = self.convert(entry.value, from, to).map_err(|_| {
+                        EvaluatorError::EvaluationError(crate::fl!(
+                            "mixed-units",
+                            "unit" => to,
+                            "other" => from
+                        ))
+                    })?;
+                    value
+                }
+                _ => entry.value,
+            };
+            total.value = total.value + value;
         }
+        Ok((total.value, total.unit))
     }
-}
 
-/// Try to evaluate an expression with unit algebra (multiplication/division)
-fn try_evaluate_with_unit_algebra(expr: &str, ctx: &EvalContext) -> Result<EvalResult> {
-    // Simple regex to match patterns like: "number unit * number unit" or "number unit * number"
-    lazy_static! {
-        static ref MULT_DIV_RE: Regex =
-            Regex::new(r"^\s*([\d.]+)\s+([a-zA-Z]+)\s*([*/])\s*([\d.]+)\s*([a-zA-Z]*)\s*$")
-                .expect("Invalid unit algebra regex");
+    /// Value of the document line at `index`, which must be above the line
+    /// being evaluated.
+    fn line_result(&self, index: usize) -> Result<EvalResult> {
+        if self.current_line.is_some_and(|current| index >= current) {
+            return Err(EvaluatorError::EvaluationError(
+                crate::fl!("line-not-above", "line" => index + 1),
+            ));
+        }
+        self.line_results
+            .get(&index)
+            .map(|entry| EvalResult::new(entry.value, entry.unit.clone()))
+            .ok_or_else(|| {
+                EvaluatorError::EvaluationError(crate::fl!("line-no-result", "line" => index + 1))
+            })
     }
 
-    if let Some(caps) = MULT_DIV_RE.captures(expr) {
-        let left_val: f64 = caps[1].parse().map_err(|_| {
-            EvaluatorError::ParseError(crate::fl!("unit-algebra-parse-left"))
-        })?;
-        let left_unit = caps[2].to_string();
-        let op = &caps[3];
-        let right_val: f64 = caps[4].parse().map_err(|_| {
-            EvaluatorError::ParseError(crate::fl!("unit-algebra-parse-right"))
+    /// Index of the line being evaluated, for the keyword `name` that needs it.
+    fn document_line(&self, name: &str) -> Result<usize> {
+        self.current_line.ok_or_else(|| {
+            EvaluatorError::EvaluationError(crate::fl!("no-line-context", "name" => name))
+        })
+    }
+
+    /// Sum or average of the lines above this one in its section, from the
+    /// nearest header down, or of the whole document for `grand total`.
+    /// Totals are left out so they aren't counted twice.
+    fn section_aggregate(&self, name: &str) -> Result<EvalResult> {
+        let current = self.document_line(name)?;
+        let mut lines = Vec::new();
+        for index in (0..current).rev() {
+            match self.line_kinds.get(&index) {
+                Some(LineKind::Header) if name != "grand total" => break,
+                Some(_) => continue,
+                None => lines.extend(self.line_results.get(&index).cloned()),
+            }
+        }
+        lines.reverse();
+        let average = name == "section avg";
+        let (total, unit) = self.total(&lines, average)?;
+        if !average {
+            return Ok(EvalResult::new(total, unit));
+        }
+        if lines.is_empty() {
+            return Err(EvaluatorError::EvaluationError(crate::fl!("section-empty")));
+        }
+        let count = Number::from(lines.len() as i64);
+        Ok(EvalResult::new(total / count, unit))
+    }
+
+    /// Sum of the block of lines with a value directly above this one, up
+    /// to the nearest blank line, header or line without a value, leaving
+    /// out totals within it.
+    fn block_above(&self, name: &str) -> Result<EvalResult> {
+        let current = self.document_line(name)?;
+        let mut block: Vec<HistoryEntry> = (0..current)
+            .rev()
+            .map_while(|index| self.line_results.get(&index).map(|entry| (index, entry)))
+            .filter(|(index, _)| self.line_kinds.get(index) != Some(&LineKind::Total))
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        block.reverse();
+        let (total, unit) = self.total(&block, false)?;
+        Ok(EvalResult::new(total, unit))
+    }
+
+    /// Uncertainty of a value in `from`, in `to` instead. Only the scale of the
+    /// conversion applies, so ± 1 C is ± 1.8 F.
+    fn convert_uncertainty(&self, error: f64, from: &str, to: &str) -> Result<f64> {
+        let source = self.parse_unit(from)?;
+        let (target, _) = self
+            .resolve_target(to, &source)
+            .ok_or_else(|| unknown_unit(to))?;
+        let factor = self.units.linear_factor(&source, &target).ok_or_else(|| {
+            EvaluatorError::EvaluationError(
+                crate::fl!("incompatible-units", "from" => from, "to" => to),
+            )
         })?;
-        let right_unit = caps.get(5).map(|m| m.as_str().to_string());
+        Ok(error * factor.to_f64())
+    }
 
-        // Perform the operation
-        let value = match op {
-            "*" => left_val * right_val,
-            "/" => left_val / right_val,
-            _ => {
-                return Err(EvaluatorError::InvalidExpression(
-                    crate::fl!("unit-algebra-unsupported-op"),
-                ))
+    /// Uncertainty of `result` in the unit `unit` of a result computed from it.
+    fn uncertainty_in(&self, result: &EvalResult, unit: Option<&str>) -> Result<Option<f64>> {
+        match (result.uncertainty, result.unit.as_deref(), unit) {
+            (Some(error), Some(from), Some(to)) if from != to => {
+                Ok(Some(self.convert_uncertainty(error, from, to)?))
             }
+            (error, _, _) => Ok(error),
+        }
+    }
+
+    /// Multiply or divide two results, combining the dimensions of their units.
+    fn combine(&self, left: EvalResult, right: EvalResult, divide: bool) -> Result<EvalResult> {
+        if divide && right.value == 0.0 {
+            return Err(EvaluatorError::EvaluationError(crate::fl!(
+                "division-by-zero"
+            )));
+        }
+        let (a, b) = (left.value.to_f64(), right.value.to_f64());
+        // First-order propagation: d(ab) = b·da + a·db, d(a/b) = da/b - a·db/b²
+        let (da, db) = (left.uncertainty, right.uncertainty);
+        let uncertainty = if divide {
+            quadrature(da.map(|da| da / b), db.map(|db| a * db / (b * b)))
+        } else {
+            quadrature(da.map(|da| da * b), db.map(|db| a * db))
+        };
+        let result = self.combine_values(left, right, divide)?;
+        // The product is rescaled when units merge (km * m is in km²)
+        let raw = if divide { a / b } else { a * b };
+        let scale = if raw != 0.0 {
+            (result.value.to_f64() / raw).abs()
+        } else {
+            1.0
         };
+        Ok(result.with_uncertainty(uncertainty.map(|error| error * scale)))
+    }
 
-        // Determine result unit based on operation and units
-        let unit = match (op, &right_unit) {
-            ("*", None) => {
-                // number unit * number = number unit (e.g., 5 feet * 2 = 10 feet)
-                Some(left_unit)
-            }
-            ("*", Some(right_u)) if right_u.is_empty() => {
-                // number unit * number = number unit (e.g., 5 feet * 2 = 10 feet)
-                Some(left_unit)
-            }
-            ("*", Some(right_u)) if right_u == &left_unit => {
-                // Same unit multiplication: feet * feet = feet² (but for now, keep first unit)
-                // This is where you'd implement proper unit squaring
-                Some(left_unit)
-            }
-            ("*", Some(right_u)) if is_currency(right_u, ctx) => {
-                // area unit * currency = currency (e.g., feet² * USD = USD)
-                Some(right_u.clone())
+    fn combine_values(
+        &self,
+        left: EvalResult,
+        right: EvalResult,
+        divide: bool,
+    ) -> Result<EvalResult> {
+        if let (Some(a), Some(b)) = (&left.unit, &right.unit) {
+            // A Celsius or Fahrenheit reading only scales by plain numbers
+            let offset = [a, b].into_iter().find(|unit| {
+                self.units
+                    .parse(unit)
+                    .and_then(|unit| self.units.temperature(&unit))
+                    .is_some_and(|t| matches!(t, Temperature::Reading(_)) && !t.is_absolute())
+            });
+            if let Some(unit) = offset {
+                return Err(EvaluatorError::EvaluationError(
+                    crate::fl!("temperature-product", "unit" => unit.as_str()),
+                ));
             }
-            ("/", None) => {
-                // number unit / number = number unit (e.g., 10 feet / 2 = 5 feet)
-                Some(left_unit)
+        }
+        let value = if divide {
+            left.value / right.value
+        } else {
+            left.value * right.value
+        };
+        let rhs = match &right.unit {
+            Some(unit) if divide => self.parse_unit(unit)?.inverse(),
+            Some(unit) => self.parse_unit(unit)?,
+            None => return Ok(EvalResult::new(value, left.unit)),
+        };
+        let lhs = match &left.unit {
+            Some(unit) => self.parse_unit(unit)?,
+            None if divide => Unit::default(),
+            None => return Ok(EvalResult::new(value, right.unit)),
+        };
+        let (unit, factor) = self
+            .units
+            .multiply(&lhs, &rhs)
+            .ok_or_else(|| unknown_unit(&rhs.to_string()))?;
+        Ok(EvalResult::new(
+            self.scale(value, factor),
+            (!unit.is_empty()).then(|| unit.to_string()),
+        ))
+    }
+
+    /// Break `value` (in `unit`) into whole amounts of each unit followed by
+    /// the remainder in the last of `rest`: `1.8 m to ft and in` is `5 ft 10.87 in`.
+    /// Leading parts that are zero are left out: `0.5 ft to ft and in` is `6 in`.
+    fn split(&self, value: Number, unit: String, rest: &[String]) -> Result<EvalResult> {
+        let zero = Number::from(0i64);
+        let negative = value < zero;
+        let mut remaining = if negative { -value } else { value };
+        let mut current = unit.clone();
+        let mut parts = Vec::new();
+        for target in rest {
+            let mut whole = remaining.trunc();
+            // 0.99999 ft from rounding error is a whole foot
+            if (remaining - whole).to_f64() > 1.0 - 1e-9 {
+                whole = whole + Number::from(1i64);
             }
-            ("/", Some(right_u)) if right_u.is_empty() => {
-                // number unit / number = number unit (e.g., 10 feet / 2 = 5 feet)
-                Some(left_unit)
+            parts.push((whole, current));
+            let (converted, display) =
+                self.convert(remaining - whole, &parts[parts.len() - 1].1, target)?;
+            remaining = if converted < zero { zero } else { converted };
+            current = display;
+        }
+        parts.push((remaining, current));
+        self.carry_rounding(&mut parts)?;
+        while parts.len() > 1 && parts[0].0 == zero {
+            parts.remove(0);
+        }
+        if negative {
+            if let Some(part) = parts.iter_mut().find(|(v, _)| *v != zero) {
+                part.0 = -part.0;
             }
-            ("/", Some(right_u)) if right_u == &left_unit => {
-                // Same unit division: feet / feet = unitless
-                None
+        }
+        Ok(EvalResult {
+            parts,
+            ..EvalResult::new(value, Some(unit))
+        })
+    }
+
+    /// Round the remainder of a split as it is shown and carry a full unit
+    /// into the parts before it, so `3599.9999 s to h min s` is `1 h 0 min 0
+    /// s` rather than `0 h 59 min 60.00 s`.
+    fn carry_rounding(&self, parts: &mut [(Number, String)]) -> Result<()> {
+        let zero = Number::from(0i64);
+        let last = parts.len() - 1;
+        let shown = self.display.rounded(parts[last].0.to_f64());
+        for i in (1..parts.len()).rev() {
+            let (one, _) = self.convert(Number::from(1i64), &parts[i - 1].1, &parts[i].1)?;
+            // Whole parts are exact; only the remainder carries rounding error
+            let (value, tolerance) = if i == last {
+                (Number::from_f64(shown, self.number_mode), 1e-9)
+            } else {
+                (parts[i].0, 0.0)
+            };
+            if value.to_f64() < one.to_f64() - tolerance {
+                break;
             }
-            _ => {
-                // For other cases, keep left unit
-                Some(left_unit)
+            let rest = value - one;
+            parts[i].0 = if rest.to_f64() < 1e-9 { zero } else { rest };
+            parts[i - 1].0 = parts[i - 1].0 + Number::from(1i64);
+        }
+        Ok(())
+    }
+
+    /// Built-in function name for `name`, following `config.functions` aliases.
+    fn function_name(&self, name: &str) -> Option<&'static str> {
+        let lower = name.to_lowercase();
+        let target = self
+            .functions
+            .get(&lower)
+            .map(|alias| alias.trim_end_matches('(').to_string())
+            .unwrap_or(lower);
+        FUNCTIONS.iter().copied().find(|f| *f == target)
+    }
+
+    /// Value of the Roman numeral `numeral`, which must be well-formed.
+    fn roman_numeral(&self, numeral: &str) -> Result<EvalResult> {
+        match parse_roman(numeral) {
+            Some(value) => Ok(EvalResult::unitless(Number::from_integer(
+                i128::from(value),
+                self.number_mode,
+            ))),
+            None => Err(EvaluatorError::EvaluationError(
+                crate::fl!("invalid-roman-numeral", "numeral" => numeral),
+            )),
+        }
+    }
+
+    fn resolve_ident(&self, name: &str) -> Result<EvalResult> {
+        // Inside a user function only its own parameters are in scope
+        if let Some(scope) = self.scopes.borrow().last() {
+            if let Some(result) = scope.get(name) {
+                return Ok(result.clone());
             }
-        };
+        }
+        if let Some((value, unit)) = self.variables.get(name) {
+            let uncertainty = self.uncertainties.get(name).copied();
+            return Ok(EvalResult::new(*value, unit.clone()).with_uncertainty(uncertainty));
+        }
+        if let Some(items) = self.lists.get(name) {
+            let items = items
+                .iter()
+                .map(|(value, unit)| EvalResult::new(*value, unit.clone()))
+                .collect();
+            return Ok(EvalResult::list(items));
+        }
 
-        Ok(EvalResult { value, unit })
-    } else {
-        Err(EvaluatorError::InvalidExpression(
-            crate::fl!("unit-algebra-not-expression"),
-        ))
+        let mode = self.number_mode;
+        match name {
+            "pi" | "PI" => Ok(EvalResult::unitless(Number::from_f64(
+                std::f64::consts::PI,
+                mode,
+            ))),
+            "e" | "E" => Ok(EvalResult::unitless(Number::from_f64(
+                std::f64::consts::E,
+                mode,
+            ))),
+            "sum" | "total" | "average" | "avg" => self.history_aggregate(name),
+            "above" => self.block_above(name),
+            "subtotal" | "section avg" | "grand total" => self.section_aggregate(name),
+            "prev line" => match self.document_line(name)?.checked_sub(1) {
+                Some(index) => self.line_result(index),
+                None => Err(EvaluatorError::EvaluationError(crate::fl!("no-line-above"))),
+            },
+            "prev" => self.previous(),
+            _ => match self.constants.get(name) {
+                Some(constant) => Ok(EvalResult::new(
+                    Number::from_f64(constant.value, mode),
+                    constant.unit.clone(),
+                )),
+                None => match line_number(name) {
+                    Some(line) => self.line_result(line - 1),
+                    // Single letters such as `C` are numerals only when asked for
+                    None if name.len() > 1 && is_roman_word(name) => self.roman_numeral(name),
+                    None => Err(EvaluatorError::InvalidExpression(
+                        crate::fl!("unknown-identifier", "name" => name),
+                    )),
+                },
+            },
+        }
     }
 }
 
-/// Check if a unit string is a currency
-fn is_currency(unit: &str, ctx: &EvalContext) -> bool {
-    ctx.rates.contains_key(&unit.to_uppercase())
-}
-
-#[allow(clippy::too_many_arguments)]
-pub fn evaluate_unit_conversion(
-    left: &str,
-    right: &str,
-    length_units: &Units,
-    time_units: &Units,
-    temperature_units: &TempUnits,
-    area_units: &Units,
-    volume_units: &Units,
-    weight_units: &Units,
-    angular_units: &Units,
-    data_units: &Units,
-    speed_units: &Units,
-    rates: &Rates,
-    custom_units: &HashMap<String, HashMap<String, f64>>,
-) -> Option<String> {
-    let right_lower = right.to_lowercase();
-    // Determine unit type based on right unit
-    if length_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_generic_conversion(left, right, length_units) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if time_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_generic_conversion(left, right, time_units) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if temperature_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_temperature_conversion(left, right) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if area_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_generic_conversion(left, right, area_units) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if volume_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_generic_conversion(left, right, volume_units) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if weight_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_generic_conversion(left, right, weight_units) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if angular_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_generic_conversion(left, right, angular_units) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if data_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_generic_conversion(left, right, data_units) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if speed_units.contains_key(&right_lower) {
-        if let Some(val) = evaluate_generic_conversion(left, right, speed_units) {
-            return Some(format!("{} {}", prettify_number(val), right));
-        }
-    } else if rates.contains_key(&right.to_uppercase()) {
-        // Currency
-        if let Some(val) = evaluate_currency_conversion(left, right, rates) {
-            return Some(format!("{} {}", prettify_number(val), right));
+impl Vocabulary for EvalContext<'_> {
+    fn unit(&self, name: &str) -> Option<String> {
+        let unit = self.units.parse(name)?;
+        if !unit.is_simple() {
+            // Compound units are normalized: m^2 -> m², USD/kilogram stays as parsed
+            return Some(unit.to_string());
         }
-    } else {
-        // Check custom units
-        for units in custom_units.values() {
-            if units.contains_key(&right_lower) {
-                if let Some(val) = evaluate_generic_conversion(left, right, units) {
-                    return Some(format!("{} {}", prettify_number(val), right));
-                }
+        if self.units.is_currency(&unit) {
+            if let Some(code) = currency_word_to_code(&name.to_lowercase()) {
+                return Some(code.to_string());
             }
         }
+        Some(name.to_string())
+    }
+
+    fn scale(&self, name: &str) -> Option<f64> {
+        // Single letters are case-sensitive (M is mega, m is meters)
+        self.scales
+            .get(name)
+            .or_else(|| {
+                (name.chars().count() > 1)
+                    .then(|| self.scales.get(&name.to_lowercase()))
+                    .flatten()
+            })
+            .copied()
+    }
+
+    fn operator(&self, phrase: &str) -> Option<&str> {
+        self.operators
+            .get(&phrase.to_lowercase())
+            .map(String::as_str)
+    }
+
+    fn is_function(&self, name: &str) -> bool {
+        self.user_functions.contains_key(name) || self.function_name(name).is_some()
+    }
+
+    fn is_constant(&self, name: &str) -> bool {
+        self.constants.contains_key(name)
+    }
+
+    fn is_currency(&self, unit: &str) -> bool {
+        self.units
+            .parse(unit)
+            .is_some_and(|unit| self.units.is_currency(&unit))
+    }
+
+    fn number_style(&self) -> NumberStyle {
+        self.number_style
+    }
+}
+
+fn unknown_unit(unit: &str) -> EvaluatorError {
+    EvaluatorError::EvaluationError(crate::fl!("unknown-unit", "unit" => unit))
+}
+
+/// Line number, counted from 1 as the editor shows it, of a `line3`
+/// reference (written `line 3` too).
+fn line_number(name: &str) -> Option<usize> {
+    let digits = name.strip_prefix("line")?;
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok().filter(|&line| line > 0)
+}
+
+/// Unit most entries are in, the earliest of them on a tie.
+fn dominant_unit(entries: &[HistoryEntry]) -> Option<String> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for unit in entries.iter().filter_map(|e| e.unit.as_deref()) {
+        match counts.iter_mut().find(|(u, _)| *u == unit) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((unit, 1)),
+        }
+    }
+    let mut best: Option<(&str, usize)> = None;
+    for (unit, count) in counts {
+        if best.is_none_or(|(_, most)| count > most) {
+            best = Some((unit, count));
+        }
+    }
+    best.map(|(unit, _)| unit.to_string())
+}
+
+/// Evaluate an expression
+///
+/// Assignments (`x = 5`, `xs = 1, 2, 3`) store the variable in the context and definitions
+/// (`f(x) = x^2`) the function; a definition evaluates to zero.
+#[allow(unused)]
+pub fn evaluate_expr(expr: &str, ctx: &mut EvalContext) -> Located<EvalResult> {
+    let tree = parse(expr, ctx)?;
+    if let ExprKind::Define { name, params, body } = tree.kind {
+        let function = UserFunction {
+            params,
+            body: *body,
+        };
+        ctx.user_functions.insert(name, function);
+        return Ok(EvalResult::unitless(Number::from(0i64)));
+    }
+    let result = evaluate(&tree, ctx)?;
+    if let ExprKind::Assign { name, value } = &tree.kind {
+        if result.moment.is_some() {
+            return Err(Diagnostic::new(EvaluatorError::EvaluationError(
+                crate::fl!("date-variable", "name" => name.as_str()),
+            ))
+            .at(value.span));
+        }
+        match &result.items {
+            Some(items) => {
+                let items = items.iter().map(|i| (i.value, i.unit.clone())).collect();
+                ctx.variables.remove(name);
+                ctx.uncertainties.remove(name);
+                ctx.lists.insert(name.clone(), items);
+            }
+            None => {
+                ctx.lists.remove(name);
+                ctx.variables
+                    .insert(name.clone(), (result.value, result.unit.clone()));
+                match result.uncertainty {
+                    Some(error) => ctx.uncertainties.insert(name.clone(), error),
+                    None => ctx.uncertainties.remove(name),
+                };
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Result whose error points at the part of the input that caused it.
+pub type Located<T> = std::result::Result<T, Diagnostic>;
+
+/// Parse a line using the context's units, scales, operator words and functions.
+pub fn parse(input: &str, ctx: &EvalContext) -> Located<Expr> {
+    ast::parse(input, ctx)
+        .map_err(|e| Diagnostic::new(EvaluatorError::ParseError(e.message)).at(e.span))
+}
+
+/// Evaluate a parsed expression. Assignments evaluate to their value;
+/// storing the variable is up to the caller.
+///
+/// Errors carry the span of the innermost expression that failed.
+pub fn evaluate(expr: &Expr, ctx: &EvalContext) -> Located<EvalResult> {
+    evaluate_node(expr, ctx).map_err(|e| e.at(expr.span))
+}
+
+fn evaluate_node(expr: &Expr, ctx: &EvalContext) -> Located<EvalResult> {
+    let mode = ctx.number_mode;
+    match &expr.kind {
+        ExprKind::Number(text) => {
+            let value = Number::parse(text, mode).ok_or_else(|| {
+                EvaluatorError::ParseError(crate::fl!("unexpected-token", "token" => text))
+            })?;
+            // Integers written in a radix are shown in full: 0xFFFF is 65535, not 65.5k
+            let is_radix = text.starts_with("0x") || text.starts_with("0b") || text.starts_with("0o");
+            Ok(EvalResult::unitless(value).with_radix(is_radix.then_some(Radix::Decimal)))
+        }
+        ExprKind::Ident(name) => Ok(ctx.resolve_ident(name)?),
+        ExprKind::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => evaluate(operand, ctx)?.map_items(|result| {
+            if result.moment.is_some() {
+                return Err(EvaluatorError::EvaluationError(
+                    crate::fl!("date-arithmetic", "op" => "-"),
+                )
+                .into());
+            }
+            Ok(EvalResult::new(-result.value, result.unit)
+                .with_radix(result.radix)
+                .with_uncertainty(result.uncertainty))
+        }),
+        ExprKind::Unary {
+            op: UnaryOp::Not,
+            operand,
+        } => {
+            evaluate(operand, ctx)?.map_items(|result| {
+                let value = integer_operand(&result, "not")?;
+                Ok(EvalResult::unitless(Number::from_integer(!value, mode))
+                    .with_radix(result.radix.or(Some(Radix::Decimal))))
+            })
+        }
+        ExprKind::Binary { op, lhs, rhs } => evaluate_binary(*op, lhs, rhs, ctx),
+        ExprKind::Percent(inner) => evaluate(inner, ctx)?
+            .map_items(|result| Ok(EvalResult::unitless(result.value / hundred(mode)))),
+        ExprKind::Of { percent, base } => {
+            let fraction = evaluate(percent, ctx)?;
+            evaluate(base, ctx)?.map_items(|base| {
+                Ok(EvalResult::new(
+                    fraction.value * base.value,
+                    base.unit.or(fraction.unit.clone()),
+                ))
+            })
+        }
+        ExprKind::PercentOp { op, lhs, rhs } => evaluate_percent_op(*op, lhs, rhs, ctx),
+        ExprKind::Quantity {
+            value,
+            unit,
+            unit_span,
+        } => {
+            let result = evaluate(value, ctx)?;
+            if let Err(e) = ctx.parse_unit(unit) {
+                // A constant after a number multiplies it: `2 phi`, `3 lightyear`
+                if !ctx.constants.contains_key(unit) {
+                    return Err(Diagnostic::from(e).at(*unit_span));
+                }
+                let constant = ctx
+                    .resolve_ident(unit)
+                    .map_err(|e| Diagnostic::from(e).at(*unit_span))?;
+                return apply_binary(BinaryOp::Mul, result, constant, ctx);
+            }
+            result.map_items(|result| {
+                Ok(EvalResult::new(result.value, Some(unit.clone()))
+                    .with_radix(result.radix)
+                    .with_uncertainty(result.uncertainty))
+            })
+        }
+        ExprKind::Compound(parts) => {
+            // 5 ft 3 in is 5.25 ft: later parts convert into the first part's unit
+            let mut total = evaluate(&parts[0], ctx)?;
+            for part in &parts[1..] {
+                let part = evaluate(part, ctx)?;
+                let value = match (&total.unit, &part.unit) {
+                    (Some(a), Some(b)) if a != b => ctx.convert(part.value, b, a)?.0,
+                    _ => part.value,
+                };
+                total.value = total.value + value;
+            }
+            Ok(total)
+        }
+        ExprKind::Call { name, args } => call_function(name, args, expr.span, ctx),
+        ExprKind::Convert { expr, targets } => {
+            let source = match &expr.kind {
+                // `C to number` and `V to roman` ask for a numeral, even one letter
+                ExprKind::Ident(name) if is_roman_word(name) && is_numeral_target(targets) => ctx
+                    .resolve_ident(name)
+                    .or_else(|_| ctx.roman_numeral(name))
+                    .map_err(|e| Diagnostic::from(e).at(expr.span))?,
+                _ => evaluate(expr, ctx)?,
+            };
+            source.map_items(|source| convert_result(source, targets, ctx))
+        }
+        ExprKind::List(items) => {
+            let items = items
+                .iter()
+                .map(|item| evaluate(item, ctx))
+                .collect::<Located<Vec<_>>>()?;
+            // Lists inside lists are spliced in: [1, 2..3] is [1, 2, 3]
+            Ok(EvalResult::list(
+                items.into_iter().flat_map(EvalResult::into_items).collect(),
+            ))
+        }
+        ExprKind::Range { from, to } => {
+            let from = evaluate(from, ctx)?;
+            let start = integer_operand(&from, "..")?;
+            let end = integer_operand(&evaluate(to, ctx)?, "..")?;
+            if start.abs_diff(end) >= MAX_RANGE_LEN {
+                return Err(EvaluatorError::EvaluationError(crate::fl!(
+                    "range-too-large",
+                    "max" => MAX_RANGE_LEN.to_string()
+                ))
+                .into());
+            }
+            let values: Vec<i128> = if start <= end {
+                (start..=end).collect()
+            } else {
+                (end..=start).rev().collect()
+            };
+            let items = values
+                .into_iter()
+                .map(|v| EvalResult::new(Number::from_integer(v, mode), from.unit.clone()))
+                .collect();
+            Ok(EvalResult::list(items))
+        }
+        ExprKind::Assign { value, .. } => evaluate(value, ctx),
+        // Definitions are stored by the caller, never evaluated in place
+        ExprKind::Define { .. } => Err(EvaluatorError::InvalidExpression(
+            crate::fl!("unexpected-token", "token" => "="),
+        )
+        .into()),
+        ExprKind::Date(word) => Moment::named(word, ctx.time_zone)
+            .map(EvalResult::moment)
+            .ok_or_else(|| EvaluatorError::EvaluationError(crate::fl!("invalid-date")).into()),
+        ExprKind::InZone {
+            expr,
+            zone,
+            zone_span,
+        } => {
+            let moment = moment_operand(expr, ctx)?;
+            let zone = resolve_zone(zone, ctx.city_aliases).ok_or_else(|| {
+                Diagnostic::new(EvaluatorError::EvaluationError(
+                    crate::fl!("unknown-time-zone", "zone" => zone.as_str()),
+                ))
+                .at(*zone_span)
+            })?;
+            Ok(EvalResult::moment(moment.in_zone(zone)))
+        }
+        ExprKind::DaysBetween { from, to } => {
+            let days = moment_operand(from, ctx)?.days_until(moment_operand(to, ctx)?);
+            Ok(EvalResult::new(
+                Number::from_f64(days, mode),
+                Some("days".to_string()),
+            ))
+        }
+        ExprKind::Interest {
+            principal,
+            rate,
+            term,
+            plan,
+        } => {
+            let (rate, months) = ctx.interest_terms(rate, term)?;
+            evaluate(principal, ctx)?.map_items(|principal| {
+                let amount = principal.value.to_f64();
+                let value = match plan {
+                    // Loans are paid monthly
+                    InterestPlan::Loan => loan_payment(rate / 12.0, months, amount)?,
+                    InterestPlan::Compound { per_year } => {
+                        finance::compound(amount, rate, months / 12.0, *per_year)
+                    }
+                };
+                Ok(EvalResult::new(
+                    Number::from_f64(value, mode),
+                    principal.unit,
+                ))
+            })
+        }
+    }
+}
+
+/// Calendar months in one `unit` of time, for units whose length varies
+/// (a year is 12 months, not 365 days).
+fn calendar_months(unit: &str) -> Option<i128> {
+    match unit.to_lowercase().as_str() {
+        "month" | "months" => Some(1),
+        "year" | "years" | "yr" | "yrs" => Some(12),
+        _ => None,
+    }
+}
+
+/// Payment that pays off `principal` over `periods`, which must be positive.
+fn loan_payment(rate: f64, periods: f64, principal: f64) -> Result<f64> {
+    if periods <= 0.0 {
+        return Err(EvaluatorError::EvaluationError(crate::fl!(
+            "no-payment-periods"
+        )));
+    }
+    Ok(finance::payment(rate, periods, principal))
+}
+
+/// Evaluate `expr`, which must be a point in time.
+fn moment_operand(expr: &Expr, ctx: &EvalContext) -> Located<Moment> {
+    let result = evaluate(expr, ctx)?;
+    result.moment.ok_or_else(|| {
+        Diagnostic::new(EvaluatorError::EvaluationError(
+            crate::fl!("date-expected", "value" => ctx.format(&result)),
+        ))
+        .at(expr.span)
+    })
+}
+
+/// Evaluate a percentage phrase. Amounts keep their unit (`20% off $80` is
+/// `64 USD`) and ratios are shown as percentages (`50 is what % of 200` is `25%`).
+fn evaluate_percent_op(
+    op: PercentOp,
+    lhs: &Expr,
+    rhs: &Expr,
+    ctx: &EvalContext,
+) -> Located<EvalResult> {
+    let left = evaluate(lhs, ctx)?;
+    let right = evaluate(rhs, ctx)?;
+    let one = Number::from(1i64);
+    let division_by_zero = |divisor: &Expr| {
+        Diagnostic::new(EvaluatorError::EvaluationError(crate::fl!(
+            "division-by-zero"
+        )))
+        .at(divisor.span)
+    };
+    match op {
+        PercentOp::Off | PercentOp::On => right.map_items(|base| {
+            let factor = if op == PercentOp::Off {
+                one - left.value
+            } else {
+                one + left.value
+            };
+            Ok(EvalResult::new(base.value * factor, base.unit))
+        }),
+        PercentOp::OfWhat if left.value == 0.0 => Err(division_by_zero(lhs)),
+        PercentOp::OfWhat => {
+            right.map_items(|part| Ok(EvalResult::new(part.value / left.value, part.unit)))
+        }
+        PercentOp::WhatPercent | PercentOp::Change => {
+            if left.items.is_some() || right.items.is_some() {
+                return Err(EvaluatorError::InvalidExpression(
+                    crate::fl!("unexpected-token", "token" => "%"),
+                )
+                .into());
+            }
+            // Compare both sides in the left operand's unit
+            let right_value = match (&left.unit, &right.unit) {
+                (Some(a), Some(b)) if a != b => ctx.convert(right.value, b, a)?.0,
+                _ => right.value,
+            };
+            // `0 is what % of 0` and `% change from 0 to 100` have no answer
+            let ratio = if op == PercentOp::WhatPercent {
+                if right_value == 0.0 {
+                    return Err(division_by_zero(rhs));
+                }
+                left.value / right_value
+            } else {
+                if left.value == 0.0 {
+                    return Err(division_by_zero(lhs));
+                }
+                (right_value - left.value) / left.value
+            };
+            Ok(EvalResult {
+                percent: true,
+                ..EvalResult::unitless(ratio)
+            })
+        }
+    }
+}
+
+/// Convert a single value to `targets`: a unit, several units for a
+/// compound result, `fraction`, a radix or a notation.
+fn convert_result(
+    source: EvalResult,
+    targets: &[String],
+    ctx: &EvalContext,
+) -> Located<EvalResult> {
+    let target = &targets[0];
+    if let [target] = targets {
+        if FRACTION_TARGETS.contains(&target.to_lowercase().as_str()) {
+            return Ok(EvalResult::new(source.value.to_fraction(), source.unit)
+                .with_uncertainty(source.uncertainty));
+        }
+        if WORDS_TARGETS.contains(&target.to_lowercase().as_str()) {
+            if !can_spell(source.value.to_f64()) {
+                return Err(
+                    EvaluatorError::EvaluationError(crate::fl!("too-large-to-spell")).into(),
+                );
+            }
+            return Ok(EvalResult {
+                words: true,
+                ..source
+            });
+        }
+        if target.eq_ignore_ascii_case(NUMBER_TARGET) {
+            // Whole numbers in full, like radix results: 1900, not 1.9k
+            return Ok(EvalResult::new(source.value, source.unit)
+                .with_radix(Some(Radix::Decimal))
+                .with_uncertainty(source.uncertainty));
+        }
+        if let Some(radix) = Radix::from_target(target) {
+            let integer = integer_operand(&source, target)?;
+            if radix == Radix::Roman && !(1..=i128::from(MAX_ROMAN)).contains(&integer) {
+                return Err(EvaluatorError::EvaluationError(
+                    crate::fl!("roman-out-of-range", "max" => MAX_ROMAN),
+                )
+                .into());
+            }
+            return Ok(EvalResult {
+                radix: Some(radix),
+                ..source
+            });
+        }
+        if let Some(notation) = Notation::from_target(target) {
+            return Ok(EvalResult {
+                notation: Some(notation),
+                ..source
+            });
+        }
+    }
+    let (value, unit) = match &source.unit {
+        // A bare number takes the target unit as-is ("100 + 400 to USD")
+        None => (
+            source.value,
+            ctx.unit(target).ok_or_else(|| unknown_unit(target))?,
+        ),
+        Some(unit) => ctx.convert(source.value, unit, target)?,
+    };
+    if targets.len() == 1 {
+        let uncertainty = match (source.uncertainty, &source.unit) {
+            (Some(error), Some(from)) => Some(ctx.convert_uncertainty(error, from, target)?),
+            (error, _) => error,
+        };
+        Ok(EvalResult::new(value, Some(unit)).with_uncertainty(uncertainty))
+    } else {
+        Ok(ctx.split(value, unit, &targets[1..])?)
+    }
+}
+
+/// Call a function defined in the document with its parameters bound to
+/// the argument values.
+fn call_user_function(
+    name: &str,
+    function: &UserFunction,
+    args: &[Expr],
+    ctx: &EvalContext,
+) -> Located<EvalResult> {
+    if args.len() != function.params.len() {
+        return Err(EvaluatorError::InvalidExpression(
+            crate::fl!("function-arity", "name" => name),
+        )
+        .into());
+    }
+    if ctx.scopes.borrow().len() >= MAX_CALL_DEPTH {
+        return Err(EvaluatorError::EvaluationError(
+            crate::fl!("function-too-deep", "name" => name),
+        )
+        .into());
+    }
+    let values = args
+        .iter()
+        .map(|arg| evaluate(arg, ctx))
+        .collect::<Located<Vec<_>>>()?;
+    let scope = function.params.iter().cloned().zip(values).collect();
+
+    ctx.scopes.borrow_mut().push(scope);
+    let result = evaluate(&function.body, ctx);
+    ctx.scopes.borrow_mut().pop();
+    // The body's spans point into the defining line, not this one
+    result.map_err(|e| Diagnostic { span: None, ..e })
+}
+
+/// Integer value of a bitwise operand, or an error naming the operator.
+fn integer_operand(result: &EvalResult, op: &str) -> Result<i128> {
+    if let Some(unit) = &result.unit {
+        return Err(EvaluatorError::EvaluationError(
+            crate::fl!("integer-unit", "op" => op, "unit" => unit.as_str()),
+        ));
+    }
+    let value = result.items.is_none().then_some(result.value);
+    value.and_then(|v| v.as_integer()).ok_or_else(|| {
+        EvaluatorError::EvaluationError(crate::fl!("integer-required", "op" => op))
+    })
+}
+
+/// Apply a bitwise operator to two integers.
+fn bitwise(op: BinaryOp, a: i128, b: i128) -> Result<i128> {
+    let overflow =
+        || EvaluatorError::EvaluationError(crate::fl!("integer-overflow", "op" => op.symbol()));
+    let shift = || {
+        u32::try_from(b).map_err(|_| {
+            EvaluatorError::EvaluationError(crate::fl!("negative-shift", "op" => op.symbol()))
+        })
+    };
+    match op {
+        BinaryOp::BitAnd => Ok(a & b),
+        BinaryOp::BitOr => Ok(a | b),
+        BinaryOp::BitXor => Ok(a ^ b),
+        BinaryOp::Shl => {
+            let shifted = a.checked_shl(shift()?).ok_or_else(overflow)?;
+            // Bits shifted out of the top, sign bit included, are an
+            // overflow, not a wrap
+            (shifted >> b == a).then_some(shifted).ok_or_else(overflow)
+        }
+        BinaryOp::Shr => Ok(a >> shift()?.min(127)),
+        BinaryOp::Add
+        | BinaryOp::Sub
+        | BinaryOp::Mul
+        | BinaryOp::Div
+        | BinaryOp::Rem
+        | BinaryOp::Pow
+        | BinaryOp::PlusMinus => Err(overflow()),
+    }
+}
+
+/// Combined uncertainty of independent errors, either of which may be signed.
+fn quadrature(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.hypot(b)),
+        (a, b) => a.or(b).map(f64::abs),
+    }
+}
+
+fn hundred(mode: NumberMode) -> Number {
+    Number::from_f64(100.0, mode)
+}
+
+/// Fraction for a percentage right operand (`10%` or `-10%`), if `expr` is one.
+fn percent_operand(expr: &Expr, ctx: &EvalContext) -> Located<Option<Number>> {
+    match &expr.kind {
+        ExprKind::Percent(inner) => {
+            Ok(Some(evaluate(inner, ctx)?.value / hundred(ctx.number_mode)))
+        }
+        ExprKind::Unary {
+            op: UnaryOp::Neg,
+            operand,
+        } => Ok(percent_operand(operand, ctx)?.map(|p| -p)),
+        _ => Ok(None),
+    }
+}
+
+fn evaluate_binary(
+    op: BinaryOp,
+    lhs: &Expr,
+    rhs: &Expr,
+    ctx: &EvalContext,
+) -> Located<EvalResult> {
+    let left = evaluate(lhs, ctx)?;
+
+    // Percentages apply to the left operand: 200 + 10% = 220, 50 * 10% = 5
+    if let Some(fraction) = percent_operand(rhs, ctx)? {
+        // A relative uncertainty: 5 m ± 2%
+        if op == BinaryOp::PlusMinus {
+            return left.map_items(|left| {
+                let error = (left.value * fraction).to_f64();
+                let uncertainty = quadrature(left.uncertainty, Some(error));
+                Ok(left.with_uncertainty(uncertainty))
+            });
+        }
+        if matches!(
+            op,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div
+        ) {
+            return left.map_items(|left| {
+                let value = match op {
+                    BinaryOp::Add => left.value + left.value * fraction,
+                    BinaryOp::Sub => left.value - left.value * fraction,
+                    BinaryOp::Mul => left.value * fraction,
+                    _ => left.value / fraction,
+                };
+                Ok(EvalResult::new(value, left.unit))
+            });
+        }
+    }
+
+    let right = evaluate(rhs, ctx)?;
+    apply_binary(op, left, right, ctx)
+}
+
+/// Apply a binary operator to two values, item by item when either is a
+/// list: `[1, 2] * 3` is `[3, 6]` and `[1, 2] + [10, 20]` is `[11, 22]`.
+fn apply_binary(
+    op: BinaryOp,
+    left: EvalResult,
+    right: EvalResult,
+    ctx: &EvalContext,
+) -> Located<EvalResult> {
+    let pairs: Vec<(EvalResult, EvalResult)> = match (&left.items, &right.items) {
+        (None, None) => return apply_scalar_binary(op, left, right, ctx),
+        (Some(l), Some(r)) if l.len() != r.len() => {
+            return Err(EvaluatorError::EvaluationError(crate::fl!(
+                "list-length-mismatch",
+                "left" => l.len(),
+                "right" => r.len()
+            ))
+            .into());
+        }
+        (Some(l), Some(r)) => l.iter().cloned().zip(r.iter().cloned()).collect(),
+        (Some(l), None) => l.iter().map(|item| (item.clone(), right.clone())).collect(),
+        (None, Some(r)) => r.iter().map(|item| (left.clone(), item.clone())).collect(),
+    };
+    let items = pairs
+        .into_iter()
+        .map(|(l, r)| apply_scalar_binary(op, l, r, ctx))
+        .collect::<Located<Vec<_>>>()?;
+    Ok(EvalResult::list(items))
+}
+
+fn apply_scalar_binary(
+    op: BinaryOp,
+    left: EvalResult,
+    right: EvalResult,
+    ctx: &EvalContext,
+) -> Located<EvalResult> {
+    if left.moment.is_some() || right.moment.is_some() {
+        return Ok(apply_moment_binary(op, left, right, ctx)?);
+    }
+    let radix = left.radix.or(right.radix);
+    let result = match op {
+        BinaryOp::Add | BinaryOp::Sub => {
+            let sum = match ctx.add_temperatures(&left, &right, op == BinaryOp::Sub)? {
+                Some(sum) => sum,
+                None => {
+                    // Convert the right operand into the left operand's unit
+                    let (right_value, unit) = match (&left.unit, &right.unit) {
+                        (Some(a), Some(b)) if a != b => {
+                            (ctx.convert(right.value, b, a)?.0, Some(a.clone()))
+                        }
+                        (a, b) => (right.value, a.clone().or(b.clone())),
+                    };
+                    let value = if op == BinaryOp::Add {
+                        left.value + right_value
+                    } else {
+                        left.value - right_value
+                    };
+                    EvalResult::new(value, unit)
+                }
+            };
+            // Independent errors add in quadrature
+            let uncertainty = quadrature(
+                ctx.uncertainty_in(&left, sum.unit.as_deref())?,
+                ctx.uncertainty_in(&right, sum.unit.as_deref())?,
+            );
+            sum.with_uncertainty(uncertainty)
+        }
+        BinaryOp::PlusMinus => {
+            let unit = left.unit.clone().or(right.unit.clone());
+            let error = EvalResult {
+                uncertainty: Some(right.value.to_f64()),
+                ..right
+            };
+            let uncertainty = quadrature(
+                ctx.uncertainty_in(&left, unit.as_deref())?,
+                ctx.uncertainty_in(&error, unit.as_deref())?,
+            );
+            EvalResult::new(left.value, unit).with_uncertainty(uncertainty)
+        }
+        BinaryOp::Mul => ctx.combine(left, right, false)?,
+        BinaryOp::Div => ctx.combine(left, right, true)?,
+        BinaryOp::Rem if right.value == 0.0 => {
+            return Err(EvaluatorError::EvaluationError(crate::fl!("division-by-zero")).into())
+        }
+        BinaryOp::Rem => EvalResult::new(left.value % right.value, left.unit.or(right.unit)),
+        BinaryOp::Pow => {
+            let exp = right.value.to_f64();
+            let unit = match left.unit {
+                // Integer powers raise the unit too: (3 m)^2 = 9 m²
+                Some(unit) if right.unit.is_none() && exp.fract() == 0.0 => {
+                    let powered = ctx.units.pow(&ctx.parse_unit(&unit)?, exp as i32);
+                    powered.filter(|u| !u.is_empty()).map(|u| u.to_string())
+                }
+                unit => unit,
+            };
+            let value = left.value.pow(right.value);
+            // d(a^b) = b·a^(b-1)·da + a^b·ln(a)·db
+            let (a, f) = (left.value.to_f64(), value.to_f64());
+            let uncertainty = quadrature(
+                left.uncertainty.map(|da| exp * a.powf(exp - 1.0) * da),
+                right.uncertainty.map(|db| f * a.ln() * db),
+            );
+            EvalResult::new(value, unit).with_uncertainty(uncertainty)
+        }
+        BinaryOp::BitAnd | BinaryOp::BitOr | BinaryOp::BitXor | BinaryOp::Shl | BinaryOp::Shr => {
+            let a = integer_operand(&left, op.symbol())?;
+            let b = integer_operand(&right, op.symbol())?;
+            let value = bitwise(op, a, b)?;
+            // Bitwise results are integers even from decimal operands
+            EvalResult::unitless(Number::from_integer(value, ctx.number_mode))
+                .with_radix(radix.or(Some(Radix::Decimal)))
+        }
+    };
+    Ok(result.with_radix(radix))
+}
+
+/// Date arithmetic: a point in time plus or minus a duration is a point in
+/// time, and the difference of two is a number of days.
+fn apply_moment_binary(
+    op: BinaryOp,
+    left: EvalResult,
+    right: EvalResult,
+    ctx: &EvalContext,
+) -> Result<EvalResult> {
+    let moved = match (op, left.moment, right.moment) {
+        (BinaryOp::Sub, Some(a), Some(b)) => {
+            let days = b.days_until(a);
+            return Ok(EvalResult::new(
+                Number::from_f64(days, ctx.number_mode),
+                Some("days".to_string()),
+            ));
+        }
+        (BinaryOp::Add | BinaryOp::Sub, Some(moment), None) => {
+            ctx.shift_moment(moment, &right, op == BinaryOp::Sub)?
+        }
+        (BinaryOp::Add, None, Some(moment)) => ctx.shift_moment(moment, &left, false)?,
+        _ => {
+            return Err(EvaluatorError::EvaluationError(
+                crate::fl!("date-arithmetic", "op" => op.symbol()),
+            ))
+        }
+    };
+    Ok(EvalResult::moment(moved))
+}
+
+/// Call the function `name` of the call at `span`. An unknown function is
+/// reported at its name, as an unknown variable is.
+fn call_function(name: &str, args: &[Expr], span: Span, ctx: &EvalContext) -> Located<EvalResult> {
+    if let Some(function) = ctx.user_functions.get(name) {
+        return call_user_function(name, function, args, ctx);
+    }
+    let mode = ctx.number_mode;
+    let func = ctx.function_name(name).ok_or_else(|| {
+        Diagnostic::new(EvaluatorError::InvalidExpression(
+            crate::fl!("unknown-function", "name" => name),
+        ))
+        .at(Span::new(span.start, span.start + name.len()))
+    })?;
+    if CALCULUS.contains(&func) || (func == "sum" && is_bound_sum(args)) {
+        return calculus(func, name, args, ctx);
+    }
+    let values = args
+        .iter()
+        .map(|arg| evaluate(arg, ctx))
+        .collect::<Located<Vec<_>>>()?;
+    if AGGREGATES.contains(&func) {
+        return Ok(aggregate(func, name, values, ctx)?);
+    }
+    if FINANCIAL.contains(&func) {
+        return Ok(financial(func, name, values, ctx)?);
+    }
+
+    match (func, values.as_slice()) {
+        ("log", [base, x]) => {
+            let value = x.value.to_f64().ln() / base.value.to_f64().ln();
+            Ok(EvalResult::unitless(Number::from_f64(value, mode)))
+        }
+        (_, [arg]) => Ok(apply_function(func, name, arg, ctx)?),
+        _ => Err(arity_error(name).into()),
+    }
+}
+
+fn arity_error(name: &str) -> EvaluatorError {
+    EvaluatorError::InvalidExpression(crate::fl!("function-arity", "name" => name))
+}
+
+/// Apply a one-argument built-in, item by item to a list.
+fn apply_function(
+    func: &str,
+    name: &str,
+    arg: &EvalResult,
+    ctx: &EvalContext,
+) -> Result<EvalResult> {
+    if let Some(items) = &arg.items {
+        let items = items
+            .iter()
+            .map(|item| apply_function(func, name, item, ctx))
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(EvalResult::list(items));
+    }
+    let mode = ctx.number_mode;
+    let x = arg.value.to_f64();
+    if let Some(error) = arg.uncertainty {
+        // First-order propagation: the slope of the function across the error
+        let at = |value: f64| -> Result<f64> {
+            let exact = EvalResult {
+                value: Number::from_f64(value, mode),
+                uncertainty: None,
+                ..arg.clone()
+            };
+            Ok(apply_function(func, name, &exact, ctx)?.value.to_f64())
+        };
+        let spread = (at(x + error)? - at(x - error)?).abs() / 2.0;
+        let exact = EvalResult {
+            uncertainty: None,
+            ..arg.clone()
+        };
+        return Ok(apply_function(func, name, &exact, ctx)?.with_uncertainty(Some(spread)));
+    }
+    // Trigonometric arguments given in angular units are converted to radians
+    let arg_unit = arg.unit.as_deref().and_then(|u| ctx.units.parse(u));
+    let radians = match arg_unit.as_ref().and_then(|u| ctx.units.angle_factor(u)) {
+        Some(factor) => x * factor,
+        None => x,
+    };
+
+    let keeps_unit = |value: Number| EvalResult::new(value, arg.unit.clone());
+    let value = match func {
+        "abs" => {
+            let zero = Number::from(0i64);
+            let value = if arg.value < zero {
+                -arg.value
+            } else {
+                arg.value
+            };
+            return Ok(keeps_unit(value));
+        }
+        "round" => return Ok(keeps_unit(Number::from_f64(x.round(), mode))),
+        "ceil" => return Ok(keeps_unit(Number::from_f64(x.ceil(), mode))),
+        "floor" => return Ok(keeps_unit(Number::from_f64(x.floor(), mode))),
+        "int" | "trunc" => return Ok(keeps_unit(Number::from_f64(x.trunc(), mode))),
+        "sign" => {
+            if x == 0.0 {
+                0.0
+            } else {
+                x.signum()
+            }
+        }
+        "sin" => radians.sin(),
+        "cos" => radians.cos(),
+        "tan" => radians.tan(),
+        "asin" => x.asin(),
+        "acos" => x.acos(),
+        "atan" => x.atan(),
+        "sinh" => x.sinh(),
+        "cosh" => x.cosh(),
+        "tanh" => x.tanh(),
+        "asinh" => x.asinh(),
+        "acosh" => x.acosh(),
+        "atanh" => x.atanh(),
+        "sqrt" | "cbrt" => {
+            let (value, n) = if func == "sqrt" {
+                (x.sqrt(), 2)
+            } else {
+                (x.cbrt(), 3)
+            };
+            // sqrt(25 m²) = 5 m; roots of other units drop the unit
+            let unit = arg_unit
+                .and_then(|u| ctx.units.root(&u, n))
+                .filter(|u| !u.is_empty())
+                .map(|u| u.to_string());
+            return Ok(EvalResult::new(Number::from_f64(value, mode), unit));
+        }
+        "exp" => x.exp(),
+        "ln" => x.ln(),
+        "log" | "log10" => x.log10(),
+        "log2" => x.log2(),
+        _ => return Err(arity_error(name)),
+    };
+    Ok(EvalResult::unitless(Number::from_f64(value, mode)))
+}
+
+/// Reduce the arguments of an aggregate built-in to one value, with lists
+/// flattened into their items: `mean(prices)`, `max(1, 5, 3)`,
+/// `percentile(1..100, 90)`. Items are converted to the first unit given.
+fn aggregate(
+    func: &str,
+    name: &str,
+    mut args: Vec<EvalResult>,
+    ctx: &EvalContext,
+) -> Result<EvalResult> {
+    let mode = ctx.number_mode;
+    // percentile(values, p): the last argument is the percentage
+    let percent = if func == "percentile" {
+        match args.pop() {
+            Some(p) if p.items.is_none() && !args.is_empty() => Some(p.value.to_f64()),
+            _ => return Err(arity_error(name)),
+        }
+    } else {
+        None
+    };
+    let items: Vec<EvalResult> = args.into_iter().flat_map(EvalResult::into_items).collect();
+    if func == "count" {
+        return Ok(EvalResult::unitless(Number::from(items.len() as i64)));
+    }
+    let needed = match func {
+        "sum" => 0,
+        "stdev" | "variance" => 2,
+        _ => 1,
+    };
+    if items.len() < needed {
+        return Err(EvaluatorError::EvaluationError(crate::fl!(
+            "not-enough-values",
+            "name" => name,
+            "count" => needed
+        )));
+    }
+
+    let (unit, values) = in_common_unit(&items, ctx)?;
+    let count = Number::from(values.len() as i64);
+    let total: Number = values.iter().copied().sum();
+    let sorted = || {
+        let mut sorted = values.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        sorted
+    };
+
+    let value = match func {
+        "sum" => total,
+        "mean" => total / count,
+        "median" => {
+            let sorted = sorted();
+            let mid = sorted.len() / 2;
+            if sorted.len() % 2 == 0 {
+                (sorted[mid - 1] + sorted[mid]) / Number::from(2i64)
+            } else {
+                sorted[mid]
+            }
+        }
+        "mode" => {
+            // Most frequent value; ties go to the one listed first
+            let frequency = |v: &Number| values.iter().filter(|w| *w == v).count();
+            let mut best = values[0];
+            for v in &values {
+                if frequency(v) > frequency(&best) {
+                    best = *v;
+                }
+            }
+            best
+        }
+        "min" | "max" => {
+            // The picked item keeps the unit it was written in
+            let mut best = 0;
+            for (i, v) in values.iter().enumerate() {
+                let better = if func == "min" {
+                    *v < values[best]
+                } else {
+                    *v > values[best]
+                };
+                if better {
+                    best = i;
+                }
+            }
+            return Ok(items[best].clone());
+        }
+        "variance" | "stdev" => {
+            // Sample variance, dividing by n - 1
+            let mean = total / count;
+            let squares: Number = values.iter().map(|v| (*v - mean) * (*v - mean)).sum();
+            let variance = squares / (count - Number::from(1i64));
+            if func == "stdev" {
+                Number::from_f64(variance.to_f64().sqrt(), mode)
+            } else {
+                // Variance is in squared units: m²
+                let unit = match &unit {
+                    Some(unit) => ctx
+                        .units
+                        .pow(&ctx.parse_unit(unit)?, 2)
+                        .filter(|u| !u.is_empty())
+                        .map(|u| u.to_string()),
+                    None => None,
+                };
+                return Ok(EvalResult::new(variance, unit));
+            }
+        }
+        "percentile" => {
+            let percent = percent.unwrap_or_default();
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(EvaluatorError::EvaluationError(crate::fl!(
+                    "percentile-out-of-range"
+                )));
+            }
+            // Linear interpolation between the closest ranks
+            let sorted = sorted();
+            let rank = percent / 100.0 * (sorted.len() - 1) as f64;
+            let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+            let weight = Number::from_f64(rank - rank.floor(), mode);
+            sorted[low] + (sorted[high] - sorted[low]) * weight
+        }
+        _ => return Err(arity_error(name)),
+    };
+    Ok(EvalResult::new(value, unit))
+}
+
+/// Values of `items` converted to the first unit among them, with that unit.
+fn in_common_unit(
+    items: &[EvalResult],
+    ctx: &EvalContext,
+) -> Result<(Option<String>, Vec<Number>)> {
+    let unit = items.iter().find_map(|item| item.unit.clone());
+    let values = items
+        .iter()
+        .map(|item| match (&unit, &item.unit) {
+            (Some(a), Some(b)) if a != b => Ok(ctx.convert(item.value, b, a)?.0),
+            _ => Ok(item.value),
+        })
+        .collect::<Result<Vec<Number>>>()?;
+    Ok((unit, values))
+}
+
+/// Evaluate a time-value-of-money built-in. Rates are per period and amounts
+/// keep their currency, so `pmt(5%/12, 360, 300k USD)` is in USD.
+///
+/// - `pmt(rate, periods, principal)`: payment that pays off a loan
+/// - `fv(rate, periods, payment[, present])`: value after deposits and growth,
+///   in spreadsheet argument order; unlike a spreadsheet's `FV`, money paid
+///   in and the result are both positive
+/// - `npv(rate, flows...)`: present value of flows, the first one now
+/// - `irr(flows...)`: rate at which `npv` is zero, shown as a percentage
+fn financial(
+    func: &str,
+    name: &str,
+    args: Vec<EvalResult>,
+    ctx: &EvalContext,
+) -> Result<EvalResult> {
+    let mode = ctx.number_mode;
+    let number = |value: f64| Number::from_f64(value, mode);
+    if matches!(func, "npv" | "irr") {
+        let (rate, flows) = match args.split_first() {
+            Some((rate, flows)) if func == "npv" && rate.items.is_none() => {
+                (rate.value.to_f64(), flows)
+            }
+            _ if func == "irr" => (0.0, args.as_slice()),
+            _ => return Err(arity_error(name)),
+        };
+        let flows: Vec<EvalResult> = flows
+            .iter()
+            .cloned()
+            .flat_map(EvalResult::into_items)
+            .collect();
+        let needed = if func == "irr" { 2 } else { 1 };
+        if flows.len() < needed {
+            return Err(EvaluatorError::EvaluationError(crate::fl!(
+                "not-enough-values",
+                "name" => name,
+                "count" => needed
+            )));
+        }
+        let (unit, values) = in_common_unit(&flows, ctx)?;
+        let values: Vec<f64> = values.iter().map(|v| v.to_f64()).collect();
+        if func == "npv" {
+            let npv = finance::net_present_value(rate, &values);
+            return Ok(EvalResult::new(number(npv), unit));
+        }
+        let irr = finance::internal_rate_of_return(&values)
+            .ok_or_else(|| EvaluatorError::EvaluationError(crate::fl!("irr-no-solution")))?;
+        return Ok(EvalResult {
+            percent: true,
+            ..EvalResult::unitless(number(irr))
+        });
+    }
+
+    if args.iter().any(|arg| arg.items.is_some()) {
+        return Err(arity_error(name));
+    }
+    match (func, args.as_slice()) {
+        ("pmt", [rate, periods, principal]) => {
+            let payment = loan_payment(
+                rate.value.to_f64(),
+                periods.value.to_f64(),
+                principal.value.to_f64(),
+            )?;
+            Ok(EvalResult::new(number(payment), principal.unit.clone()))
+        }
+        ("fv", [rate, periods, amounts @ ..]) if matches!(amounts.len(), 1 | 2) => {
+            // A present value in another currency is converted to the payment's
+            let (unit, amounts) = in_common_unit(amounts, ctx)?;
+            let present = amounts.get(1).map_or(0.0, |p| p.to_f64());
+            let value = finance::future_value(
+                rate.value.to_f64(),
+                periods.value.to_f64(),
+                present,
+                amounts[0].to_f64(),
+            );
+            Ok(EvalResult::new(number(value), unit))
+        }
+        _ => Err(arity_error(name)),
+    }
+}
+
+/// Whether `sum(...)` sums an expression over a bound variable,
+/// `sum(k^2, k, 1, 100)`, rather than adding up its arguments.
+fn is_bound_sum(args: &[Expr]) -> bool {
+    match args {
+        [body, Expr {
+            kind: ExprKind::Ident(var),
+            ..
+        }, _, _] => body.any(&|kind| matches!(kind, ExprKind::Ident(name) if name == var)),
+        _ => false,
+    }
+}
+
+/// Evaluate a calculus built-in. The first argument is evaluated with the
+/// variable named by the second bound, on top of any user function
+/// parameters in scope; the remaining arguments are the bounds or point.
+///
+/// - `integrate(f, x, a, b)`: definite integral, by adaptive Simpson's rule
+/// - `derive(f, x, a)`: derivative at `a`, by extrapolated central differences
+/// - `sum(f, k, a, b)`, `prod(f, k, a, b)`: over the integers `a` to `b`
+///
+/// Units carry through: integrating m/s over seconds gives metres.
+fn calculus(func: &str, name: &str, args: &[Expr], ctx: &EvalContext) -> Located<EvalResult> {
+    let arity = if func == "derive" { 3 } else { 4 };
+    if args.len() != arity {
+        return Err(arity_error(name).into());
+    }
+    let ExprKind::Ident(var) = &args[1].kind else {
+        return Err(Diagnostic::from(EvaluatorError::InvalidExpression(
+            crate::fl!("calculus-variable", "name" => name),
+        ))
+        .at(args[1].span));
+    };
+    let bounds = args[2..]
+        .iter()
+        .map(|arg| evaluate(arg, ctx))
+        .collect::<Located<Vec<_>>>()?;
+    if bounds.iter().any(|bound| bound.items.is_some()) {
+        return Err(arity_error(name).into());
+    }
+
+    let mut scope = ctx.scopes.borrow().last().cloned().unwrap_or_default();
+    scope.insert(var.clone(), EvalResult::unitless(Number::from(0i64)));
+    ctx.scopes.borrow_mut().push(scope);
+    let result = bound_calculus(func, name, var, &args[0], &bounds, ctx);
+    ctx.scopes.borrow_mut().pop();
+    result
+}
+
+/// [`calculus`] with the scope for `var` in place.
+fn bound_calculus(
+    func: &str,
+    name: &str,
+    var: &str,
+    body: &Expr,
+    bounds: &[EvalResult],
+    ctx: &EvalContext,
+) -> Located<EvalResult> {
+    let mode = ctx.number_mode;
+    let at = |value: EvalResult| -> Located<EvalResult> {
+        if let Some(scope) = ctx.scopes.borrow_mut().last_mut() {
+            scope.insert(var.to_string(), value);
+        }
+        let result = evaluate(body, ctx)?;
+        if result.items.is_some() {
+            return Err(Diagnostic::from(EvaluatorError::EvaluationError(
+                crate::fl!("calculus-single-value", "name" => name),
+            ))
+            .at(body.span));
+        }
+        Ok(result)
+    };
+    let diverges = || {
+        Diagnostic::from(EvaluatorError::EvaluationError(
+            crate::fl!("calculus-diverges", "name" => name),
+        ))
+    };
+
+    match func {
+        "sum" | "prod" => {
+            let from = integer_operand(&bounds[0], name)?;
+            let to = integer_operand(&bounds[1], name)?;
+            if to >= from && to.abs_diff(from) >= MAX_RANGE_LEN {
+                return Err(EvaluatorError::EvaluationError(crate::fl!(
+                    "range-too-large",
+                    "max" => MAX_RANGE_LEN.to_string()
+                ))
+                .into());
+            }
+            let (op, empty) = match func {
+                "sum" => (BinaryOp::Add, 0),
+                _ => (BinaryOp::Mul, 1),
+            };
+            // An empty range gives the sum of nothing, or the product
+            let mut total = EvalResult::unitless(Number::from(empty as i64));
+            for k in from..=to {
+                let term = at(EvalResult::unitless(Number::from_integer(k, mode)))?;
+                total = apply_binary(op, total, term, ctx)?;
+            }
+            Ok(total)
+        }
+        "integrate" => {
+            let (a, b) = (&bounds[0], &bounds[1]);
+            // Both bounds in one unit, which the variable takes
+            let (b, unit) = match (&a.unit, &b.unit) {
+                (Some(from), Some(to)) if from != to => {
+                    (ctx.convert(b.value, to, from)?.0, a.unit.clone())
+                }
+                (from, to) => (b.value, from.clone().or(to.clone())),
+            };
+            let mut body_unit = None;
+            let mut f = |x: f64| -> Located<f64> {
+                let result = at(EvalResult::new(Number::from_f64(x, mode), unit.clone()))?;
+                body_unit = result.unit;
+                Ok(result.value.to_f64())
+            };
+            let value = integral(&mut f, a.value.to_f64(), b.to_f64())?;
+            if !value.is_finite() {
+                return Err(diverges());
+            }
+            let area = EvalResult::new(Number::from_f64(value, mode), body_unit);
+            Ok(ctx.combine(area, EvalResult::new(Number::from(1i64), unit), false)?)
+        }
+        _ => {
+            let point = &bounds[0];
+            let x = point.value.to_f64();
+            let mut body_unit = None;
+            let mut f = |x: f64| -> Located<f64> {
+                let result = at(EvalResult::new(
+                    Number::from_f64(x, mode),
+                    point.unit.clone(),
+                ))?;
+                body_unit = result.unit;
+                Ok(result.value.to_f64())
+            };
+            let slope = derivative(&mut f, x)?;
+            if !slope.is_finite() {
+                return Err(diverges());
+            }
+            let slope = EvalResult::new(Number::from_f64(slope, mode), body_unit);
+            let per = EvalResult::new(Number::from(1i64), point.unit.clone());
+            Ok(ctx.combine(slope, per, true)?)
+        }
+    }
+}
+
+/// Integral of `f` from `a` to `b` by adaptive Simpson's rule.
+fn integral(f: &mut dyn FnMut(f64) -> Located<f64>, a: f64, b: f64) -> Located<f64> {
+    let width = (b - a) / INTEGRAL_PIECES as f64;
+    let mut pieces = Vec::with_capacity(INTEGRAL_PIECES);
+    for piece in 0..INTEGRAL_PIECES {
+        let (lo, hi) = (a + width * piece as f64, a + width * (piece + 1) as f64);
+        let values = (f(lo)?, f((lo + hi) / 2.0)?, f(hi)?);
+        let whole = (hi - lo) / 6.0 * (values.0 + 4.0 * values.1 + values.2);
+        pieces.push(((lo, hi), values, whole));
+    }
+    // Error allowed per piece, relative to the size of the whole integral
+    let scale: f64 = pieces.iter().map(|(_, _, whole)| whole.abs()).sum();
+    let tolerance = 1e-10 * scale.max(f64::MIN_POSITIVE) / INTEGRAL_PIECES as f64;
+    let mut total = 0.0;
+    for (range, values, whole) in pieces {
+        total += simpson(f, range, values, whole, tolerance, 0)?;
+    }
+    Ok(total)
+}
+
+/// One step of adaptive Simpson's rule: `whole` is the estimate over `range`
+/// from the function values at its ends and middle.
+fn simpson(
+    f: &mut dyn FnMut(f64) -> Located<f64>,
+    (lo, hi): (f64, f64),
+    (f_lo, f_mid, f_hi): (f64, f64, f64),
+    whole: f64,
+    tolerance: f64,
+    depth: u32,
+) -> Located<f64> {
+    let mid = (lo + hi) / 2.0;
+    let (left_mid, right_mid) = (f((lo + mid) / 2.0)?, f((mid + hi) / 2.0)?);
+    let left = (mid - lo) / 6.0 * (f_lo + 4.0 * left_mid + f_mid);
+    let right = (hi - mid) / 6.0 * (f_mid + 4.0 * right_mid + f_hi);
+    let error = left + right - whole;
+    if depth >= MAX_INTEGRAL_DEPTH || error.abs() <= 15.0 * tolerance || !error.is_finite() {
+        // Richardson correction of the two halves
+        return Ok(left + right + error / 15.0);
+    }
+    let (tolerance, depth) = (tolerance / 2.0, depth + 1);
+    let (left_values, right_values) = ((f_lo, left_mid, f_mid), (f_mid, right_mid, f_hi));
+    let left = simpson(f, (lo, mid), left_values, left, tolerance, depth)?;
+    let right = simpson(f, (mid, hi), right_values, right, tolerance, depth)?;
+    Ok(left + right)
+}
+
+/// Derivative of `f` at `x` by central differences with step `h`, `h/2`
+/// and `h/4`, extrapolated twice to cancel the error terms in `h²` and `h⁴`.
+fn derivative(f: &mut dyn FnMut(f64) -> Located<f64>, x: f64) -> Located<f64> {
+    let h = 1e-2 * x.abs().max(1.0);
+    let mut central = |h: f64| -> Located<f64> { Ok((f(x + h)? - f(x - h)?) / (2.0 * h)) };
+    let (d1, d2, d4) = (central(h)?, central(h / 2.0)?, central(h / 4.0)?);
+    let (r1, r2) = ((4.0 * d2 - d1) / 3.0, (4.0 * d4 - d2) / 3.0);
+    Ok((16.0 * r2 - r1) / 15.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AppStateBuilder;
+
+    fn eval_with(config: &Config, input: &str) -> Located<EvalResult> {
+        let state = AppStateBuilder::new(config).build();
+        let mut variables = HashMap::new();
+        let mut ctx = EvalContext::new(&mut variables, &[], &state, config);
+        evaluate_expr(input, &mut ctx)
+    }
+
+    fn eval(input: &str) -> EvalResult {
+        eval_with(&Config::default(), input).unwrap_or_else(|e| panic!("{}: {}", input, e))
+    }
+
+    fn eval_decimal(input: &str) -> EvalResult {
+        let config = Config {
+            number_mode: "decimal".to_string(),
+            ..Config::default()
+        };
+        eval_with(&config, input).unwrap_or_else(|e| panic!("{}: {}", input, e))
+    }
+
+    fn eval_fraction(input: &str) -> String {
+        let config = Config {
+            number_mode: "fraction".to_string(),
+            ..Config::default()
+        };
+        eval_with(&config, input)
+            .unwrap_or_else(|e| panic!("{}: {}", input, e))
+            .to_string()
+    }
+
+    fn approx(input: &str, expected: f64) {
+        let value = eval(input).value.to_f64();
+        assert!(
+            (value - expected).abs() < 1e-6,
+            "{} evaluated to {}, expected {}",
+            input,
+            value,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_arithmetic_precedence() {
+        approx("2 + 3 * 4 ^ 2", 50.0);
+        approx("-2^2", -4.0);
+        approx("2 ^ 3 ^ 2", 512.0);
+        approx("(1 + 2) * 3 % 4", 1.0);
+        approx("10 mod 3", 1.0);
+    }
+
+    #[test]
+    fn test_decimal_mode_is_exact() {
+        let result = eval_decimal("0.1 + 0.2");
+        assert!(matches!(result.value, Number::Decimal(_)));
+        assert_eq!(result.value.to_string(), "0.3");
+        assert_eq!(eval_decimal("19.99 * 3").value.to_string(), "59.97");
+        assert_eq!(eval_decimal("10% of 0.3").value.to_string(), "0.03");
+
+        // Operators keep decimals exact through the parser's precedence
+        let result = eval_decimal("2 + 3 * 4 ^ 2");
+        assert!(matches!(result.value, Number::Decimal(_)));
+        assert_eq!(result.value.to_string(), "50");
+        assert_eq!(eval_decimal("-2^2").value.to_string(), "-4");
+        assert_eq!(eval_decimal("(1.1 + 2.2) * 3 % 4").value.to_string(), "1.9");
+        assert_eq!(eval_decimal("1 / 4 - 0.05").value.to_string(), "0.2");
+    }
+
+    #[test]
+    fn test_fraction_mode_is_exact() {
+        assert_eq!(eval_fraction("1/3 + 1/6"), "1/2");
+        assert_eq!(eval_fraction("2 3/4 cups"), "2 3/4 cups");
+        assert_eq!(eval_fraction("2 3/4 cups * 2"), "5 1/2 cups");
+        assert_eq!(eval_fraction("-1 1/2 - 1/4"), "-1 3/4");
+        assert_eq!(eval_fraction("3/8 in to mm"), "9 21/40 mm");
+        assert_eq!(eval_fraction("1/2 + 1/2"), "1.00");
+        // Floats that aren't short decimals stay floats
+        assert_eq!(eval_fraction("pi"), "3.14");
+        assert_eq!(eval_fraction("sqrt(2)"), "1.41");
+        assert_eq!(eval_fraction("2 * pi"), "6.28");
+        // A line's own notation shows the fraction as a number
+        assert_eq!(eval_fraction("1/3 to 4 dp"), "0.3333");
+        assert_eq!(eval_fraction("1/3 to sci"), "3.33e-1");
+        assert_eq!(eval_fraction("2 3/4 cups to 1 dp"), "2.8 cups");
+    }
+
+    #[test]
+    fn test_to_fraction_target() {
+        assert_eq!(eval("0.375 to fraction").to_string(), "3/8");
+        assert_eq!(eval("1/3 + 1/6 to fraction").to_string(), "1/2");
+        assert_eq!(eval("5.3 in to fraction").to_string(), "5 3/10 in");
+        assert_eq!(eval("2 3/4").to_string(), "2.75");
+    }
+
+    #[test]
+    fn test_programmer_mode() {
+        let show = |input: &str| eval(input).to_string();
+        assert_eq!(show("0xFF"), "255");
+        assert_eq!(show("0xFFFF + 1"), "65536");
+        assert_eq!(show("0o755 & 0o700"), "448");
+        assert_eq!(show("0b1010 | 0b0101"), "15");
+        assert_eq!(show("6 xor 3"), "5");
+        assert_eq!(show("1 << 10"), "1024");
+        assert_eq!(show("0xF0 >> 4"), "15");
+        assert_eq!(show("not 0"), "-1");
+        assert_eq!(show("255 to hex"), "0xFF");
+        assert_eq!(show("0xFF in binary"), "0b11111111");
+        assert_eq!(show("493 to octal"), "0o755");
+        assert_eq!(show("-255 to hex"), "-0xFF");
+        assert_eq!(show("0xF / 2"), "7.50");
+        assert_eq!(show("2000"), "2.0k");
+
+        let config = Config::default();
+        assert!(eval_with(&config, "1.5 & 1").is_err());
+        assert!(eval_with(&config, "2.5 to hex").is_err());
+        assert!(eval_with(&config, "1 << 200").is_err());
+        let message = |input: &str| eval_with(&config, input).unwrap_err().to_string();
+        assert!(message("1 << 127").contains("overflow"));
+        assert!(message("1 << -1").contains("negative"));
+        assert!(message("5 km & 3").contains("km"));
+        assert!(eval_with(&config, "not 5 km").is_err());
+        assert_eq!(show("1 << 126"), (1i128 << 126).to_string());
+    }
+
+    #[test]
+    fn test_lists_and_statistics() {
+        let show = |input: &str| eval(input).to_string();
+        assert_eq!(show("[1, 2, 3]"), "[1.00, 2.00, 3.00]");
+        assert_eq!(show("1..4"), "[1.00, 2.00, 3.00, 4.00]");
+        assert_eq!(show("3..1"), "[3.00, 2.00, 1.00]");
+        assert_eq!(show("[1, 2] * 3"), "[3.00, 6.00]");
+        assert_eq!(show("[1, 2] + [10, 20]"), "[11.00, 22.00]");
+        assert_eq!(show("[100, 200] + 10%"), "[110, 220]");
+        assert_eq!(show("sqrt [4, 9]"), "[2.00, 3.00]");
+        assert_eq!(show("[5 km, 3 miles] to km"), "[5.00 km, 4.83 km]");
+        assert_eq!(show("[5 km, 3 mi] to km"), "[5.00 km, 4.83 km]");
+
+        assert_eq!(show("sum(1..10)"), "55.00");
+        assert_eq!(show("mean(1, 2, 3, 4)"), "2.50");
+        assert_eq!(show("median [5, 1, 3]"), "3.00");
+        assert_eq!(show("median [4, 1, 3, 2]"), "2.50");
+        assert_eq!(show("mode [1, 2, 2, 3, 3]"), "2.00");
+        assert_eq!(show("count(1..10, 20)"), "11.00");
+        assert_eq!(show("max [1, 7, 3]"), "7.00");
+        assert_eq!(show("min(1 km, 500 m)"), "500 m");
+        assert_eq!(show("sum [1 km, 500 m]"), "1.50 km");
+        assert_eq!(show("percentile(1..101, 90)"), "91.00");
+        approx("variance [2, 4, 4, 4, 5, 5, 7, 9]", 32.0 / 7.0);
+        approx("stdev [2, 4, 4, 4, 5, 5, 7, 9]", (32.0f64 / 7.0).sqrt());
+        assert_eq!(show("variance [1 m, 3 m]"), "2.00 m²");
+
+        let config = Config::default();
+        assert!(eval_with(&config, "[1, 2] + [1, 2, 3]").is_err());
+        assert!(eval_with(&config, "stdev [1]").is_err());
+        assert!(eval_with(&config, "mean []").is_err());
+        assert!(eval_with(&config, "percentile([1, 2], 150)").is_err());
+        assert!(eval_with(&config, "1..1e9").is_err());
+        assert!(eval_with(&config, "1.5..3").is_err());
+    }
+
+    #[test]
+    fn test_percentage_phrases() {
+        let show = |input: &str| eval(input).to_string();
+        assert_eq!(show("50 is what % of 200"), "25.00%");
+        assert_eq!(show("50 as a % of 200"), "25.00%");
+        assert_eq!(show("500 m as % of 2 km"), "25.00%");
+        assert_eq!(show("% change from 80 to 100"), "25.00%");
+        assert_eq!(show("% change from 100 to 80"), "-20.00%");
+        assert_eq!(show("20% off 150"), "120");
+        assert_eq!(show("20% off $80"), "64.00 USD");
+        assert_eq!(show("15% on 200"), "230");
+        assert_eq!(show("5% of what is 20"), "400");
+        assert_eq!(show("10% of what is 5 km"), "50.00 km");
+        assert_eq!(show("10% off [100, 200]"), "[90.00, 180]");
+        assert_eq!(eval_decimal("10% off 19.99").value.to_string(), "17.991");
+
+        let config = Config::default();
+        assert!(eval_with(&config, "5 kg as a % of 2 m").is_err());
+        for (input, span) in [
+            ("% change from 0 to 100", Span::new(14, 15)),
+            ("0 is what % of 0", Span::new(15, 16)),
+            ("0% of what is 20", Span::new(0, 2)),
+        ] {
+            let err = eval_with(&config, input).unwrap_err();
+            assert_eq!(
+                err.kind,
+                EvaluatorError::EvaluationError(crate::fl!("division-by-zero")),
+                "{}",
+                input
+            );
+            assert_eq!(err.span, Some(span), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_notation_targets() {
+        let show = |input: &str| eval(input).to_string();
+        assert_eq!(show("1234.56789 to 2 dp"), "1234.57");
+        assert_eq!(show("5 km to 3 DP"), "5.000 km");
+        assert_eq!(show("1234567 to 3 sf"), "1230000");
+        assert_eq!(show("299792458 to sci"), "3e8");
+        assert_eq!(show("0.000047 m to eng"), "47e-6 m");
+        assert_eq!(show("[1500, 0.26] to 1 dp"), "[1500.0, 0.3]");
+        assert_eq!(show("50 is what % of 300 to 1 dp"), "16.7%");
+        assert!(eval_with(&Config::default(), "5 to 2 km").is_err());
+    }
+
+    #[test]
+    fn test_words_target() {
+        let show = |input: &str| eval(input).to_string();
+        assert_eq!(
+            show("1234.5 to words"),
+            "one thousand two hundred thirty-four point five"
+        );
+        assert_eq!(show("3 km in words"), "three km");
+        assert_eq!(show("[1, 2] to words"), "[one, two]");
+        assert!(eval_with(&Config::default(), "1e20 to words").is_err());
+    }
+
+    #[test]
+    fn test_constants() {
+        approx("c to km/h", 1_079_252_848.8);
+        approx("1 lightyear to km", 9_460_730_472_580.8);
+        approx("AU to km", 149_597_870.7);
+        approx("2 * g", 19.6133);
+        assert_eq!(eval("c").unit.as_deref(), Some("m/s"));
+        assert_eq!(eval("G to sci").to_string(), "6.67e-11 m³/kg·s²");
+        assert_eq!(eval("5 g").unit.as_deref(), Some("g"));
+        approx("2 phi", 3.236068);
+        approx("golden ratio * 2", 3.236068);
+        approx("Speed of Light to km/s", 299_792.458);
+        assert_eq!(eval("electron mass").to_string(), "9.11e-31 kg");
+        assert_eq!(eval("2 electron mass").to_string(), "1.82e-30 kg");
+        assert_eq!(eval("avogadro").to_string(), "6.02e23");
+        assert_eq!(eval("boltzmann").to_string(), "1.38e-23 J/K");
+
+        let mut config = Config::default();
+        config.constants.insert(
+            "answer".to_string(),
+            Constant {
+                value: 42.0,
+                unit: Some("km".to_string()),
+            },
+        );
+        let state = AppStateBuilder::new(&config).build();
+        let mut variables = HashMap::new();
+        variables.insert("c".to_string(), (Number::Float(3.0), None));
+        let mut ctx = EvalContext::new(&mut variables, &[], &state, &config);
+        let result = evaluate_expr("answer to m", &mut ctx).unwrap();
+        assert_eq!(result.value.to_f64(), 42_000.0);
+        let result = evaluate_expr("c * 2", &mut ctx).unwrap();
+        assert_eq!(result.value.to_f64(), 6.0);
+    }
+
+    #[test]
+    fn test_financial_functions() {
+        approx("pmt(5%/12, 360, 300000)", 1610.464869);
+        approx("fv(5%, 10, 0, 1000)", 1628.894627);
+        approx("fv(5%, 10, 100)", 1257.789254);
+        approx("fv(0, 10, 100, 1000)", 2000.0);
+        approx("npv(10%, -1000, 300, 400, 500)", -21.036814);
+        assert_eq!(eval("pmt(1%, 12, 1000 USD)").unit.as_deref(), Some("USD"));
+        assert_eq!(eval("npv(0, [-100 EUR, 50 EUR])").to_string(), "-50.00 EUR");
+        assert_eq!(eval("irr(-1000, 300, 400, 500)").to_string(), "8.90%");
+        assert_eq!(eval("irr([-100, 110])").to_string(), "10.00%");
+
+        let config = Config::default();
+        assert!(eval_with(&config, "irr(100, 200)").is_err());
+        assert!(eval_with(&config, "irr(-100)").is_err());
+        assert!(eval_with(&config, "pmt(1%, 12)").is_err());
+        assert!(eval_with(&config, "pmt(1%, 0, 1000)").is_err());
+    }
+
+    #[test]
+    fn test_interest_phrases() {
+        approx("mortgage 300k at 6.5% over 30 years", 1896.204070);
+        approx("loan of 12k at 0% for 24 months", 500.0);
+        approx("$5000 at 4% for 10 years compounded monthly", 7454.163412);
+        approx("1000 at 5% for 2 years", 1102.5);
+        approx("(mortgage 300k at 6.5% over 30 years) * 12", 22754.448846);
+        let result = eval("mortgage $300k at 6.5% over 30 years in EUR");
+        assert_eq!(result.unit.as_deref(), Some("EUR"));
+        let result = eval("$5000 at 4% for 10 years compounded monthly to EUR");
+        assert_eq!(result.unit.as_deref(), Some("EUR"));
+
+        let config = Config::default();
+        let message = |input: &str| eval_with(&config, input).unwrap_err().to_string();
+        assert!(message("mortgage 300k at 6.5% over 0 years").contains("period"));
+        assert!(message("mortgage 300k at 6.5% over 30").contains("30"));
+        assert!(message("1000 at 5 km for 2 years").contains("km"));
+    }
+
+    #[test]
+    fn test_calculus_functions() {
+        approx("integrate(x^2, x, 0, 3)", 9.0);
+        approx("integrate(sin(x), x, 0, pi)", 2.0);
+        approx("integrate(1/x, x, 1, e)", 1.0);
+        approx("integrate(x, x, 2, 0)", -2.0);
+        approx("derive(sin(x), x, pi/4)", std::f64::consts::FRAC_1_SQRT_2);
+        approx("derive(x^3, x, 2)", 12.0);
+        assert_eq!(eval("sum(k^2, k, 1, 100)").value.to_f64(), 338350.0);
+        assert_eq!(eval("prod(k, k, 1, 5)").value.to_f64(), 120.0);
+        assert_eq!(eval("sum(k, k, 5, 1)").value.to_f64(), 0.0);
+        // Without a bound variable sum still adds up its arguments
+        assert_eq!(eval("sum(1, 2, 3, 4)").to_string(), "10.00");
+
+        // Units carry through
+        assert_eq!(
+            eval("integrate(2 m/s, t, 0 s, 10 s)").to_string(),
+            "20.00 m"
+        );
+        assert_eq!(eval("sum(k m, k, 1, 3)").to_string(), "6.00 m");
+
+        let config = Config::default();
+        let state = AppStateBuilder::new(&config).build();
+        let mut variables = HashMap::new();
+        let mut ctx = EvalContext::new(&mut variables, &[], &state, &config);
+        let mut show = |input: &str| evaluate_expr(input, &mut ctx).unwrap().to_string();
+        // The bound variable shadows a variable and sees function parameters
+        show("x = 100");
+        assert_eq!(show("integrate(x, x, 0, 2)"), "2.00");
+        show("area(a) = integrate(a * x, x, 0, 1)");
+        assert_eq!(show("area(4)"), "2.00");
+        assert_eq!(show("x"), "100");
+
+        assert!(eval_with(&config, "integrate(x^2, 2, 0, 3)").is_err());
+        assert!(eval_with(&config, "derive(x^2, x)").is_err());
+        assert!(eval_with(&config, "prod(k, k, 1, 2.5)").is_err());
+        assert!(eval_with(&config, "sum(k, k, 1, 1e9)").is_err());
+    }
+
+    #[test]
+    fn test_list_variables() {
+        let config = Config::default();
+        let state = AppStateBuilder::new(&config).build();
+        let mut variables = HashMap::new();
+        let mut ctx = EvalContext::new(&mut variables, &[], &state, &config);
+        let mut show = |input: &str| evaluate_expr(input, &mut ctx).unwrap().to_string();
+        assert_eq!(show("prices = 12, 15, 9"), "[12.00, 15.00, 9.00]");
+        assert_eq!(show("mean(prices)"), "12.00");
+        assert_eq!(show("prices * 2"), "[24.00, 30.00, 18.00]");
+        assert_eq!(show("prices = 5"), "5.00");
+        assert_eq!(show("prices + 1"), "6.00");
+        assert!(ctx.lists.is_empty());
+    }
+
+    #[test]
+    fn test_float_mode_keeps_f64_semantics() {
+        assert_eq!(eval("0.1 + 0.2").value.to_f64(), 0.1 + 0.2);
+    }
+
+    #[test]
+    fn test_percentages() {
+        approx("15 - (30% of 15)", 10.5);
+        approx("(100 + 50%)", 150.0);
+        approx("100 - 20%", 80.0);
+        approx("50% of 200 + 50", 125.0);
+        approx("(10% of (20% of (50% of 1000)))", 10.0);
+        approx("-50 - 20%", -40.0);
+        approx("(100 / (50 - 20%))", 2.5);
+        approx("50 % 7", 1.0);
+    }
+
+    #[test]
+    fn test_functions_and_constants() {
+        approx("sqrt 16", 4.0);
+        approx("log(100)", 2.0);
+        approx("ln(e)", 1.0);
+        approx("max(min(10, 5), min(8, 3))", 5.0);
+        approx("sin(90 degrees)", 1.0);
+        approx("abs(-3) + sign(-2)", 2.0);
+    }
+
+    #[test]
+    fn test_units_and_conversions() {
+        let result = eval("5 m + 20 cm");
+        assert_eq!(result.unit.as_deref(), Some("m"));
+        assert!((result.value.to_f64() - 5.2).abs() < 1e-9);
+
+        let result = eval("(5 km to m) + 1 m");
+        assert_eq!(result.unit.as_deref(), Some("m"));
+        assert!((result.value.to_f64() - 5001.0).abs() < 1e-9);
+
+        approx("1 km / 500 m", 2.0);
+        approx("0 celsius to fahrenheit", 32.0);
+        // Common abbreviations
+        approx("3 mi to km", 4.828032);
+        approx("2 hrs to mins", 120.0);
+        approx("1 yd to ft", 3.0);
+        approx("1 gal to l", 3.78541);
+        approx("1 ha to m2", 10_000.0);
+        approx("1 deg to rad", 0.0174533);
+
+        let result = eval("100 dollars to euros");
+        assert_eq!(result.unit.as_deref(), Some("EUR"));
+        let result = eval("1000 yen to pounds");
+        assert_eq!(result.unit.as_deref(), Some("GBP"));
+        let result = eval("453.592 grams to pounds");
+        assert_eq!(result.unit.as_deref(), Some("pounds"));
+        assert!((result.value.to_f64() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_uncertainty_propagation() {
+        let error = |input: &str| eval(input).uncertainty.unwrap();
+        let result = eval("(5.0 ± 0.1 m) * (2.0 ± 0.05 m)");
+        assert_eq!(result.unit.as_deref(), Some("m²"));
+        assert!((result.uncertainty.unwrap() - 0.320156).abs() < 1e-6);
+        assert_eq!(result.to_string(), "10.00 ± 0.32 m²");
+        let result = eval("5.0 ± 0.1 m * 2.0 ± 0.05 m");
+        assert_eq!(result.to_string(), "10.00 ± 0.32 m²");
+
+        assert!((error("(10 ± 0.3) + (5 ± 0.4)") - 0.5).abs() < 1e-9);
+        assert!((error("(10 ± 0.2) / (4 ± 0.1)") - 0.0800391).abs() < 1e-6);
+        assert!((error("(2 ± 0.1)^3") - 1.2).abs() < 1e-9);
+        assert!((error("sqrt(16 ± 0.4)") - 0.05).abs() < 1e-5);
+        assert!((error("5 m ± 2%") - 0.1).abs() < 1e-9);
+        assert!((error("5 m ± 10 cm") - 0.1).abs() < 1e-9);
+        assert!((error("(1 ± 0.01 km) * (1 m)") - 1e-5).abs() < 1e-12);
+
+        // Conversions scale the error without the temperature offset
+        assert!((error("(5.0 ± 0.1 m) to cm") - 10.0).abs() < 1e-9);
+        assert!((error("(20 ± 0.5 C) to F") - 0.9).abs() < 1e-9);
+        assert!(eval("2 * 3").uncertainty.is_none());
+    }
+
+    #[test]
+    fn test_temperature_differences() {
+        let result = eval("20 C - 50 F");
+        assert_eq!(result.unit.as_deref(), Some("C difference"));
+        assert!((result.value.to_f64() - 10.0).abs() < 1e-9);
+
+        let result = eval("10 °C difference in °F");
+        assert_eq!(result.unit.as_deref(), Some("°F difference"));
+        assert!((result.value.to_f64() - 18.0).abs() < 1e-9);
+
+        let result = eval("20 C + 9 ΔF");
+        assert_eq!(result.unit.as_deref(), Some("C"));
+        assert!((result.value.to_f64() - 25.0).abs() < 1e-9);
+        approx("5 C difference + 68 F", 77.0);
+        approx("491.67 R to C", 0.0);
+
+        // Kelvin and Rankine readings take part in unit algebra
+        approx("2 J/K * 300 K", 600.0);
+        approx("1 J/K * 540 R", 300.0);
+        approx("1 J/K * 10 C difference", 10.0);
+
+        let config = Config::default();
+        for input in ["20 C + 5 C", "50 F + 50 F", "1 J/K * 10 C", "5 ΔC - 20 C"] {
+            assert!(eval_with(&config, input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_unit_algebra() {
+        let unit_of = |input: &str| eval(input).unit;
+        assert_eq!(unit_of("10 m * 5 m").as_deref(), Some("m²"));
+        assert_eq!(unit_of("100 km / 2 h").as_deref(), Some("km/h"));
+        assert_eq!(unit_of("(3 m)^2").as_deref(), Some("m²"));
+        assert_eq!(unit_of("sqrt(25 m^2)").as_deref(), Some("m"));
+        assert_eq!(unit_of("3 $/kg * 10 kg").as_deref(), Some("USD"));
+        assert_eq!(unit_of("$10/kg * 3 kg").as_deref(), Some("USD"));
+        assert_eq!(unit_of("$4 per kg * 500 g").as_deref(), Some("USD"));
+        assert_eq!(unit_of("2 kW * 3 h").as_deref(), Some("kW·h"));
+        assert_eq!(unit_of("10 km/h * 2 h").as_deref(), Some("km"));
+        assert_eq!(unit_of("60 mph * 2 h").as_deref(), Some("mi"));
+        assert_eq!(unit_of("5 m/s * 10 s").as_deref(), Some("m"));
+        assert_eq!(unit_of("1 hectare / 1 m2"), None);
+
+        approx("10 m * 5 m", 50.0);
+        approx("5 m * 20 cm", 1.0);
+        approx("100 km / 2 h to mph", 31.068560);
+        approx("1 hectare / 1 m2", 10000.0);
+        approx("10 ft * 20 ft to m²", 18.580608);
+        approx("0.15 USD/kWh * 300 kWh", 45.0);
+        approx("$10/kg * 3 kg", 30.0);
+        approx("$4 per kg * 500 g", 2.0);
+        approx("2 kW * 3 h to kWh", 6.0);
+        approx("60 mph * 30 min", 30.0);
+        approx("10 N * 2 m to J", 20.0);
+        approx("5 kg * 9.8 m/s^2 to N", 49.0);
+        approx("1 atm to kpa", 101.325);
+    }
+
+    #[test]
+    fn test_compound_quantities() {
+        approx("5 ft 3 in", 5.25);
+        approx("1h 30min", 1.5);
+        approx("2 lb 4 oz", 2.25);
+        approx("5 ft 3 in to cm", 160.02);
+        assert_eq!(eval("1h 30min").unit.as_deref(), Some("h"));
+
+        assert_eq!(eval("1.8 m to ft and in").to_string(), "5 ft 10.87 in");
+        assert_eq!(eval("5000 s to h min s").to_string(), "1 h 23 min 20 s");
+        assert_eq!(eval("-90 min to h min").to_string(), "-1 h 30 min");
+        // The remainder is rounded as shown and carried into the larger units
+        assert_eq!(eval("0.91439 m to ft and in").to_string(), "3 ft 0 in");
+        assert_eq!(eval("7199.999 s to h min s").to_string(), "2 h 0 min 0 s");
+        assert_eq!(eval("3599.9999 s to h min s").to_string(), "1 h 0 min 0 s");
+        assert_eq!(eval("0.5 ft to ft and in").to_string(), "6 in");
+        assert_eq!(eval("100000 min to h min").to_string(), "1666 h 40 min");
+        let result = eval("5000 s to h min s");
+        assert_eq!(result.unit.as_deref(), Some("h"));
+        assert!((result.value.to_f64() - 5000.0 / 3600.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_errors() {
+        let config = Config::default();
+        assert!(eval_with(&config, "10 xyz in abc").is_err());
+        assert!(eval_with(&config, "5 m to kg").is_err());
+        assert!(eval_with(&config, "undefined_name + 1").is_err());
+        assert!(eval_with(&config, "2 +").is_err());
+        assert!(eval_with(&config, "max()").is_err());
+        assert!(eval_with(&config, "10 m * 5 m to hectares").is_ok());
+        assert!(eval_with(&config, "100 km / 2 h to km").is_err());
+        assert!(eval_with(&config, "5 m + 2 s").is_err());
+        assert!(eval_with(&config, "5 ft 3 kg").is_err());
+        assert!(eval_with(&config, "5 kg to ft and in").is_err());
+        assert!(eval_with(&config, "1 / 0").is_err());
+        assert!(eval_with(&config, "5 km / 0").is_err());
+        assert!(eval_with(&config, "5 % 0").is_err());
+    }
+
+    #[test]
+    fn test_assignment_stores_variable() {
+        let config = Config::default();
+        let state = AppStateBuilder::new(&config).build();
+        let mut variables = HashMap::new();
+        let mut ctx = EvalContext::new(&mut variables, &[], &state, &config);
+        evaluate_expr("price = 10 USD", &mut ctx).unwrap();
+        let result = evaluate_expr("price * 2", &mut ctx).unwrap();
+        assert_eq!(result.value, 20.0);
+        assert_eq!(result.unit.as_deref(), Some("USD"));
     }
-    None
 }
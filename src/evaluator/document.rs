@@ -0,0 +1,908 @@
+//! Dependencies between the lines of a document.
+//!
+//! Each line may define a name (`price = 10`, `tax(x) = x * 0.08`) and read
+//! names defined on other lines. [`DocumentGraph`] links every read to the
+//! line defining it, and [`Document`] evaluates lines in that order,
+//! re-evaluating only the lines whose text or inputs changed since the last
+//! pass.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+use crate::evaluator::lexer::{self, TokenKind};
+use crate::evaluator::{AgentRegistry, Diagnostic, Number};
+use crate::models::{AppState, HistoryEntry, LineKind, UserFunction};
+
+/// Words that refer to lines by position rather than by name: `line 3`,
+/// `prev line`, `above`, `subtotal`, `section avg`, `grand total` and the
+/// history keywords.
+const POSITIONAL_WORDS: &[&str] = &[
+    "line", "above", "subtotal", "section", "grand", "sum", "total", "average", "avg", "prev",
+];
+
+/// Names a line of a document defines and reads.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineDeps {
+    /// Variable, list or function the line defines.
+    pub defines: Option<String>,
+    /// Identifiers the line reads. Those no line defines are units,
+    /// constants or keywords.
+    pub reads: BTreeSet<String>,
+    /// Whether the line refers to the lines above it by position.
+    pub positional: bool,
+}
+
+impl LineDeps {
+    /// Dependencies of one line, from its tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use numby::evaluator::document::LineDeps;
+    ///
+    /// let deps = LineDeps::of("total = price * qty // per order");
+    /// assert_eq!(deps.defines.as_deref(), Some("total"));
+    /// assert!(deps.reads.contains("price") && deps.reads.contains("qty"));
+    /// assert!(LineDeps::of("line 2 * 3").positional);
+    /// ```
+    pub fn of(line: &str) -> Self {
+        if lexer::section_header(line).is_some() {
+            return Self::default();
+        }
+        let Ok(tokens) = lexer::tokenize(lexer::strip_comment(line)) else {
+            return Self::default();
+        };
+        let kinds: Vec<&TokenKind> = tokens.iter().map(|token| &token.kind).collect();
+
+        let (defines, params, body) = match kinds.as_slice() {
+            [TokenKind::Ident(name), TokenKind::Equals, rest @ ..]
+                if rest.first() != Some(&&TokenKind::Equals) =>
+            {
+                (Some(name.clone()), Vec::new(), 2)
+            }
+            [TokenKind::Ident(name), TokenKind::LParen, ..] => match definition_head(&kinds) {
+                Some((params, width)) => (Some(name.clone()), params, width),
+                None => (None, Vec::new(), 0),
+            },
+            _ => (None, Vec::new(), 0),
+        };
+
+        let mut deps = Self {
+            defines,
+            ..Self::default()
+        };
+        for (i, kind) in kinds.iter().enumerate().skip(body) {
+            let TokenKind::Ident(word) = kind else {
+                continue;
+            };
+            let lower = word.to_lowercase();
+            let line_number = lower
+                .strip_prefix("line")
+                .is_some_and(|digits| digits.bytes().all(|b| b.is_ascii_digit()));
+            if line_number || POSITIONAL_WORDS.contains(&lower.as_str()) {
+                deps.positional = true;
+            }
+            // `x` in `f(x) = x * 2` is the parameter, not a variable
+            if !params.contains(word) && kinds.get(i + 1) != Some(&&TokenKind::Equals) {
+                deps.reads.insert(word.clone());
+            }
+        }
+        deps
+    }
+}
+
+/// Lines, counted from 1, that `line` refers to by number as `line 3` or
+/// `line3`, each with the byte range of the number in `line`. `$3` is
+/// three dollars, not a reference.
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::document::line_references;
+///
+/// assert_eq!(line_references("line 2 + line1 * $9"), vec![(5..6, 2), (13..14, 1)]);
+/// ```
+pub fn line_references(line: &str) -> Vec<(Range<usize>, usize)> {
+    if lexer::section_header(line).is_some() {
+        return Vec::new();
+    }
+    let Ok(tokens) = lexer::tokenize(lexer::strip_comment(line)) else {
+        return Vec::new();
+    };
+    let mut references = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let TokenKind::Ident(word) = &token.kind else {
+            continue;
+        };
+        let (span, digits) = match tokens.get(i + 1) {
+            Some(next) if word.eq_ignore_ascii_case("line") => match &next.kind {
+                TokenKind::Number(digits) => (next.span.start..next.span.end, digits.as_str()),
+                _ => continue,
+            },
+            _ => match word.get(..4).zip(word.get(4..)) {
+                Some((prefix, digits)) if prefix.eq_ignore_ascii_case("line") => {
+                    (token.span.start + 4..token.span.end, digits)
+                }
+                _ => continue,
+            },
+        };
+        let written = line.get(span.clone()) == Some(digits);
+        if let Some(number) = digits.parse().ok().filter(|&n| written && n > 0) {
+            references.push((span, number));
+        }
+    }
+    references
+}
+
+/// A line inserted at or deleted from an index of a document, which moves
+/// the lines after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEdit {
+    Inserted(usize),
+    Deleted(usize),
+}
+
+impl LineEdit {
+    /// Number, counted from 1, of line `number` after the edit. References
+    /// to a deleted line go to the line above, which it was joined to.
+    pub fn renumber(self, number: usize) -> usize {
+        match self {
+            Self::Inserted(at) if number > at => number + 1,
+            Self::Deleted(at) if number > at.max(1) => number - 1,
+            _ => number,
+        }
+    }
+}
+
+/// `line` with each line it refers to by number renumbered by `renumber`,
+/// as when lines above it are inserted or deleted.
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::document::renumber_line_references;
+///
+/// let shifted = renumber_line_references("line 2 + line1 // line 1", |n| n + 1);
+/// assert_eq!(shifted, "line 3 + line2 // line 1");
+/// ```
+pub fn renumber_line_references(line: &str, renumber: impl Fn(usize) -> usize) -> String {
+    let mut renumbered = line.to_string();
+    for (span, number) in line_references(line).into_iter().rev() {
+        renumbered.replace_range(span, &renumber(number).to_string());
+    }
+    renumbered
+}
+
+/// Parameters of a `name(a, b) =` head and the number of tokens it takes.
+fn definition_head(kinds: &[&TokenKind]) -> Option<(Vec<String>, usize)> {
+    let mut params = Vec::new();
+    let mut pos = 2;
+    if kinds.get(pos)? != &&TokenKind::RParen {
+        loop {
+            match kinds.get(pos)? {
+                TokenKind::Ident(param) => params.push(param.clone()),
+                _ => return None,
+            }
+            match kinds.get(pos + 1)? {
+                TokenKind::Comma => pos += 2,
+                TokenKind::RParen => break,
+                _ => return None,
+            }
+        }
+        pos += 1;
+    }
+    (kinds.get(pos + 1)? == &&TokenKind::Equals).then_some((params, pos + 2))
+}
+
+/// Which lines of a document depend on which.
+///
+/// A name read on a line refers to its nearest definition above, so
+/// `x = x + 1` builds on an earlier `x`. A name only defined further down
+/// refers to its first definition there. Lines that refer to lines by
+/// position depend on every line above them.
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::document::DocumentGraph;
+///
+/// let graph = DocumentGraph::new(&["total = price * 2", "price = 10", "a = b", "b = a"]);
+/// let (order, cycles) = graph.order();
+/// assert_eq!(order, vec![1, 0, 2, 3]);
+/// assert_eq!(cycles, vec![vec![2, 3]]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DocumentGraph {
+    lines: Vec<LineDeps>,
+    /// For each line, the line defining each name it reads.
+    bindings: Vec<BTreeMap<String, usize>>,
+    /// For each line, the lines it depends on.
+    inputs: Vec<BTreeSet<usize>>,
+}
+
+impl DocumentGraph {
+    pub fn new(lines: &[&str]) -> Self {
+        let lines: Vec<LineDeps> = lines.iter().map(|line| LineDeps::of(line)).collect();
+        let mut definitions: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, deps) in lines.iter().enumerate() {
+            if let Some(name) = &deps.defines {
+                definitions.entry(name).or_default().push(index);
+            }
+        }
+
+        let mut bindings = Vec::with_capacity(lines.len());
+        let mut inputs = Vec::with_capacity(lines.len());
+        for (index, deps) in lines.iter().enumerate() {
+            let mut bound = BTreeMap::new();
+            for name in &deps.reads {
+                let Some(defined_at) = definitions.get(name.as_str()) else {
+                    continue;
+                };
+                let above = defined_at.iter().rev().find(|&&line| line < index);
+                let below = defined_at.iter().find(|&&line| line > index);
+                if let Some(&line) = above.or(below) {
+                    bound.insert(name.clone(), line);
+                }
+            }
+            let mut depends_on: BTreeSet<usize> = bound.values().copied().collect();
+            if deps.positional {
+                depends_on.extend(0..index);
+            }
+            bindings.push(bound);
+            inputs.push(depends_on);
+        }
+
+        Self {
+            lines,
+            bindings,
+            inputs,
+        }
+    }
+
+    /// Dependencies of the line at `index`.
+    pub fn deps(&self, index: usize) -> &LineDeps {
+        &self.lines[index]
+    }
+
+    /// The line defining each name the line at `index` reads.
+    pub fn bindings(&self, index: usize) -> &BTreeMap<String, usize> {
+        &self.bindings[index]
+    }
+
+    /// Lines in an order where each comes after the lines it depends on,
+    /// top-down where the order is free, and the cycles of lines that depend
+    /// on each other. Lines that only depend on a cycle come last.
+    pub fn order(&self) -> (Vec<usize>, Vec<Vec<usize>>) {
+        let count = self.lines.len();
+        let mut dependents = vec![Vec::new(); count];
+        let mut waiting: Vec<usize> = self.inputs.iter().map(BTreeSet::len).collect();
+        for (index, inputs) in self.inputs.iter().enumerate() {
+            for &input in inputs {
+                dependents[input].push(index);
+            }
+        }
+
+        let mut ready: BinaryHeap<Reverse<usize>> = (0..count)
+            .filter(|&index| waiting[index] == 0)
+            .map(Reverse)
+            .collect();
+        let mut order = Vec::with_capacity(count);
+        while let Some(Reverse(index)) = ready.pop() {
+            order.push(index);
+            for &dependent in &dependents[index] {
+                waiting[dependent] -= 1;
+                if waiting[dependent] == 0 {
+                    ready.push(Reverse(dependent));
+                }
+            }
+        }
+
+        // What is left is on a cycle or downstream of one
+        let placed: HashSet<usize> = order.iter().copied().collect();
+        let left: Vec<usize> = (0..count).filter(|index| !placed.contains(index)).collect();
+        let reach: HashMap<usize, HashSet<usize>> = left
+            .iter()
+            .map(|&index| (index, self.reachable(index, &dependents, &placed)))
+            .collect();
+        let mut cycles: Vec<Vec<usize>> = Vec::new();
+        for &index in &left {
+            if !reach[&index].contains(&index) || cycles.iter().any(|c| c.contains(&index)) {
+                continue;
+            }
+            let cycle = left
+                .iter()
+                .copied()
+                .filter(|other| reach[&index].contains(other) && reach[other].contains(&index))
+                .collect();
+            cycles.push(cycle);
+        }
+        order.extend(left);
+        (order, cycles)
+    }
+
+    /// Lines that depend on `index`, directly or not, among those not placed.
+    fn reachable(
+        &self,
+        index: usize,
+        dependents: &[Vec<usize>],
+        placed: &HashSet<usize>,
+    ) -> HashSet<usize> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![index];
+        while let Some(line) = stack.pop() {
+            for &dependent in &dependents[line] {
+                if !placed.contains(&dependent) && seen.insert(dependent) {
+                    stack.push(dependent);
+                }
+            }
+        }
+        seen
+    }
+}
+
+/// What a line defines, restored before evaluating lines that read it.
+#[derive(Clone, Debug)]
+enum Definition {
+    Variable((Number, Option<String>), Option<f64>),
+    List(Vec<(Number, Option<String>)>),
+    Function(UserFunction),
+}
+
+/// How a line evaluated, kept while its text and inputs stay the same.
+#[derive(Clone, Debug)]
+struct Outcome {
+    output: Result<String, String>,
+    result: Option<HistoryEntry>,
+    kind: Option<LineKind>,
+    definition: Option<Definition>,
+}
+
+/// A document evaluated as a whole: lines in dependency order, so a line
+/// may use a name defined further down, with each line's outcome kept so
+/// an edit only re-evaluates the lines it affects.
+///
+/// # Examples
+///
+/// ```
+/// use numby::config::Config;
+/// use numby::evaluator::{AgentRegistry, Document};
+/// use numby::models::AppState;
+///
+/// let config = Config::default();
+/// let registry = AgentRegistry::new(&config).unwrap();
+/// let mut state = AppState::builder(&config).build();
+/// let mut document = Document::default();
+///
+/// let results = document.evaluate(&registry, &["total = price * 2", "price = 10"], &mut state);
+/// assert_eq!(results[0], Some(Ok("20.00".to_string())));
+/// assert!(document.notes()[&0].contains("price"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Document {
+    /// Outcomes of the last pass by line fingerprint.
+    outcomes: HashMap<u64, Outcome>,
+    /// Lines of the last pass, and the graph built from them.
+    lines: Vec<String>,
+    graph: DocumentGraph,
+    /// Notes on lines of the last pass that evaluated but read a name
+    /// defined further down.
+    notes: BTreeMap<usize, String>,
+    /// Position in the session history of the result each line added, in
+    /// `session` history mode.
+    recorded: HashMap<usize, usize>,
+}
+
+impl Document {
+    /// Evaluate `lines` and return each line's result, or `None` for blank
+    /// lines. Definitions in `state` end up as the document defines them;
+    /// names no line defines any more are removed.
+    ///
+    /// The graph is only rebuilt when `lines` differ from the last pass. In
+    /// `session` history mode each line's result goes into the session
+    /// history, replacing the one it added before it was edited.
+    pub fn evaluate(
+        &mut self,
+        registry: &AgentRegistry,
+        lines: &[&str],
+        state: &mut AppState,
+    ) -> Vec<Option<Result<String, String>>> {
+        if !self
+            .lines
+            .iter()
+            .map(String::as_str)
+            .eq(lines.iter().copied())
+        {
+            self.graph = DocumentGraph::new(lines);
+            self.recorded = follow_lines(std::mem::take(&mut self.recorded), &self.lines, lines);
+            self.lines = lines.iter().map(|line| line.to_string()).collect();
+        }
+        let graph = &self.graph;
+        let session = registry.config().history_mode == "session";
+        let defined: HashSet<&str> = (0..lines.len())
+            .filter_map(|index| graph.deps(index).defines.as_deref())
+            .collect();
+        remove_undefined(state, &defined);
+
+        let (order, cycles) = graph.order();
+        let mut in_cycle = HashMap::new();
+        for cycle in &cycles {
+            let numbers: Vec<String> = cycle.iter().map(|line| (line + 1).to_string()).collect();
+            let message = crate::fl!("circular-reference", "lines" => numbers.join(", "));
+            for &line in cycle {
+                in_cycle.insert(line, message.clone());
+            }
+        }
+
+        let seed = {
+            let mut hasher = DefaultHasher::new();
+            state.cache.generation().hash(&mut hasher);
+            crate::i18n::get_locale().to_string().hash(&mut hasher);
+            state.time_format.hash(&mut hasher);
+            state.date_format.hash(&mut hasher);
+            hasher.finish()
+        };
+        // Session history entries can be replaced, so hash them, not their count
+        let history = {
+            let mut hasher = DefaultHasher::new();
+            if let Ok(history) = state.history.read() {
+                for entry in history.iter() {
+                    (entry.value.to_f64().to_bits(), &entry.unit).hash(&mut hasher);
+                }
+            }
+            hasher.finish()
+        };
+
+        let mut fingerprints = vec![0u64; lines.len()];
+        let mut definitions: Vec<Option<Definition>> = vec![None; lines.len()];
+        let mut outputs = vec![None; lines.len()];
+        let mut outcomes = HashMap::new();
+        let mut notes = BTreeMap::new();
+        for index in order {
+            let line = lines[index].trim();
+            let deps = graph.deps(index);
+            let bindings = graph.bindings(index);
+
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            line.hash(&mut hasher);
+            for (name, &defined_at) in bindings {
+                (name, fingerprints[defined_at]).hash(&mut hasher);
+            }
+            if deps.positional {
+                (index, &fingerprints[..index], history).hash(&mut hasher);
+            }
+            let fingerprint = hasher.finish();
+            fingerprints[index] = fingerprint;
+
+            set_current_line(state, Some(index));
+            if line.is_empty() || in_cycle.contains_key(&index) {
+                let _ = state.set_line_result(None);
+                let _ = state.set_line_kind(None);
+                set_current_line(state, None);
+                outputs[index] = in_cycle.get(&index).map(|message| Err(message.clone()));
+                continue;
+            }
+
+            let outcome = match self.outcomes.get(&fingerprint) {
+                Some(outcome) => {
+                    let _ = state.set_line_result(outcome.result.clone());
+                    let _ = state.set_line_kind(outcome.kind);
+                    outcome.clone()
+                }
+                None => {
+                    // Names read here hold the values of the lines they refer to;
+                    // names defined only on this line aren't defined yet
+                    for name in &deps.reads {
+                        if let Some(&defined_at) = bindings.get(name) {
+                            set_definition(state, name, definitions[defined_at].as_ref());
+                        } else if deps.defines.as_ref() == Some(name) {
+                            set_definition(state, name, None);
+                        }
+                    }
+                    let evaluated = registry.evaluate_without_history(line, state);
+                    let add_to_history = matches!(evaluated, Ok((_, true)));
+                    let output = evaluated
+                        .map(|(result, _)| result)
+                        .map_err(|e| undefined_reference(line, &e, deps, bindings));
+                    let definition = match (&output, &deps.defines) {
+                        (Ok(_), Some(name)) => definition(state, name),
+                        _ => None,
+                    };
+                    let outcome = Outcome {
+                        output,
+                        result: state
+                            .line_results
+                            .read()
+                            .ok()
+                            .and_then(|results| results.get(&index).cloned()),
+                        kind: state
+                            .line_kinds
+                            .read()
+                            .ok()
+                            .and_then(|kinds| kinds.get(&index).copied()),
+                        definition,
+                    };
+                    if session && add_to_history && outcome.kind.is_none() {
+                        if let Some(entry) = &outcome.result {
+                            record(&mut self.recorded, state, index, entry.clone());
+                        }
+                    }
+                    outcome
+                }
+            };
+            if outcome.output.is_ok() {
+                let below = bindings.iter().find(|(_, &line)| line > index);
+                if let Some((name, line)) = below {
+                    let note = crate::fl!("forward-reference", "name" => name, "line" => line + 1);
+                    notes.insert(index, note);
+                }
+            }
+            set_current_line(state, None);
+            definitions[index] = outcome.definition.clone();
+            outputs[index] = Some(outcome.output.clone());
+            outcomes.insert(fingerprint, outcome);
+        }
+
+        // Each name keeps its last definition in the document
+        let mut last = HashMap::new();
+        for (index, definition) in definitions.iter().enumerate() {
+            if let Some(name) = &graph.deps(index).defines {
+                if definition.is_some() || !last.contains_key(name) {
+                    last.insert(name, definition.as_ref());
+                }
+            }
+        }
+        for (name, definition) in last {
+            set_definition(state, name, definition);
+        }
+
+        self.outcomes = outcomes;
+        self.notes = notes;
+        outputs
+    }
+
+    /// Notes on lines of the last pass that evaluated, by line index: a
+    /// name read above the line defining it.
+    pub fn notes(&self) -> &BTreeMap<usize, String> {
+        &self.notes
+    }
+}
+
+/// `recorded`, keyed by the lines of `old`, keyed by where those lines are
+/// in `new` instead. Lines before and after the part that changed move with
+/// the lines inserted or deleted between them; changed lines keep their
+/// index, as when edited in place, and those that were deleted are dropped.
+fn follow_lines(
+    recorded: HashMap<usize, usize>,
+    old: &[String],
+    new: &[&str],
+) -> HashMap<usize, usize> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == *b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == *b)
+        .count();
+    let changed = new.len() - prefix - suffix;
+    recorded
+        .into_iter()
+        .filter_map(|(index, position)| {
+            if index < prefix {
+                Some((index, position))
+            } else if index >= old.len() - suffix {
+                Some((index + new.len() - old.len(), position))
+            } else {
+                Some((index, position)).filter(|_| index - prefix < changed)
+            }
+        })
+        .collect()
+}
+
+/// Put the result of the line at `index` in the session history, in place
+/// of the one it added before, whose position is in `recorded`.
+fn record(
+    recorded: &mut HashMap<usize, usize>,
+    state: &AppState,
+    index: usize,
+    entry: HistoryEntry,
+) {
+    let position = recorded.get(&index).copied();
+    if let Ok(mut history) = state.history.write() {
+        if let Some(slot) = position.and_then(|position| history.get_mut(position)) {
+            *slot = entry;
+            return;
+        }
+        recorded.insert(index, history.len());
+    }
+    let _ = state.add_history(entry.value, entry.unit);
+}
+
+/// Message for a line that failed: names no line defines, variables or
+/// functions, are reported as such rather than as unknown.
+fn undefined_reference(
+    line: &str,
+    error: &Diagnostic,
+    deps: &LineDeps,
+    bindings: &BTreeMap<String, usize>,
+) -> String {
+    let name = error.span.and_then(|span| line.get(span.start..span.end));
+    match name {
+        Some(name)
+            if deps.reads.contains(name)
+                && !bindings.contains_key(name)
+                && (error.message() == crate::fl!("unknown-identifier", "name" => name)
+                    || error.message() == crate::fl!("unknown-function", "name" => name)) =>
+        {
+            crate::fl!("undefined-reference", "name" => name)
+        }
+        _ => error.message().to_string(),
+    }
+}
+
+fn set_current_line(state: &AppState, line: Option<usize>) {
+    if let Ok(mut current_line) = state.current_line.write() {
+        *current_line = line;
+    }
+}
+
+/// What `name` is defined as in `state`.
+fn definition(state: &AppState, name: &str) -> Option<Definition> {
+    if let Some(function) = state.functions.read().ok()?.get(name) {
+        return Some(Definition::Function(function.clone()));
+    }
+    if let Some(items) = state.lists.read().ok()?.get(name) {
+        return Some(Definition::List(items.clone()));
+    }
+    let value = state.variables.read().ok()?.get(name).cloned()?;
+    let uncertainty = state.uncertainties.read().ok()?.get(name).copied();
+    Some(Definition::Variable(value, uncertainty))
+}
+
+/// Define `name` in `state` as `definition`, or remove it.
+fn set_definition(state: &AppState, name: &str, definition: Option<&Definition>) {
+    let (Ok(mut variables), Ok(mut uncertainties), Ok(mut lists), Ok(mut functions)) = (
+        state.variables.write(),
+        state.uncertainties.write(),
+        state.lists.write(),
+        state.functions.write(),
+    ) else {
+        return;
+    };
+    variables.remove(name);
+    uncertainties.remove(name);
+    lists.remove(name);
+    functions.remove(name);
+    match definition {
+        Some(Definition::Variable(value, uncertainty)) => {
+            variables.insert(name.to_string(), value.clone());
+            if let Some(uncertainty) = uncertainty {
+                uncertainties.insert(name.to_string(), *uncertainty);
+            }
+        }
+        Some(Definition::List(items)) => {
+            lists.insert(name.to_string(), items.clone());
+        }
+        Some(Definition::Function(function)) => {
+            functions.insert(name.to_string(), function.clone());
+        }
+        None => {}
+    }
+}
+
+/// Remove variables, lists and functions no line of the document defines,
+/// such as those of a deleted line.
+fn remove_undefined(state: &AppState, defined: &HashSet<&str>) {
+    let keep = |name: &String| defined.contains(name.as_str());
+    if let Ok(mut variables) = state.variables.write() {
+        variables.retain(|name, _| keep(name));
+    }
+    if let Ok(mut uncertainties) = state.uncertainties.write() {
+        uncertainties.retain(|name, _| keep(name));
+    }
+    if let Ok(mut lists) = state.lists.write() {
+        lists.retain(|name, _| keep(name));
+    }
+    if let Ok(mut functions) = state.functions.write() {
+        functions.retain(|name, _| keep(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::models::AppStateBuilder;
+
+    #[test]
+    fn test_line_deps() {
+        let deps = LineDeps::of("tax(rate, x) = x * rate + base");
+        assert_eq!(deps.defines.as_deref(), Some("tax"));
+        assert_eq!(
+            deps.reads.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec!["base"]
+        );
+        assert_eq!(LineDeps::of("x = x + 1").reads.len(), 1);
+        assert_eq!(LineDeps::of("f(2) + 1").defines, None);
+        assert_eq!(LineDeps::of("x == 5").defines, None);
+        assert_eq!(LineDeps::of("# price = 10"), LineDeps::default());
+        assert!(LineDeps::of("line3 + 1").positional);
+        assert!(!LineDeps::of("lines + 1").positional);
+        assert!(!LineDeps::of("$2 * 3").positional);
+    }
+
+    #[test]
+    fn test_graph_order() {
+        let graph = DocumentGraph::new(&["x = 1", "y = x + z", "x = 5", "z = x * 2", "sum"]);
+        // `y` reads the `x` above it and the `z` below it
+        assert_eq!(graph.bindings(1).get("x"), Some(&0));
+        assert_eq!(graph.bindings(1).get("z"), Some(&3));
+        assert_eq!(graph.bindings(3).get("x"), Some(&2));
+        let (order, cycles) = graph.order();
+        assert_eq!(order, vec![0, 2, 3, 1, 4]);
+        assert!(cycles.is_empty());
+
+        // A cycle, and a line depending on it
+        let graph = DocumentGraph::new(&["a = b + 1", "b = c", "c = a", "d = a", "e = 1"]);
+        let (order, cycles) = graph.order();
+        assert_eq!(order, vec![4, 0, 1, 2, 3]);
+        assert_eq!(cycles, vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_document_evaluate() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        let mut document = Document::default();
+
+        let results = document.evaluate(
+            &registry,
+            &[
+                "price = 10",
+                "qty = 3",
+                "",
+                "price * qty",
+                "total = price * tax",
+                "tax = 2",
+            ],
+            &mut state,
+        );
+        assert_eq!(results[2], None);
+        assert_eq!(results[3], Some(Ok("30.00".to_string())));
+        assert_eq!(results[4], Some(Ok("20.00".to_string())));
+
+        // Editing the first line updates the lines reading `price`
+        let results = document.evaluate(
+            &registry,
+            &[
+                "price = 20",
+                "qty = 3",
+                "",
+                "price * qty",
+                "total = price * tax",
+                "tax = 2",
+            ],
+            &mut state,
+        );
+        assert_eq!(results[3], Some(Ok("60.00".to_string())));
+        assert_eq!(results[4], Some(Ok("40.00".to_string())));
+
+        // Deleting a line removes what it defined
+        let results = document.evaluate(&registry, &["qty = 3", "", "price * qty"], &mut state);
+        assert!(matches!(results[2], Some(Err(_))));
+        assert!(!state.variables.read().unwrap().contains_key("price"));
+        assert!(!state.variables.read().unwrap().contains_key("total"));
+
+        // Lines depending on each other are reported, not evaluated
+        let results = document.evaluate(&registry, &["a = b + 1", "b = a * 2"], &mut state);
+        assert!(matches!(&results[0], Some(Err(e)) if e.contains("Circular reference")));
+        assert_eq!(results[0], results[1]);
+        assert!(!state.variables.read().unwrap().contains_key("a"));
+    }
+
+    #[test]
+    fn test_document_redefinition() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        let mut document = Document::default();
+
+        // Each line sees the definition above it, whatever order lines
+        // are evaluated in
+        let results = document.evaluate(
+            &registry,
+            &[
+                "x = 2",
+                "y = x * 10",
+                "x = x + 1",
+                "x * 10",
+                "double(n) = n * 2",
+                "double(x)",
+            ],
+            &mut state,
+        );
+        assert_eq!(results[1], Some(Ok("20.00".to_string())));
+        assert_eq!(results[3], Some(Ok("30.00".to_string())));
+        assert_eq!(results[5], Some(Ok("6.00".to_string())));
+        assert_eq!(
+            state
+                .variables
+                .read()
+                .unwrap()
+                .get("x")
+                .map(|(n, _)| n.to_f64()),
+            Some(3.0)
+        );
+        assert!(state.functions.read().unwrap().contains_key("double"));
+    }
+
+    #[test]
+    fn test_document_diagnostics() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        let mut document = Document::default();
+
+        let results = document.evaluate(
+            &registry,
+            &[
+                "total = price * 2",
+                "price = 10",
+                "total + tax",
+                "5 fet",
+                "f(3)",
+            ],
+            &mut state,
+        );
+        // A name used above its definition still evaluates, with a note
+        assert_eq!(results[0], Some(Ok("20.00".to_string())));
+        assert!(document.notes()[&0].contains("price"));
+        assert!(document.notes()[&0].contains('2'));
+        // A name no line defines is reported as such, unlike a unit typo
+        assert!(matches!(&results[2], Some(Err(e)) if e.contains("tax") && e.contains("line")));
+        assert!(matches!(&results[3], Some(Err(e)) if !e.contains("line")));
+        // and so is a function
+        assert_eq!(
+            results[4],
+            Some(Err(crate::fl!("undefined-reference", "name" => "f")))
+        );
+        assert_eq!(document.notes().len(), 1);
+
+        let results = document.evaluate(&registry, &["price = 10", "price * 2"], &mut state);
+        assert_eq!(results[1], Some(Ok("20.00".to_string())));
+        assert!(document.notes().is_empty());
+    }
+
+    #[test]
+    fn test_document_session_history() {
+        let config = Config {
+            history_mode: "session".to_string(),
+            ..Config::default()
+        };
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        let mut document = Document::default();
+
+        let results = document.evaluate(&registry, &["10", "20", "sum"], &mut state);
+        assert_eq!(results[2], Some(Ok("30.00".to_string())));
+        // Editing a line replaces its result in the history
+        let results = document.evaluate(&registry, &["10", "25", "sum"], &mut state);
+        assert_eq!(results[2], Some(Ok("35.00".to_string())));
+        assert_eq!(state.history.read().unwrap().len(), 2);
+        // Inserting a line above keeps the results of the lines below
+        let results = document.evaluate(&registry, &["5", "10", "25", "sum"], &mut state);
+        assert_eq!(results[3], Some(Ok("40.00".to_string())));
+        let results = document.evaluate(&registry, &["5", "10", "20", "sum"], &mut state);
+        assert_eq!(results[3], Some(Ok("35.00".to_string())));
+        // and deleting one leaves its result in the history
+        let results = document.evaluate(&registry, &["10", "20", "sum"], &mut state);
+        assert_eq!(results[2], Some(Ok("35.00".to_string())));
+        let results = document.evaluate(&registry, &["10", "30", "sum"], &mut state);
+        assert_eq!(results[2], Some(Ok("45.00".to_string())));
+        assert_eq!(state.history.read().unwrap().len(), 3);
+    }
+}
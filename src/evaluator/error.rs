@@ -1,6 +1,8 @@
 use std::fmt;
 
-#[derive(Debug)]
+use crate::evaluator::lexer::Span;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum EvaluatorError {
     LockError(String),
     InvalidExpression(String),
@@ -9,6 +11,19 @@ pub enum EvaluatorError {
     EvaluationError(String),
 }
 
+impl EvaluatorError {
+    /// The error text without its category prefix, e.g. "Unknown unit 'fet'".
+    pub fn message(&self) -> &str {
+        match self {
+            EvaluatorError::LockError(details)
+            | EvaluatorError::InvalidExpression(details)
+            | EvaluatorError::ParseError(details)
+            | EvaluatorError::ConfigError(details)
+            | EvaluatorError::EvaluationError(details) => details,
+        }
+    }
+}
+
 impl fmt::Display for EvaluatorError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -42,3 +57,54 @@ impl fmt::Display for EvaluatorError {
 impl std::error::Error for EvaluatorError {}
 
 pub type Result<T> = std::result::Result<T, EvaluatorError>;
+
+/// An [`EvaluatorError`] located in the input line, together with the agent
+/// that reported it. This is what front ends show when a line fails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub kind: EvaluatorError,
+    /// Byte range of the offending token or expression, if known.
+    pub span: Option<Span>,
+    /// Name of the agent that rejected the input.
+    pub agent: Option<&'static str>,
+}
+
+impl Diagnostic {
+    pub fn new(kind: EvaluatorError) -> Self {
+        Self {
+            kind,
+            span: None,
+            agent: None,
+        }
+    }
+
+    /// Attach `span` unless a more precise one is already set.
+    pub fn at(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    /// Record the reporting agent unless one is already set.
+    pub fn by_agent(mut self, agent: &'static str) -> Self {
+        self.agent.get_or_insert(agent);
+        self
+    }
+
+    pub fn message(&self) -> &str {
+        self.kind.message()
+    }
+}
+
+impl From<EvaluatorError> for Diagnostic {
+    fn from(kind: EvaluatorError) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
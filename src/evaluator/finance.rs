@@ -0,0 +1,183 @@
+//! Time-value-of-money math behind `pmt`, `fv`, `npv` and `irr` and the
+//! loan and compound interest phrases of the finance agent.
+//!
+//! Rates are per period as fractions (`0.05 / 12` for 5% a year paid
+//! monthly). Amounts are plain numbers; the caller keeps track of their
+//! currency.
+
+/// Fixed payment that pays off `principal` over `periods` at `rate`.
+pub fn payment(rate: f64, periods: f64, principal: f64) -> f64 {
+    if rate == 0.0 {
+        principal / periods
+    } else {
+        principal * rate / (1.0 - (1.0 + rate).powf(-periods))
+    }
+}
+
+/// Value of `present` after `periods` at `rate`, plus `payment` deposited at
+/// the end of every period.
+pub fn future_value(rate: f64, periods: f64, present: f64, payment: f64) -> f64 {
+    if rate == 0.0 {
+        return present + payment * periods;
+    }
+    let growth = (1.0 + rate).powf(periods);
+    present * growth + payment * (growth - 1.0) / rate
+}
+
+/// Net present value of cash flows one period apart, the first one now.
+pub fn net_present_value(rate: f64, flows: &[f64]) -> f64 {
+    flows
+        .iter()
+        .enumerate()
+        .map(|(period, flow)| flow / (1.0 + rate).powi(period as i32))
+        .sum()
+}
+
+/// Rate at which the net present value of `flows` is zero, or `None` if
+/// the flows never change sign or no rate between -99% and 1000% fits.
+pub fn internal_rate_of_return(flows: &[f64]) -> Option<f64> {
+    let (mut low, mut high) = (-0.99, 10.0);
+    let mut low_npv = net_present_value(low, flows);
+    if low_npv.signum() == net_present_value(high, flows).signum() {
+        return None;
+    }
+    // Bisection: slower than Newton's method but cannot diverge
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        let npv = net_present_value(mid, flows);
+        if npv.abs() < 1e-9 {
+            return Some(mid);
+        }
+        if npv.signum() == low_npv.signum() {
+            low = mid;
+            low_npv = npv;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+/// Compounding periods per year for a word such as `monthly`. `None` for
+/// `continuously`, which has no discrete periods.
+pub fn compounding_periods(word: &str) -> Option<Option<f64>> {
+    match word {
+        "daily" => Some(Some(365.0)),
+        "weekly" => Some(Some(52.0)),
+        "monthly" => Some(Some(12.0)),
+        "quarterly" => Some(Some(4.0)),
+        "semiannually" | "semi-annually" => Some(Some(2.0)),
+        "annually" | "yearly" => Some(Some(1.0)),
+        "continuously" => Some(None),
+        _ => None,
+    }
+}
+
+/// `principal` after `years` at the yearly `rate`, compounded `per_year`
+/// times a year or continuously when `per_year` is `None`.
+pub fn compound(principal: f64, rate: f64, years: f64, per_year: Option<f64>) -> f64 {
+    match per_year {
+        Some(n) => principal * (1.0 + rate / n).powf(n * years),
+        None => principal * (rate * years).exp(),
+    }
+}
+
+/// One year of a loan's amortization schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduleYear {
+    pub year: usize,
+    /// Sum of the year's payments.
+    pub payment: f64,
+    /// Part of the payments that went to the principal.
+    pub principal: f64,
+    pub interest: f64,
+    /// Balance left at the end of the year.
+    pub balance: f64,
+}
+
+/// Yearly summary of paying off `principal` monthly over `months` at the
+/// yearly `rate`.
+pub fn amortization(principal: f64, rate: f64, months: usize) -> Vec<ScheduleYear> {
+    let monthly_rate = rate / 12.0;
+    let installment = payment(monthly_rate, months as f64, principal);
+    let mut balance = principal;
+    let mut years: Vec<ScheduleYear> = Vec::new();
+    for month in 0..months {
+        let interest = balance * monthly_rate;
+        // The last payment clears whatever rounding error is left
+        let repaid = if month + 1 == months {
+            balance
+        } else {
+            installment - interest
+        };
+        balance -= repaid;
+        if month % 12 == 0 {
+            years.push(ScheduleYear {
+                year: month / 12 + 1,
+                payment: 0.0,
+                principal: 0.0,
+                interest: 0.0,
+                balance,
+            });
+        }
+        if let Some(year) = years.last_mut() {
+            year.payment += repaid + interest;
+            year.principal += repaid;
+            year.interest += interest;
+            year.balance = balance;
+        }
+    }
+    years
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "got {}, expected {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_payment_and_future_value() {
+        close(payment(0.05 / 12.0, 360.0, 300_000.0), 1610.46);
+        close(payment(0.0, 12.0, 1200.0), 100.0);
+        close(future_value(0.05, 10.0, 1000.0, 0.0), 1628.89);
+        close(future_value(0.0, 10.0, 1000.0, 100.0), 2000.0);
+    }
+
+    #[test]
+    fn test_npv_and_irr() {
+        let flows = [-1000.0, 300.0, 400.0, 500.0];
+        close(net_present_value(0.1, &flows), -21.04);
+        let irr = internal_rate_of_return(&flows).unwrap();
+        close(net_present_value(irr, &flows), 0.0);
+        close(irr * 100.0, 8.90);
+        assert_eq!(internal_rate_of_return(&[100.0, 200.0]), None);
+    }
+
+    #[test]
+    fn test_compound_interest() {
+        close(compound(5000.0, 0.04, 10.0, Some(12.0)), 7454.16);
+        close(compound(5000.0, 0.04, 10.0, Some(1.0)), 7401.22);
+        close(compound(1000.0, 0.05, 1.0, None), 1051.27);
+        assert_eq!(compounding_periods("continuously"), Some(None));
+        assert_eq!(compounding_periods("hourly"), None);
+    }
+
+    #[test]
+    fn test_amortization_schedule() {
+        let schedule = amortization(300_000.0, 0.065, 360);
+        assert_eq!(schedule.len(), 30);
+        let monthly = payment(0.065 / 12.0, 360.0, 300_000.0);
+        close(schedule[0].payment, 12.0 * monthly);
+        close(schedule[29].balance, 0.0);
+        let repaid: f64 = schedule.iter().map(|year| year.principal).sum();
+        close(repaid, 300_000.0);
+    }
+}
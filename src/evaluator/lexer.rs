@@ -0,0 +1,792 @@
+//! Tokenizer for calculator input.
+//!
+//! Turns a single line into a flat list of [`Token`]s with byte-offset spans.
+//! Literal normalization (digit grouping, decimal commas, currency symbols,
+//! `×`/`÷`/`π`) happens here so the parser only deals with clean tokens.
+//! Comments (`//` and `#`) end the line.
+
+use crate::conversions::symbol_to_currency_code;
+use crate::i18n::NumberStyle;
+
+/// Byte range of a token or expression in the original input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// Numeric literal as plain decimal text (`.` as the decimal separator)
+    /// with grouping removed, or a radix
+    /// literal with a lowercase prefix (`0xFF`, `0b101`, `0o17`).
+    Number(String),
+    /// Word: identifier, unit, keyword or operator word.
+    Ident(String),
+    /// Currency symbol, already mapped to its ISO code.
+    Currency(&'static str),
+    /// Date written `2025-01-31`, or `2025-01-31 13:20` with a time, as
+    /// `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS`.
+    Date(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    Percent,
+    Equals,
+    /// `,` between list items or arguments, or `;` where `,` is the decimal separator
+    Comma,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    /// `..` between the bounds of a range
+    DotDot,
+    Amp,
+    Pipe,
+    /// `<<`
+    Shl,
+    /// `>>`
+    Shr,
+    /// `±` (or `+/-`) between a value and its uncertainty
+    PlusMinus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Error produced when the input contains a character the lexer cannot place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub message: String,
+    pub span: Span,
+}
+
+/// Return the part of `input` before a `//` or `#` comment.
+pub fn strip_comment(input: &str) -> &str {
+    match input.find("//").into_iter().chain(input.find('#')).min() {
+        Some(pos) => &input[..pos],
+        None => input,
+    }
+}
+
+/// Title of a `# Groceries` or `## Rent` section header line. A `#` with
+/// no space after it, or nothing after the space, stays a plain comment.
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::lexer::section_header;
+///
+/// assert_eq!(section_header("## Rent"), Some("Rent"));
+/// assert_eq!(section_header("#note"), None);
+/// assert_eq!(section_header("5 # note"), None);
+/// ```
+pub fn section_header(input: &str) -> Option<&str> {
+    let rest = input.trim().trim_start_matches('#');
+    if rest.len() == input.trim().len() || !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.trim()).filter(|title| !title.is_empty())
+}
+
+/// Tokenize one line of input.
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::lexer::{tokenize, TokenKind};
+///
+/// let tokens = tokenize("1,000 + $5 // note").unwrap();
+/// let kinds: Vec<TokenKind> = tokens.into_iter().map(|t| t.kind).collect();
+/// assert_eq!(
+///     kinds,
+///     vec![
+///         TokenKind::Number("1000".to_string()),
+///         TokenKind::Plus,
+///         TokenKind::Currency("USD"),
+///         TokenKind::Number("5".to_string()),
+///     ]
+/// );
+/// ```
+pub fn tokenize(input: &str) -> Result<Vec<Token>, LexError> {
+    tokenize_with(input, NumberStyle::POINT)
+}
+
+/// Tokenize one line of input with numbers written in `style`.
+///
+/// A lone `,` or `.` inside a number is the locale's decimal separator
+/// unless it groups thousands. When both appear, as in `1.234,5` or
+/// `1,234.5`, the last one is the decimal separator whatever the locale.
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::lexer::{tokenize_with, TokenKind};
+/// use numby::i18n::NumberStyle;
+///
+/// let german = NumberStyle::for_locale("de");
+/// let tokens = tokenize_with("1.234,5; 3.5", german).unwrap();
+/// let kinds: Vec<TokenKind> = tokens.into_iter().map(|t| t.kind).collect();
+/// assert_eq!(
+///     kinds,
+///     vec![
+///         TokenKind::Number("1234.5".to_string()),
+///         TokenKind::Comma,
+///         TokenKind::Number("3.5".to_string()),
+///     ]
+/// );
+/// ```
+pub fn tokenize_with(input: &str, style: NumberStyle) -> Result<Vec<Token>, LexError> {
+    let source = strip_comment(input);
+    let chars: Vec<(usize, char)> = source.char_indices().collect();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut groups: Vec<Group> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (start, c) = chars[i];
+        let end_of = |idx: usize| chars.get(idx).map(|(p, _)| *p).unwrap_or(source.len());
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if let Some((text, next)) = lex_date(&chars, i) {
+            tokens.push(Token {
+                kind: TokenKind::Date(text),
+                span: Span::new(start, end_of(next)),
+            });
+            i = next;
+            continue;
+        }
+
+        if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(|(_, n)| n.is_ascii_digit()))
+        {
+            let (text, next) = lex_number(&chars, i, style).ok_or_else(|| LexError {
+                message: crate::fl!("unexpected-character", "char" => &c.to_string()),
+                span: Span::new(start, end_of(i + 1)),
+            })?;
+            let span = Span::new(start, end_of(next));
+            let literal = &source[span.start..span.end];
+            if radix_overflows(&text) {
+                return Err(LexError {
+                    message: crate::fl!("literal-overflow", "literal" => literal),
+                    span,
+                });
+            }
+            if text.contains('.') && literal.rfind(',') > literal.rfind('.') {
+                if let Some(group) = groups.last_mut() {
+                    group.decimal_commas.push(span);
+                }
+            }
+            tokens.push(Token {
+                kind: TokenKind::Number(text),
+                span,
+            });
+            i = next;
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let mut next = i + 1;
+            while next < chars.len() && (chars[next].1.is_alphanumeric() || chars[next].1 == '_') {
+                next += 1;
+            }
+            let word = &source[start..end_of(next)];
+            let name = if word == "π" { "pi" } else { word };
+            tokens.push(Token {
+                kind: TokenKind::Ident(name.to_string()),
+                span: Span::new(start, end_of(next)),
+            });
+            i = next;
+            continue;
+        }
+
+        let kind = match c {
+            '+' if source[start..].starts_with("+/-") => {
+                tokens.push(Token {
+                    kind: TokenKind::PlusMinus,
+                    span: Span::new(start, end_of(i + 3)),
+                });
+                i += 3;
+                continue;
+            }
+            '+' => TokenKind::Plus,
+            '±' => TokenKind::PlusMinus,
+            '-' | '−' => TokenKind::Minus,
+            '*' | '×' => TokenKind::Star,
+            '/' | '÷' => TokenKind::Slash,
+            '^' => TokenKind::Caret,
+            '%' => TokenKind::Percent,
+            '=' => TokenKind::Equals,
+            ',' | ';' => {
+                if let (';', Some(group)) = (c, groups.last_mut()) {
+                    group.semicolons = true;
+                }
+                TokenKind::Comma
+            }
+            '(' | '[' => {
+                // List items and the arguments of `max(...)` may be separated by `,`
+                let listed = c == '['
+                    || tokens.last().is_some_and(|t| {
+                        matches!(t.kind, TokenKind::Ident(_)) && t.span.end == start
+                    });
+                groups.push(Group {
+                    listed,
+                    ..Group::default()
+                });
+                if c == '(' {
+                    TokenKind::LParen
+                } else {
+                    TokenKind::LBracket
+                }
+            }
+            ')' | ']' => {
+                if let Some(group) = groups.pop() {
+                    group.check(source)?;
+                }
+                if c == ')' {
+                    TokenKind::RParen
+                } else {
+                    TokenKind::RBracket
+                }
+            }
+            '.' if chars.get(i + 1).map(|(_, n)| *n) == Some('.') => {
+                tokens.push(Token {
+                    kind: TokenKind::DotDot,
+                    span: Span::new(start, end_of(i + 2)),
+                });
+                i += 2;
+                continue;
+            }
+            '&' => TokenKind::Amp,
+            '|' => TokenKind::Pipe,
+            '<' | '>' if chars.get(i + 1).map(|(_, n)| *n) == Some(c) => {
+                tokens.push(Token {
+                    kind: if c == '<' {
+                        TokenKind::Shl
+                    } else {
+                        TokenKind::Shr
+                    },
+                    span: Span::new(start, end_of(i + 2)),
+                });
+                i += 2;
+                continue;
+            }
+            // `°C` and `°F` are one word; a bare `°` is the angle unit
+            '°' if chars
+                .get(i + 1)
+                .is_some_and(|(_, n)| "CFKRcfkr".contains(*n))
+                && !chars
+                    .get(i + 2)
+                    .is_some_and(|(_, n)| n.is_alphanumeric() || *n == '_') =>
+            {
+                tokens.push(Token {
+                    kind: TokenKind::Ident(source[start..end_of(i + 2)].to_string()),
+                    span: Span::new(start, end_of(i + 2)),
+                });
+                i += 2;
+                continue;
+            }
+            '°' => TokenKind::Ident("°".to_string()),
+            _ => match symbol_to_currency_code(&c.to_string()) {
+                Some(code) => TokenKind::Currency(code),
+                None => {
+                    return Err(LexError {
+                        message: crate::fl!("unexpected-character", "char" => &c.to_string()),
+                        span: Span::new(start, end_of(i + 1)),
+                    })
+                }
+            },
+        };
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, end_of(i + 1)),
+        });
+        i += 1;
+    }
+
+    for group in groups {
+        group.check(source)?;
+    }
+    Ok(tokens)
+}
+
+/// Brackets being lexed, to catch decimal commas that may be separators.
+#[derive(Default)]
+struct Group {
+    /// A call's arguments or a list's items, which `,` may separate.
+    listed: bool,
+    /// Items are separated with `;`, so a `,` between digits is a decimal.
+    semicolons: bool,
+    /// Numbers read with a decimal comma.
+    decimal_commas: Vec<Span>,
+}
+
+impl Group {
+    /// `max(1,5)` could be one number or two unless the items are separated
+    /// with `;`, as in `max(1,5; 2)`.
+    fn check(&self, source: &str) -> Result<(), LexError> {
+        match self.decimal_commas.first() {
+            Some(&span) if self.listed && !self.semicolons => Err(LexError {
+                message: crate::fl!(
+                    "ambiguous-decimal-comma",
+                    "number" => &source[span.start..span.end]
+                ),
+                span,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether a radix literal such as `0xFF` is too large for a 128-bit integer.
+fn radix_overflows(text: &str) -> bool {
+    let radix = match text.get(..2) {
+        Some("0x") => 16,
+        Some("0b") => 2,
+        Some("0o") => 8,
+        _ => return false,
+    };
+    u128::from_str_radix(&text[2..], radix).is_err()
+}
+
+/// Lex a numeric literal starting at `i`, returning its normalized text and
+/// the index just past it.
+fn lex_number(chars: &[(usize, char)], i: usize, style: NumberStyle) -> Option<(String, usize)> {
+    let at = |idx: usize| chars.get(idx).map(|(_, c)| *c);
+
+    // Radix literals: 0x1F, 0b101, 0o17
+    if at(i) == Some('0') {
+        let radix = match at(i + 1) {
+            Some('x') | Some('X') => Some(16),
+            Some('b') | Some('B') => Some(2),
+            Some('o') | Some('O') => Some(8),
+            _ => None,
+        };
+        if let Some(radix) = radix {
+            let mut next = i + 2;
+            let mut digits = String::new();
+            while let Some(c) = at(next) {
+                if c.is_digit(radix) {
+                    digits.push(c);
+                } else if c != '_' {
+                    break;
+                }
+                next += 1;
+            }
+            if !digits.is_empty() {
+                let prefix = at(i + 1)?.to_ascii_lowercase();
+                return Some((format!("0{}{}", prefix, digits), next));
+            }
+        }
+    }
+
+    // With both separators in the literal the last one is the decimal one
+    let decimal = mixed_decimal_separator(chars, i).unwrap_or(style.decimal);
+    let mut text = String::new();
+    let mut next = i;
+    while let Some(c) = at(next) {
+        let digit_follows = at(next + 1).is_some_and(|n| n.is_ascii_digit());
+        if c.is_ascii_digit() {
+            text.push(c);
+            next += 1;
+        } else if c == '_' && digit_follows && !text.is_empty() {
+            next += 1;
+        } else if (c == '.' || c == ',') && digit_follows {
+            if text.contains('.') {
+                break;
+            } else if c != decimal && is_digit_group(chars, next + 1) {
+                // Thousands grouping: 10,000 (or 10.000 in German)
+                next += 1;
+            } else if c == decimal || c == '.' {
+                // A `.` that groups nothing is a decimal point in any locale
+                text.push('.');
+                next += 1;
+            } else {
+                break;
+            }
+        } else if is_space_grouping(c, style)
+            && !text.is_empty()
+            && !text.contains('.')
+            && is_digit_group(chars, next + 1)
+        {
+            // 10 000 with a no-break space
+            next += 1;
+        } else {
+            break;
+        }
+    }
+
+    // Scientific notation: 3e8, 1.5E-3
+    if matches!(at(next), Some('e') | Some('E')) {
+        let sign = matches!(at(next + 1), Some('+') | Some('-'));
+        let first_digit = if sign { next + 2 } else { next + 1 };
+        if at(first_digit).is_some_and(|c| c.is_ascii_digit()) {
+            text.push('e');
+            if sign {
+                text.push(at(next + 1)?);
+            }
+            next = first_digit;
+            while let Some(c) = at(next).filter(|c| c.is_ascii_digit()) {
+                text.push(c);
+                next += 1;
+            }
+        }
+    }
+
+    if text.is_empty() {
+        None
+    } else {
+        Some((text, next))
+    }
+}
+
+/// Lex a `2025-01-31` date starting at `i`, with an optional `13:20` or
+/// `13:20:45` time after a space or `T`, returning its normalized text and
+/// the index just past it.
+fn lex_date(chars: &[(usize, char)], i: usize) -> Option<(String, usize)> {
+    let at = |idx: usize| chars.get(idx).map(|(_, c)| *c);
+    // Digits from `start` up to the first non-digit, if there are `min..=max` of them
+    let digits = |start: usize, min: usize, max: usize| {
+        let len = (start..)
+            .take_while(|&idx| at(idx).is_some_and(|c| c.is_ascii_digit()))
+            .count();
+        (min..=max).contains(&len).then(|| {
+            let text: String = (start..start + len).filter_map(at).collect();
+            (text.parse::<u32>().ok(), start + len)
+        })
+    };
+
+    let (Some(year), next) = digits(i, 4, 4)? else {
+        return None;
+    };
+    if at(next) != Some('-') {
+        return None;
+    }
+    let (Some(month), next) = digits(next + 1, 2, 2)? else {
+        return None;
+    };
+    if at(next) != Some('-') {
+        return None;
+    }
+    let (Some(day), mut next) = digits(next + 1, 2, 2)? else {
+        return None;
+    };
+    if at(next).is_some_and(|c| c.is_alphanumeric() || c == '.' || c == '_')
+        && at(next) != Some('T')
+    {
+        return None;
+    }
+    let mut text = format!("{:04}-{:02}-{:02}", year, month, day);
+
+    // Time of day: 2025-01-31 13:20 or 2025-01-31T13:20:45
+    if matches!(at(next), Some(' ') | Some('T')) {
+        if let Some((Some(hour), colon)) = digits(next + 1, 1, 2) {
+            if at(colon) == Some(':') {
+                if let Some((Some(minute), mut end)) = digits(colon + 1, 2, 2) {
+                    let mut second = 0;
+                    if at(end) == Some(':') {
+                        if let Some((Some(s), after)) = digits(end + 1, 2, 2) {
+                            second = s;
+                            end = after;
+                        }
+                    }
+                    text.push_str(&format!(" {:02}:{:02}:{:02}", hour, minute, second));
+                    next = end;
+                }
+            }
+        }
+    }
+    (at(next) != Some('T')).then_some((text, next))
+}
+
+/// The separator written last in the number starting at `i` if it uses
+/// both `.` and `,`, as in `1.234,5`.
+fn mixed_decimal_separator(chars: &[(usize, char)], i: usize) -> Option<char> {
+    let mut seen = (false, false);
+    let mut last = None;
+    for (idx, &(_, c)) in chars.iter().enumerate().skip(i) {
+        let digit_follows = chars.get(idx + 1).is_some_and(|(_, n)| n.is_ascii_digit());
+        match c {
+            '.' | ',' if digit_follows => {
+                if c == '.' {
+                    seen.0 = true;
+                } else {
+                    seen.1 = true;
+                }
+                last = Some(c);
+            }
+            '_' => {}
+            c if c.is_ascii_digit() => {}
+            _ => break,
+        }
+    }
+    if seen == (true, true) {
+        last
+    } else {
+        None
+    }
+}
+
+/// True for the space-like characters that group digits: the no-break
+/// spaces French and Russian use, and in those locales a plain space too,
+/// as in `1 234,5`.
+fn is_space_grouping(c: char, style: NumberStyle) -> bool {
+    matches!(c, '\u{A0}' | '\u{202F}')
+        || ((c == style.grouping || c == ' ') && style.grouping.is_whitespace())
+}
+
+/// True if exactly three digits start at `i` and are not followed by another digit.
+fn is_digit_group(chars: &[(usize, char)], i: usize) -> bool {
+    (i..i + 3).all(|idx| chars.get(idx).is_some_and(|(_, c)| c.is_ascii_digit()))
+        && !chars.get(i + 3).is_some_and(|(_, c)| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<TokenKind> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.kind)
+            .collect()
+    }
+
+    fn num(text: &str) -> TokenKind {
+        TokenKind::Number(text.to_string())
+    }
+
+    fn ident(text: &str) -> TokenKind {
+        TokenKind::Ident(text.to_string())
+    }
+
+    #[test]
+    fn test_number_literals() {
+        assert_eq!(kinds("1_000_000"), vec![num("1000000")]);
+        assert_eq!(kinds("10,000.5"), vec![num("10000.5")]);
+        assert_eq!(
+            kinds("0X1F 0b1_01 0o17"),
+            vec![num("0x1F"), num("0b101"), num("0o17")]
+        );
+        assert_eq!(kinds("3e8 1.5E-3"), vec![num("3e8"), num("1.5e-3")]);
+        assert_eq!(kinds(".5"), vec![num(".5")]);
+    }
+
+    #[test]
+    fn test_locale_number_literals() {
+        let kinds_in = |locale: &str, input: &str| -> Vec<TokenKind> {
+            tokenize_with(input, NumberStyle::for_locale(locale))
+                .unwrap()
+                .into_iter()
+                .map(|t| t.kind)
+                .collect()
+        };
+        assert_eq!(kinds_in("de", "3,5"), vec![num("3.5")]);
+        assert_eq!(kinds_in("de", "1.234.567,25"), vec![num("1234567.25")]);
+        // A `.` that can't group digits is still a decimal point
+        assert_eq!(kinds_in("de", "3.25"), vec![num("3.25")]);
+        assert_eq!(kinds_in("fr", "1\u{202F}234,5"), vec![num("1234.5")]);
+        assert_eq!(kinds_in("ru", "1\u{A0}000"), vec![num("1000")]);
+        // Both separators: the last one is the decimal separator
+        assert_eq!(kinds_in("en-US", "1.234,5"), vec![num("1234.5")]);
+        assert_eq!(kinds_in("de", "1,234.5"), vec![num("1234.5")]);
+        assert_eq!(
+            kinds_in("de", "max(1,5; 2)"),
+            vec![
+                ident("max"),
+                TokenKind::LParen,
+                num("1.5"),
+                TokenKind::Comma,
+                num("2"),
+                TokenKind::RParen,
+            ]
+        );
+        assert_eq!(
+            kinds_in("de", "1,5..3"),
+            vec![num("1.5"), TokenKind::DotDot, num("3")]
+        );
+        // A plain space groups digits where the locale groups with a space
+        assert_eq!(kinds_in("fr", "1 234,5"), vec![num("1234.5")]);
+        assert_eq!(kinds_in("de", "1 234"), vec![num("1"), num("234")]);
+        // Outside a call or list a decimal comma needs no `;`
+        assert_eq!(kinds_in("de", "(1,5 + 2)")[1..2], [num("1.5")]);
+    }
+
+    #[test]
+    fn test_decimal_comma_in_calls_and_lists_is_ambiguous() {
+        let style = NumberStyle::for_locale("de");
+        for input in ["max(1,2)", "[1,2,3]", "[1, 2,5]", "sum(max(1,5); 2)"] {
+            let err = tokenize_with(input, style).unwrap_err();
+            assert!(err.message.contains("';'"), "{}: {}", input, err.message);
+        }
+        let err = tokenize_with("max(1,2)", style).unwrap_err();
+        assert_eq!(err.span, Span::new(4, 7));
+        for input in ["max(1,5; 2)", "[1,2; 3]", "max(1, 2)", "5 of (1,5)"] {
+            assert!(tokenize_with(input, style).is_ok(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_commas_outside_grouping_are_separators() {
+        assert_eq!(
+            kinds("min(5, 3,8)"),
+            vec![
+                ident("min"),
+                TokenKind::LParen,
+                num("5"),
+                TokenKind::Comma,
+                num("3"),
+                TokenKind::Comma,
+                num("8"),
+                TokenKind::RParen,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_units_and_currency_symbols() {
+        assert_eq!(
+            kinds("100USD to €"),
+            vec![
+                num("100"),
+                ident("USD"),
+                ident("to"),
+                TokenKind::Currency("EUR")
+            ]
+        );
+        assert_eq!(kinds("2e"), vec![num("2"), ident("e")]);
+        assert_eq!(kinds("20°C"), vec![num("20"), ident("°C")]);
+        assert_eq!(kinds("90° cos"), vec![num("90"), ident("°"), ident("cos")]);
+    }
+
+    #[test]
+    fn test_unicode_operators() {
+        assert_eq!(
+            kinds("5 × π ÷ 2"),
+            vec![
+                num("5"),
+                TokenKind::Star,
+                ident("pi"),
+                TokenKind::Slash,
+                num("2")
+            ]
+        );
+        assert_eq!(
+            kinds("5 ± 0.1 +/- 0.2"),
+            vec![
+                num("5"),
+                TokenKind::PlusMinus,
+                num("0.1"),
+                TokenKind::PlusMinus,
+                num("0.2")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        assert_eq!(
+            kinds("1 << 4 & 0xF | 2>>1"),
+            vec![
+                num("1"),
+                TokenKind::Shl,
+                num("4"),
+                TokenKind::Amp,
+                num("0xF"),
+                TokenKind::Pipe,
+                num("2"),
+                TokenKind::Shr,
+                num("1"),
+            ]
+        );
+        assert!(tokenize("1 < 2").is_err());
+    }
+
+    #[test]
+    fn test_lists_and_ranges() {
+        assert_eq!(
+            kinds("[1.5, 2]"),
+            vec![
+                TokenKind::LBracket,
+                num("1.5"),
+                TokenKind::Comma,
+                num("2"),
+                TokenKind::RBracket,
+            ]
+        );
+        assert_eq!(
+            kinds("1..100"),
+            vec![num("1"), TokenKind::DotDot, num("100")]
+        );
+        assert_eq!(
+            kinds("0.5..2"),
+            vec![num("0.5"), TokenKind::DotDot, num("2")]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_spans() {
+        let tokens = tokenize("10 + 5 # note").unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[2].span, Span::new(5, 6));
+        assert!(tokenize("// only a comment").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(
+            kinds("2025-01-31 + 30 days")[0],
+            TokenKind::Date("2025-01-31".to_string())
+        );
+        assert_eq!(
+            kinds("2025-01-31 13:20")[0],
+            TokenKind::Date("2025-01-31 13:20:00".to_string())
+        );
+        assert_eq!(
+            kinds("2025-01-31T08:05:09"),
+            vec![TokenKind::Date("2025-01-31 08:05:09".to_string())]
+        );
+        // Anything else with dashes stays arithmetic
+        assert_eq!(kinds("2025-1-31").len(), 5);
+        assert_eq!(kinds("2025-01-311").len(), 5);
+    }
+
+    #[test]
+    fn test_unexpected_character() {
+        let err = tokenize("2 @ 3").unwrap_err();
+        assert_eq!(err.span, Span::new(2, 3));
+    }
+
+    #[test]
+    fn test_radix_literal_overflow() {
+        let literal = format!("0x{}", "F".repeat(33));
+        let err = tokenize(&format!("1 + {}", literal)).unwrap_err();
+        assert_eq!(
+            err.message,
+            crate::fl!("literal-overflow", "literal" => literal.as_str())
+        );
+        assert_eq!(err.span, Span::new(4, 4 + literal.len()));
+        assert!(tokenize(&format!("0x{}", "F".repeat(32))).is_ok());
+    }
+}
@@ -4,20 +4,32 @@
 //! to process user input and return results.
 
 pub mod agents;
+pub mod ast;
 pub mod cache;
+pub mod calendar;
 mod core;
+pub mod document;
 pub mod error;
 pub mod events;
-mod preprocessing;
+pub mod finance;
+pub mod lexer;
+pub mod number;
+pub mod roman;
+pub mod units;
 
 pub use cache::CacheManager;
-pub use core::{evaluate_expr, evaluate_expr_with_original, evaluate_unit_conversion, EvalContext};
-pub use error::{EvaluatorError, Result};
+#[allow(unused)]
+pub use core::{evaluate_expr, EvalResult, Radix};
+pub use core::{evaluate, parse, EvalContext};
+pub use document::Document;
+pub use error::{Diagnostic, EvaluatorError, Result};
 pub use events::{EventSubscriber, StateEvent};
-pub use preprocessing::{preprocess, preprocess_input};
+#[allow(unused)]
+pub use number::Fraction;
+pub use number::{Number, NumberMode};
 
 use crate::config::Config;
-use crate::models::{Agent, AppState};
+use crate::models::{Agent, AgentOutput, AppState, HistoryEntry, LineKind};
 use crate::security::validate_input_size;
 
 /// Registry of evaluation agents that process user input.
@@ -37,7 +49,7 @@ use crate::security::validate_input_size;
 ///
 /// // Evaluate basic math
 /// let result = registry.evaluate("2 + 2", &mut state);
-/// assert!(result.is_some());
+/// assert!(result.is_ok());
 /// ```
 pub struct AgentRegistry {
     agents: Vec<Box<dyn Agent>>,
@@ -63,7 +75,10 @@ impl AgentRegistry {
     pub fn new(config: &Config) -> Result<Self> {
         let mut agents: Vec<Box<dyn Agent>> = vec![
             Box::new(agents::HistoryAgent),
+            Box::new(agents::SolveAgent),
+            Box::new(agents::RomanAgent),
             Box::new(agents::VariableAgent),
+            Box::new(agents::FinanceAgent),
             Box::new(agents::PercentageAgent),
             Box::new(agents::DateTimeAgent),
             Box::new(agents::UnitAgent),
@@ -79,6 +94,11 @@ impl AgentRegistry {
         })
     }
 
+    /// Configuration the agents evaluate with.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     fn validate_agents(agents: &[Box<dyn Agent>]) -> Result<()> {
         let mut priorities = std::collections::HashMap::new();
 
@@ -111,7 +131,8 @@ impl AgentRegistry {
     ///
     /// # Returns
     ///
-    /// Returns Some((result_string, should_add_to_history)) or None if no agent could handle input.
+    /// Returns Ok((result_string, should_add_to_history)), or a [`Diagnostic`] saying
+    /// why the input could not be evaluated and which agent rejected it.
     ///
     /// # Examples
     ///
@@ -125,18 +146,36 @@ impl AgentRegistry {
     /// let mut state = AppState::builder(&config).build();
     ///
     /// let result = registry.evaluate("10 + 5", &mut state);
-    /// assert!(result.is_some());
+    /// assert!(result.is_ok());
     /// ```
-    pub fn evaluate(&self, input: &str, state: &mut AppState) -> Option<(String, bool)> {
+    pub fn evaluate(
+        &self,
+        input: &str,
+        state: &mut AppState,
+    ) -> std::result::Result<(String, bool), Diagnostic> {
         // Validate input size
-        if let Err(e) = validate_input_size(input) {
-            eprintln!("{}", crate::fl!("input-validation-error", "error" => &e));
-            return None;
-        }
+        validate_input_size(input).map_err(input_error)?;
 
         self.evaluate_with_history(input, state, true)
     }
 
+    /// Evaluate like [`evaluate`](Self::evaluate), returning the display text
+    /// with the raw value and unit of the result.
+    ///
+    /// Used by the FFI and JNI bindings, which cannot parse the value back
+    /// out of text formatted with separators or exponents. The value is
+    /// `None` for results that are not a single number, such as dates.
+    #[allow(unused)]
+    pub fn evaluate_with_value(
+        &self,
+        input: &str,
+        state: &mut AppState,
+    ) -> std::result::Result<(String, Option<Number>, Option<String>), Diagnostic> {
+        validate_input_size(input).map_err(input_error)?;
+        let (formatted, _, value, unit) = self.evaluate_output(input, state, true)?;
+        Ok((formatted, value, unit))
+    }
+
     /// Evaluate expression for display only (does not modify state).
     ///
     /// Used for showing live preview of what would happen without committing changes.
@@ -148,7 +187,8 @@ impl AgentRegistry {
     ///
     /// # Returns
     ///
-    /// Returns Some((result_string, should_add_to_history)) or None if no agent could handle input.
+    /// Returns Ok((result_string, should_add_to_history)), or a [`Diagnostic`] saying
+    /// why the input could not be evaluated and which agent rejected it.
     ///
     /// # Examples
     ///
@@ -165,12 +205,13 @@ impl AgentRegistry {
     /// let result = registry.evaluate_for_display("x = 100", &state);
     /// // State remains unchanged
     /// ```
-    pub fn evaluate_for_display(&self, input: &str, state: &AppState) -> Option<(String, bool)> {
+    pub fn evaluate_for_display(
+        &self,
+        input: &str,
+        state: &AppState,
+    ) -> std::result::Result<(String, bool), Diagnostic> {
         // Validate input size
-        if let Err(e) = validate_input_size(input) {
-            eprintln!("{}", crate::fl!("input-validation-error", "error" => &e));
-            return None;
-        }
+        validate_input_size(input).map_err(input_error)?;
         let mut temp_state = state.clone();
         temp_state.is_display_only = true;
         self.evaluate_with_history(input, &mut temp_state, false)
@@ -185,12 +226,9 @@ impl AgentRegistry {
         &self,
         input: &str,
         state: &mut AppState,
-    ) -> Option<(String, bool)> {
+    ) -> std::result::Result<(String, bool), Diagnostic> {
         // Validate input size
-        if let Err(e) = validate_input_size(input) {
-            eprintln!("{}", crate::fl!("input-validation-error", "error" => &e));
-            return None;
-        }
+        validate_input_size(input).map_err(input_error)?;
         self.evaluate_with_history(input, state, false)
     }
 
@@ -199,45 +237,124 @@ impl AgentRegistry {
         input: &str,
         state: &mut AppState,
         modify_history: bool,
-    ) -> Option<(String, bool)> {
+    ) -> std::result::Result<(String, bool), Diagnostic> {
+        let (formatted, add_to_history, _, _) =
+            self.evaluate_output(input, state, modify_history)?;
+        Ok((formatted, add_to_history))
+    }
+
+    /// Run the agents on `input` and return the output of the first one that
+    /// handles it.
+    fn evaluate_output(
+        &self,
+        input: &str,
+        state: &mut AppState,
+        modify_history: bool,
+    ) -> std::result::Result<AgentOutput, Diagnostic> {
         // Store original input so agents can access it
         if let Ok(mut orig) = state.original_input.write() {
             *orig = Some(input.to_string());
         }
-        let preprocessed = preprocess(input, state, &self.config);
+        // A header opens a section of the document and has no value
+        let in_document = state.current_line.read().is_ok_and(|line| line.is_some());
+        if in_document && lexer::section_header(input).is_some() {
+            let _ = state.set_line_result(None);
+            let _ = state.set_line_kind(Some(LineKind::Header));
+            return Ok((String::new(), false, None, None));
+        }
+        let line = lexer::strip_comment(input).trim();
         // Check if this is a history command (don't add history command results to history)
-        let is_history_command = matches!(
-            preprocessed.trim(),
-            "sum" | "total" | "average" | "avg" | "prev"
-        );
+        let is_history_command = matches!(line, "sum" | "total" | "average" | "avg" | "prev");
 
+        let mut first_error: Option<Diagnostic> = None;
         for agent in &self.agents {
-            if agent.can_handle(&preprocessed, state) {
-                let result = agent.process(&preprocessed, state, &self.config);
-                if let Some((_res, add_to_history, raw_value, unit)) = &result {
-                    if modify_history && *add_to_history && !is_history_command {
-                        // Add to history if it's an expression and modify_history is true
-                        // but NOT if it's a history command
-                        if let Some(value) = raw_value {
-                            let _ = state.add_history(*value, unit.clone());
+            if agent.can_handle(line, state) {
+                match agent.process(line, state, &self.config) {
+                    Ok(Some((res, add_to_history, raw_value, unit))) => {
+                        let _ = state.set_line_result(raw_value.map(|value| HistoryEntry {
+                            value,
+                            unit: unit.clone(),
+                        }));
+                        let is_total = is_history_command || adds_up_lines(line);
+                        let _ = state.set_line_kind(is_total.then_some(LineKind::Total));
+                        if modify_history && add_to_history && !is_history_command {
+                            // Add to history if it's an expression and modify_history is true
+                            // but NOT if it's a history command
+                            if let Some(value) = raw_value {
+                                let _ = state.add_history(value, unit.clone());
+                            }
                         }
+                        return Ok((res, add_to_history, raw_value, unit));
+                    }
+                    // If this agent can't fully handle the input, fall through to
+                    // lower-priority agents instead of aborting the pipeline.
+                    // The highest-priority agent's error is the one reported.
+                    Ok(None) => {}
+                    Err(e) => {
+                        first_error.get_or_insert(e.by_agent(agent.name()));
                     }
-                    return result.map(|(res, add_to_history, _, _)| (res, add_to_history));
                 }
-
-                // If this agent can't fully handle the input (returns None),
-                // fall through to lower-priority agents instead of aborting the pipeline.
             }
         }
-        None
+        let _ = state.set_line_result(None);
+        let _ = state.set_line_kind(None);
+        Err(first_error.unwrap_or_else(|| {
+            Diagnostic::new(EvaluatorError::InvalidExpression(crate::fl!(
+                "error-evaluating-expression"
+            )))
+        }))
     }
 }
 
+/// True if `line` adds up other lines of the document, with `subtotal`,
+/// `above`, `section avg` or `grand total`.
+fn adds_up_lines(line: &str) -> bool {
+    let Ok(tokens) = lexer::tokenize(line) else {
+        return false;
+    };
+    let words: Vec<String> = tokens
+        .into_iter()
+        .map(|token| match token.kind {
+            lexer::TokenKind::Ident(word) => word.to_lowercase(),
+            _ => String::new(),
+        })
+        .collect();
+    words.iter().enumerate().any(|(i, word)| {
+        let next = words.get(i + 1).map(String::as_str);
+        match word.as_str() {
+            "subtotal" | "above" => true,
+            "grand" => next == Some("total"),
+            "section" => matches!(next, Some("avg" | "average")),
+            _ => false,
+        }
+    })
+}
+
+/// Diagnostic for input rejected by [`validate_input_size`].
+fn input_error(error: String) -> Diagnostic {
+    Diagnostic::new(EvaluatorError::InvalidExpression(
+        crate::fl!("input-validation-error", "error" => &error),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::models::AppStateBuilder;
 
+    /// Evaluate `input` as line `line` of a document, as the editor does.
+    fn evaluate_line(
+        registry: &AgentRegistry,
+        state: &mut AppState,
+        line: usize,
+        input: &str,
+    ) -> std::result::Result<String, Diagnostic> {
+        *state.current_line.write().unwrap() = Some(line);
+        let result = registry.evaluate(input, state);
+        *state.current_line.write().unwrap() = None;
+        result.map(|(result, _)| result)
+    }
+
     #[test]
     fn test_history_sum_with_prettified_numbers() {
         let config = Config::default();
@@ -261,15 +378,42 @@ mod tests {
         assert_eq!(history[2].value, 2000.0);
         assert_eq!(history[3].value, 100.0);
 
-        let sum: f64 = history.iter().map(|h| h.value).sum();
+        let sum: f64 = history.iter().map(|h| h.value.to_f64()).sum();
         assert_eq!(sum, 2800.0, "Sum should be 2800, not 800");
         drop(history);
 
         // Test sum command
         let result = registry.evaluate("sum", &mut state);
-        assert!(result.is_some());
+        assert!(result.is_ok());
         let (sum_str, _) = result.unwrap();
-        assert_eq!(sum_str, "2800", "sum command should return 2800");
+        assert_eq!(sum_str, "2.8k", "sum command should return 2800");
+    }
+
+    #[test]
+    fn test_decimal_mode_keeps_money_exact() {
+        let config = Config {
+            number_mode: "decimal".to_string(),
+            ..Config::default()
+        };
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        for input in ["0.1 + 0.2", "price = 19.99", "price * 3", "10% of 0.3"] {
+            let _ = registry.evaluate(input, &mut state);
+            std::thread::sleep(std::time::Duration::from_millis(51));
+        }
+
+        let history = state.history.read().unwrap();
+        assert_eq!(history[0].value.to_string(), "0.3");
+        assert_eq!(history[2].value.to_string(), "59.97");
+        assert_eq!(history[3].value.to_string(), "0.03");
+        drop(history);
+
+        let (sum_str, _) = registry.evaluate("sum", &mut state).unwrap();
+        assert_eq!(sum_str, "80.29");
+
+        let (fixed, _) = registry.evaluate("0.1 * 3 to 20 dp", &mut state).unwrap();
+        assert_eq!(fixed, "0.30000000000000000000");
     }
 
     #[test]
@@ -285,11 +429,272 @@ mod tests {
         }
 
         let result = registry.evaluate("sum + 100", &mut state);
-        assert!(result.is_some(), "sum should be usable in expressions");
+        assert!(result.is_ok(), "sum should be usable in expressions");
         let (val, _) = result.unwrap();
         assert!(val.contains("115"), "expected 115, got {}", val);
     }
 
+    #[test]
+    fn test_history_aggregates_convert_units() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        for input in ["5 km", "3 miles", "2 km"] {
+            let _ = registry.evaluate(input, &mut state);
+            std::thread::sleep(std::time::Duration::from_millis(51));
+        }
+        // Miles are converted to the kilometres most entries are in
+        // and shown like any other result
+        let (result, _) = registry.evaluate("sum", &mut state).unwrap();
+        assert_eq!(result, "11.83 km");
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        let (result, _) = registry.evaluate("avg", &mut state).unwrap();
+        assert_eq!(result, "3.94 km");
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        let (result, _) = registry.evaluate("prev", &mut state).unwrap();
+        assert_eq!(result, "2.00 km");
+
+        // Amounts of money don't add up with lengths
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        let _ = registry.evaluate("20 USD", &mut state);
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        let err = registry.evaluate("sum", &mut state).unwrap_err();
+        assert!(err.message().contains("USD"), "got {}", err.message());
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        assert!(registry.evaluate("total + 1 km", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_history_aggregates_add_temperatures_like_plus() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        for input in ["20 C", "9 °F difference"] {
+            let _ = registry.evaluate(input, &mut state);
+            std::thread::sleep(std::time::Duration::from_millis(51));
+        }
+        // A change moves the reading, as in `20 C + 9 ΔF`
+        let (result, _) = registry.evaluate("sum", &mut state).unwrap();
+        assert_eq!(result, "25.00 C");
+
+        // but two readings don't add up
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        let _ = registry.evaluate("30 C", &mut state);
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        let err = registry.evaluate("sum", &mut state).unwrap_err();
+        assert!(
+            err.message().contains("temperature"),
+            "got {}",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn test_history_average_of_temperature_readings() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        for input in ["20 C", "30 C", "86 F"] {
+            let _ = registry.evaluate(input, &mut state);
+            std::thread::sleep(std::time::Duration::from_millis(51));
+        }
+        // Readings average on one scale, 86 F counting as 30 C
+        let (result, _) = registry.evaluate("avg", &mut state).unwrap();
+        assert_eq!(result, "26.67 C");
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        assert!(registry.evaluate("sum", &mut state).is_err());
+    }
+
+    #[test]
+    fn test_line_references() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        assert!(evaluate_line(&registry, &mut state, 0, "prev line").is_err());
+        evaluate_line(&registry, &mut state, 0, "rent = 120").unwrap();
+        evaluate_line(&registry, &mut state, 1, "30").unwrap();
+        evaluate_line(&registry, &mut state, 3, "0.5 km").unwrap();
+        evaluate_line(&registry, &mut state, 4, "250 m").unwrap();
+        // `above` adds up the block up to the blank line
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 5, "above").unwrap(),
+            "0.75 km"
+        );
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 6, "both = line 1 + line2").unwrap(),
+            "150"
+        );
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 7, "prev line * 2").unwrap(),
+            "300"
+        );
+        assert!(evaluate_line(&registry, &mut state, 8, "line 3").is_err());
+        assert!(evaluate_line(&registry, &mut state, 8, "line 9").is_err());
+        // `$2` is two dollars, not line 2
+        assert!(evaluate_line(&registry, &mut state, 8, "$2")
+            .unwrap()
+            .contains("2.00"));
+        // Outside a document there are no lines to refer to
+        assert!(registry.evaluate("above", &mut state).is_err());
+        assert!(registry
+            .evaluate("$2 * 2", &mut state)
+            .unwrap()
+            .0
+            .contains("4.00"));
+
+        // Inserting a line above moves the lines down, and the references
+        // to them in the text of other lines with them
+        let text = |state: &AppState, line| state.line_content.read().unwrap()[&line].clone();
+        state.shift_lines_on_insert(0).unwrap();
+        assert_eq!(text(&state, 7), "both = line 2 + line3");
+        let moved = text(&state, 7);
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 7, &moved).unwrap(),
+            "150"
+        );
+        // Deleting it again moves them back up
+        state.shift_lines_on_delete(0).unwrap();
+        assert_eq!(text(&state, 6), "both = line 1 + line2");
+        // A reference to a deleted line goes to the line it was joined to
+        state.shift_lines_on_delete(1).unwrap();
+        assert_eq!(text(&state, 5), "both = line 1 + line1");
+        let joined = text(&state, 5);
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 5, &joined).unwrap(),
+            "240"
+        );
+    }
+
+    #[test]
+    fn test_section_totals() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        fn evaluate_document(
+            registry: &AgentRegistry,
+            state: &mut AppState,
+            lines: &[&str],
+        ) -> Vec<String> {
+            let mut results = Vec::new();
+            for (line, input) in lines.iter().enumerate() {
+                let result = evaluate_line(registry, state, line, input);
+                results.push(result.unwrap_or_else(|e| e.message().to_string()));
+            }
+            results
+        }
+
+        let results = evaluate_document(
+            &registry,
+            &mut state,
+            &[
+                "# Groceries",
+                "3.5",
+                "2.5",
+                "subtotal",
+                "section avg",
+                "## Rent",
+                "50",
+                "",
+                "10",
+                "subtotal",
+                "above",
+                "grand total",
+            ],
+        );
+        assert_eq!(results[0], "");
+        assert_eq!(results[3], "6.00");
+        // Subtotals aren't averaged in with the items
+        assert_eq!(results[4], "3.00");
+        // A section runs past blank lines, up to the next header
+        assert_eq!(results[9], "60.00");
+        assert_eq!(results[10], "10.00");
+        assert_eq!(results[11], "66.00");
+
+        let results = evaluate_document(&registry, &mut state, &["# Empty", "section avg"]);
+        assert!(results[1].contains("section"), "got {}", results[1]);
+        // A `#` comment without a title is not a header
+        let results = evaluate_document(&registry, &mut state, &["5", "#note", "subtotal"]);
+        assert_eq!(results[2], "5.00");
+        // A `# note` between priced lines is a header, and `//` notes aren't
+        let results = evaluate_document(
+            &registry,
+            &mut state,
+            &[
+                "# Groceries",
+                "3.5",
+                "# from the corner shop",
+                "2.5",
+                "subtotal",
+                "// paid in cash",
+                "4",
+                "subtotal",
+                "grand total",
+            ],
+        );
+        assert_eq!(results[4], "2.50");
+        assert_eq!(results[7], "6.50");
+        assert_eq!(results[8], "10.00");
+    }
+
+    #[test]
+    fn test_document_history() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        registry.evaluate("1000", &mut state).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(51));
+
+        // Results from outside the document don't count, nor does
+        // evaluating a line again
+        evaluate_line(&registry, &mut state, 0, "10").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        evaluate_line(&registry, &mut state, 0, "10").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        evaluate_line(&registry, &mut state, 1, "20").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 2, "sum").unwrap(),
+            "30.00"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        // Lines below don't count either, and totals aren't summed again
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 3, "avg").unwrap(),
+            "15.00"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 1, "prev").unwrap(),
+            "10.00"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        assert!(evaluate_line(&registry, &mut state, 0, "prev").is_err());
+
+        // Outside a document, `sum` covers every result of the session
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        let (_, value, _) = registry.evaluate_with_value("sum", &mut state).unwrap();
+        assert_eq!(value.unwrap().to_f64(), 1040.0);
+
+        // Session mode uses the session's history in documents as well
+        let config = Config {
+            history_mode: "session".to_string(),
+            ..Config::default()
+        };
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        registry.evaluate("100", &mut state).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        evaluate_line(&registry, &mut state, 0, "10").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(51));
+        assert_eq!(
+            evaluate_line(&registry, &mut state, 1, "sum").unwrap(),
+            "110"
+        );
+    }
+
     #[test]
     fn test_sum_to_currency_with_trailing_math() {
         let config = Config::default();
@@ -304,7 +709,7 @@ mod tests {
 
         let result = registry.evaluate("sum to USD + 100", &mut state);
         assert!(
-            result.is_some(),
+            result.is_ok(),
             "conversion with trailing math should work"
         );
         let (val, _) = result.unwrap();
@@ -324,7 +729,7 @@ mod tests {
 
         // Line 1: Create first variable
         let result1 = registry.evaluate("hause = 100000 USD", &mut state);
-        assert!(result1.is_some());
+        assert!(result1.is_ok());
         let (val1, _) = result1.unwrap();
         assert!(
             val1.contains("100")
@@ -344,7 +749,7 @@ mod tests {
 
         // Line 2: Create second variable
         let result2 = registry.evaluate("salary = 4000 USD", &mut state);
-        assert!(result2.is_some());
+        assert!(result2.is_ok());
         let (val2, _) = result2.unwrap();
         assert!(
             val2.contains("4")
@@ -366,7 +771,7 @@ mod tests {
 
         // Line 3: Use variables in calculation
         let result3 = registry.evaluate("hause / salary", &mut state);
-        assert!(result3.is_some());
+        assert!(result3.is_ok());
         let (val3, _) = result3.unwrap();
         // 100000 / 4000 = 25
         assert!(val3.contains("25"));
@@ -384,7 +789,7 @@ mod tests {
         let mut state = AppStateBuilder::new(&config).build();
 
         // Set up initial variable
-        registry.evaluate("x = 100", &mut state);
+        assert!(registry.evaluate("x = 100", &mut state).is_ok());
 
         // Verify x = 100
         let vars = state.variables.read().unwrap();
@@ -393,7 +798,7 @@ mod tests {
 
         // Call evaluate_for_display with assignment (should not modify variables)
         let display_result = registry.evaluate_for_display("x = 200", &state);
-        assert!(display_result.is_some());
+        assert!(display_result.is_ok());
 
         // Verify x is still 100 (not modified by display evaluation)
         let vars = state.variables.read().unwrap();
@@ -404,7 +809,7 @@ mod tests {
         std::thread::sleep(std::time::Duration::from_millis(51));
 
         // Call evaluate with assignment (should modify variables)
-        registry.evaluate("x = 200", &mut state);
+        assert!(registry.evaluate("x = 200", &mut state).is_ok());
 
         // Verify x is now 200
         let vars = state.variables.read().unwrap();
@@ -419,17 +824,17 @@ mod tests {
 
         // Test case: room_length = 5 meters to feet
         let result = registry.evaluate("room_length = 5 meters to feet", &mut state);
-        assert!(result.is_some());
+        assert!(result.is_ok());
         std::thread::sleep(std::time::Duration::from_millis(51));
 
         // room_width = 4 meters to feet
         let result = registry.evaluate("room_width = 4 meters to feet", &mut state);
-        assert!(result.is_some());
+        assert!(result.is_ok());
         std::thread::sleep(std::time::Duration::from_millis(51));
 
         // area = room_length * room_width
         let result = registry.evaluate("area = room_length * room_width", &mut state);
-        assert!(result.is_some(), "Failed to multiply variables with units");
+        assert!(result.is_ok(), "Failed to multiply variables with units");
         let (area_str, _) = result.unwrap();
         // 16.40 feet * 13.12 feet ≈ 215.2 square feet
         assert!(area_str.contains("215") || area_str.contains("214"));
@@ -437,12 +842,12 @@ mod tests {
 
         // cost_per_sqft = 8.50 USD
         let result = registry.evaluate("cost_per_sqft = 8.50 USD", &mut state);
-        assert!(result.is_some());
+        assert!(result.is_ok());
         std::thread::sleep(std::time::Duration::from_millis(51));
 
         // total_cost = area * cost_per_sqft
         let result = registry.evaluate("total_cost = area * cost_per_sqft", &mut state);
-        assert!(result.is_some(), "Failed to multiply area by cost");
+        assert!(result.is_ok(), "Failed to multiply area by cost");
         let (total_str, _) = result.unwrap();
         // 215.2 * 8.50 ≈ 1829 USD
         // Result should be in USD, not feet
@@ -458,6 +863,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_speed_from_distance_and_time_variables() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        for line in ["distance = 100 km", "time = 2 hours", "speed = distance / time"] {
+            assert!(registry.evaluate(line, &mut state).is_ok());
+            std::thread::sleep(std::time::Duration::from_millis(51));
+        }
+        let vars = state.variables.read().unwrap();
+        let (speed, unit) = vars.get("speed").unwrap();
+        assert_eq!(*speed, 50.0);
+        assert_eq!(unit.as_deref(), Some("km/h"));
+        drop(vars);
+
+        let (result, _) = registry.evaluate("speed in mph", &mut state).unwrap();
+        assert!(result.contains("31.07") && result.contains("mph"), "{}", result);
+    }
+
+    #[test]
+    fn test_errors_report_reason_span_and_agent() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        let err = registry.evaluate("5 fet", &mut state).unwrap_err();
+        assert_eq!(
+            err.kind,
+            EvaluatorError::EvaluationError(crate::fl!("unknown-unit", "unit" => "fet"))
+        );
+        assert_eq!(err.span, Some(lexer::Span::new(2, 5)));
+        assert_eq!(err.agent, Some("math"));
+
+        let err = registry.evaluate("5 kg to USD", &mut state).unwrap_err();
+        assert!(err.message().contains("kg") && err.message().contains("USD"));
+        assert_eq!(err.agent, Some("unit"));
+
+        let err = registry.evaluate("x = 5 fet", &mut state).unwrap_err();
+        assert_eq!(err.span, Some(lexer::Span::new(6, 9)));
+        assert_eq!(err.agent, Some("variable"));
+        assert!(state.variables.read().unwrap().get("x").is_none());
+
+        let err = registry.evaluate("2 +", &mut state).unwrap_err();
+        assert!(matches!(err.kind, EvaluatorError::ParseError(_)));
+        assert_eq!(err.span, Some(lexer::Span::new(3, 3)));
+    }
+
     #[test]
     fn test_comma_separated_numbers() {
         let config = Config::default();
@@ -466,12 +919,12 @@ mod tests {
 
         // Test single number with comma
         let result = registry.evaluate("10,000", &mut state);
-        assert!(result.is_some(), "Failed to parse number with comma");
+        assert!(result.is_ok(), "Failed to parse number with comma");
         std::thread::sleep(std::time::Duration::from_millis(51));
 
         // Test addition with comma-separated numbers
         let result = registry.evaluate("10,000 + 5,000", &mut state);
-        assert!(result.is_some(), "Failed to add comma-separated numbers");
+        assert!(result.is_ok(), "Failed to add comma-separated numbers");
         let (sum_str, _) = result.unwrap();
         assert!(sum_str.contains("15"), "Result should contain 15");
         std::thread::sleep(std::time::Duration::from_millis(51));
@@ -479,7 +932,7 @@ mod tests {
         // Test with currency unit
         let result = registry.evaluate("10,000 USD", &mut state);
         assert!(
-            result.is_some(),
+            result.is_ok(),
             "Failed to parse comma number with currency"
         );
         let (val_str, _) = result.unwrap();
@@ -498,7 +951,7 @@ mod tests {
 
         // Create variable x = 5
         let result = registry.evaluate("x = 5", &mut state);
-        assert!(result.is_some(), "Failed to create variable x");
+        assert!(result.is_ok(), "Failed to create variable x");
         std::thread::sleep(std::time::Duration::from_millis(51));
 
         // Verify x exists
@@ -509,7 +962,7 @@ mod tests {
 
         // Copy x to y
         let result = registry.evaluate("y = x", &mut state);
-        assert!(result.is_some(), "Failed to copy variable x to y");
+        assert!(result.is_ok(), "Failed to copy variable x to y");
         std::thread::sleep(std::time::Duration::from_millis(51));
 
         // Verify both x and y exist
@@ -532,7 +985,7 @@ mod tests {
             *current_line = Some(0);
         }
         let result = registry.evaluate("n = 10", &mut state);
-        assert!(result.is_some(), "Failed to create variable n");
+        assert!(result.is_ok(), "Failed to create variable n");
         {
             let mut current_line = state.current_line.write().unwrap();
             *current_line = None;
@@ -551,7 +1004,7 @@ mod tests {
             *current_line = Some(0);
         }
         let result = registry.evaluate("ns = 10", &mut state);
-        assert!(result.is_some(), "Failed to create variable ns");
+        assert!(result.is_ok(), "Failed to create variable ns");
         {
             let mut current_line = state.current_line.write().unwrap();
             *current_line = None;
@@ -564,4 +1017,291 @@ mod tests {
         assert!(vars.contains_key("ns"), "Variable ns should exist");
         assert_eq!(vars.get("ns").unwrap().0, 10.0);
     }
+
+    #[test]
+    fn test_user_functions() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        let (result, _) = registry
+            .evaluate("tax(amount) = amount * 0.08", &mut state)
+            .unwrap();
+        assert_eq!(result, "tax(amount)");
+        assert!(state.history.read().unwrap().is_empty());
+
+        let (result, _) = registry.evaluate("tax(200)", &mut state).unwrap();
+        assert_eq!(result, "16.00");
+        let (result, _) = registry.evaluate("tax 50 + 1", &mut state).unwrap();
+        assert_eq!(result, "5.00");
+
+        // Parameters shadow variables of the same name
+        registry.evaluate("amount = 1000", &mut state).unwrap();
+        let (result, _) = registry.evaluate("tax(100)", &mut state).unwrap();
+        assert_eq!(result, "8.00");
+
+        registry.evaluate("area(w, h) = w * h", &mut state).unwrap();
+        let (result, _) = registry.evaluate("area(3 m, 4 m)", &mut state).unwrap();
+        assert!(result.contains("12") && result.contains("m²"), "{}", result);
+        let (result, _) = registry
+            .evaluate("area(2 m, 50 cm) to cm^2", &mut state)
+            .unwrap();
+        assert_eq!(result, "10.0k cm²");
+
+        let err = registry.evaluate("area(1)", &mut state).unwrap_err();
+        assert_eq!(
+            err.kind,
+            EvaluatorError::InvalidExpression(crate::fl!("function-arity", "name" => "area"))
+        );
+
+        registry.evaluate("loop(x) = loop(x) + 1", &mut state).unwrap();
+        let err = registry.evaluate("2 * loop(1)", &mut state).unwrap_err();
+        assert_eq!(
+            err.kind,
+            EvaluatorError::EvaluationError(crate::fl!("function-too-deep", "name" => "loop"))
+        );
+        assert_eq!(err.span, Some(lexer::Span::new(4, 11)));
+    }
+
+    #[test]
+    fn test_display_format_from_config() {
+        use crate::prettify::{DisplayFormat, DisplayOverride, Precision};
+
+        let config = Config {
+            display: DisplayFormat {
+                scientific_above: Some(1e9),
+                currency: DisplayOverride {
+                    precision: Some(Precision::Decimals(2)),
+                    thousands_separator: Some(",".to_string()),
+                    abbreviate: Some(false),
+                },
+                ..DisplayFormat::default()
+            },
+            ..Config::default()
+        };
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        let mut show = |input: &str| registry.evaluate(input, &mut state).unwrap().0;
+        assert_eq!(show("1500"), "1.5k");
+        assert_eq!(show("$1500 + $34.5"), "1,534.50 USD");
+        assert_eq!(show("2 * 10^10"), "2e10");
+        assert_eq!(show("total = $1234567"), "1,234,567.00 USD");
+        assert_eq!(show("1500 to 2 dp"), "1500.00");
+
+        // Bindings get the value itself, not the formatted text
+        let (formatted, value, unit) = registry.evaluate_with_value("$1234.5", &mut state).unwrap();
+        assert_eq!(formatted, "1,234.50 USD");
+        assert_eq!(value.map(|v| v.to_f64()), Some(1234.5));
+        assert_eq!(unit.as_deref(), Some("USD"));
+    }
+
+    #[test]
+    fn test_number_locale() {
+        let mut config = Config {
+            number_locale: Some("de".to_string()),
+            ..Config::default()
+        };
+        config.display.abbreviate = false;
+        config.display.group_digits = true;
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        let mut show = |input: &str| registry.evaluate(input, &mut state).unwrap().0;
+        assert_eq!(show("3,5 + 1,25"), "4,75");
+        assert_eq!(show("1.234,5 * 2"), "2.469");
+        assert_eq!(show("€1.500,50 + 2,5 €"), "1.503,00 EUR");
+        assert_eq!(show("1.000.000 to sci"), "1e6");
+        assert_eq!(show("max(1,5; 2,25)"), "2,25");
+        assert_eq!(show("[1,5; 2]"), "[1,50; 2,00]");
+        assert_eq!(show("3.26 to 1 dp"), "3,3");
+        assert_eq!(show("12,50 EUR"), "12,50 EUR");
+        assert!(registry.evaluate("max(1,2)", &mut state).is_err());
+
+        // With a decimal point, a currency on the last of bare numbers could
+        // be a decimal comma
+        let english = Config {
+            number_locale: Some("en-US".to_string()),
+            ..Config::default()
+        };
+        let registry = AgentRegistry::new(&english).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&english).build();
+        let err = registry.evaluate("12,50 EUR", &mut state).unwrap_err();
+        assert_eq!(err.span, Some(lexer::Span::new(0, 9)));
+        assert!(registry.evaluate("12, 15, 9 EUR", &mut state).is_err());
+        let (result, _) = registry.evaluate("12 EUR, 50 EUR", &mut state).unwrap();
+        assert_eq!(result, "[12.00 EUR, 50.00 EUR]");
+
+        let french = Config {
+            number_locale: Some("fr".to_string()),
+            ..Config::default()
+        };
+        let registry = AgentRegistry::new(&french).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&french).build();
+        let (result, _) = registry.evaluate("1 234,5 - 1234", &mut state).unwrap();
+        assert_eq!(result, "0,50");
+    }
+
+    #[test]
+    fn test_currency_symbol_placement() {
+        let cases = [
+            ("en-US", "€12.50", "-€2.00", "5.00 CHF"),
+            ("de", "12,50 €", "-2,00 €", "5,00 CHF"),
+        ];
+        for (locale, amount, negative, no_symbol) in cases {
+            let mut config = Config {
+                number_locale: Some(locale.to_string()),
+                ..Config::default()
+            };
+            config.display.currency_symbols = true;
+            let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+            let mut state = AppStateBuilder::new(&config).build();
+
+            let mut show = |input: &str| registry.evaluate(input, &mut state).unwrap().0;
+            assert_eq!(show("10 EUR + 2.5 EUR"), amount);
+            assert_eq!(show("-2 EUR"), negative);
+            assert_eq!(show("5 CHF"), no_symbol);
+        }
+    }
+
+    #[test]
+    fn test_loan_and_compound_interest_phrases() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        let (result, _) = registry
+            .evaluate("mortgage $300k at 6.5% over 30 years", &mut state)
+            .unwrap();
+        assert_eq!(result, "1896.20 USD");
+        let (result, _) = registry
+            .evaluate("$5000 at 4% for 10 years compounded monthly", &mut state)
+            .unwrap();
+        assert_eq!(result, "7454.16 USD");
+
+        let history = state.history.read().unwrap();
+        assert!((history[0].value.to_f64() - 1896.20).abs() < 0.01);
+        assert!((history[1].value.to_f64() - 7454.16).abs() < 0.01);
+        assert_eq!(history[1].unit.as_deref(), Some("USD"));
+        drop(history);
+
+        let schedule =
+            agents::amortization_schedule("loan of 12k at 5% over 2 years", &state, &config);
+        let (years, unit) = schedule.unwrap();
+        assert_eq!(years.len(), 2);
+        assert_eq!(unit, None);
+        assert!(years[1].balance.abs() < 0.01);
+        assert!(registry
+            .evaluate("loan x at 5% over 2 years", &mut state)
+            .is_err());
+
+        // The phrases combine with conversions and assignments
+        let (result, _) = registry
+            .evaluate("mortgage $300k at 6.5% over 30 years in EUR", &mut state)
+            .unwrap();
+        assert!(result.ends_with("EUR"));
+        let (result, _) = registry
+            .evaluate(
+                "$5000 at 4% for 10 years compounded monthly to EUR",
+                &mut state,
+            )
+            .unwrap();
+        assert!(result.ends_with("EUR"));
+        registry
+            .evaluate("payment = mortgage $300k at 6.5% over 30 years", &mut state)
+            .unwrap();
+        let variables = state.variables.read().unwrap();
+        assert!((variables["payment"].0.to_f64() - 1896.20).abs() < 0.01);
+        drop(variables);
+        let schedule = agents::amortization_schedule(
+            "mortgage $300k at 6.5% over 30 years in EUR",
+            &state,
+            &config,
+        );
+        assert_eq!(schedule.unwrap().0.len(), 30);
+    }
+
+    #[test]
+    fn test_list_variables_and_aggregates() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        let (result, add_to_history) = registry
+            .evaluate("prices = 12, 15, 9", &mut state)
+            .unwrap();
+        assert_eq!(result, "[12.00, 15.00, 9.00]");
+        assert!(!add_to_history);
+        assert_eq!(state.lists.read().unwrap()["prices"].len(), 3);
+
+        let (result, _) = registry.evaluate("median(prices)", &mut state).unwrap();
+        assert_eq!(result, "12.00");
+        let (result, _) = registry.evaluate("prices * 2", &mut state).unwrap();
+        assert_eq!(result, "[24.00, 30.00, 18.00]");
+        // Only the scalar result went into history
+        assert_eq!(state.history.read().unwrap().len(), 1);
+
+        // Bare `sum` still totals the history
+        let (result, _) = registry.evaluate("sum", &mut state).unwrap();
+        assert_eq!(result, "12.00");
+        let (result, _) = registry.evaluate("sum prices", &mut state).unwrap();
+        assert_eq!(result, "36.00");
+
+        // Reassigning a scalar replaces the list
+        registry.evaluate("prices = 10", &mut state).unwrap();
+        assert!(state.lists.read().unwrap().is_empty());
+        assert!(state.variables.read().unwrap().contains_key("prices"));
+    }
+
+    #[test]
+    fn test_uncertain_variables() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+
+        registry.evaluate("l = 5.0 ± 0.1 m", &mut state).unwrap();
+        registry.evaluate("w = 2.0 +/- 0.05 m", &mut state).unwrap();
+        assert_eq!(state.uncertainties.read().unwrap()["l"], 0.1);
+
+        let (result, _) = registry.evaluate("l * w", &mut state).unwrap();
+        assert_eq!(result, "10.00 ± 0.32 m²");
+        let (result, _) = registry.evaluate("l to cm", &mut state).unwrap();
+        assert!(result.ends_with("± 10.00 cm"), "got {}", result);
+
+        // An exact value replaces the measurement
+        registry.evaluate("l = 5 m", &mut state).unwrap();
+        assert!(state.uncertainties.read().unwrap().get("l").is_none());
+        let (result, _) = registry.evaluate("l * 2", &mut state).unwrap();
+        assert_eq!(result, "10.00 m");
+    }
+
+    #[test]
+    fn test_function_line_cleanup() {
+        let config = Config::default();
+        let registry = AgentRegistry::new(&config).expect("Failed to create registry");
+        let mut state = AppStateBuilder::new(&config).build();
+        evaluate_line(&registry, &mut state, 0, "double(x) = x * 2").unwrap();
+        evaluate_line(&registry, &mut state, 1, "rate = 3").unwrap();
+        assert!(state.functions.read().unwrap().contains_key("double"));
+
+        // Editing the defining line replaces the function
+        evaluate_line(&registry, &mut state, 0, "triple(x) = x * 3").unwrap();
+        let functions = state.functions.read().unwrap();
+        assert!(!functions.contains_key("double"));
+        assert!(functions.contains_key("triple"));
+        drop(functions);
+        assert!(registry.evaluate("double(2)", &mut state).is_err());
+
+        // Redefining a variable's line as a function drops the variable
+        evaluate_line(&registry, &mut state, 1, "rate(x) = x / 100").unwrap();
+        assert!(!state.variables.read().unwrap().contains_key("rate"));
+        let (result, _) = registry.evaluate("rate(50)", &mut state).unwrap();
+        assert_eq!(result, "0.50");
+
+        // Deleting the defining line removes the function
+        state.shift_lines_on_delete(0).unwrap();
+        assert!(!state.functions.read().unwrap().contains_key("triple"));
+        assert!(state.functions.read().unwrap().contains_key("rate"));
+        assert!(registry.evaluate("triple(2)", &mut state).is_err());
+    }
 }
@@ -0,0 +1,664 @@
+//! Numeric values carried through evaluation.
+//!
+//! Numby evaluates with `f64` by default. When `number_mode` is set to
+//! `"decimal"` in the config, literals are parsed into exact base-10 decimals so
+//! that money sums like `0.1 + 0.2` stay exact through variables, history
+//! aggregates, percentages and currency conversion. Rounding only happens when a
+//! value is formatted for display.
+//!
+//! In `"fraction"` mode literals become exact rationals instead, so
+//! `1/3 + 1/6` is `1/2` and imperial measures like `3/8 in` never round.
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, MathematicalOps, RoundingStrategy};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Numeric backend used when parsing literals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberMode {
+    /// IEEE 754 double precision (default).
+    #[default]
+    Float,
+    /// Exact base-10 decimal arithmetic (28 significant digits).
+    Decimal,
+    /// Exact rational arithmetic, displayed as fractions.
+    Fraction,
+}
+
+impl NumberMode {
+    /// Parse the `number_mode` config value. Unknown values fall back to `Float`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use numby::evaluator::NumberMode;
+    ///
+    /// assert_eq!(NumberMode::from_config("decimal"), NumberMode::Decimal);
+    /// assert_eq!(NumberMode::from_config("fraction"), NumberMode::Fraction);
+    /// assert_eq!(NumberMode::from_config("float"), NumberMode::Float);
+    /// assert_eq!(NumberMode::from_config("unknown"), NumberMode::Float);
+    /// ```
+    pub fn from_config(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "decimal" | "exact" => NumberMode::Decimal,
+            "fraction" | "rational" => NumberMode::Fraction,
+            _ => NumberMode::Float,
+        }
+    }
+}
+
+/// A numeric value: a float, an exact decimal or an exact fraction.
+///
+/// Arithmetic between two decimals stays decimal, and between a fraction and
+/// a fraction or decimal stays a fraction; mixing with a float (or
+/// overflowing the exact range) yields a float.
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::{Number, NumberMode};
+///
+/// let a = Number::parse("0.1", NumberMode::Decimal).unwrap();
+/// let b = Number::parse("0.2", NumberMode::Decimal).unwrap();
+/// assert_eq!((a + b).to_string(), "0.3");
+///
+/// let x = Number::parse("0.1", NumberMode::Float).unwrap();
+/// let y = Number::parse("0.2", NumberMode::Float).unwrap();
+/// assert_ne!((x + y).to_string(), "0.3");
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub enum Number {
+    Float(f64),
+    Decimal(Decimal),
+    Fraction(Fraction),
+}
+
+impl Number {
+    /// Parse a numeric literal in the given mode.
+    ///
+    /// Radix literals (`0xFF`, `0b101`, `0o17`) are read as integers.
+    pub fn parse(literal: &str, mode: NumberMode) -> Option<Self> {
+        let literal = literal.trim();
+        if let Some(value) = parse_radix_literal(literal) {
+            return Self::parse(&value.to_string(), mode);
+        }
+        match mode {
+            NumberMode::Float => literal.parse::<f64>().ok().map(Number::Float),
+            NumberMode::Decimal => Decimal::from_str(literal)
+                .or_else(|_| Decimal::from_scientific(literal))
+                .ok()
+                .map(|d| Number::Decimal(d.normalize()))
+                .or_else(|| literal.parse::<f64>().ok().map(Number::Float)),
+            NumberMode::Fraction => Self::parse(literal, NumberMode::Decimal).map(|n| match n {
+                Number::Decimal(d) => Fraction::from_decimal(d).map_or(n, Number::Fraction),
+                other => other,
+            }),
+        }
+    }
+
+    /// Convert an `f64` into the given mode.
+    ///
+    /// In decimal mode the shortest round-trip representation of the float is
+    /// used, so `0.1_f64` becomes exactly `0.1`. Fraction mode does the same
+    /// for short decimals such as conversion factors (`0.3048` is `381/1250`)
+    /// and keeps other floats, like `pi` or a currency rate, as floats rather
+    /// than huge fractions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use numby::evaluator::{Number, NumberMode};
+    ///
+    /// let foot = Number::from_f64(0.3048, NumberMode::Fraction);
+    /// assert_eq!(foot.to_string(), "381/1250");
+    /// let pi = Number::from_f64(std::f64::consts::PI, NumberMode::Fraction);
+    /// assert!(matches!(pi, Number::Float(_)));
+    /// ```
+    pub fn from_f64(value: f64, mode: NumberMode) -> Self {
+        match mode {
+            NumberMode::Float => Number::Float(value),
+            NumberMode::Decimal => Self::parse(&value.to_string(), mode)
+                .filter(|n| matches!(n, Number::Decimal(_)))
+                .unwrap_or(Number::Float(value)),
+            NumberMode::Fraction => match Self::parse(&value.to_string(), mode) {
+                Some(Number::Fraction(f)) if f.den <= MAX_APPROX_DENOMINATOR => Number::Fraction(f),
+                _ => Number::Float(value),
+            },
+        }
+    }
+
+    /// Lossy conversion to `f64`.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Number::Float(v) => v,
+            Number::Decimal(d) => d.to_f64().unwrap_or(f64::NAN),
+            Number::Fraction(f) => f.to_f64(),
+        }
+    }
+
+    /// Build an integer in the given mode.
+    pub fn from_integer(value: i128, mode: NumberMode) -> Self {
+        Self::parse(&value.to_string(), mode).unwrap_or(Number::Float(value as f64))
+    }
+
+    /// The value as an integer, if it is a whole number.
+    pub fn as_integer(self) -> Option<i128> {
+        match self {
+            Number::Float(v) => (v.fract() == 0.0 && v.abs() < 1e38).then_some(v as i128),
+            Number::Decimal(d) => d.fract().is_zero().then(|| d.to_i128()).flatten(),
+            Number::Fraction(f) => (f.den == 1).then_some(f.num),
+        }
+    }
+
+    /// The value written with `decimals` decimals, if it is a decimal. The
+    /// digits are exact, so `0.1 * 3` to 20 decimals is
+    /// `0.30000000000000000000` rather than the nearest `f64`.
+    pub fn to_fixed(self, decimals: usize) -> Option<String> {
+        match self {
+            Number::Decimal(d) => {
+                let rounded = d.round_dp_with_strategy(
+                    u32::try_from(decimals).unwrap_or(u32::MAX),
+                    RoundingStrategy::MidpointAwayFromZero,
+                );
+                Some(format!("{:.*}", decimals, rounded))
+            }
+            _ => None,
+        }
+    }
+
+    /// Nearest simple fraction, for the `to fraction` conversion target.
+    ///
+    /// Fractions are returned unchanged; other values use the closest fraction
+    /// with a denominator of at most a million.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use numby::evaluator::Number;
+    ///
+    /// assert_eq!(Number::from(0.375).to_fraction().to_string(), "3/8");
+    /// assert_eq!(Number::from(0.1 + 0.2).to_fraction().to_string(), "3/10");
+    /// ```
+    pub fn to_fraction(self) -> Number {
+        match self {
+            Number::Fraction(_) => self,
+            _ => Fraction::approximate(self.to_f64()).map_or(self, Number::Fraction),
+        }
+    }
+
+    /// Raise to a power. Integer exponents on decimals and fractions stay exact.
+    pub fn pow(self, exp: Number) -> Number {
+        if let (Number::Fraction(base), Some(e)) = (self, exp.as_fraction()) {
+            if let Some(result) = e.to_integer().and_then(|e| base.checked_powi(e)) {
+                return Number::Fraction(result);
+            }
+        }
+        if let (Number::Decimal(base), Number::Decimal(e)) = (self, exp) {
+            if e.fract().is_zero() {
+                if let Some(result) = e.to_i64().and_then(|e| base.checked_powi(e)) {
+                    return Number::Decimal(result.normalize());
+                }
+            }
+        }
+        Number::Float(self.to_f64().powf(exp.to_f64()))
+    }
+
+    /// Integer part, rounding toward zero.
+    pub fn trunc(self) -> Number {
+        match self {
+            Number::Float(v) => Number::Float(v.trunc()),
+            Number::Decimal(d) => Number::Decimal(d.trunc().normalize()),
+            Number::Fraction(f) => Number::Fraction(f.trunc()),
+        }
+    }
+
+    /// Exact fraction for this value, if it is a fraction or decimal.
+    fn as_fraction(self) -> Option<Fraction> {
+        match self {
+            Number::Float(_) => None,
+            Number::Decimal(d) => Fraction::from_decimal(d),
+            Number::Fraction(f) => Some(f),
+        }
+    }
+
+    fn combine(
+        self,
+        other: Number,
+        decimal_op: impl Fn(Decimal, Decimal) -> Option<Decimal>,
+        fraction_op: impl Fn(Fraction, Fraction) -> Option<Fraction>,
+        float_op: impl Fn(f64, f64) -> f64,
+    ) -> Number {
+        if matches!(self, Number::Fraction(_)) || matches!(other, Number::Fraction(_)) {
+            if let (Some(a), Some(b)) = (self.as_fraction(), other.as_fraction()) {
+                if let Some(result) = fraction_op(a, b) {
+                    return Number::Fraction(result);
+                }
+            }
+        } else if let (Number::Decimal(a), Number::Decimal(b)) = (self, other) {
+            if let Some(result) = decimal_op(a, b) {
+                return Number::Decimal(result.normalize());
+            }
+        }
+        Number::Float(float_op(self.to_f64(), other.to_f64()))
+    }
+}
+
+impl From<f64> for Number {
+    fn from(value: f64) -> Self {
+        Number::Float(value)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(value: i64) -> Self {
+        Number::Decimal(Decimal::from(value))
+    }
+}
+
+impl From<Decimal> for Number {
+    fn from(value: Decimal) -> Self {
+        Number::Decimal(value)
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Number::Float(v) => write!(f, "{}", v),
+            Number::Decimal(d) => write!(f, "{}", d.normalize()),
+            Number::Fraction(fraction) => write!(f, "{}", fraction),
+        }
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Number::Decimal(a), Number::Decimal(b)) => a == b,
+            (Number::Fraction(a), Number::Fraction(b)) => a == b,
+            _ => self.to_f64() == other.to_f64(),
+        }
+    }
+}
+
+impl PartialEq<f64> for Number {
+    fn eq(&self, other: &f64) -> bool {
+        self.to_f64() == *other
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Decimal(a), Number::Decimal(b)) => a.partial_cmp(b),
+            (Number::Fraction(a), Number::Fraction(b)) => a.checked_cmp(b),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+}
+
+impl std::ops::Add for Number {
+    type Output = Number;
+    fn add(self, other: Number) -> Number {
+        self.combine(
+            other,
+            |a, b| a.checked_add(b),
+            |a, b| a.checked_add(b),
+            |a, b| a + b,
+        )
+    }
+}
+
+impl std::ops::Sub for Number {
+    type Output = Number;
+    fn sub(self, other: Number) -> Number {
+        self.combine(
+            other,
+            |a, b| a.checked_sub(b),
+            |a, b| a.checked_sub(b),
+            |a, b| a - b,
+        )
+    }
+}
+
+impl std::ops::Mul for Number {
+    type Output = Number;
+    fn mul(self, other: Number) -> Number {
+        self.combine(
+            other,
+            |a, b| a.checked_mul(b),
+            |a, b| a.checked_mul(b),
+            |a, b| a * b,
+        )
+    }
+}
+
+impl std::ops::Div for Number {
+    type Output = Number;
+    fn div(self, other: Number) -> Number {
+        self.combine(
+            other,
+            |a, b| a.checked_div(b),
+            |a, b| a.checked_div(b),
+            |a, b| a / b,
+        )
+    }
+}
+
+impl std::ops::Rem for Number {
+    type Output = Number;
+    fn rem(self, other: Number) -> Number {
+        self.combine(
+            other,
+            |a, b| a.checked_rem(b),
+            |a, b| a.checked_rem(b),
+            |a, b| a % b,
+        )
+    }
+}
+
+impl std::ops::Neg for Number {
+    type Output = Number;
+    fn neg(self) -> Number {
+        match self {
+            Number::Float(v) => Number::Float(-v),
+            Number::Decimal(d) => Number::Decimal(-d),
+            Number::Fraction(f) => Number::Fraction(-f),
+        }
+    }
+}
+
+impl std::iter::Sum for Number {
+    fn sum<I: Iterator<Item = Number>>(iter: I) -> Number {
+        // Start from an exact zero so all-decimal input stays decimal.
+        iter.fold(Number::Decimal(Decimal::ZERO), |acc, n| acc + n)
+    }
+}
+
+/// Value of a `0x`, `0b` or `0o` literal.
+fn parse_radix_literal(literal: &str) -> Option<u128> {
+    let radix = match literal.get(..2)? {
+        "0x" | "0X" => 16,
+        "0b" | "0B" => 2,
+        "0o" | "0O" => 8,
+        _ => return None,
+    };
+    u128::from_str_radix(&literal[2..].replace('_', ""), radix).ok()
+}
+
+/// Largest denominator [`Number::to_fraction`] picks for an inexact value,
+/// and of the fractions [`Number::from_f64`] makes in fraction mode.
+const MAX_APPROX_DENOMINATOR: i128 = 1_000_000;
+
+/// An exact rational number, kept in lowest terms with a positive denominator.
+///
+/// Operations return `None` on overflow so that [`Number`] can fall back to a
+/// float.
+///
+/// # Examples
+///
+/// ```
+/// use numby::evaluator::Fraction;
+///
+/// let f = Fraction::new(22, 8).unwrap();
+/// assert_eq!(f.to_string(), "11/4");
+/// assert_eq!(f.mixed(), "2 3/4");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fraction {
+    num: i128,
+    den: i128,
+}
+
+impl Fraction {
+    /// Build `num/den` in lowest terms. `None` if `den` is zero.
+    pub fn new(num: i128, den: i128) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+        let divisor = gcd(num, den);
+        let (num, den) = (num / divisor, den / divisor);
+        if den < 0 {
+            Some(Self {
+                num: num.checked_neg()?,
+                den: den.checked_neg()?,
+            })
+        } else {
+            Some(Self { num, den })
+        }
+    }
+
+    pub fn denominator(&self) -> i128 {
+        self.den
+    }
+
+    /// Exact fraction for a decimal, e.g. `0.375` is `3/8`.
+    fn from_decimal(value: Decimal) -> Option<Self> {
+        Self::new(value.mantissa(), 10i128.checked_pow(value.scale())?)
+    }
+
+    /// Closest fraction to `value` with a denominator of at most
+    /// [`MAX_APPROX_DENOMINATOR`], found with continued fractions.
+    fn approximate(value: f64) -> Option<Self> {
+        if !value.is_finite() || value.abs() >= 1e18 {
+            return None;
+        }
+        let target = value.abs();
+        let tolerance = 1e-12 * target.max(1.0);
+        let (mut num, mut prev_num) = (1i128, 0i128);
+        let (mut den, mut prev_den) = (0i128, 1i128);
+        let mut remainder = target;
+        loop {
+            let whole = remainder.floor();
+            let next_num = (whole as i128)
+                .checked_mul(num)?
+                .checked_add(prev_num)?;
+            let next_den = (whole as i128)
+                .checked_mul(den)?
+                .checked_add(prev_den)?;
+            if next_den > MAX_APPROX_DENOMINATOR {
+                break;
+            }
+            (prev_num, num) = (num, next_num);
+            (prev_den, den) = (den, next_den);
+            let fraction = remainder - whole;
+            if (num as f64 / den as f64 - target).abs() <= tolerance || fraction < 1e-15 {
+                break;
+            }
+            remainder = 1.0 / fraction;
+        }
+        let num = if value < 0.0 { -num } else { num };
+        Self::new(num, den)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// The value as an integer, if it is one.
+    fn to_integer(self) -> Option<i64> {
+        (self.den == 1).then(|| i64::try_from(self.num).ok()).flatten()
+    }
+
+    fn trunc(self) -> Self {
+        Self {
+            num: self.num / self.den,
+            den: 1,
+        }
+    }
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        // Scale by the gcd of the denominators to keep intermediates small
+        let divisor = gcd(self.den, other.den);
+        let num = self
+            .num
+            .checked_mul(other.den / divisor)?
+            .checked_add(other.num.checked_mul(self.den / divisor)?)?;
+        Self::new(num, (self.den / divisor).checked_mul(other.den)?)
+    }
+
+    fn checked_sub(self, other: Self) -> Option<Self> {
+        self.checked_add(-other)
+    }
+
+    fn checked_mul(self, other: Self) -> Option<Self> {
+        // Cross-reduce first: (a/b) * (c/d) = (a/gcd(a,d) * c/gcd(c,b)) / ...
+        let left = gcd(self.num, other.den);
+        let right = gcd(other.num, self.den);
+        Self::new(
+            (self.num / left).checked_mul(other.num / right)?,
+            (self.den / right).checked_mul(other.den / left)?,
+        )
+    }
+
+    fn checked_div(self, other: Self) -> Option<Self> {
+        self.checked_mul(Self::new(other.den, other.num)?)
+    }
+
+    fn checked_rem(self, other: Self) -> Option<Self> {
+        let quotient = self.checked_div(other)?.trunc();
+        self.checked_sub(other.checked_mul(quotient)?)
+    }
+
+    fn checked_powi(self, exp: i64) -> Option<Self> {
+        let base = if exp < 0 {
+            Self::new(self.den, self.num)?
+        } else {
+            self
+        };
+        let exp = u32::try_from(exp.unsigned_abs()).ok()?;
+        Self::new(base.num.checked_pow(exp)?, base.den.checked_pow(exp)?)
+    }
+
+    fn checked_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (
+            self.num.checked_mul(other.den),
+            other.num.checked_mul(self.den),
+        ) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+
+    /// Mixed-number form for display: `11/4` is `2 3/4`, `-1/2` stays `-1/2`.
+    pub fn mixed(&self) -> String {
+        let whole = self.num / self.den;
+        let rest = (self.num % self.den).abs();
+        match (whole, rest) {
+            (_, 0) => whole.to_string(),
+            (0, _) => self.to_string(),
+            _ => format!("{} {}/{}", whole, rest, self.den),
+        }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    // gcd(0, 0) is taken as 1 so callers can always divide by it
+    i128::try_from(a).ok().filter(|&g| g != 0).unwrap_or(1)
+}
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+impl std::ops::Neg for Fraction {
+    type Output = Fraction;
+    fn neg(self) -> Fraction {
+        Fraction {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixed_operands_fall_back_to_float() {
+        let result = Number::Decimal(Decimal::ONE) + Number::Float(0.5);
+        assert!(matches!(result, Number::Float(_)));
+        assert_eq!(result, 1.5);
+    }
+
+    #[test]
+    fn test_from_f64_uses_shortest_representation() {
+        let n = Number::from_f64(0.1, NumberMode::Decimal);
+        assert_eq!(n.to_string(), "0.1");
+        assert!(matches!(n, Number::Decimal(_)));
+    }
+
+    #[test]
+    fn test_sum_of_decimals() {
+        let values = ["19.99", "5.01", "0.1", "0.2"]
+            .iter()
+            .map(|v| Number::parse(v, NumberMode::Decimal).unwrap());
+        assert_eq!(values.sum::<Number>().to_string(), "25.3");
+    }
+
+    #[test]
+    fn test_to_fixed_keeps_decimal_digits() {
+        let parse = |v: &str| Number::parse(v, NumberMode::Decimal).unwrap();
+        let product = parse("0.1") * parse("3");
+        assert_eq!(product.to_fixed(20).unwrap(), "0.30000000000000000000");
+        assert_eq!(parse("2.675").to_fixed(2).unwrap(), "2.68");
+        assert_eq!(parse("-1.5").to_fixed(0).unwrap(), "-2");
+        assert_eq!(Number::from(0.3).to_fixed(2), None);
+    }
+
+    #[test]
+    fn test_radix_literals_and_integers() {
+        let hex = Number::parse("0xFF", NumberMode::Float).unwrap();
+        assert_eq!(hex, 255.0);
+        assert_eq!(hex.as_integer(), Some(255));
+        assert!(matches!(
+            Number::parse("0b1010", NumberMode::Decimal),
+            Some(Number::Decimal(_))
+        ));
+        assert_eq!(Number::from(2.5).as_integer(), None);
+        assert_eq!(Number::from_integer(-3, NumberMode::Fraction).as_integer(), Some(-3));
+    }
+
+    #[test]
+    fn test_fraction_arithmetic_is_exact() {
+        let parse = |v: &str| Number::parse(v, NumberMode::Fraction).unwrap();
+        assert_eq!((parse("1") / parse("3") + parse("1") / parse("6")).to_string(), "1/2");
+        assert_eq!((parse("0.375") * parse("2")).to_string(), "3/4");
+        assert_eq!(parse("2").pow(parse("-2")).to_string(), "1/4");
+        assert_eq!((parse("7") % parse("3") / parse("2")).to_string(), "1/2");
+        assert!(parse("1") / parse("3") < parse("0.34"));
+    }
+
+    #[test]
+    fn test_fraction_mixes_with_decimal_and_float() {
+        let third = Number::from(1i64) / Number::parse("3", NumberMode::Fraction).unwrap();
+        assert!(matches!(third, Number::Fraction(_)));
+        assert!(matches!(third + Number::Float(0.5), Number::Float(_)));
+        let overflow = Number::Fraction(Fraction::new(1, i128::MAX).unwrap())
+            * Number::Fraction(Fraction::new(1, 3).unwrap());
+        assert!(matches!(overflow, Number::Float(_)));
+    }
+
+    #[test]
+    fn test_to_fraction_approximates() {
+        assert_eq!(Number::from(1.0 / 3.0).to_fraction().to_string(), "1/3");
+        assert_eq!(Number::from(-2.75).to_fraction().to_string(), "-11/4");
+        assert_eq!(Number::from(5.0).to_fraction().to_string(), "5");
+        let pi = Number::from(std::f64::consts::PI).to_fraction();
+        assert!((pi.to_f64() - std::f64::consts::PI).abs() < 1e-10);
+        assert!(matches!(Number::from(f64::NAN).to_fraction(), Number::Float(_)));
+    }
+}
@@ -0,0 +1,82 @@
+//! Roman numerals: reading `MCMXCIV` and writing results `to roman`.
+//!
+//! Only uppercase numerals are read. A single letter such as `C` or `V` is
+//! a numeral only where the line asks for one (`C to number`); elsewhere it
+//! keeps its meaning as a unit or name.
+
+/// Symbols from largest to smallest, with the subtractive pairs.
+const NUMERALS: &[(u32, &str)] = &[
+    (1000, "M"),
+    (900, "CM"),
+    (500, "D"),
+    (400, "CD"),
+    (100, "C"),
+    (90, "XC"),
+    (50, "L"),
+    (40, "XL"),
+    (10, "X"),
+    (9, "IX"),
+    (5, "V"),
+    (4, "IV"),
+    (1, "I"),
+];
+
+/// Largest value Roman numerals can write without overlines.
+pub const MAX_ROMAN: u32 = 3999;
+
+/// True if `word` is written with the uppercase letters `IVXLCDM` only,
+/// whether or not it is a well-formed numeral.
+pub fn is_roman_word(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| "IVXLCDM".contains(c))
+}
+
+/// Value of a Roman numeral in its standard form: `MCMXCIV` is 1994, while
+/// `IIII`, `VX` or `IM` are rejected.
+pub fn parse_roman(numeral: &str) -> Option<u32> {
+    let mut rest = numeral;
+    let mut value = 0;
+    for (amount, symbol) in NUMERALS {
+        while let Some(tail) = rest.strip_prefix(symbol) {
+            value += amount;
+            rest = tail;
+        }
+    }
+    // Only the canonical spelling round-trips
+    (rest.is_empty() && to_roman(value).as_deref() == Some(numeral)).then_some(value)
+}
+
+/// `n` as a Roman numeral, for 1 to 3999.
+pub fn to_roman(n: u32) -> Option<String> {
+    if n == 0 || n > MAX_ROMAN {
+        return None;
+    }
+    let mut rest = n;
+    let mut numeral = String::new();
+    for (amount, symbol) in NUMERALS {
+        while rest >= *amount {
+            numeral.push_str(symbol);
+            rest -= amount;
+        }
+    }
+    Some(numeral)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        assert_eq!(parse_roman("MCMXCIV"), Some(1994));
+        assert_eq!(parse_roman("MMMCMXCIX"), Some(3999));
+        assert_eq!(parse_roman("IV"), Some(4));
+        for malformed in ["IIII", "VX", "IM", "MCMC", "XM", "VV", ""] {
+            assert_eq!(parse_roman(malformed), None, "{}", malformed);
+        }
+        assert_eq!(to_roman(2025).as_deref(), Some("MMXXV"));
+        assert_eq!(to_roman(0), None);
+        assert_eq!(to_roman(4000), None);
+        assert!(is_roman_word("IIII"));
+        assert!(!is_roman_word("mix"));
+    }
+}
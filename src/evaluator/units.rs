@@ -0,0 +1,765 @@
+//! Dimensioned unit registry.
+//!
+//! Every configured unit table is mapped onto exponents of base dimensions:
+//! area is length², speed is length/time and each custom category becomes a
+//! dimension of its own, except energy, power, force and pressure, which are
+//! derived from mass, length and time so that `N·m` converts to `J`.
+//! Compound units such as `m²`, `km/h` or `USD/kg` are parsed from their
+//! written form, so variables and history keep storing plain unit strings
+//! while arithmetic works on dimensions.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::{Div, Mul};
+
+use crate::config::Config;
+use crate::conversions::currency_word_to_code;
+use crate::models::{AppState, Rates, TempUnits, Units};
+
+/// Exponents of base dimensions, e.g. `{length: 1, time: -1}` for speed.
+pub type Dimension<'a> = BTreeMap<&'a str, i32>;
+
+/// Dimensions of the built-in tables, in the order [`UnitRegistry`] searches them.
+const TABLE_DIMENSIONS: [&[(&str, i32)]; 8] = [
+    &[("length", 1)],
+    &[("time", 1)],
+    &[("length", 2)],
+    &[("length", 3)],
+    &[("mass", 1)],
+    &[("angle", 1)],
+    &[("data", 1)],
+    &[("length", 1), ("time", -1)],
+];
+
+/// Custom categories whose units are derived from base dimensions. Their
+/// tables are in SI units (joules, watts, newtons, pascals), which use
+/// kilograms where the mass table counts grams.
+const DERIVED_CATEGORIES: &[(&str, &[(&str, i32)])] = &[
+    ("energy", &[("mass", 1), ("length", 2), ("time", -2)]),
+    ("power", &[("mass", 1), ("length", 2), ("time", -3)]),
+    ("force", &[("mass", 1), ("length", 1), ("time", -2)]),
+    ("pressure", &[("mass", 1), ("length", -1), ("time", -2)]),
+];
+
+/// Grams per kilogram, the factor from SI units to the registry's base units.
+const GRAMS_PER_KILOGRAM: f64 = 1000.0;
+
+const SUPERSCRIPTS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+/// Factor from a unit to the base units of its dimension. Kept as a fraction
+/// so currency rates (units per USD) are divided rather than inverted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Factor {
+    pub num: f64,
+    pub den: f64,
+}
+
+impl Factor {
+    pub const ONE: Factor = Factor { num: 1.0, den: 1.0 };
+
+    fn new(value: f64) -> Self {
+        Self {
+            num: value,
+            den: 1.0,
+        }
+    }
+
+    fn powi(self, exp: i32) -> Self {
+        if exp >= 0 {
+            Self {
+                num: self.num.powi(exp),
+                den: self.den.powi(exp),
+            }
+        } else {
+            Self {
+                num: self.den.powi(-exp),
+                den: self.num.powi(-exp),
+            }
+        }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.num / self.den
+    }
+}
+
+impl Mul for Factor {
+    type Output = Factor;
+
+    fn mul(self, rhs: Factor) -> Factor {
+        Factor {
+            num: self.num * rhs.num,
+            den: self.den * rhs.den,
+        }
+    }
+}
+
+impl Div for Factor {
+    type Output = Factor;
+
+    fn div(self, rhs: Factor) -> Factor {
+        self * rhs.powi(-1)
+    }
+}
+
+/// A possibly compound unit: unit names with integer exponents, in the
+/// order they were written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Unit {
+    terms: Vec<(String, i32)>,
+}
+
+impl Unit {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// True for a plain unit name such as `km` (not `km/h` or `m²`).
+    pub fn is_simple(&self) -> bool {
+        matches!(self.terms.as_slice(), [(_, 1)])
+    }
+
+    pub fn inverse(&self) -> Unit {
+        Unit {
+            terms: self
+                .terms
+                .iter()
+                .map(|(name, exp)| (name.clone(), -exp))
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |positive: bool| {
+            self.terms
+                .iter()
+                .filter(|(_, exp)| (*exp > 0) == positive)
+                .map(|(name, exp)| match exp.abs() {
+                    1 => name.clone(),
+                    n => format!("{}{}", name, superscript(n)),
+                })
+                .collect::<Vec<_>>()
+                .join("·")
+        };
+        let (numerator, denominator) = (join(true), join(false));
+        match (numerator.is_empty(), denominator.is_empty()) {
+            (_, true) => write!(f, "{}", numerator),
+            (true, false) => write!(f, "1/{}", denominator),
+            (false, false) => write!(f, "{}/{}", numerator, denominator),
+        }
+    }
+}
+
+fn superscript(n: i32) -> String {
+    n.to_string()
+        .chars()
+        .map(|c| match c.to_digit(10) {
+            Some(d) => SUPERSCRIPTS[d as usize],
+            None => '⁻',
+        })
+        .collect()
+}
+
+/// Split a trailing exponent off a unit factor: `m²`, `s⁻¹`, `m^2`, `cm2`.
+fn split_exponent(text: &str) -> Option<(&str, i32)> {
+    let sup_start = text
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| SUPERSCRIPTS.contains(c) || *c == '⁻')
+        .last()
+        .map(|(i, _)| i);
+    if let Some(start) = sup_start.filter(|i| *i > 0) {
+        let digits: String = text[start..]
+            .chars()
+            .map(|c| match SUPERSCRIPTS.iter().position(|s| *s == c) {
+                Some(d) => char::from(b'0' + d as u8),
+                None => '-',
+            })
+            .collect();
+        return Some((&text[..start], digits.parse().ok()?));
+    }
+    if let Some((name, exp)) = text.rsplit_once('^') {
+        return Some((name, exp.parse().ok()?));
+    }
+    let digits_start = text.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    if digits_start > 0 && digits_start < text.len() {
+        return Some((&text[..digits_start], text[digits_start..].parse().ok()?));
+    }
+    None
+}
+
+/// Where a unit name was found.
+#[derive(Debug, Clone, Copy)]
+enum Source<'a> {
+    Table(&'a Units),
+    /// Temperature scale (`celsius`, `fahrenheit`, `kelvin`), converted affinely.
+    Temperature(&'a str),
+    /// Change in temperature on a scale (`5 C difference`, `ΔF`), converted
+    /// without the offset.
+    TemperatureDifference(&'a str),
+    /// Currency, by ISO code.
+    Currency(&'a str),
+}
+
+#[derive(Debug, Clone)]
+struct BaseUnit<'a> {
+    factor: Factor,
+    dimension: Dimension<'a>,
+    source: Source<'a>,
+}
+
+impl BaseUnit<'_> {
+    /// A reading on a scale whose zero isn't absolute zero (`C`, `F`), which
+    /// can't be scaled into or merged with other units.
+    fn is_offset_temperature(&self) -> bool {
+        matches!(self.source, Source::Temperature(scale) if !Temperature::Reading(scale).is_absolute())
+    }
+}
+
+/// A bare temperature unit: a reading on a scale, or a change on one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Temperature<'a> {
+    Reading(&'a str),
+    Difference(&'a str),
+}
+
+impl<'a> Temperature<'a> {
+    pub fn scale(self) -> &'a str {
+        match self {
+            Temperature::Reading(scale) | Temperature::Difference(scale) => scale,
+        }
+    }
+
+    /// Whether a reading on this scale is measured from absolute zero.
+    pub fn is_absolute(self) -> bool {
+        matches!(self.scale(), "kelvin" | "rankine")
+    }
+}
+
+/// How to convert between two units of the same dimension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Conversion<'a> {
+    Scale(Factor),
+    Temperature(&'a str, &'a str),
+}
+
+/// All configured units (tables, temperatures, currencies and custom
+/// categories) behind a single dimensioned lookup.
+#[derive(Clone, Copy)]
+pub struct UnitRegistry<'a> {
+    tables: [&'a Units; 8],
+    custom: &'a HashMap<String, Units>,
+    temperatures: &'a TempUnits,
+    rates: &'a Rates,
+}
+
+impl<'a> UnitRegistry<'a> {
+    /// Build the registry from the state's unit tables and rates and the
+    /// config's custom unit categories.
+    pub fn new(state: &'a AppState, config: &'a Config) -> Self {
+        Self {
+            tables: [
+                &state.length_units,
+                &state.time_units,
+                &state.area_units,
+                &state.volume_units,
+                &state.weight_units,
+                &state.angular_units,
+                &state.data_units,
+                &state.speed_units,
+            ],
+            custom: &config.custom_units,
+            temperatures: &state.temperature_units,
+            rates: &state.rates,
+        }
+    }
+
+    /// Every unit table with its dimension and the scale from its values to
+    /// base units, built-in ones first.
+    fn tables(&self) -> impl Iterator<Item = (&'a Units, Dimension<'a>, f64)> + '_ {
+        self.tables
+            .iter()
+            .zip(TABLE_DIMENSIONS)
+            .map(|(table, dims)| (*table, dims.iter().copied().collect::<Dimension>(), 1.0))
+            .chain(self.custom.iter().map(|(category, table)| {
+                match DERIVED_CATEGORIES.iter().find(|(name, _)| name == category) {
+                    Some((_, dims)) => (table, dims.iter().copied().collect(), GRAMS_PER_KILOGRAM),
+                    None => (table, Dimension::from([(category.as_str(), 1)]), 1.0),
+                }
+            }))
+    }
+
+    fn base(&self, name: &str) -> Option<BaseUnit<'a>> {
+        let lower = name.to_lowercase();
+        for (table, dimension, scale) in self.tables() {
+            if let Some(factor) = table.get(name).or_else(|| table.get(&lower)) {
+                return Some(BaseUnit {
+                    factor: Factor::new(factor * scale),
+                    dimension,
+                    source: Source::Table(table),
+                });
+            }
+        }
+
+        if let Some(temperature) = self.temperature_name(name) {
+            // Linear factor of a temperature difference, relative to kelvin
+            let factor = match temperature.scale() {
+                "fahrenheit" | "rankine" => 5.0 / 9.0,
+                _ => 1.0,
+            };
+            return Some(BaseUnit {
+                factor: Factor::new(factor),
+                dimension: Dimension::from([("temperature", 1)]),
+                source: match temperature {
+                    Temperature::Reading(scale) => Source::Temperature(scale),
+                    Temperature::Difference(scale) => Source::TemperatureDifference(scale),
+                },
+            });
+        }
+
+        let code = self.currency(name)?;
+        Some(BaseUnit {
+            // Rates are units per USD
+            factor: Factor {
+                num: 1.0,
+                den: self.rates[code],
+            },
+            dimension: Dimension::from([("currency", 1)]),
+            source: Source::Currency(code),
+        })
+    }
+
+    /// Temperature named by `name`: a scale (`C`, `°F`, `kelvin`), or a change
+    /// on one written `ΔC` or `C difference`.
+    fn temperature_name(&self, name: &str) -> Option<Temperature<'a>> {
+        let lookup = |name: &str| {
+            let name = name.trim().trim_start_matches('°');
+            self.temperatures
+                .get(name)
+                .or_else(|| self.temperatures.get(&name.to_lowercase()))
+                .map(String::as_str)
+        };
+        if let Some(scale) = lookup(name) {
+            return Some(Temperature::Reading(scale));
+        }
+        let change = name
+            .strip_prefix('Δ')
+            .or_else(|| name.strip_suffix(" difference"))?;
+        lookup(change).map(Temperature::Difference)
+    }
+
+    /// ISO code for a currency code or word (`eur`, `dollars`) with a known rate.
+    pub fn currency(&self, name: &str) -> Option<&'a str> {
+        let code = currency_word_to_code(&name.to_lowercase())
+            .map(str::to_string)
+            .unwrap_or_else(|| name.to_uppercase());
+        self.rates
+            .get_key_value(&code)
+            .map(|(code, _)| code.as_str())
+    }
+
+    /// Parse a written unit: a configured name (`miles per hour`) or a
+    /// compound of names with exponents (`km/h`, `m²`, `kg·m/s^2`).
+    pub fn parse(&self, text: &str) -> Option<Unit> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        let whole = || {
+            self.base(text).map(|base| Unit {
+                terms: vec![(term_name(text, &base), 1)],
+            })
+        };
+        if text.contains('/') {
+            self.parse_compound(text).or_else(whole)
+        } else {
+            whole().or_else(|| self.parse_compound(text))
+        }
+    }
+
+    fn parse_compound(&self, text: &str) -> Option<Unit> {
+        let mut terms = Vec::new();
+        for (i, part) in text.split('/').enumerate() {
+            let sign = if i == 0 { 1 } else { -1 };
+            if i == 0 && part.trim() == "1" {
+                continue;
+            }
+            for factor in part.split(['·', '*']) {
+                let factor = factor.trim();
+                let (name, exp) = match self.base(factor) {
+                    Some(_) => (factor, 1),
+                    None => split_exponent(factor)?,
+                };
+                let base = self.base(name)?;
+                if exp == 0 {
+                    return None;
+                }
+                terms.push((term_name(name, &base), sign * exp));
+            }
+        }
+        (!terms.is_empty()).then_some(Unit { terms })
+    }
+
+    pub fn dimension(&self, unit: &Unit) -> Option<Dimension<'a>> {
+        let mut dimension = Dimension::new();
+        for (name, exp) in &unit.terms {
+            for (base, power) in self.base(name)?.dimension {
+                *dimension.entry(base).or_insert(0) += power * exp;
+            }
+        }
+        dimension.retain(|_, power| *power != 0);
+        Some(dimension)
+    }
+
+    fn factor(&self, unit: &Unit) -> Option<Factor> {
+        unit.terms.iter().try_fold(Factor::ONE, |acc, (name, exp)| {
+            Some(acc * self.base(name)?.factor.powi(*exp))
+        })
+    }
+
+    /// The temperature a bare temperature unit (`C`, `kelvin`, `ΔF`) measures.
+    pub fn temperature(&self, unit: &Unit) -> Option<Temperature<'a>> {
+        match unit.terms.as_slice() {
+            [(name, 1)] => match self.base(name)?.source {
+                Source::Temperature(scale) => Some(Temperature::Reading(scale)),
+                Source::TemperatureDifference(scale) => Some(Temperature::Difference(scale)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Conversion from one unit to another, or `None` if their dimensions differ.
+    /// Readings convert affinely and changes linearly, never one into the other.
+    pub fn conversion(&self, from: &Unit, to: &Unit) -> Option<Conversion<'a>> {
+        match (self.temperature(from), self.temperature(to)) {
+            (Some(Temperature::Reading(a)), Some(Temperature::Reading(b))) => {
+                return Some(Conversion::Temperature(a, b))
+            }
+            (Some(Temperature::Reading(_)), Some(_)) | (Some(_), Some(Temperature::Reading(_))) => {
+                return None
+            }
+            _ => {}
+        }
+        self.linear_factor(from, to).map(Conversion::Scale)
+    }
+
+    /// Factor a change in one unit is worth in another: the conversion without
+    /// any temperature offset, or `None` if their dimensions differ.
+    pub fn linear_factor(&self, from: &Unit, to: &Unit) -> Option<Factor> {
+        if self.dimension(from)? != self.dimension(to)? {
+            return None;
+        }
+        Some(self.factor(from)? / self.factor(to)?)
+    }
+
+    pub fn is_currency(&self, unit: &Unit) -> bool {
+        self.dimension(unit) == Some(Dimension::from([("currency", 1)]))
+    }
+
+    /// Factor to radians for a plain angle unit.
+    pub fn angle_factor(&self, unit: &Unit) -> Option<f64> {
+        if self.dimension(unit)? == Dimension::from([("angle", 1)]) {
+            self.factor(unit).map(Factor::to_f64)
+        } else {
+            None
+        }
+    }
+
+    /// Product of two units, with the factor the product of the values has to
+    /// be scaled by. Terms of the same dimension are merged into the left
+    /// operand's unit (`km * m` is `km²`, `J/K * R` is `J`), a product whose
+    /// dimensions cancel to a plain quantity is a single unit where one fits
+    /// (`mph * h` is `mi`) and a dimensionless result has no unit. Celsius
+    /// and Fahrenheit readings are never merged, since their zero is offset.
+    pub fn multiply(&self, lhs: &Unit, rhs: &Unit) -> Option<(Unit, Factor)> {
+        let mut terms: Vec<(String, i32)> = Vec::new();
+        let mut scale = Factor::ONE;
+        for (name, exp) in lhs.terms.iter().chain(&rhs.terms) {
+            let base = self.base(name)?;
+            let symbol = self.symbol(name, &base);
+            let merge_with = terms.iter().position(|(existing, _)| {
+                *existing == symbol
+                    || self.base(existing).is_some_and(|other| {
+                        other.dimension == base.dimension
+                            && !other.is_offset_temperature()
+                            && !base.is_offset_temperature()
+                    })
+            });
+            match merge_with {
+                Some(i) => {
+                    let other = self.base(&terms[i].0)?;
+                    scale = scale * (base.factor / other.factor).powi(*exp);
+                    terms[i].1 += exp;
+                }
+                None => terms.push((symbol, *exp)),
+            }
+        }
+        terms.retain(|(_, exp)| *exp != 0);
+
+        let unit = Unit { terms };
+        if !unit.is_empty() && self.dimension(&unit)?.is_empty() {
+            // hectare / m² is a plain number
+            return Some((Unit::default(), scale * self.factor(&unit)?));
+        }
+        if let Some((simple, factor)) = self.cancel(&unit) {
+            return Some((simple, scale * factor));
+        }
+        Some((unit, scale))
+    }
+
+    /// A compound whose terms' dimensions cancel to a plain quantity (length,
+    /// time, a currency) as a single unit, with the factor values are scaled
+    /// by: `mph·h` is `mi`, and `mph·min` is `mi` too once the minutes are
+    /// taken as hours. `None` if no unit of the tables fits.
+    fn cancel(&self, unit: &Unit) -> Option<(Unit, Factor)> {
+        let dimension = self.dimension(unit)?;
+        if unit.terms.len() < 2 || !is_plain(&dimension) {
+            return None;
+        }
+        let mut powers = Dimension::new();
+        let mut cancels = false;
+        for (name, exp) in &unit.terms {
+            for (base, power) in self.base(name)?.dimension {
+                let total = powers.entry(base).or_insert(0);
+                cancels |= *total * power * exp < 0;
+                *total += power * exp;
+            }
+        }
+        if !cancels {
+            return None;
+        }
+        // The unit as written, then with one plain term (`min`, not `mph`)
+        // swapped for another unit of its table
+        let mut variants = vec![(unit.clone(), Factor::ONE)];
+        for (i, (name, exp)) in unit.terms.iter().enumerate() {
+            let base = self.base(name)?;
+            let Source::Table(table) = base.source else {
+                continue;
+            };
+            if !is_plain(&base.dimension) {
+                continue;
+            }
+            for alias in table.keys() {
+                let mut variant = unit.clone();
+                variant.terms[i].0 = alias.clone();
+                let other = self.base(alias)?;
+                variants.push((variant, (base.factor / other.factor).powi(*exp)));
+            }
+        }
+        variants.into_iter().find_map(|(variant, scale)| {
+            let name = self.unit_with_factor(&dimension, self.factor(&variant)?)?;
+            Some((
+                Unit {
+                    terms: vec![(name, 1)],
+                },
+                scale,
+            ))
+        })
+    }
+
+    /// Shortest single-word name of a unit of `dimension` worth `factor`
+    /// base units.
+    fn unit_with_factor(&self, dimension: &Dimension, factor: Factor) -> Option<String> {
+        let factor = factor.to_f64();
+        self.tables()
+            .filter(|(_, table_dimension, _)| table_dimension == dimension)
+            .flat_map(|(table, _, scale)| {
+                table
+                    .iter()
+                    .filter(move |(_, value)| ((*value * scale) / factor - 1.0).abs() < 1e-9)
+                    .map(|(alias, _)| alias)
+            })
+            .filter(|alias| !alias.contains(' '))
+            .filter(|alias| {
+                // The name has to resolve back to a unit of this dimension
+                self.base(alias)
+                    .is_some_and(|base| base.dimension == *dimension)
+            })
+            .min_by_key(|alias| (alias.chars().count(), *alias))
+            .cloned()
+    }
+
+    /// `unit` raised to an integer power.
+    pub fn pow(&self, unit: &Unit, exp: i32) -> Option<Unit> {
+        let (unit, _) = self.multiply(&Unit::default(), unit)?;
+        Some(Unit {
+            terms: unit
+                .terms
+                .into_iter()
+                .filter(|_| exp != 0)
+                .map(|(name, power)| (name, power * exp))
+                .collect(),
+        })
+    }
+
+    /// The `n`th root of `unit`, if every exponent is divisible by `n`.
+    pub fn root(&self, unit: &Unit, n: i32) -> Option<Unit> {
+        let (unit, _) = self.multiply(&Unit::default(), unit)?;
+        unit.terms
+            .iter()
+            .all(|(_, exp)| exp % n == 0)
+            .then(|| Unit {
+                terms: unit
+                    .terms
+                    .iter()
+                    .map(|(name, exp)| (name.clone(), exp / n))
+                    .collect(),
+            })
+    }
+
+    /// Short name used when a unit becomes part of a compound: the currency
+    /// code, or the shortest single-word alias with the same factor (`hours`
+    /// becomes `h`).
+    fn symbol(&self, name: &str, base: &BaseUnit) -> String {
+        match base.source {
+            Source::Currency(code) => code.to_string(),
+            Source::Temperature(_) | Source::TemperatureDifference(_) => name.to_string(),
+            Source::Table(table) => table
+                .iter()
+                .filter(|(alias, factor)| {
+                    Factor::new(**factor) == base.factor && !alias.contains(' ')
+                })
+                .map(|(alias, _)| alias.as_str())
+                .filter(|alias| {
+                    // The alias has to resolve back to the same unit
+                    self.base(alias).is_some_and(|other| {
+                        other.factor == base.factor && other.dimension == base.dimension
+                    })
+                })
+                .min_by_key(|alias| (alias.chars().count(), *alias))
+                .unwrap_or(name)
+                .to_string(),
+        }
+    }
+}
+
+/// Whether `dimension` is a single base dimension, like length but not area.
+fn is_plain(dimension: &Dimension) -> bool {
+    dimension.len() == 1 && dimension.values().all(|power| *power == 1)
+}
+
+/// Name stored for a parsed term: currencies by ISO code, anything else as written.
+fn term_name(name: &str, base: &BaseUnit) -> String {
+    match base.source {
+        Source::Currency(code) => code.to_string(),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_registry(test: impl FnOnce(UnitRegistry)) {
+        let config = Config::default();
+        let state = AppState::builder(&config).build();
+        test(UnitRegistry::new(&state, &config));
+    }
+
+    #[test]
+    fn test_tables_map_to_dimensions() {
+        with_registry(|units| {
+            let dim = |text: &str| units.dimension(&units.parse(text).unwrap()).unwrap();
+            assert_eq!(dim("hectares"), Dimension::from([("length", 2)]));
+            assert_eq!(dim("mph"), Dimension::from([("length", 1), ("time", -1)]));
+            assert_eq!(dim("km/h"), dim("knots"));
+            assert_eq!(dim("m²"), dim("acre"));
+            assert_eq!(dim("liters"), dim("m^3"));
+            assert_eq!(
+                dim("USD/kg"),
+                Dimension::from([("currency", 1), ("mass", -1)])
+            );
+            assert_eq!(dim("joules"), dim("kg·m²/s²"));
+            assert_eq!(dim("N·m"), dim("J"));
+            assert_eq!(dim("kW·h"), dim("kWh"));
+            assert!(units.parse("m/xyz").is_none());
+        });
+    }
+
+    #[test]
+    fn test_multiply_combines_exponents() {
+        with_registry(|units| {
+            let unit = |text: &str| units.parse(text).unwrap();
+            let (area, scale) = units.multiply(&unit("meters"), &unit("meters")).unwrap();
+            assert_eq!(area.to_string(), "m²");
+            assert_eq!(scale, Factor::ONE);
+
+            let (speed, _) = units
+                .multiply(&unit("km"), &unit("hours").inverse())
+                .unwrap();
+            assert_eq!(speed.to_string(), "km/h");
+
+            let (area, scale) = units.multiply(&unit("m"), &unit("cm")).unwrap();
+            assert_eq!(area.to_string(), "m²");
+            assert!((scale.to_f64() - 0.01).abs() < 1e-12);
+
+            let (ratio, scale) = units
+                .multiply(&unit("hectare"), &unit("m²").inverse())
+                .unwrap();
+            assert!(ratio.is_empty());
+            assert!((scale.to_f64() - 10000.0).abs() < 1e-9);
+
+            let (distance, scale) = units.multiply(&unit("mph"), &unit("h")).unwrap();
+            assert_eq!(distance.to_string(), "mi");
+            assert_eq!(scale, Factor::ONE);
+
+            let (distance, scale) = units.multiply(&unit("mph"), &unit("min")).unwrap();
+            assert_eq!(distance.to_string(), "mi");
+            assert!((scale.to_f64() - 1.0 / 60.0).abs() < 1e-12);
+
+            let (energy, _) = units.multiply(&unit("kW"), &unit("h")).unwrap();
+            assert_eq!(energy.to_string(), "kW·h");
+        });
+    }
+
+    #[test]
+    fn test_conversion_checks_dimensions() {
+        with_registry(|units| {
+            let unit = |text: &str| units.parse(text).unwrap();
+            match units.conversion(&unit("km/h"), &unit("mph")) {
+                Some(Conversion::Scale(factor)) => {
+                    assert!((factor.to_f64() - 0.621371).abs() < 1e-6)
+                }
+                other => panic!("unexpected conversion {:?}", other),
+            }
+            assert!(units.conversion(&unit("m"), &unit("kg")).is_none());
+            assert_eq!(
+                units.conversion(&unit("celsius"), &unit("F")),
+                Some(Conversion::Temperature("celsius", "fahrenheit"))
+            );
+        });
+    }
+
+    #[test]
+    fn test_temperature_differences() {
+        with_registry(|units| {
+            let unit = |text: &str| units.parse(text).unwrap();
+            assert_eq!(
+                units.temperature(&unit("°C")),
+                Some(Temperature::Reading("celsius"))
+            );
+            assert_eq!(
+                units.temperature(&unit("ΔF")),
+                Some(Temperature::Difference("fahrenheit"))
+            );
+            match units.conversion(&unit("C difference"), &unit("ΔF")) {
+                Some(Conversion::Scale(factor)) => assert!((factor.to_f64() - 1.8).abs() < 1e-12),
+                other => panic!("unexpected conversion {:?}", other),
+            }
+            assert!(units
+                .conversion(&unit("C difference"), &unit("F"))
+                .is_none());
+            assert!(units.conversion(&unit("K"), &unit("ΔK")).is_none());
+
+            // Kelvin and Rankine start at absolute zero and merge like other units
+            let (merged, scale) = units.multiply(&unit("J/K"), &unit("R")).unwrap();
+            assert_eq!(merged.terms.len(), 1);
+            assert!((scale.to_f64() - 5.0 / 9.0).abs() < 1e-12);
+            let (kept, _) = units.multiply(&unit("J/K"), &unit("C")).unwrap();
+            assert_eq!(kept.terms.len(), 3);
+        });
+    }
+}
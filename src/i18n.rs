@@ -70,6 +70,53 @@ pub fn init_locale(config_locale: Option<&str>) {
     });
 }
 
+/// How a locale writes numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberStyle {
+    /// Decimal separator: `.` in English, `,` in German.
+    pub decimal: char,
+    /// Digit grouping character: `,` in English, `.` in German, a narrow
+    /// no-break space in French.
+    pub grouping: char,
+    /// Currency symbols go before the amount (`$12.50`) rather than after
+    /// it (`12,50 €`).
+    pub symbol_first: bool,
+}
+
+impl NumberStyle {
+    /// `1,234.5`
+    pub const POINT: NumberStyle = NumberStyle {
+        decimal: '.',
+        grouping: ',',
+        symbol_first: true,
+    };
+
+    /// Number style of a locale such as `de` or `fr-CA`. Locales without a
+    /// decimal comma write `1,234.5`.
+    pub fn for_locale(locale: &str) -> NumberStyle {
+        let language = locale.split(['-', '_']).next().unwrap_or_default();
+        let grouping = match language.to_lowercase().as_str() {
+            "de" | "es" => '.',
+            "fr" => '\u{202F}',
+            "ru" | "be" => '\u{A0}',
+            _ => return NumberStyle::POINT,
+        };
+        NumberStyle {
+            decimal: ',',
+            grouping,
+            symbol_first: false,
+        }
+    }
+}
+
+/// Number style of `locale`, or of the current locale if `None`.
+pub fn number_style(locale: Option<&str>) -> NumberStyle {
+    match locale {
+        Some(locale) => NumberStyle::for_locale(locale),
+        None => NumberStyle::for_locale(&get_locale().to_string()),
+    }
+}
+
 /// Get a localized string with arguments
 pub fn fl(message_id: &str, args: Option<&HashMap<String, String>>) -> String {
     let locale = get_locale();
@@ -7,7 +7,6 @@
 //! - `config`: Configuration loading and management.
 //! - `evaluator`: Expression evaluation and conversions.
 //! - `models`: Data structures and state management.
-//! - `parser`: Parsing utilities and regex replacements.
 //! - `conversions`: Unit and currency conversion functions.
 //! - `prettify`: Number formatting for display.
 //! - `security`: Path validation and input sanitization.
@@ -19,9 +18,9 @@ pub mod currency_fetcher;
 pub mod evaluator;
 pub mod i18n;
 pub mod models;
-pub mod parser;
 pub mod prettify;
 pub mod security;
+pub mod spell;
 
 #[cfg(feature = "android")]
 pub mod android_jni;
@@ -63,45 +62,40 @@ mod event_tests {
     }
 
     #[test]
+    #[cfg(feature = "desktop")]
     fn test_cache_invalidation_on_variable_change() {
+        use ratatui::text::Span;
+
         let config = Config::default();
         let state = AppState::builder(&config).build();
+        let spans = |text: &str| vec![Span::raw(text.to_string())];
 
         // Set a cache value
         state
             .cache
-            .set_display("test_key".to_string(), Some("cached_value".to_string()));
+            .set_highlight("test_key".to_string(), spans("cached_value"));
 
         // Verify it's cached
         assert_eq!(
-            state.cache.get_display("test_key"),
-            Some(Some("cached_value".to_string()))
+            state.cache.get_highlight("test_key"),
+            Some(spans("cached_value"))
         );
 
-        // Trigger a variable change event
-        state.publish_event(StateEvent::VariableChanged("test".to_string()));
-
-        // Cache should be invalidated for prefix "test"
-        // Keys that don't start with "test" should still be there
+        // Set a key that doesn't start with the variable name
         state
             .cache
-            .set_display("other_key".to_string(), Some("other_value".to_string()));
+            .set_highlight("other_key".to_string(), spans("other_value"));
 
-        // Set a cache with "test" prefix
-        state
-            .cache
-            .set_display("test_key".to_string(), Some("new_value".to_string()));
-
-        // Trigger event
+        // Trigger a variable change event
         state.publish_event(StateEvent::VariableChanged("test".to_string()));
 
         // The key with "test" prefix should be cleared
-        assert_eq!(state.cache.get_display("test_key"), None);
+        assert_eq!(state.cache.get_highlight("test_key"), None);
 
         // But other keys should remain
         assert_eq!(
-            state.cache.get_display("other_key"),
-            Some(Some("other_value".to_string()))
+            state.cache.get_highlight("other_key"),
+            Some(spans("other_value"))
         );
     }
 
@@ -197,6 +191,10 @@ pub extern "C" fn libnumby_context_new() -> *mut NumbyContext {
     Box::into_raw(Box::new(crate::models::AppState::builder(&config).build()))
 }
 
+/// Evaluate `input`, returning the result's value, or 0 for results that
+/// are not a single number, such as lists and dates. `out_unit` is set to
+/// the result's unit and left null when it has none.
+///
 /// # Safety
 ///
 /// This function dereferences raw pointers and must be called with valid pointers.
@@ -255,22 +253,19 @@ pub unsafe extern "C" fn libnumby_evaluate(
         }
     };
 
-    match registry.evaluate(input_str, context) {
-        Some((result_str, _)) => {
-            // Parse result_str, e.g., "3.11 miles" -> value=3.11, formatted="3.11 miles", unit="miles"
-            let parts: Vec<&str> = result_str.split_whitespace().collect();
-            let value = parts
-                .first()
-                .and_then(|s| crate::conversions::parse_number_with_scale(s))
-                .unwrap_or(0.0);
+    match registry.evaluate_with_value(input_str, context) {
+        Ok((result_str, value, unit)) => {
+            // e.g. "3.11 miles" -> value=3.11, formatted="3.11 miles", unit="miles".
+            // The formatted text may use separators, exponents, `±` or words,
+            // so the value and unit come from the evaluator, never the text.
+            let value = value.map_or(0.0, |v| v.to_f64());
 
             // Safe string conversion
             if let Ok(formatted_cstr) = CString::new(result_str.clone()) {
                 *out_formatted = formatted_cstr.into_raw();
             }
 
-            if parts.len() > 1 {
-                let unit_str = parts[1..].join(" ");
+            if let Some(unit_str) = unit {
                 if let Ok(unit_cstr) = CString::new(unit_str) {
                     *out_unit = unit_cstr.into_raw();
                 }
@@ -278,8 +273,8 @@ pub unsafe extern "C" fn libnumby_evaluate(
 
             value
         }
-        None => {
-            if let Ok(s) = CString::new("Evaluation failed") {
+        Err(e) => {
+            if let Ok(s) = CString::new(e.message()) {
                 *out_error = s.into_raw();
             }
             0.0
@@ -352,7 +347,7 @@ pub unsafe extern "C" fn libnumby_load_config(ctx: *mut NumbyContext, path: *con
                 return -1;
             }
 
-            match serde_json::from_str::<crate::config::Config>(&contents) {
+            match crate::config::parse_config(&contents) {
                 Ok(config) => {
                     let context = &mut *ctx;
                     // Update context with new config values
@@ -366,6 +361,8 @@ pub unsafe extern "C" fn libnumby_load_config(ctx: *mut NumbyContext, path: *con
                     context.data_units = config.data_units;
                     context.speed_units = config.speed_units;
                     context.rates = config.currencies;
+                    context.display = config.display;
+                    context.constants = config.constants;
                     set_config_override_path(validated_path);
                     0
                 }
@@ -485,7 +482,7 @@ pub unsafe extern "C" fn libnumby_clear_history(ctx: *mut NumbyContext) -> i32 {
     }
 }
 
-/// Clear all variables from the context
+/// Clear all variables, lists and user-defined functions from the context
 ///
 /// # Safety
 ///
@@ -497,12 +494,20 @@ pub unsafe extern "C" fn libnumby_clear_variables(ctx: *mut NumbyContext) -> i32
     }
 
     let context = &mut *ctx;
-    match context.variables.write() {
-        Ok(mut variables) => {
+    match (
+        context.variables.write(),
+        context.uncertainties.write(),
+        context.lists.write(),
+        context.functions.write(),
+    ) {
+        (Ok(mut variables), Ok(mut uncertainties), Ok(mut lists), Ok(mut functions)) => {
             variables.clear();
+            uncertainties.clear();
+            lists.clear();
+            functions.clear();
             0
         }
-        Err(_) => -1,
+        _ => -1,
     }
 }
 
@@ -725,6 +730,53 @@ pub extern "C" fn libnumby_get_api_rates_date() -> *mut c_char {
     }
 }
 
+#[cfg(test)]
+mod ffi_tests {
+    use super::*;
+    use std::ptr;
+
+    /// Value, formatted text and unit `libnumby_evaluate` reports for `input`.
+    fn evaluate(input: &str) -> (f64, String, Option<String>) {
+        let input = CString::new(input).unwrap();
+        let (mut formatted, mut unit, mut error) =
+            (ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+        unsafe {
+            let ctx = libnumby_context_new();
+            let value =
+                libnumby_evaluate(ctx, input.as_ptr(), &mut formatted, &mut unit, &mut error);
+            assert!(error.is_null(), "{:?}", CStr::from_ptr(error));
+            let text = |s: *mut c_char| {
+                (!s.is_null()).then(|| CStr::from_ptr(s).to_string_lossy().into_owned())
+            };
+            let result = (value, text(formatted).unwrap(), text(unit));
+            libnumby_free_string(formatted);
+            libnumby_free_string(unit);
+            libnumby_context_free(ctx);
+            result
+        }
+    }
+
+    #[test]
+    fn test_evaluate_reports_the_result_unit() {
+        let (value, _, unit) = evaluate("5 km + 500 m");
+        assert_eq!(value, 5.5);
+        assert_eq!(unit.as_deref(), Some("km"));
+
+        // Uncertainties, words and lists have text after the number that
+        // isn't a unit
+        let (value, formatted, unit) = evaluate("10 ± 1");
+        assert_eq!((value, unit), (10.0, None));
+        assert!(formatted.contains('±'));
+
+        let (value, _, unit) = evaluate("1234 to words");
+        assert_eq!((value, unit), (1234.0, None));
+
+        let (value, formatted, unit) = evaluate("[1,2] * 2");
+        assert_eq!((value, unit), (0.0, None));
+        assert_eq!(formatted, "[2.00, 4.00]");
+    }
+}
+
 #[cfg(test)]
 mod i18n_tests {
     // NOTE: These tests modify global locale state and should be run with:
@@ -5,9 +5,9 @@ mod currency_fetcher;
 mod evaluator;
 mod i18n;
 mod models;
-mod parser;
 mod prettify;
 mod security;
+mod spell;
 mod tui;
 mod utils;
 
@@ -3,20 +3,53 @@
 //! This module defines the application state, agent trait, and helper types
 //! for managing variables, history, and unit conversions.
 
-use crate::evaluator::{CacheManager, EvaluatorError, EventSubscriber, Result, StateEvent};
+use crate::config::Constant;
+use crate::evaluator::ast::Expr;
+use crate::evaluator::document::{renumber_line_references, LineEdit};
+use crate::evaluator::{
+    CacheManager, Diagnostic, EvaluatorError, EventSubscriber, Number, Result, StateEvent,
+};
+use crate::prettify::DisplayFormat;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 /// Thread-safe map of variable names to (value, optional_unit).
-pub type VarMap = Arc<RwLock<HashMap<String, (f64, Option<String>)>>>;
+pub type VarMap = Arc<RwLock<HashMap<String, (Number, Option<String>)>>>;
+
+/// Thread-safe map of list variable names to their items.
+pub type ListMap = Arc<RwLock<HashMap<String, Vec<(Number, Option<String>)>>>>;
+
+/// Thread-safe map of variable names to the uncertainty of their value, in
+/// the variable's unit.
+pub type UncertaintyMap = Arc<RwLock<HashMap<String, f64>>>;
+
+/// Function defined in the document, e.g. `tax(amount) = amount * 0.08`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserFunction {
+    pub params: Vec<String>,
+    pub body: Expr,
+}
+
+/// Thread-safe map of user-defined function names to their definitions.
+pub type FunctionMap = Arc<RwLock<HashMap<String, UserFunction>>>;
 
 /// Represents a single history entry with an optional unit.
 #[derive(Clone, Debug, PartialEq)]
 pub struct HistoryEntry {
-    pub value: f64,
+    pub value: Number,
     pub unit: Option<String>,
 }
 
+/// Role of a document line in sections and totals.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    /// A `# Groceries` header, opening a section.
+    Header,
+    /// A line adding up others (`subtotal`, `grand total`), left out of
+    /// later totals so nothing is counted twice.
+    Total,
+}
+
 /// Map of unit names to conversion factors.
 pub type Units = HashMap<String, f64>;
 
@@ -26,18 +59,26 @@ pub type Rates = HashMap<String, f64>;
 /// Map of temperature unit names to their type strings.
 pub type TempUnits = HashMap<String, String>;
 
+/// Successful agent output: formatted result, whether to add it to history,
+/// raw value and unit.
+pub type AgentOutput = (String, bool, Option<Number>, Option<String>);
+
 /// Trait for evaluation agents that process specific types of input.
 ///
 /// Agents are checked in priority order until one can handle the input.
 pub trait Agent: Send + Sync {
+    /// Short name reported with errors, e.g. `"unit"`.
+    fn name(&self) -> &'static str;
     fn priority(&self) -> i32;
     fn can_handle(&self, input: &str, state: &AppState) -> bool;
+    /// Evaluate `input`. `Ok(None)` declines it so lower-priority agents get a
+    /// turn; `Err` explains why the input could not be evaluated.
     fn process(
         &self,
         input: &str,
         state: &mut AppState,
         config: &crate::config::Config,
-    ) -> Option<(String, bool, Option<f64>, Option<String>)>;
+    ) -> std::result::Result<Option<AgentOutput>, Diagnostic>;
 }
 
 /// Main application state containing all runtime data.
@@ -56,6 +97,9 @@ pub trait Agent: Send + Sync {
 #[derive(Clone)]
 pub struct AppState {
     pub variables: VarMap,
+    pub functions: FunctionMap,
+    pub lists: ListMap,
+    pub uncertainties: UncertaintyMap,
     pub history: Arc<RwLock<Vec<HistoryEntry>>>,
     pub status: Arc<RwLock<String>>,
     pub current_filename: Option<String>,
@@ -71,14 +115,18 @@ pub struct AppState {
     pub rates: HashMap<String, f64>,
     pub time_format: String,
     pub date_format: String,
+    /// Number display settings, kept here so bindings can reload them.
+    pub display: DisplayFormat,
+    /// Named constants, kept here so bindings can reload them.
+    pub constants: HashMap<String, Constant>,
     pub cache: Arc<CacheManager>,
     pub subscribers: Arc<RwLock<Vec<Arc<dyn EventSubscriber>>>>,
     pub is_display_only: bool,
     /// Stores the original input (before preprocessing) temporarily during evaluation
     /// Used by agents that need to access variable names before they are replaced
     pub original_input: Arc<RwLock<Option<String>>>,
-    /// Tracks which line created which variable for cleanup when lines are edited
-    /// Maps line_index -> variable_name
+    /// Tracks which line created which variable, list or function for cleanup when lines are edited
+    /// Maps line_index -> name
     pub line_variables: Arc<RwLock<HashMap<usize, String>>>,
     /// The current line index being evaluated (set by TUI before evaluation)
     /// None if not in TUI mode or line context not available
@@ -86,6 +134,13 @@ pub struct AppState {
     /// Tracks the last evaluated content of each line to detect edits
     /// Maps line_index -> evaluated_content
     pub line_content: Arc<RwLock<HashMap<usize, String>>>,
+    /// Value each line of the document last evaluated to, for `line 3`,
+    /// `prev line` and `above`. Lines without a single value have no entry.
+    /// Maps line_index -> result
+    pub line_results: Arc<RwLock<HashMap<usize, HistoryEntry>>>,
+    /// Section headers and total lines of the document, for `subtotal`,
+    /// `section avg` and `grand total`. Maps line_index -> kind
+    pub line_kinds: Arc<RwLock<HashMap<usize, LineKind>>>,
     /// Override path for config file (used on Android/iOS)
     #[allow(dead_code)]
     pub config_override_path: Option<String>,
@@ -107,6 +162,9 @@ impl AppStateBuilder {
         let subscribers = Arc::new(RwLock::new(vec![cache.clone() as Arc<dyn EventSubscriber>]));
         AppState {
             variables: Arc::new(RwLock::new(HashMap::new())),
+            functions: Arc::new(RwLock::new(HashMap::new())),
+            lists: Arc::new(RwLock::new(HashMap::new())),
+            uncertainties: Arc::new(RwLock::new(HashMap::new())),
             history: Arc::new(RwLock::new(Vec::new())),
             status: Arc::new(RwLock::new(String::new())),
             current_filename: None,
@@ -123,12 +181,16 @@ impl AppStateBuilder {
             rates: self.config.currencies.clone(),
             time_format: self.config.time_format.clone(),
             date_format: self.config.date_format.clone(),
+            display: self.config.display.clone(),
+            constants: self.config.constants.clone(),
             cache,
             subscribers,
             is_display_only: false,
             line_variables: Arc::new(RwLock::new(HashMap::new())),
             current_line: Arc::new(RwLock::new(None)),
             line_content: Arc::new(RwLock::new(HashMap::new())),
+            line_results: Arc::new(RwLock::new(HashMap::new())),
+            line_kinds: Arc::new(RwLock::new(HashMap::new())),
             config_override_path: None,
         }
     }
@@ -151,6 +213,9 @@ impl AppState {
         let subscribers = Arc::new(RwLock::new(vec![cache.clone() as Arc<dyn EventSubscriber>]));
         Self {
             variables: Arc::new(RwLock::new(HashMap::new())),
+            functions: Arc::new(RwLock::new(HashMap::new())),
+            lists: Arc::new(RwLock::new(HashMap::new())),
+            uncertainties: Arc::new(RwLock::new(HashMap::new())),
             history: Arc::new(RwLock::new(Vec::new())),
             status: Arc::new(RwLock::new(String::new())),
             current_filename: None,
@@ -166,6 +231,8 @@ impl AppState {
             rates: config.currencies.clone(),
             time_format: config.time_format.clone(),
             date_format: config.date_format.clone(),
+            display: config.display.clone(),
+            constants: config.constants.clone(),
             cache,
             subscribers,
             is_display_only: false,
@@ -173,6 +240,8 @@ impl AppState {
             line_variables: Arc::new(RwLock::new(HashMap::new())),
             current_line: Arc::new(RwLock::new(None)),
             line_content: Arc::new(RwLock::new(HashMap::new())),
+            line_results: Arc::new(RwLock::new(HashMap::new())),
+            line_kinds: Arc::new(RwLock::new(HashMap::new())),
             config_override_path: None,
         }
     }
@@ -248,7 +317,7 @@ impl AppState {
     /// }
     /// ```
     #[allow(unused)]
-    pub fn get_variable(&self, name: &str) -> Result<Option<(f64, Option<String>)>> {
+    pub fn get_variable(&self, name: &str) -> Result<Option<(Number, Option<String>)>> {
         Ok(self
             .variables
             .read()
@@ -269,11 +338,19 @@ impl AppState {
     /// state.set_variable("x".to_string(), 42.0, Some("m".to_string())).unwrap();
     /// ```
     #[allow(unused)]
-    pub fn set_variable(&self, name: String, value: f64, unit: Option<String>) -> Result<()> {
+    pub fn set_variable(
+        &self,
+        name: String,
+        value: impl Into<Number>,
+        unit: Option<String>,
+    ) -> Result<()> {
         self.variables
             .write()
             .map_err(|e| EvaluatorError::LockError(format!("Variable lock: {}", e)))?
-            .insert(name.clone(), (value, unit));
+            .insert(name.clone(), (value.into(), unit));
+        if let Ok(mut uncertainties) = self.uncertainties.write() {
+            uncertainties.remove(&name);
+        }
         self.publish_event(StateEvent::VariableChanged(name));
         Ok(())
     }
@@ -289,7 +366,8 @@ impl AppState {
     /// let state = AppState::builder(&config).build();
     /// state.add_history(42.0, None).unwrap();
     /// ```
-    pub fn add_history(&self, value: f64, unit: Option<String>) -> Result<()> {
+    pub fn add_history(&self, value: impl Into<Number>, unit: Option<String>) -> Result<()> {
+        let value = value.into();
         self.history
             .write()
             .map_err(|e| EvaluatorError::LockError(format!("History lock: {}", e)))?
@@ -297,7 +375,7 @@ impl AppState {
                 value,
                 unit: unit.clone(),
             });
-        self.publish_event(StateEvent::HistoryAdded(value));
+        self.publish_event(StateEvent::HistoryAdded(value.to_f64()));
         Ok(())
     }
 
@@ -321,6 +399,60 @@ impl AppState {
             .clone())
     }
 
+    /// Record what the line being evaluated (`current_line`) evaluated to,
+    /// or that it has no single value. Does nothing outside a document.
+    ///
+    /// # Example
+    /// ```
+    /// use numby::config::Config;
+    /// use numby::models::{AppState, HistoryEntry};
+    ///
+    /// let config = Config::default();
+    /// let state = AppState::builder(&config).build();
+    /// *state.current_line.write().unwrap() = Some(2);
+    /// state.set_line_result(Some(HistoryEntry { value: 5.0.into(), unit: None })).unwrap();
+    /// assert!(state.line_results.read().unwrap().contains_key(&2));
+    /// ```
+    pub fn set_line_result(&self, result: Option<HistoryEntry>) -> Result<()> {
+        let line = *self
+            .current_line
+            .read()
+            .map_err(|e| EvaluatorError::LockError(format!("Current line lock: {}", e)))?;
+        let Some(line) = line else {
+            return Ok(());
+        };
+        let mut line_results = self
+            .line_results
+            .write()
+            .map_err(|e| EvaluatorError::LockError(format!("Line results lock: {}", e)))?;
+        match result {
+            Some(result) => line_results.insert(line, result),
+            None => line_results.remove(&line),
+        };
+        Ok(())
+    }
+
+    /// Record whether the line being evaluated is a section header or a
+    /// total. Does nothing outside a document.
+    pub fn set_line_kind(&self, kind: Option<LineKind>) -> Result<()> {
+        let line = *self
+            .current_line
+            .read()
+            .map_err(|e| EvaluatorError::LockError(format!("Current line lock: {}", e)))?;
+        let Some(line) = line else {
+            return Ok(());
+        };
+        let mut line_kinds = self
+            .line_kinds
+            .write()
+            .map_err(|e| EvaluatorError::LockError(format!("Line kinds lock: {}", e)))?;
+        match kind {
+            Some(kind) => line_kinds.insert(line, kind),
+            None => line_kinds.remove(&line),
+        };
+        Ok(())
+    }
+
     /// Publish an event to all subscribers.
     ///
     /// # Example
@@ -389,8 +521,17 @@ impl AppState {
         }
     }
 
+    /// Renumber the `line 3` references in the text of each line to follow
+    /// the lines they refer to across `edit`.
+    fn renumber_references(line_content: &mut HashMap<usize, String>, edit: LineEdit) {
+        for text in line_content.values_mut() {
+            *text = renumber_line_references(text, |number| edit.renumber(number));
+        }
+    }
+
     /// Shift line indices when a line is inserted.
-    /// All lines at or after `at_line` are shifted down by 1.
+    /// All lines at or after `at_line` are shifted down by 1, and references
+    /// to them in the text of other lines are renumbered.
     pub fn shift_lines_on_insert(&self, at_line: usize) -> Result<()> {
         let mut line_vars = self
             .line_variables
@@ -405,27 +546,58 @@ impl AppState {
             .write()
             .map_err(|e| EvaluatorError::LockError(format!("Line content lock: {}", e)))?;
 
+        Self::renumber_references(&mut line_content, LineEdit::Inserted(at_line));
         Self::shift_hashmap(&mut line_content, |idx| idx >= at_line, 1);
+        drop(line_content);
+
+        let mut line_results = self
+            .line_results
+            .write()
+            .map_err(|e| EvaluatorError::LockError(format!("Line results lock: {}", e)))?;
+
+        Self::shift_hashmap(&mut line_results, |idx| idx >= at_line, 1);
+        drop(line_results);
+
+        let mut line_kinds = self
+            .line_kinds
+            .write()
+            .map_err(|e| EvaluatorError::LockError(format!("Line kinds lock: {}", e)))?;
+
+        Self::shift_hashmap(&mut line_kinds, |idx| idx >= at_line, 1);
 
         Ok(())
     }
 
     /// Shift line indices when a line is deleted.
     /// The variable at `deleted_line` is removed, and all lines after it shift up by 1.
+    /// References to the deleted line now refer to the line above it.
     pub fn shift_lines_on_delete(&self, deleted_line: usize) -> Result<()> {
         let mut line_vars = self
             .line_variables
             .write()
             .map_err(|e| EvaluatorError::LockError(format!("Line variables lock: {}", e)))?;
 
-        // If this line had a variable, delete it
+        // If this line had a variable, list or function, delete it
         if let Some(var_name) = line_vars.remove(&deleted_line) {
-            // Also delete the variable itself
+            // Also delete the definition itself
             let mut vars = self
                 .variables
                 .write()
                 .map_err(|e| EvaluatorError::LockError(format!("Variables lock: {}", e)))?;
             vars.remove(&var_name);
+            drop(vars);
+            self.uncertainties
+                .write()
+                .map_err(|e| EvaluatorError::LockError(format!("Uncertainties lock: {}", e)))?
+                .remove(&var_name);
+            self.functions
+                .write()
+                .map_err(|e| EvaluatorError::LockError(format!("Functions lock: {}", e)))?
+                .remove(&var_name);
+            self.lists
+                .write()
+                .map_err(|e| EvaluatorError::LockError(format!("Lists lock: {}", e)))?
+                .remove(&var_name);
         }
 
         Self::shift_hashmap(&mut line_vars, |idx| idx > deleted_line, -1);
@@ -437,7 +609,26 @@ impl AppState {
             .map_err(|e| EvaluatorError::LockError(format!("Line content lock: {}", e)))?;
 
         line_content.remove(&deleted_line);
+        Self::renumber_references(&mut line_content, LineEdit::Deleted(deleted_line));
         Self::shift_hashmap(&mut line_content, |idx| idx > deleted_line, -1);
+        drop(line_content);
+
+        let mut line_results = self
+            .line_results
+            .write()
+            .map_err(|e| EvaluatorError::LockError(format!("Line results lock: {}", e)))?;
+
+        line_results.remove(&deleted_line);
+        Self::shift_hashmap(&mut line_results, |idx| idx > deleted_line, -1);
+        drop(line_results);
+
+        let mut line_kinds = self
+            .line_kinds
+            .write()
+            .map_err(|e| EvaluatorError::LockError(format!("Line kinds lock: {}", e)))?;
+
+        line_kinds.remove(&deleted_line);
+        Self::shift_hashmap(&mut line_kinds, |idx| idx > deleted_line, -1);
 
         Ok(())
     }
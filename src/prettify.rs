@@ -1,5 +1,9 @@
 //! Number formatting utilities for pretty display.
 
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::NumberStyle;
+
 /// Format a number with appropriate scale suffixes (k, M, B, T).
 ///
 /// This function formats numbers in a human-readable way:
@@ -9,6 +13,10 @@
 /// - Numbers >= 1 thousand use 'k' suffix
 /// - Numbers >= 100 show no decimal places
 /// - Numbers < 100 show 2 decimal places
+/// - Numbers from 1e15 up and nonzero numbers below 0.01 use scientific
+///   notation, so tiny and huge values don't show as `0.00` or `6e11T`
+///
+/// This is [`format_number`] with the default [`DisplayFormat`].
 ///
 /// # Examples
 ///
@@ -20,21 +28,500 @@
 /// assert_eq!(prettify_number(3200000000.0), "3.2B");
 /// assert_eq!(prettify_number(42.5), "42.50");
 /// assert_eq!(prettify_number(150.0), "150");
+/// assert_eq!(prettify_number(6.674e-11), "6.67e-11");
+/// assert_eq!(prettify_number(6.022e23), "6.02e23");
 /// ```
+#[allow(unused)]
 pub fn prettify_number(num: f64) -> String {
+    format_number(num, &DisplayFormat::default())
+}
+
+/// How many digits a number is shown with.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// Two decimals below 100, whole numbers from 100 and one decimal
+    /// before a k/M/B/T suffix.
+    Auto,
+    /// Fixed number of decimals: `{"decimals": 2}` shows `1234.50`.
+    Decimals(usize),
+    /// Significant digits: `{"significant": 3}` shows `1230` and `0.00123`.
+    Significant(usize),
+}
+
+/// Number display settings, the `display` section of the config.
+///
+/// The defaults reproduce [`prettify_number`], except that currency amounts
+/// are shown in full with two decimals (`1234.56 USD`); a config only lists
+/// the settings it changes.
+///
+/// # Examples
+///
+/// ```
+/// use numby::prettify::{format_number, DisplayFormat, Precision};
+///
+/// let invoice = DisplayFormat {
+///     precision: Precision::Decimals(2),
+///     thousands_separator: ",".to_string(),
+///     abbreviate: false,
+///     ..DisplayFormat::default()
+/// };
+/// assert_eq!(format_number(1234567.891, &invoice), "1,234,567.89");
+///
+/// let physics = DisplayFormat {
+///     scientific_above: Some(1e6),
+///     ..DisplayFormat::default()
+/// };
+/// assert_eq!(format_number(299792458.0, &physics), "3e8");
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DisplayFormat {
+    pub precision: Precision,
+    /// Put between groups of three integer digits, e.g. `","` or `" "`.
+    /// Empty for no grouping, or the locale's grouping with `group_digits`.
+    pub thousands_separator: String,
+    /// Group digits with the number locale's character (`1.234.567` in
+    /// German) when no `thousands_separator` is set.
+    pub group_digits: bool,
+    /// Decimal separator; the number locale's when unset.
+    pub decimal_separator: Option<char>,
+    /// Shorten numbers from 1000 with k, M, B and T suffixes.
+    pub abbreviate: bool,
+    /// Magnitudes from this one up are shown in scientific notation.
+    pub scientific_above: Option<f64>,
+    /// Nonzero magnitudes below this one are shown in scientific notation.
+    pub scientific_below: Option<f64>,
+    /// Use engineering notation (exponents that are multiples of 3) where
+    /// scientific notation applies.
+    pub engineering: bool,
+    /// Settings for currency amounts that differ from the ones above.
+    pub currency: DisplayOverride,
+    /// Show currency amounts with their symbol where they have one
+    /// (`$12.50`) rather than their code (`12.50 USD`).
+    pub currency_symbols: bool,
+    /// Put the currency symbol before the amount; the number locale's
+    /// placement when unset, so `$12.50` in English and `12,50 €` in German.
+    pub symbol_first: Option<bool>,
+}
+
+impl Default for DisplayFormat {
+    fn default() -> Self {
+        Self {
+            precision: Precision::Auto,
+            thousands_separator: String::new(),
+            group_digits: false,
+            decimal_separator: None,
+            abbreviate: true,
+            scientific_above: Some(1e15),
+            scientific_below: Some(1e-2),
+            engineering: false,
+            currency: DisplayOverride {
+                precision: Some(Precision::Decimals(2)),
+                abbreviate: Some(false),
+                thousands_separator: None,
+            },
+            currency_symbols: false,
+            symbol_first: None,
+        }
+    }
+}
+
+/// Per-category overrides of a [`DisplayFormat`]; unset fields keep the
+/// general setting.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct DisplayOverride {
+    pub precision: Option<Precision>,
+    pub thousands_separator: Option<String>,
+    pub abbreviate: Option<bool>,
+}
+
+/// Display format chosen for a single line with a conversion target such
+/// as `to sci` or `to 4 dp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    Scientific,
+    Engineering,
+    Decimals(usize),
+    Significant(usize),
+}
+
+impl Notation {
+    /// Notation for a conversion target: `sci`, `eng`, `4 dp` or `3 sf`.
+    pub fn from_target(target: &str) -> Option<Self> {
+        let target = target.to_lowercase();
+        match target.as_str() {
+            "sci" | "scientific" => return Some(Notation::Scientific),
+            "eng" | "engineering" => return Some(Notation::Engineering),
+            _ => {}
+        }
+        let (digits, kind) = target.split_once(' ')?;
+        let digits = digits.parse().ok()?;
+        match kind {
+            "dp" => Some(Notation::Decimals(digits)),
+            "sf" => Some(Notation::Significant(digits)),
+            _ => None,
+        }
+    }
+}
+
+impl DisplayFormat {
+    /// The format for one value, with the currency overrides applied to
+    /// currency amounts.
+    pub fn for_value(&self, is_currency: bool) -> DisplayFormat {
+        let mut format = self.clone();
+        if is_currency {
+            let overrides = &self.currency;
+            format.precision = overrides.precision.unwrap_or(format.precision);
+            if let Some(separator) = &overrides.thousands_separator {
+                format.thousands_separator = separator.clone();
+            }
+            format.abbreviate = overrides.abbreviate.unwrap_or(format.abbreviate);
+        }
+        format
+    }
+
+    /// The format with the separators of `style` filling in unset ones.
+    pub fn localized(&self, style: NumberStyle) -> DisplayFormat {
+        let mut format = self.clone();
+        format.decimal_separator.get_or_insert(style.decimal);
+        format.symbol_first.get_or_insert(style.symbol_first);
+        if format.group_digits && format.thousands_separator.is_empty() {
+            format.thousands_separator = style.grouping.to_string();
+        }
+        format
+    }
+
+    /// The format with a line's own notation applied. Fixed precision shows
+    /// the number in full rather than abbreviated.
+    pub fn with_notation(mut self, notation: Option<Notation>) -> DisplayFormat {
+        match notation {
+            Some(Notation::Scientific) | Some(Notation::Engineering) => {
+                self.scientific_above = Some(0.0);
+                self.engineering = notation == Some(Notation::Engineering);
+            }
+            Some(Notation::Decimals(digits)) => {
+                self.precision = Precision::Decimals(digits);
+                self.abbreviate = false;
+            }
+            Some(Notation::Significant(digits)) => {
+                self.precision = Precision::Significant(digits);
+                self.abbreviate = false;
+            }
+            None => {}
+        }
+        self
+    }
+
+    /// The format for whole amounts, such as the leading parts of `5 ft
+    /// 10.87 in`: no decimals, no abbreviation and no exponent.
+    pub fn whole(&self) -> DisplayFormat {
+        DisplayFormat {
+            precision: Precision::Decimals(0),
+            abbreviate: false,
+            scientific_above: None,
+            scientific_below: None,
+            ..self.clone()
+        }
+    }
+
+    /// `num` rounded to the decimals it is shown with in full (without a
+    /// suffix or exponent): `59.999` is `60.0` with the default two decimals.
+    pub fn rounded(&self, num: f64) -> f64 {
+        let decimals = match self.precision {
+            Precision::Auto if num.abs() >= 1e2 => 0,
+            Precision::Auto => 2,
+            Precision::Decimals(decimals) => decimals as i32,
+            Precision::Significant(_) if num == 0.0 || !num.is_finite() => return num,
+            Precision::Significant(digits) => {
+                digits.max(1) as i32 - 1 - num.abs().log10().floor() as i32
+            }
+        };
+        let scale = 10f64.powi(decimals);
+        (num * scale).round() / scale
+    }
+
+    /// Whether a number of magnitude `abs_num` is shown with an exponent.
+    pub fn uses_exponent(&self, abs_num: f64) -> bool {
+        self.scientific_above.is_some_and(|above| abs_num >= above)
+            || self
+                .scientific_below
+                .is_some_and(|below| abs_num > 0.0 && abs_num < below)
+    }
+}
+
+/// Format a number according to `format`.
+///
+/// # Examples
+///
+/// ```
+/// use numby::prettify::{format_number, DisplayFormat, Precision};
+///
+/// let format = DisplayFormat {
+///     precision: Precision::Significant(3),
+///     engineering: true,
+///     scientific_below: Some(0.01),
+///     ..DisplayFormat::default()
+/// };
+/// assert_eq!(format_number(0.000047, &format), "47.0e-6");
+/// assert_eq!(format_number(3.14159, &format), "3.14");
+/// ```
+pub fn format_number(num: f64, format: &DisplayFormat) -> String {
+    if !num.is_finite() {
+        // `inf`, `-inf` or `NaN`, never scaled or suffixed
+        return num.to_string();
+    }
     let abs_num = num.abs();
-    if abs_num >= 1e12 {
-        format!("{:.1}T", num / 1e12)
-    } else if abs_num >= 1e9 {
-        format!("{:.1}B", num / 1e9)
-    } else if abs_num >= 1e6 {
-        format!("{:.1}M", num / 1e6)
-    } else if abs_num >= 1e3 {
-        format!("{:.1}k", num / 1e3)
-    } else if abs_num >= 1e2 {
-        // For 100+, round to nearest integer
-        format!("{:.0}", num)
-    } else {
-        format!("{:.2}", num) // For smaller, 2 decimals
+    let decimal = format.decimal_separator.unwrap_or('.');
+    if format.uses_exponent(abs_num) {
+        let text = format_exponent(num, format.precision, format.engineering);
+        return text.replacen('.', &decimal.to_string(), 1);
+    }
+    if format.abbreviate {
+        let suffixes = [(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "k")];
+        if let Some((scale, suffix)) = suffixes.iter().find(|(scale, _)| abs_num >= *scale) {
+            let scaled = num / scale;
+            let text = match format.precision {
+                Precision::Auto => format!("{:.1}", scaled),
+                precision => format_fixed(scaled, precision),
+            };
+            return format!("{}{}", text.replacen('.', &decimal.to_string(), 1), suffix);
+        }
+    }
+    let text = match format.precision {
+        // For 100+, round to nearest integer; for smaller, 2 decimals
+        Precision::Auto if abs_num >= 1e2 => format!("{:.0}", num),
+        Precision::Auto => format!("{:.2}", num),
+        precision => format_fixed(num, precision),
+    };
+    localize_digits(&text, &format.thousands_separator, decimal)
+}
+
+/// `text`, a number written with `.` and no grouping such as `-1234.50`,
+/// with the separators of `format`.
+pub fn localize_number(text: &str, format: &DisplayFormat) -> String {
+    let decimal = format.decimal_separator.unwrap_or('.');
+    localize_digits(text, &format.thousands_separator, decimal)
+}
+
+/// `num` with a fixed number of decimals or significant digits.
+fn format_fixed(num: f64, precision: Precision) -> String {
+    match precision {
+        Precision::Decimals(decimals) => format!("{:.*}", decimals, num),
+        Precision::Significant(digits) => {
+            let digits = digits.max(1) as i32;
+            if num == 0.0 || !num.is_finite() {
+                return format!("{:.*}", (digits - 1) as usize, num);
+            }
+            let magnitude = num.abs().log10().floor() as i32;
+            let decimals = digits - 1 - magnitude;
+            if decimals >= 0 {
+                format!("{:.*}", decimals as usize, num)
+            } else {
+                // Round away digits left of the decimal point: 123456 as 123000
+                let step = 10f64.powi(-decimals);
+                format!("{:.0}", (num / step).round() * step)
+            }
+        }
+        Precision::Auto => format!("{:.2}", num),
+    }
+}
+
+/// `num` as a mantissa and a power of ten: `1.5e-7`, or `150e-9` in
+/// engineering notation.
+fn format_exponent(num: f64, precision: Precision, engineering: bool) -> String {
+    if num == 0.0 {
+        return format!("{}e0", format_mantissa(0.0, precision));
+    }
+    let mut exponent = num.abs().log10().floor() as i32;
+    if engineering {
+        exponent = exponent.div_euclid(3) * 3;
+    }
+    let mut mantissa = format_mantissa(num / 10f64.powi(exponent), precision);
+    // Rounding may carry into another digit: 9.999 as 10.00
+    let limit = if engineering { 1000.0 } else { 10.0 };
+    if mantissa.parse::<f64>().is_ok_and(|m| m.abs() >= limit) {
+        exponent += if engineering { 3 } else { 1 };
+        mantissa = format_mantissa(num / 10f64.powi(exponent), precision);
+    }
+    format!("{}e{}", mantissa, exponent)
+}
+
+fn format_mantissa(mantissa: f64, precision: Precision) -> String {
+    match precision {
+        // Up to two decimals, without trailing zeros: 3e8, 1.5e-7
+        Precision::Auto => {
+            let text = format!("{:.2}", mantissa);
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+        precision => format_fixed(mantissa, precision),
+    }
+}
+
+/// Insert `separator` between groups of three digits of the integer part
+/// and write the decimal point as `decimal`.
+fn localize_digits(text: &str, separator: &str, decimal: char) -> String {
+    if separator.is_empty() {
+        return text.replacen('.', &decimal.to_string(), 1);
+    }
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", text),
+    };
+    let (integer, fraction) = match unsigned.find('.') {
+        Some(dot) => unsigned.split_at(dot),
+        None => (unsigned, ""),
+    };
+    if !integer.bytes().all(|b| b.is_ascii_digit()) {
+        return text.replacen('.', &decimal.to_string(), 1);
+    }
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    let fraction = fraction.replacen('.', &decimal.to_string(), 1);
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_format_matches_prettify() {
+        let format = DisplayFormat::default();
+        for num in [
+            0.0f64, 0.5, -42.5, 99.999, 150.0, 999.4, 1500.0, -2.5e6, 3.2e9, 7e12,
+        ] {
+            let expected = if num.abs() >= 1e12 {
+                format!("{:.1}T", num / 1e12)
+            } else if num.abs() >= 1e9 {
+                format!("{:.1}B", num / 1e9)
+            } else if num.abs() >= 1e6 {
+                format!("{:.1}M", num / 1e6)
+            } else if num.abs() >= 1e3 {
+                format!("{:.1}k", num / 1e3)
+            } else if num.abs() >= 1e2 {
+                format!("{:.0}", num)
+            } else {
+                format!("{:.2}", num)
+            };
+            assert_eq!(format_number(num, &format), expected);
+        }
+    }
+
+    #[test]
+    fn test_non_finite_numbers() {
+        let format = DisplayFormat::default();
+        assert_eq!(format_number(f64::INFINITY, &format), "inf");
+        assert_eq!(format_number(f64::NEG_INFINITY, &format), "-inf");
+        assert_eq!(format_number(f64::NAN, &format), "NaN");
+        let fixed = DisplayFormat {
+            precision: Precision::Decimals(2),
+            abbreviate: false,
+            thousands_separator: ",".to_string(),
+            ..DisplayFormat::default()
+        };
+        assert_eq!(format_number(f64::INFINITY, &fixed), "inf");
+    }
+
+    #[test]
+    fn test_precision_and_separators() {
+        let mut format = DisplayFormat {
+            precision: Precision::Decimals(2),
+            thousands_separator: " ".to_string(),
+            abbreviate: false,
+            scientific_below: None,
+            ..DisplayFormat::default()
+        };
+        assert_eq!(format_number(-1234567.5, &format), "-1 234 567.50");
+        assert_eq!(format_number(12.0, &format), "12.00");
+
+        format.precision = Precision::Significant(3);
+        assert_eq!(format_number(123456.0, &format), "123 000");
+        assert_eq!(format_number(0.0012345, &format), "0.00123");
+
+        format.abbreviate = true;
+        assert_eq!(format_number(123456.0, &format), "123k");
+    }
+
+    #[test]
+    fn test_scientific_and_engineering() {
+        let mut format = DisplayFormat {
+            scientific_above: Some(1e9),
+            scientific_below: Some(1e-3),
+            ..DisplayFormat::default()
+        };
+        assert_eq!(format_number(6.02214e23, &format), "6.02e23");
+        assert_eq!(format_number(-0.00015, &format), "-1.5e-4");
+        assert_eq!(format_number(9.999e9, &format), "1e10");
+        assert_eq!(format_number(0.0, &format), "0.00");
+
+        let default = DisplayFormat::default();
+        assert_eq!(format_number(6.674e-11, &default), "6.67e-11");
+        assert_eq!(format_number(0.005, &default), "5e-3");
+        assert_eq!(format_number(0.01, &default), "0.01");
+        assert_eq!(format_number(6.02214e23, &default), "6.02e23");
+        assert_eq!(format_number(7e12, &default), "7.0T");
+
+        format.engineering = true;
+        assert_eq!(format_number(6.02214e23, &format), "602.21e21");
+        assert_eq!(format_number(0.00015, &format), "150e-6");
+        assert_eq!(format_number(999.999e9, &format), "1e12");
+    }
+
+    #[test]
+    fn test_currency_override_and_notation() {
+        let format = DisplayFormat {
+            currency: DisplayOverride {
+                precision: Some(Precision::Decimals(2)),
+                abbreviate: Some(false),
+                thousands_separator: Some(",".to_string()),
+            },
+            ..DisplayFormat::default()
+        };
+        assert_eq!(format_number(1999.5, &format.for_value(false)), "2.0k");
+        assert_eq!(format_number(1999.5, &format.for_value(true)), "1,999.50");
+
+        // Currency amounts are shown in full by default
+        let default = DisplayFormat::default();
+        assert_eq!(format_number(1234.56, &default.for_value(true)), "1234.56");
+        assert_eq!(format_number(1234.56, &default.for_value(false)), "1.2k");
+
+        let line = format
+            .for_value(false)
+            .with_notation(Notation::from_target("4 dp"));
+        assert_eq!(format_number(1234.56789, &line), "1234.5679");
+        let line = format
+            .for_value(false)
+            .with_notation(Notation::from_target("sci"));
+        assert_eq!(format_number(1234.56789, &line), "1.23e3");
+        assert_eq!(
+            Notation::from_target("3 SF"),
+            Some(Notation::Significant(3))
+        );
+        assert_eq!(Notation::from_target("4 km"), None);
+    }
+
+    #[test]
+    fn test_whole_and_rounded() {
+        let format = DisplayFormat {
+            thousands_separator: ",".to_string(),
+            ..DisplayFormat::default()
+        };
+        assert_eq!(format_number(12345.0, &format.whole()), "12,345");
+        assert_eq!(format.rounded(59.999), 60.0);
+        assert_eq!(format.rounded(11.994), 11.99);
+        assert_eq!(format.rounded(123.6), 124.0);
+        let significant = DisplayFormat {
+            precision: Precision::Significant(2),
+            ..DisplayFormat::default()
+        };
+        assert_eq!(significant.rounded(0.01234), 0.012);
+        assert_eq!(significant.rounded(1234.0), 1200.0);
     }
 }
@@ -0,0 +1,781 @@
+//! Numbers spelled out in words (`1234.5 to words`) in the languages the
+//! interface is translated into.
+//!
+//! Whole numbers follow each language's own rules (`vingt et un`,
+//! `einundzwanzig`, `一万零五`); digits after the decimal point are read out
+//! one by one.
+
+/// Numbers from this magnitude up are not spelled out.
+const LIMIT: f64 = 1e15;
+
+/// Significant digits kept when reading the fractional part, so float noise
+/// such as `0.30000000000000004` reads as "zero point three".
+const SIGNIFICANT_DIGITS: usize = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    English,
+    Spanish,
+    French,
+    German,
+    Russian,
+    Belarusian,
+    Japanese,
+    SimplifiedChinese,
+    TraditionalChinese,
+}
+
+impl Language {
+    /// Language of a locale such as `de` or `zh-TW`. Unsupported languages
+    /// fall back to English.
+    fn for_locale(locale: &str) -> Language {
+        let mut parts = locale.split(['-', '_']);
+        let language = parts.next().unwrap_or_default().to_lowercase();
+        match language.as_str() {
+            "es" => Language::Spanish,
+            "fr" => Language::French,
+            "de" => Language::German,
+            "ru" => Language::Russian,
+            "be" => Language::Belarusian,
+            "ja" => Language::Japanese,
+            "zh" if parts
+                .any(|p| matches!(p.to_uppercase().as_str(), "TW" | "HK" | "MO" | "HANT")) =>
+            {
+                Language::TraditionalChinese
+            }
+            "zh" => Language::SimplifiedChinese,
+            _ => Language::English,
+        }
+    }
+
+    fn whole(self, n: u64) -> String {
+        match self {
+            Language::English => english(n),
+            Language::Spanish => spanish(n),
+            Language::French => french(n),
+            Language::German => german(n),
+            Language::Russian => slavic(n, &RUSSIAN),
+            Language::Belarusian => slavic(n, &BELARUSIAN),
+            Language::Japanese => cjk(n, &JAPANESE),
+            Language::SimplifiedChinese => cjk(n, &SIMPLIFIED_CHINESE),
+            Language::TraditionalChinese => cjk(n, &TRADITIONAL_CHINESE),
+        }
+    }
+
+    /// Separator between words; CJK languages write numbers without spaces.
+    fn space(self) -> &'static str {
+        match self {
+            Language::Japanese | Language::SimplifiedChinese | Language::TraditionalChinese => "",
+            _ => " ",
+        }
+    }
+
+    fn point(self) -> &'static str {
+        match self {
+            Language::English => "point",
+            Language::Spanish => "coma",
+            Language::French => "virgule",
+            Language::German => "Komma",
+            Language::Russian => "запятая",
+            Language::Belarusian => "коска",
+            Language::Japanese | Language::SimplifiedChinese => "点",
+            Language::TraditionalChinese => "點",
+        }
+    }
+
+    fn minus(self) -> &'static str {
+        match self {
+            Language::English | Language::German => "minus",
+            Language::Spanish => "menos",
+            Language::French => "moins",
+            Language::Russian => "минус",
+            Language::Belarusian => "мінус",
+            Language::Japanese => "マイナス",
+            Language::SimplifiedChinese => "负",
+            Language::TraditionalChinese => "負",
+        }
+    }
+
+    /// Word joining the whole amount and the cents on a cheque.
+    fn and(self) -> Option<&'static str> {
+        match self {
+            Language::English => Some("and"),
+            Language::Spanish => Some("con"),
+            Language::French => Some("et"),
+            Language::German => Some("und"),
+            Language::Russian => Some("и"),
+            Language::Belarusian => Some("і"),
+            Language::Japanese | Language::SimplifiedChinese | Language::TraditionalChinese => None,
+        }
+    }
+}
+
+/// True if `value` is small enough to be spelled out.
+pub fn can_spell(value: f64) -> bool {
+    value.is_finite() && value.abs() < LIMIT
+}
+
+/// `value` in words in the language of `locale`, or `None` if it is too
+/// large to spell.
+///
+/// # Examples
+///
+/// ```
+/// use numby::spell::spell_number;
+///
+/// assert_eq!(
+///     spell_number(1234.5, "en-US").unwrap(),
+///     "one thousand two hundred thirty-four point five"
+/// );
+/// assert_eq!(spell_number(21.0, "fr").unwrap(), "vingt et un");
+/// ```
+pub fn spell_number(value: f64, locale: &str) -> Option<String> {
+    if !can_spell(value) {
+        return None;
+    }
+    let language = Language::for_locale(locale);
+    let (whole, fraction) = split_decimal(value.abs());
+    let space = language.space();
+
+    let mut text = language.whole(whole);
+    if !fraction.is_empty() {
+        let digits: Vec<String> = fraction
+            .bytes()
+            .map(|digit| language.whole(u64::from(digit - b'0')))
+            .collect();
+        text = [text, language.point().to_string(), digits.join(space)].join(space);
+    }
+    if value < 0.0 && (whole > 0 || !fraction.is_empty()) {
+        text = format!("{}{}{}", language.minus(), space, text);
+    }
+    Some(text)
+}
+
+/// Cheque-style `amount` of `currency`: the whole amount in words and the
+/// cents as a fraction of 100.
+///
+/// # Examples
+///
+/// ```
+/// use numby::spell::spell_amount;
+///
+/// assert_eq!(
+///     spell_amount(1234.5, "USD", "en-US").unwrap(),
+///     "one thousand two hundred thirty-four and 50/100 USD"
+/// );
+/// ```
+pub fn spell_amount(amount: f64, currency: &str, locale: &str) -> Option<String> {
+    if !can_spell(amount) {
+        return None;
+    }
+    let language = Language::for_locale(locale);
+    let cents = (amount.abs() * 100.0).round() as u64;
+    let mut words = vec![language.whole(cents / 100)];
+    if amount < 0.0 && cents > 0 {
+        words.insert(0, language.minus().to_string());
+    }
+    let mut text = words.join(language.space());
+    if let Some(and) = language.and() {
+        text = format!("{} {}", text, and);
+    }
+    Some(format!("{} {:02}/100 {}", text, cents % 100, currency))
+}
+
+/// Whole part and the digits after the decimal point of a non-negative
+/// value, rounded to [`SIGNIFICANT_DIGITS`].
+fn split_decimal(value: f64) -> (u64, String) {
+    let int_digits = (value.trunc() as u64).to_string().len();
+    let decimals = SIGNIFICANT_DIGITS.saturating_sub(int_digits);
+    let text = format!("{:.*}", decimals, value);
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    (
+        whole.parse().unwrap_or_default(),
+        fraction.trim_end_matches('0').to_string(),
+    )
+}
+
+/// Nonzero groups of three digits with their power of 1000, largest first:
+/// `1_002_003` is `[(2, 1), (1, 2), (0, 3)]`.
+fn thousands_groups(n: u64) -> Vec<(usize, u64)> {
+    let mut groups = Vec::new();
+    let mut rest = n;
+    let mut power = 0;
+    while rest > 0 {
+        if !rest.is_multiple_of(1000) {
+            groups.push((power, rest % 1000));
+        }
+        rest /= 1000;
+        power += 1;
+    }
+    groups.reverse();
+    groups
+}
+
+const ENGLISH_ONES: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven",
+    "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+const ENGLISH_TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const ENGLISH_SCALES: [&str; 5] = ["", "thousand", "million", "billion", "trillion"];
+
+fn english(n: u64) -> String {
+    if n == 0 {
+        return ENGLISH_ONES[0].to_string();
+    }
+    let words: Vec<String> = thousands_groups(n)
+        .into_iter()
+        .map(|(power, group)| match power {
+            0 => english_below_thousand(group),
+            _ => format!(
+                "{} {}",
+                english_below_thousand(group),
+                ENGLISH_SCALES[power]
+            ),
+        })
+        .collect();
+    words.join(" ")
+}
+
+fn english_below_thousand(n: u64) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    let below_hundred = match rest {
+        0 => String::new(),
+        1..=19 => ENGLISH_ONES[rest as usize].to_string(),
+        _ if rest % 10 == 0 => ENGLISH_TENS[(rest / 10) as usize].to_string(),
+        _ => format!(
+            "{}-{}",
+            ENGLISH_TENS[(rest / 10) as usize],
+            ENGLISH_ONES[(rest % 10) as usize]
+        ),
+    };
+    match (hundreds, rest) {
+        (0, _) => below_hundred,
+        (_, 0) => format!("{} hundred", ENGLISH_ONES[hundreds as usize]),
+        _ => format!(
+            "{} hundred {}",
+            ENGLISH_ONES[hundreds as usize], below_hundred
+        ),
+    }
+}
+
+const SPANISH_ONES: [&str; 30] = [
+    "cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve", "diez",
+    "once", "doce", "trece", "catorce", "quince", "dieciséis", "diecisiete", "dieciocho",
+    "diecinueve", "veinte", "veintiuno", "veintidós", "veintitrés", "veinticuatro", "veinticinco",
+    "veintiséis", "veintisiete", "veintiocho", "veintinueve",
+];
+const SPANISH_TENS: [&str; 10] = [
+    "", "", "", "treinta", "cuarenta", "cincuenta", "sesenta", "setenta", "ochenta", "noventa",
+];
+const SPANISH_HUNDREDS: [&str; 10] = [
+    "", "ciento", "doscientos", "trescientos", "cuatrocientos", "quinientos", "seiscientos",
+    "setecientos", "ochocientos", "novecientos",
+];
+
+/// Spanish uses the long scale: a billón is a million millions, and a
+/// thousand millions is "mil millones".
+fn spanish(n: u64) -> String {
+    if n == 0 {
+        return SPANISH_ONES[0].to_string();
+    }
+    let (billions, millions, rest) = (
+        n / 1_000_000_000_000,
+        n / 1_000_000 % 1_000_000,
+        n % 1_000_000,
+    );
+    let mut words = Vec::new();
+    for (count, one, many) in [
+        (billions, "billón", "billones"),
+        (millions, "millón", "millones"),
+    ] {
+        match count {
+            0 => {}
+            1 => words.push(format!("un {}", one)),
+            _ => words.push(format!("{} {}", spanish_below_million(count, true), many)),
+        }
+    }
+    if rest > 0 {
+        words.push(spanish_below_million(rest, false));
+    }
+    words.join(" ")
+}
+
+/// `apocope` shortens a final "uno" to "un" before a noun: "veintiún mil".
+fn spanish_below_million(n: u64, apocope: bool) -> String {
+    let (thousands, rest) = (n / 1000, n % 1000);
+    let mut words = Vec::new();
+    match thousands {
+        0 => {}
+        1 => words.push("mil".to_string()),
+        _ => words.push(format!("{} mil", spanish_below_thousand(thousands, true))),
+    }
+    if rest > 0 {
+        words.push(spanish_below_thousand(rest, apocope));
+    }
+    words.join(" ")
+}
+
+fn spanish_below_thousand(n: u64, apocope: bool) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    if n == 100 {
+        return "cien".to_string();
+    }
+    let one = if apocope { "un" } else { "uno" };
+    let below_hundred = match rest {
+        0 => String::new(),
+        1 => one.to_string(),
+        21 if apocope => "veintiún".to_string(),
+        2..=29 => SPANISH_ONES[rest as usize].to_string(),
+        _ if rest % 10 == 0 => SPANISH_TENS[(rest / 10) as usize].to_string(),
+        _ if rest % 10 == 1 => format!("{} y {}", SPANISH_TENS[(rest / 10) as usize], one),
+        _ => format!(
+            "{} y {}",
+            SPANISH_TENS[(rest / 10) as usize],
+            SPANISH_ONES[(rest % 10) as usize]
+        ),
+    };
+    match (hundreds, rest) {
+        (0, _) => below_hundred,
+        (_, 0) => SPANISH_HUNDREDS[hundreds as usize].to_string(),
+        _ => format!("{} {}", SPANISH_HUNDREDS[hundreds as usize], below_hundred),
+    }
+}
+
+const FRENCH_ONES: [&str; 17] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf", "dix", "onze",
+    "douze", "treize", "quatorze", "quinze", "seize",
+];
+const FRENCH_TENS: [&str; 7] = [
+    "", "", "vingt", "trente", "quarante", "cinquante", "soixante",
+];
+const FRENCH_SCALES: [(&str, &str); 5] = [
+    ("", ""),
+    ("mille", "mille"),
+    ("million", "millions"),
+    ("milliard", "milliards"),
+    ("billion", "billions"),
+];
+
+fn french(n: u64) -> String {
+    if n == 0 {
+        return FRENCH_ONES[0].to_string();
+    }
+    let words: Vec<String> = thousands_groups(n)
+        .into_iter()
+        .map(|(power, group)| match (power, group) {
+            (0, _) => french_below_thousand(group, true),
+            // Mille takes no article or plural: "mille", "deux mille"
+            (1, 1) => "mille".to_string(),
+            (1, _) => format!("{} mille", french_below_thousand(group, false)),
+            (_, 1) => format!("un {}", FRENCH_SCALES[power].0),
+            _ => format!(
+                "{} {}",
+                french_below_thousand(group, true),
+                FRENCH_SCALES[power].1
+            ),
+        })
+        .collect();
+    words.join(" ")
+}
+
+/// `plural` keeps the s of a final "cents" or "quatre-vingts", which is
+/// dropped before "mille".
+fn french_below_thousand(n: u64, plural: bool) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    let hundred = match hundreds {
+        0 => String::new(),
+        1 => "cent".to_string(),
+        _ if rest == 0 && plural => format!("{} cents", FRENCH_ONES[hundreds as usize]),
+        _ => format!("{} cent", FRENCH_ONES[hundreds as usize]),
+    };
+    match (hundreds, rest) {
+        (_, 0) => hundred,
+        (0, _) => french_below_hundred(rest, plural),
+        _ => format!("{} {}", hundred, french_below_hundred(rest, plural)),
+    }
+}
+
+fn french_below_hundred(n: u64, plural: bool) -> String {
+    let (tens, ones) = (n / 10, n % 10);
+    match (tens, ones) {
+        _ if n <= 16 => FRENCH_ONES[n as usize].to_string(),
+        (1, _) => format!("dix-{}", FRENCH_ONES[ones as usize]),
+        (2..=6, 0) => FRENCH_TENS[tens as usize].to_string(),
+        (2..=6, 1) => format!("{} et un", FRENCH_TENS[tens as usize]),
+        (2..=6, _) => format!(
+            "{}-{}",
+            FRENCH_TENS[tens as usize], FRENCH_ONES[ones as usize]
+        ),
+        (7, 1) => "soixante et onze".to_string(),
+        (7, _) => format!("soixante-{}", french_below_hundred(10 + ones, plural)),
+        (8, 0) if plural => "quatre-vingts".to_string(),
+        (8, 0) => "quatre-vingt".to_string(),
+        (8, _) => format!("quatre-vingt-{}", FRENCH_ONES[ones as usize]),
+        _ => format!("quatre-vingt-{}", french_below_hundred(10 + ones, plural)),
+    }
+}
+
+const GERMAN_ONES: [&str; 20] = [
+    "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun", "zehn",
+    "elf", "zwölf", "dreizehn", "vierzehn", "fünfzehn", "sechzehn", "siebzehn", "achtzehn",
+    "neunzehn",
+];
+const GERMAN_TENS: [&str; 10] = [
+    "", "", "zwanzig", "dreißig", "vierzig", "fünfzig", "sechzig", "siebzig", "achtzig", "neunzig",
+];
+const GERMAN_SCALES: [(&str, &str); 5] = [
+    ("", ""),
+    ("", ""),
+    ("Million", "Millionen"),
+    ("Milliarde", "Milliarden"),
+    ("Billion", "Billionen"),
+];
+
+/// Numbers below a million are one word ("eintausendzweihundert"); millions
+/// and up are separate nouns ("zwei Millionen").
+fn german(n: u64) -> String {
+    if n == 0 {
+        return GERMAN_ONES[0].to_string();
+    }
+    let mut words = Vec::new();
+    for (power, group) in thousands_groups(n / 1_000_000 * 1_000_000) {
+        words.push(match group {
+            1 => format!("eine {}", GERMAN_SCALES[power].0),
+            _ => format!(
+                "{} {}",
+                german_below_thousand(group, "ein"),
+                GERMAN_SCALES[power].1
+            ),
+        });
+    }
+    let (thousands, rest) = (n / 1000 % 1000, n % 1000);
+    let mut word = String::new();
+    if thousands > 0 {
+        word.push_str(&german_below_thousand(thousands, "ein"));
+        word.push_str("tausend");
+    }
+    if rest > 0 {
+        word.push_str(&german_below_thousand(rest, "eins"));
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words.join(" ")
+}
+
+/// `one` is the form of a final 1: "eins" at the end of a number, "ein"
+/// before "tausend".
+fn german_below_thousand(n: u64, one: &str) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    let mut word = String::new();
+    if hundreds > 0 {
+        word.push_str(if hundreds == 1 {
+            "ein"
+        } else {
+            GERMAN_ONES[hundreds as usize]
+        });
+        word.push_str("hundert");
+    }
+    match rest {
+        0 => {}
+        1 => word.push_str(one),
+        2..=19 => word.push_str(GERMAN_ONES[rest as usize]),
+        _ if rest % 10 == 0 => word.push_str(GERMAN_TENS[(rest / 10) as usize]),
+        _ => {
+            let ones = rest % 10;
+            word.push_str(if ones == 1 {
+                "ein"
+            } else {
+                GERMAN_ONES[ones as usize]
+            });
+            word.push_str("und");
+            word.push_str(GERMAN_TENS[(rest / 10) as usize]);
+        }
+    }
+    word
+}
+
+/// Number words of Russian or Belarusian.
+struct Slavic {
+    ones: [&'static str; 20],
+    /// Feminine "one" and "two", used with "thousand".
+    feminine: [&'static str; 2],
+    tens: [&'static str; 10],
+    hundreds: [&'static str; 10],
+    /// Forms of thousand, million, ... after 1, after 2-4 and after 5 or more.
+    scales: [[&'static str; 3]; 4],
+}
+
+const RUSSIAN: Slavic = Slavic {
+    ones: [
+        "ноль", "один", "два", "три", "четыре", "пять", "шесть", "семь", "восемь", "девять",
+        "десять", "одиннадцать", "двенадцать", "тринадцать", "четырнадцать", "пятнадцать",
+        "шестнадцать", "семнадцать", "восемнадцать", "девятнадцать",
+    ],
+    feminine: ["одна", "две"],
+    tens: [
+        "", "", "двадцать", "тридцать", "сорок", "пятьдесят", "шестьдесят", "семьдесят",
+        "восемьдесят", "девяносто",
+    ],
+    hundreds: [
+        "", "сто", "двести", "триста", "четыреста", "пятьсот", "шестьсот", "семьсот", "восемьсот",
+        "девятьсот",
+    ],
+    scales: [
+        ["тысяча", "тысячи", "тысяч"],
+        ["миллион", "миллиона", "миллионов"],
+        ["миллиард", "миллиарда", "миллиардов"],
+        ["триллион", "триллиона", "триллионов"],
+    ],
+};
+
+const BELARUSIAN: Slavic = Slavic {
+    ones: [
+        "нуль", "адзін", "два", "тры", "чатыры", "пяць", "шэсць", "сем", "восем", "дзевяць",
+        "дзесяць", "адзінаццаць", "дванаццаць", "трынаццаць", "чатырнаццаць", "пятнаццаць",
+        "шаснаццаць", "сямнаццаць", "васямнаццаць", "дзевятнаццаць",
+    ],
+    feminine: ["адна", "дзве"],
+    tens: [
+        "", "", "дваццаць", "трыццаць", "сорак", "пяцьдзясят", "шэсцьдзясят", "семдзесят",
+        "восемдзесят", "дзевяноста",
+    ],
+    hundreds: [
+        "", "сто", "дзвесце", "трыста", "чатырыста", "пяцьсот", "шэсцьсот", "семсот", "восемсот",
+        "дзевяцьсот",
+    ],
+    scales: [
+        ["тысяча", "тысячы", "тысяч"],
+        ["мільён", "мільёны", "мільёнаў"],
+        ["мільярд", "мільярды", "мільярдаў"],
+        ["трыльён", "трыльёны", "трыльёнаў"],
+    ],
+};
+
+fn slavic(n: u64, words: &Slavic) -> String {
+    if n == 0 {
+        return words.ones[0].to_string();
+    }
+    let parts: Vec<String> = thousands_groups(n)
+        .into_iter()
+        .map(|(power, group)| {
+            // Thousand is feminine: "одна тысяча", "две тысячи"
+            let count = slavic_below_thousand(group, words, power == 1);
+            match power {
+                0 => count,
+                _ => format!("{} {}", count, words.scales[power - 1][plural_form(group)]),
+            }
+        })
+        .collect();
+    parts.join(" ")
+}
+
+fn slavic_below_thousand(n: u64, words: &Slavic, feminine: bool) -> String {
+    let (hundreds, tens, ones) = (n / 100, n / 10 % 10, n % 10);
+    let mut parts = Vec::new();
+    if hundreds > 0 {
+        parts.push(words.hundreds[hundreds as usize]);
+    }
+    if tens == 1 {
+        parts.push(words.ones[(n % 100) as usize]);
+    } else {
+        if tens > 1 {
+            parts.push(words.tens[tens as usize]);
+        }
+        match ones {
+            0 => {}
+            1 | 2 if feminine => parts.push(words.feminine[ones as usize - 1]),
+            _ => parts.push(words.ones[ones as usize]),
+        }
+    }
+    parts.join(" ")
+}
+
+/// Which form a noun takes after `n`: 0 for 1 (21, 31...), 1 for 2-4, 2
+/// otherwise, including 11-14.
+fn plural_form(n: u64) -> usize {
+    match (n % 10, n % 100) {
+        (_, 11..=14) => 2,
+        (1, _) => 0,
+        (2..=4, _) => 1,
+        _ => 2,
+    }
+}
+
+/// Number characters of Japanese or Chinese.
+struct Cjk {
+    digits: [&'static str; 10],
+    /// Ten, hundred and thousand.
+    units: [&'static str; 3],
+    /// 10^4, 10^8 and 10^12.
+    myriads: [&'static str; 3],
+    /// Chinese writes 零 for skipped places (一千零五) and keeps 一 before
+    /// 百 and 千; Japanese does neither (千五).
+    chinese: bool,
+}
+
+const JAPANESE: Cjk = Cjk {
+    digits: ["零", "一", "二", "三", "四", "五", "六", "七", "八", "九"],
+    units: ["十", "百", "千"],
+    myriads: ["万", "億", "兆"],
+    chinese: false,
+};
+
+const SIMPLIFIED_CHINESE: Cjk = Cjk {
+    digits: ["零", "一", "二", "三", "四", "五", "六", "七", "八", "九"],
+    units: ["十", "百", "千"],
+    myriads: ["万", "亿", "万亿"],
+    chinese: true,
+};
+
+const TRADITIONAL_CHINESE: Cjk = Cjk {
+    digits: ["零", "一", "二", "三", "四", "五", "六", "七", "八", "九"],
+    units: ["十", "百", "千"],
+    myriads: ["萬", "億", "兆"],
+    chinese: true,
+};
+
+fn cjk(n: u64, chars: &Cjk) -> String {
+    if n == 0 {
+        return chars.digits[0].to_string();
+    }
+    let mut groups = Vec::new();
+    let mut rest = n;
+    while rest > 0 {
+        groups.push(rest % 10_000);
+        rest /= 10_000;
+    }
+
+    let mut text = String::new();
+    let mut skipped = false;
+    for (power, &group) in groups.iter().enumerate().rev() {
+        if group == 0 {
+            skipped = !text.is_empty();
+            continue;
+        }
+        // 一万零五, 一亿零五: a gap before the next group reads as 零
+        if chars.chinese && !text.is_empty() && (skipped || group < 1000) {
+            text.push_str(chars.digits[0]);
+        }
+        text.push_str(&cjk_below_myriad(group, chars, text.is_empty()));
+        if power > 0 {
+            text.push_str(chars.myriads[power - 1]);
+        }
+        skipped = false;
+    }
+    text
+}
+
+/// One group of four digits. `leading` is true for the first group of the
+/// number, where Chinese reads 10-19 as 十, 十一 rather than 一十.
+fn cjk_below_myriad(n: u64, chars: &Cjk, leading: bool) -> String {
+    let mut text = String::new();
+    let mut zero = false;
+    for place in (0..4).rev() {
+        let digit = (n / 10u64.pow(place)) % 10;
+        if digit == 0 {
+            zero = !text.is_empty();
+            continue;
+        }
+        if chars.chinese && zero {
+            text.push_str(chars.digits[0]);
+        }
+        zero = false;
+        let omit_one = digit == 1
+            && place > 0
+            && (!chars.chinese || (place == 1 && leading && text.is_empty()));
+        if !omit_one {
+            text.push_str(chars.digits[digit as usize]);
+        }
+        if place > 0 {
+            text.push_str(chars.units[place as usize - 1]);
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spell(value: f64, locale: &str) -> String {
+        spell_number(value, locale).unwrap()
+    }
+
+    #[test]
+    fn test_english() {
+        assert_eq!(spell(0.0, "en-US"), "zero");
+        assert_eq!(spell(15.0, "en-US"), "fifteen");
+        assert_eq!(spell(1_000_021.0, "en-US"), "one million twenty-one");
+        assert_eq!(spell(-0.05, "en"), "minus zero point zero five");
+        assert_eq!(spell(0.1 + 0.2, "en"), "zero point three");
+        assert_eq!(spell(999e12, "en"), "nine hundred ninety-nine trillion");
+        assert!(spell_number(1e15, "en").is_none());
+        assert!(spell_number(f64::NAN, "en").is_none());
+    }
+
+    #[test]
+    fn test_european_languages() {
+        assert_eq!(
+            spell(1234.5, "es"),
+            "mil doscientos treinta y cuatro coma cinco"
+        );
+        assert_eq!(spell(21_000.0, "es"), "veintiún mil");
+        assert_eq!(spell(100.0, "es"), "cien");
+        assert_eq!(spell(2_500_000_000.0, "es"), "dos mil quinientos millones");
+        assert_eq!(spell(1_000_001.0, "es"), "un millón uno");
+
+        assert_eq!(spell(71.0, "fr"), "soixante et onze");
+        assert_eq!(spell(80.0, "fr"), "quatre-vingts");
+        assert_eq!(spell(97.0, "fr"), "quatre-vingt-dix-sept");
+        assert_eq!(spell(200.0, "fr"), "deux cents");
+        assert_eq!(spell(80_000.0, "fr"), "quatre-vingt mille");
+        assert_eq!(spell(2_000_000.0, "fr"), "deux millions");
+
+        assert_eq!(spell(1234.0, "de"), "eintausendzweihundertvierunddreißig");
+        assert_eq!(spell(101.0, "de"), "einhunderteins");
+        assert_eq!(spell(1_000_000.0, "de"), "eine Million");
+        assert_eq!(
+            spell(2_300_000.0, "de"),
+            "zwei Millionen dreihunderttausend"
+        );
+        assert_eq!(spell(1.5, "de"), "eins Komma fünf");
+    }
+
+    #[test]
+    fn test_slavic_languages() {
+        assert_eq!(spell(2021.0, "ru"), "две тысячи двадцать один");
+        assert_eq!(spell(11_000.0, "ru"), "одиннадцать тысяч");
+        assert_eq!(spell(1_000_000.0, "ru"), "один миллион");
+        assert_eq!(spell(3.5, "ru"), "три запятая пять");
+        assert_eq!(spell(1001.0, "be"), "адна тысяча адзін");
+        assert_eq!(spell(5_000_000.0, "be"), "пяць мільёнаў");
+    }
+
+    #[test]
+    fn test_cjk_languages() {
+        assert_eq!(spell(1234.5, "ja"), "千二百三十四点五");
+        assert_eq!(spell(10_000.0, "ja"), "一万");
+        assert_eq!(spell(12.0, "zh-CN"), "十二");
+        assert_eq!(spell(112.0, "zh-CN"), "一百一十二");
+        assert_eq!(spell(1005.0, "zh-CN"), "一千零五");
+        assert_eq!(spell(100_005.0, "zh-CN"), "十万零五");
+        assert_eq!(spell(100_000_005.0, "zh-TW"), "一億零五");
+        assert_eq!(spell(-3.25, "zh-TW"), "負三點二五");
+    }
+
+    #[test]
+    fn test_spell_amount() {
+        assert_eq!(
+            spell_amount(1234.5, "EUR", "de").unwrap(),
+            "eintausendzweihundertvierunddreißig und 50/100 EUR"
+        );
+        assert_eq!(spell_amount(3.0, "JPY", "ja").unwrap(), "三 00/100 JPY");
+        assert_eq!(
+            spell_amount(-0.999, "USD", "en").unwrap(),
+            "minus one and 00/100 USD"
+        );
+    }
+}
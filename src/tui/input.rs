@@ -7,17 +7,20 @@ use ropey::Rope;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::evaluator::document::{line_references, LineEdit};
 use crate::models::AppState;
 use crate::security::{
     sanitize_terminal_string, validate_file_path, validate_input_size, MAX_EXPR_LENGTH,
 };
 use crate::utils;
 
-/// Helper function to delete a text selection
+/// Helper function to delete a text selection. Each line break in it
+/// deletes the line after it, as Backspace does.
 fn delete_selection(
     input: &mut Rope,
     cursor_pos: &mut usize,
     selection_start: Option<usize>,
+    state: &AppState,
 ) -> bool {
     if let Some(start) = selection_start {
         let (from, to) = if start < *cursor_pos {
@@ -25,75 +28,48 @@ fn delete_selection(
         } else {
             (*cursor_pos, start)
         };
+        let line_idx = input.char_to_line(from);
+        let joined = input.char_to_line(to) - line_idx;
         input.remove(from..to);
         *cursor_pos = from;
+        for _ in 0..joined {
+            renumber_references(input, cursor_pos, LineEdit::Deleted(line_idx + 1));
+            let _ = state.shift_lines_on_delete(line_idx + 1);
+        }
         true
     } else {
         false
     }
 }
 
-/// Helper function to clear selection
-fn clear_selection(selection_start: &mut Option<usize>) {
-    *selection_start = None;
-}
-
-/// Check if string contains an assignment operator (not comparison)
-pub fn contains_assignment_check(s: &str) -> bool {
-    // Look for '=' that is not part of ==, !=, <=, >=
-    let bytes = s.as_bytes();
-    for i in 0..bytes.len() {
-        if bytes[i] == b'=' {
-            // Check character before
-            let has_op_before = i > 0 && matches!(bytes[i - 1], b'!' | b'<' | b'>' | b'=');
-            // Check character after
-            let has_eq_after = i + 1 < bytes.len() && bytes[i + 1] == b'=';
-
-            if !has_op_before && !has_eq_after {
-                return true;
+/// Renumber the `line 3` references in the buffer to follow the lines they
+/// refer to across `edit`, keeping the cursor on the same character.
+fn renumber_references(input: &mut Rope, cursor_pos: &mut usize, edit: LineEdit) {
+    for line_idx in (0..input.len_lines()).rev() {
+        let line = input.line(line_idx).to_string();
+        let line_start = input.line_to_char(line_idx);
+        for (span, number) in line_references(&line).into_iter().rev() {
+            let renumbered = edit.renumber(number);
+            if renumbered == number {
+                continue;
             }
-        }
-    }
-    false
-}
-
-/// Re-evaluate current line if it has been edited and contains an assignment
-fn reevaluate_if_changed(
-    input: &ropey::Rope,
-    cursor_pos: usize,
-    state: &mut AppState,
-    registry: &crate::evaluator::AgentRegistry,
-) {
-    let line_idx = input.char_to_line(cursor_pos);
-    let current_line = utils::get_current_line(input, cursor_pos);
-    let trimmed = current_line.trim();
-
-    // Only re-evaluate if line contains an assignment (not comparison)
-    if trimmed.is_empty() || !contains_assignment_check(trimmed) {
-        return;
-    }
-
-    // Check if this line's content has changed from last evaluation
-    if let Ok(line_content) = state.line_content.read() {
-        if let Some(last_content) = line_content.get(&line_idx) {
-            // If content hasn't changed, no need to re-evaluate
-            if last_content == trimmed {
-                return;
+            let renumbered = renumbered.to_string();
+            let from = line_start + line[..span.start].chars().count();
+            let to = from + line[span].chars().count();
+            input.remove(from..to);
+            input.insert(from, &renumbered);
+            if *cursor_pos >= to {
+                *cursor_pos = *cursor_pos + renumbered.len() - (to - from);
+            } else if *cursor_pos > from {
+                *cursor_pos = (*cursor_pos).min(from + renumbered.len());
             }
         }
     }
+}
 
-    // Line was edited and contains assignment - re-evaluate
-    if validate_input_size(trimmed).is_ok() {
-        if let Ok(mut current_line) = state.current_line.write() {
-            *current_line = Some(line_idx);
-        }
-        // Re-evaluate without touching history to avoid double-counting
-        registry.evaluate_without_history(trimmed, state);
-        if let Ok(mut current_line) = state.current_line.write() {
-            *current_line = None;
-        }
-    }
+/// Helper function to clear selection
+fn clear_selection(selection_start: &mut Option<usize>) {
+    *selection_start = None;
 }
 
 /// Finds the current line index and column from cursor position
@@ -157,7 +133,7 @@ pub fn handle_normal_mode(
                 let trimmed = current_line.trim();
                 // Validate before evaluation
                 if validate_input_size(trimmed).is_ok() {
-                    if let Some((result, _)) = registry.evaluate(trimmed, state) {
+                    if let Ok((result, _)) = registry.evaluate(trimmed, state) {
                         utils::copy_to_clipboard(&result);
                     }
                 }
@@ -196,7 +172,7 @@ pub fn handle_normal_mode(
         match key.code {
             KeyCode::Char(c) => {
                 // Delete selection if exists
-                if delete_selection(input, cursor_pos, *selection_start) {
+                if delete_selection(input, cursor_pos, *selection_start, state) {
                     clear_selection(selection_start);
                     text_changed = true;
                 }
@@ -213,7 +189,7 @@ pub fn handle_normal_mode(
                 }
             }
             KeyCode::Backspace => {
-                if !delete_selection(input, cursor_pos, *selection_start) {
+                if !delete_selection(input, cursor_pos, *selection_start, state) {
                     if *cursor_pos > 0 {
                         // Check if we're deleting a newline
                         let char_before = input.char(*cursor_pos - 1);
@@ -223,6 +199,9 @@ pub fn handle_normal_mode(
                         } else {
                             0
                         };
+                        if deleting_newline {
+                            renumber_references(input, cursor_pos, LineEdit::Deleted(line_idx));
+                        }
 
                         input.remove(*cursor_pos - 1..*cursor_pos);
                         *cursor_pos = cursor_pos.saturating_sub(1);
@@ -239,7 +218,7 @@ pub fn handle_normal_mode(
                 }
             }
             KeyCode::Delete => {
-                if !delete_selection(input, cursor_pos, *selection_start) {
+                if !delete_selection(input, cursor_pos, *selection_start, state) {
                     if *cursor_pos < input.len_chars() {
                         // Check if we're deleting a newline
                         let char_at_cursor = input.char(*cursor_pos);
@@ -249,6 +228,9 @@ pub fn handle_normal_mode(
                         } else {
                             0
                         };
+                        if deleting_newline {
+                            renumber_references(input, cursor_pos, LineEdit::Deleted(line_idx));
+                        }
 
                         input.remove(*cursor_pos..*cursor_pos + 1);
                         text_changed = true;
@@ -264,37 +246,20 @@ pub fn handle_normal_mode(
                 }
             }
             KeyCode::Left => {
-                // Re-evaluate if moving to a different line
-                let old_line = input.char_to_line(*cursor_pos);
-                let old_cursor = *cursor_pos;
                 *cursor_pos = cursor_pos.saturating_sub(1);
-                let new_line = input.char_to_line(*cursor_pos);
-                if old_line != new_line {
-                    reevaluate_if_changed(input, old_cursor, state, registry);
-                }
                 clear_selection(selection_start);
             }
             KeyCode::Right => {
                 if *cursor_pos < input.len_chars() {
-                    let old_line = input.char_to_line(*cursor_pos);
-                    let old_cursor = *cursor_pos;
                     *cursor_pos += 1;
-                    let new_line = input.char_to_line(*cursor_pos);
-                    if old_line != new_line {
-                        reevaluate_if_changed(input, old_cursor, state, registry);
-                    }
                 }
                 clear_selection(selection_start);
             }
             KeyCode::Up => {
-                // Re-evaluate current line if it was edited
-                reevaluate_if_changed(input, *cursor_pos, state, registry);
                 *cursor_pos = move_cursor_up(input, *cursor_pos);
                 clear_selection(selection_start);
             }
             KeyCode::Down => {
-                // Re-evaluate current line if it was edited
-                reevaluate_if_changed(input, *cursor_pos, state, registry);
                 *cursor_pos = move_cursor_down(input, *cursor_pos);
                 clear_selection(selection_start);
             }
@@ -309,33 +274,22 @@ pub fn handle_normal_mode(
             KeyCode::Enter => {
                 clear_selection(selection_start);
                 let current_line = utils::get_current_line(input, *cursor_pos);
-                let trimmed = current_line.trim();
-                if !trimmed.is_empty() {
-                    // Validate current line before evaluation
-                    if let Err(e) = validate_input_size(trimmed) {
-                        let _ = state.set_status(
-                            crate::fl!("line-validation-error", "error" => &e.to_string()),
-                        );
-                    } else {
-                        // Set current line index for variable tracking
-                        let line_idx = input.char_to_line(*cursor_pos);
-                        if let Ok(mut current_line) = state.current_line.write() {
-                            *current_line = Some(line_idx);
-                        }
-                        registry.evaluate(trimmed, state);
-                        // Clear current line after evaluation
-                        if let Ok(mut current_line) = state.current_line.write() {
-                            *current_line = None;
-                        }
-                    }
+                // The document evaluates the line, and records it in the
+                // session history; only say here if it is too long
+                if let Err(e) = validate_input_size(current_line.trim()) {
+                    let _ = state
+                        .set_status(crate::fl!("line-validation-error", "error" => &e.to_string()));
                 }
-                // Insert newline - shift line tracking
-                let line_idx = input.char_to_line(*cursor_pos);
+                // Insert newline - shift line tracking. At the start of a
+                // line the new line goes above it and the whole line moves
+                let (line_idx, col) = find_cursor_line_col(input, *cursor_pos);
+                let inserted = if col == 0 { line_idx } else { line_idx + 1 };
+                renumber_references(input, cursor_pos, LineEdit::Inserted(inserted));
                 input.insert(*cursor_pos, "\n");
                 *cursor_pos += 1;
                 text_changed = true;
                 // Shift all lines after the insertion point
-                let _ = state.shift_lines_on_insert(line_idx + 1);
+                let _ = state.shift_lines_on_insert(inserted);
             }
             _ => {
                 clear_selection(selection_start);
@@ -363,7 +317,7 @@ fn format_buffer_as_markdown_list(
         let result = registry
             .evaluate_for_display(trimmed, state)
             .map(|(r, _)| r)
-            .unwrap_or_else(|| crate::fl!("error-evaluating-expression"));
+            .unwrap_or_else(|e| crate::fl!("error-evaluating-line", "details" => e.message()));
         rows.push((trimmed.to_string(), result));
     }
 
@@ -423,3 +377,60 @@ pub(crate) fn save_file(state: &mut AppState, input: &Rope, new_filename: Option
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    /// Press `code` in `text` with the cursor at `cursor_pos` and the
+    /// selection, if any, from `selection_start`.
+    fn press(
+        code: KeyCode,
+        text: &str,
+        mut cursor_pos: usize,
+        mut selection_start: Option<usize>,
+    ) -> String {
+        let config = Config::default();
+        let registry = crate::evaluator::AgentRegistry::new(&config).unwrap();
+        let mut state = AppState::builder(&config).build();
+        let mut input = Rope::from_str(text);
+        handle_normal_mode(
+            KeyEvent::new(code, KeyModifiers::NONE),
+            &mut input,
+            &mut cursor_pos,
+            &mut state,
+            &registry,
+            &mut selection_start,
+        );
+        input.to_string()
+    }
+
+    #[test]
+    fn test_enter_renumbers_references() {
+        // At the start of a line the whole line moves down
+        assert_eq!(
+            press(KeyCode::Enter, "10\n20\nline 2 + 1", 3, None),
+            "10\n\n20\nline 3 + 1"
+        );
+        // Within a line the rest of it moves down
+        assert_eq!(
+            press(KeyCode::Enter, "10\n20\nline 2 + 1", 4, None),
+            "10\n2\n0\nline 2 + 1"
+        );
+    }
+
+    #[test]
+    fn test_deleting_a_selection_renumbers_references() {
+        // Selecting from the end of line 1 to the end of line 3 joins three
+        // lines into one
+        assert_eq!(
+            press(KeyCode::Backspace, "1\n2\n3\n4\nline 4 * 2", 5, Some(1)),
+            "1\n4\nline 2 * 2"
+        );
+        assert_eq!(
+            press(KeyCode::Char('x'), "1\n2\n3\nline 3", 1, Some(3)),
+            "1x\n3\nline 2"
+        );
+    }
+}
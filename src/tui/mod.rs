@@ -11,6 +11,7 @@ use ropey::Rope;
 use std::fs;
 use std::io;
 
+use crate::evaluator::Document;
 use crate::{i18n, models::AppState};
 
 mod input;
@@ -59,8 +60,9 @@ pub fn run(
     }
     let mut scroll_offset = 0;
     let mut selection_start: Option<usize> = None;
-    let mut last_edit_time: Option<std::time::Instant> = None;
-    let mut pending_eval_line: Option<usize> = None;
+    let mut document = Document::default();
+    let mut results = Vec::new();
+    let mut document_changed = true;
     let mut help_visible = false;
     let mut locale_picker_visible = false;
     let mut format_picker_visible = false;
@@ -78,28 +80,13 @@ pub fn run(
 
     // Main event loop
     loop {
-        // Check if we should re-evaluate a pending line (after 150ms of no typing)
-        if let (Some(edit_time), Some(line_idx)) = (last_edit_time, pending_eval_line) {
-            if edit_time.elapsed() >= std::time::Duration::from_millis(150) {
-                // Re-evaluate the pending line
-                if line_idx < input.len_lines() {
-                    let line_text = input.line(line_idx).to_string();
-                    let trimmed = line_text.trim();
-                    if !trimmed.is_empty() && input::contains_assignment_check(trimmed) {
-                        if let Ok(mut current_line) = state.current_line.write() {
-                            *current_line = Some(line_idx);
-                        }
-                        // Re-evaluate without mutating history
-                        registry.evaluate_without_history(trimmed, state);
-                        if let Ok(mut current_line) = state.current_line.write() {
-                            *current_line = None;
-                        }
-                    }
-                }
-                // Clear pending evaluation
-                last_edit_time = None;
-                pending_eval_line = None;
-            }
+        // Re-evaluate the lines affected by edits or settings changes since
+        // the last pass
+        if document_changed {
+            let lines: Vec<String> = input.lines().map(|line| line.to_string()).collect();
+            let lines: Vec<&str> = lines.iter().map(|line| line.trim()).collect();
+            results = document.evaluate(registry, &lines, state);
+            document_changed = false;
         }
 
         // Render UI
@@ -113,7 +100,8 @@ pub fn run(
                     cursor_pos,
                     state,
                     config,
-                    registry,
+                    results: &results,
+                    notes: document.notes(),
                     show_status: status_timer > 0,
                     scroll_offset: &mut scroll_offset,
                     help_visible,
@@ -209,6 +197,7 @@ pub fn run(
                                     ));
                                     status_timer = STATUS_TIMER_DURATION;
                                     format_picker_visible = false;
+                                    document_changed = true;
                                 }
                                 KeyCode::Esc => {
                                     format_picker_visible = false;
@@ -248,20 +237,18 @@ pub fn run(
                 if locale_picker_visible {
                     match key.code {
                         KeyCode::Up => {
-                            if locale_selection > 0 {
-                                locale_selection -= 1;
-                            }
+                            locale_selection = locale_selection.saturating_sub(1);
                             if locale_selection < locale_scroll_offset {
                                 locale_scroll_offset = locale_selection;
                             }
                         }
-                        KeyCode::Down => {
-                            if locale_selection + 1 < i18n::AVAILABLE_LOCALES.len() {
-                                locale_selection += 1;
-                                let max_offset = locale_selection.saturating_sub(7);
-                                if locale_selection >= locale_scroll_offset + 8 {
-                                    locale_scroll_offset = max_offset;
-                                }
+                        KeyCode::Down
+                            if locale_selection + 1 < i18n::AVAILABLE_LOCALES.len() =>
+                        {
+                            locale_selection += 1;
+                            let max_offset = locale_selection.saturating_sub(7);
+                            if locale_selection >= locale_scroll_offset + 8 {
+                                locale_scroll_offset = max_offset;
                             }
                         }
                         KeyCode::Enter => {
@@ -274,6 +261,7 @@ pub fn run(
                                         "name" => *name
                                     ));
                                     status_timer = STATUS_TIMER_DURATION;
+                                    document_changed = true;
                                 }
                             }
                             locale_picker_visible = false;
@@ -414,22 +402,14 @@ pub fn run(
                     continue;
                 }
 
-                {
-                    let changed = input::handle_normal_mode(
-                        key,
-                        &mut input,
-                        &mut cursor_pos,
-                        state,
-                        registry,
-                        &mut selection_start,
-                    );
-
-                    // If text was edited, mark for re-evaluation
-                    if changed {
-                        last_edit_time = Some(std::time::Instant::now());
-                        pending_eval_line = Some(input.char_to_line(cursor_pos));
-                    }
-                }
+                document_changed |= input::handle_normal_mode(
+                    key,
+                    &mut input,
+                    &mut cursor_pos,
+                    state,
+                    registry,
+                    &mut selection_start,
+                );
             }
         }
     }
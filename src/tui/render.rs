@@ -6,6 +6,7 @@ use ratatui::{
     Frame,
 };
 use ropey::Rope;
+use std::collections::BTreeMap;
 
 use super::syntax;
 use crate::fl;
@@ -17,7 +18,10 @@ pub struct RenderContext<'a> {
     pub cursor_pos: usize,
     pub state: &'a AppState,
     pub config: &'a crate::config::Config,
-    pub registry: &'a crate::evaluator::AgentRegistry,
+    /// Result of each line of the input, `None` for blank lines.
+    pub results: &'a [Option<Result<String, String>>],
+    /// Notes on lines that evaluated, by line index.
+    pub notes: &'a BTreeMap<usize, String>,
     pub show_status: bool,
     pub scroll_offset: &'a mut usize,
     pub help_visible: bool,
@@ -108,16 +112,7 @@ pub fn render_ui(f: &mut Frame, mut ctx: RenderContext) {
             ctx.locale_visible,
         );
     } else if ctx.format_picker_visible {
-        render_format_overlay(
-            f,
-            size,
-            ctx.format_selection_time,
-            ctx.format_selection_date,
-            ctx.format_time_offset,
-            ctx.format_date_offset,
-            ctx.format_focus_time,
-            ctx.state,
-        );
+        render_format_overlay(f, size, &ctx);
     } else if ctx.help_visible {
         render_help_overlay(f, size);
     }
@@ -334,16 +329,12 @@ fn render_locale_overlay(
     f.render_widget(footer, regions[2]);
 }
 
-fn render_format_overlay(
-    f: &mut Frame,
-    size: Rect,
-    time_idx: usize,
-    date_idx: usize,
-    time_offset: usize,
-    date_offset: usize,
-    focus_time: bool,
-    _state: &AppState,
-) {
+fn render_format_overlay(f: &mut Frame, size: Rect, ctx: &RenderContext) {
+    let time_idx = ctx.format_selection_time;
+    let date_idx = ctx.format_selection_date;
+    let time_offset = ctx.format_time_offset;
+    let date_offset = ctx.format_date_offset;
+    let focus_time = ctx.format_focus_time;
     let options_time = ["iso", "long", "short", "time", "12h"];
     let options_date = ["iso", "long", "short"];
 
@@ -534,63 +525,36 @@ fn render_input_panel(f: &mut Frame, rect: Rect, ctx: &RenderContext) {
 fn render_results_panel(f: &mut Frame, rect: Rect, ctx: &RenderContext) {
     let mut right_text = Text::default();
 
-    for line in ctx
-        .input
-        .lines()
+    let visible = ctx
+        .results
+        .iter()
+        .enumerate()
         .skip(*ctx.scroll_offset)
-        .take(rect.height as usize)
-    {
-        let line_str = line.to_string();
-        let line_trim = line_str.trim();
-
-        if line_trim.is_empty() {
-            right_text.lines.push(Line::default());
-            continue;
-        }
-
-        // Create cache key that includes variables state
-        let vars = ctx
-            .state
-            .variables
-            .read()
-            .expect("Failed to acquire read lock on variables");
-        // Use sorted BTreeMap for deterministic cache key generation
-        let vars_sorted: std::collections::BTreeMap<_, _> =
-            vars.iter().map(|(k, v)| (k.as_str(), v)).collect();
-        let vars_hash = format!("{:?}", vars_sorted);
-        drop(vars); // Release read lock
-
-        let cache_key = format!(
-            "{}::{}::{}",
-            line_trim,
-            vars_hash,
-            ctx.state.cache.generation()
-        );
-
-        // Check display cache
-        let result = if let Some(cached) = ctx.state.cache.get_display(&cache_key) {
-            cached
-        } else {
-            // Evaluate and cache
-            let eval_result = ctx
-                .registry
-                .evaluate_for_display(line_trim, ctx.state)
-                .map(|(r, _)| r);
-            if let Some(ref res) = eval_result {
-                ctx.state.cache.set_display(cache_key, Some(res.clone()));
-            } else {
-                ctx.state.cache.set_display(cache_key, None);
+        .take(rect.height as usize);
+    for (index, result) in visible {
+        match result {
+            None => right_text.lines.push(Line::default()),
+            Some(Ok(result)) => {
+                let mut spans = Vec::new();
+                // A note on the line goes before its result, as quietly as errors
+                if let Some(note) = ctx.notes.get(&index) {
+                    spans.push(Span::styled(
+                        note.as_str(),
+                        Style::default().fg(Color::Yellow).dim(),
+                    ));
+                    spans.push(Span::raw("  "));
+                }
+                spans.push(Span::styled(
+                    result.as_str(),
+                    Style::default().fg(Color::Green).bold(),
+                ));
+                right_text.lines.push(Line::from(spans));
             }
-            eval_result
-        };
-
-        if let Some(result) = result {
-            right_text.lines.push(Line::from(Span::styled(
-                result,
-                Style::default().fg(Color::Green).bold(),
-            )));
-        } else {
-            right_text.lines.push(Line::default());
+            // Say why the line failed, without competing with real results
+            Some(Err(message)) => right_text.lines.push(Line::from(Span::styled(
+                message.as_str(),
+                Style::default().fg(Color::Red).dim(),
+            ))),
         }
     }
 
@@ -33,6 +33,11 @@ pub fn highlight_word_owned(
         || config.speed_units.contains_key(&lower)
     {
         Span::styled(word.to_string(), Style::default().fg(Color::Yellow).bold())
+    } else if config.constants.contains_key(clean_word) {
+        Span::styled(
+            word.to_string(),
+            Style::default().fg(Color::LightBlue).bold(),
+        )
     } else if config.currencies.contains_key(&clean_word.to_uppercase())
         || is_currency_word(&lower)
     {
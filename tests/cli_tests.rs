@@ -148,8 +148,8 @@ fn test_byn_conversion() {
         "100 usd to byn",
     ]);
     assert!(stdout.contains("byn"));
-    // 100 * 3.41 = 341 BYN (no decimals for round numbers)
-    let re = Regex::new(r"341 byn").unwrap();
+    // 100 * 3.41 = 341 BYN (currency amounts keep two decimals)
+    let re = Regex::new(r"341\.00 byn").unwrap();
     assert!(re.is_match(stdout.trim()));
 }
 
@@ -165,8 +165,8 @@ fn test_dollar_symbol_to_byn() {
         "100$ to byn",
     ]);
     assert!(stdout.contains("byn"));
-    // 100 * 3.41 = 341 BYN (no decimals for round numbers)
-    let re = Regex::new(r"341 byn").unwrap();
+    // 100 * 3.41 = 341 BYN (currency amounts keep two decimals)
+    let re = Regex::new(r"341\.00 byn").unwrap();
     assert!(re.is_match(stdout.trim()));
 }
 
@@ -295,8 +295,8 @@ fn test_variable_assignment_with_conversion() {
         "JPY:150",
         "flight = 850 USD to JPY",
     ]);
-    // 850 * 150 = 127500 JPY (displayed as 127.5k JPY)
-    let re = Regex::new(r"127\.5k JPY").unwrap();
+    // 850 * 150 = 127500 JPY (currency amounts are shown in full)
+    let re = Regex::new(r"127500\.00 JPY").unwrap();
     assert!(re.is_match(stdout.trim()));
 }
 
@@ -311,8 +311,8 @@ fn test_arithmetic_with_conversion_in_assignment() {
         "JPY:150",
         "hotel = 150 USD * 5 to JPY",
     ]);
-    // (150 * 5) * 150 = 112500 JPY (displayed as 112.5k JPY)
-    let re = Regex::new(r"112\.5k JPY").unwrap();
+    // (150 * 5) * 150 = 112500 JPY (currency amounts are shown in full)
+    let re = Regex::new(r"112500\.00 JPY").unwrap();
     assert!(re.is_match(stdout.trim()));
 }
 
@@ -419,8 +419,8 @@ fn test_complex_currency_conversion() {
         "10 + 200000 usd in jpy",
     ]);
     assert!(stdout.contains("jpy"));
-    // (10 + 200000) * 154 = 30,801,540 = 30.8M jpy
-    let re = Regex::new(r"30\.8M jpy").unwrap();
+    // (10 + 200000) * 154 = 30,801,540 jpy
+    let re = Regex::new(r"30801540\.00 jpy").unwrap();
     assert!(re.is_match(stdout.trim()));
 }
 
@@ -445,8 +445,8 @@ fn test_large_number_with_unit_conversion() {
         "1M usd in eur",
     ]);
     assert!(stdout.contains("eur"));
-    // 1000000 * 0.85 = 850000 = 850.0k eur
-    let re = Regex::new(r"850\.0k eur").unwrap();
+    // 1000000 * 0.85 = 850000 eur
+    let re = Regex::new(r"850000\.00 eur").unwrap();
     assert!(re.is_match(stdout.trim()));
 }
 
@@ -462,8 +462,8 @@ fn test_mixed_operations_with_conversion() {
         "100 usd * 2 in jpy",
     ]);
     assert!(stdout.contains("jpy"));
-    // 200 * 154 = 30800 = 30.8k jpy
-    let re = Regex::new(r"30\.8k jpy").unwrap();
+    // 200 * 154 = 30800 jpy
+    let re = Regex::new(r"30800\.00 jpy").unwrap();
     assert!(re.is_match(stdout.trim()));
 }
 
@@ -492,22 +492,22 @@ fn test_history_commands_dont_add_to_history() {
     let mut state = numby::models::AppState::builder(&config).build();
     *state.history.write().unwrap() = vec![
         numby::models::HistoryEntry {
-            value: 10.0,
+            value: 10.0.into(),
             unit: None,
         },
         numby::models::HistoryEntry {
-            value: 20.0,
+            value: 20.0.into(),
             unit: None,
         },
         numby::models::HistoryEntry {
-            value: 30.0,
+            value: 30.0.into(),
             unit: None,
         },
     ];
 
     // Test sum command
     let result = registry.evaluate("sum", &mut state);
-    assert_eq!(result, Some(("60".to_string(), true)));
+    assert_eq!(result, Ok(("60.00".to_string(), true)));
     // History should still have 3 items (not 4)
     assert_eq!(state.history.read().unwrap().len(), 3);
 
@@ -515,7 +515,7 @@ fn test_history_commands_dont_add_to_history() {
 
     // Test average command
     let result = registry.evaluate("average", &mut state);
-    assert_eq!(result, Some(("20".to_string(), true)));
+    assert_eq!(result, Ok(("20.00".to_string(), true)));
     // History should still have 3 items
     assert_eq!(state.history.read().unwrap().len(), 3);
 
@@ -523,7 +523,7 @@ fn test_history_commands_dont_add_to_history() {
 
     // Test prev command
     let result = registry.evaluate("prev", &mut state);
-    assert_eq!(result, Some(("30".to_string(), true)));
+    assert_eq!(result, Ok(("30.00".to_string(), true)));
     // History should still have 3 items
     assert_eq!(state.history.read().unwrap().len(), 3);
 
@@ -531,11 +531,11 @@ fn test_history_commands_dont_add_to_history() {
 
     // Test that regular expressions still add to history
     let result = registry.evaluate("40 + 5", &mut state);
-    assert_eq!(result, Some(("45.00".to_string(), true)));
+    assert_eq!(result, Ok(("45.00".to_string(), true)));
     // History should now have 4 items
     assert_eq!(state.history.read().unwrap().len(), 4);
     let hist = state.history.read().unwrap();
-    let values: Vec<f64> = hist.iter().map(|h| h.value).collect();
+    let values: Vec<f64> = hist.iter().map(|h| h.value.to_f64()).collect();
     assert_eq!(values, vec![10.0, 20.0, 30.0, 45.0]);
 }
 
@@ -733,6 +733,139 @@ fn test_percentage_parens_complex_nesting() {
     assert!(stdout.contains("100"), "got {}", stdout);
 }
 
+#[test]
+fn test_percentage_phrases() {
+    let (stdout, _) = run_command(&["run", "--", "50 is what % of 200"]);
+    assert!(stdout.contains("25.00%"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "% change from 80 to 100"]);
+    assert!(stdout.contains("25.00%"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "20% off $80"]);
+    assert!(stdout.contains("64.00 USD"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "5% of what is 20"]);
+    assert!(stdout.contains("400"), "got {}", stdout);
+}
+
+#[test]
+fn test_notation_targets() {
+    let (stdout, _) = run_command(&["run", "--", "1234.56789 to 2 dp"]);
+    assert!(stdout.contains("1234.57"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "299792458 to sci"]);
+    assert!(stdout.contains("3e8"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "0.000047 m to eng"]);
+    assert!(stdout.contains("47e-6 m"), "got {}", stdout);
+}
+
+#[test]
+fn test_roman_numerals() {
+    let (stdout, _) = run_command(&["run", "--", "2025 to roman"]);
+    assert!(stdout.contains("MMXXV"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "MCMXCIV + 6 to roman"]);
+    assert!(stdout.contains("MM"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "IIII + 1"]);
+    assert!(stdout.contains("Invalid Roman numeral"), "got {}", stdout);
+}
+
+#[test]
+fn test_solve() {
+    let (stdout, _) = run_command(&["run", "--", "solve 2x + 3 = 11 for x"]);
+    assert!(stdout.contains("4.00"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "solve x^2 - x = 6 for x"]);
+    assert!(stdout.contains("[-2.00, 3.00]"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "solve pmt(r, 360, 300000) = 1800 for r"]);
+    assert!(stdout.contains("5.01e-3"), "got {}", stdout);
+
+    let (stdout, stderr) = run_command(&["run", "--", "solve x^2 = -1 for x"]);
+    let output = format!("{}{}", stdout, stderr);
+    assert!(output.contains("solves this equation"), "got {}", output);
+}
+
+#[test]
+fn test_calculus_functions() {
+    let (stdout, _) = run_command(&["run", "--", "integrate(x^2, x, 0, 3)"]);
+    assert!(stdout.contains("9.00"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "derive(sin(x), x, pi/4)"]);
+    assert!(stdout.contains("0.71"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "prod(k, k, 1, 5)"]);
+    assert!(stdout.contains("120"), "got {}", stdout);
+
+    let (stdout, stderr) = run_command(&["run", "--", "integrate(x, 1, 0, 1)"]);
+    let output = format!("{}{}", stdout, stderr);
+    assert!(output.contains("needs a variable name"), "got {}", output);
+}
+
+#[test]
+fn test_section_totals() {
+    let document = "# Groceries\n3.5\n2.5\nsubtotal\n## Rent\n50\nsubtotal\ngrand total";
+    let (stdout, _) = run_command(&["run", "--", "--format", "plain", document]);
+    let results: Vec<&str> = stdout.lines().collect();
+    assert_eq!(results.len(), 6, "got {}", stdout);
+    assert!(results[2].contains("6.00"), "got {}", stdout);
+    assert!(results[4].contains("50.00"), "got {}", stdout);
+    assert!(results[5].contains("56.00"), "got {}", stdout);
+}
+
+#[test]
+fn test_words_target() {
+    let (stdout, _) = run_command(&["run", "--", "1234.5 to words"]);
+    assert!(
+        stdout.contains("one thousand two hundred thirty-four point five"),
+        "got {}",
+        stdout
+    );
+
+    let (stdout, _) = run_command(&["run", "--", "$1234.50 to words"]);
+    assert!(
+        stdout.contains("one thousand two hundred thirty-four and 50/100 USD"),
+        "got {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_constants() {
+    let (stdout, _) = run_command(&["run", "--", "c to km/h"]);
+    assert!(stdout.contains("1.1B km/h"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "1 lightyear to km"]);
+    assert!(stdout.contains("9.5T km"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "G to sci"]);
+    assert!(stdout.contains("6.67e-11"), "got {}", stdout);
+}
+
+#[test]
+fn test_uncertainty() {
+    let (stdout, _) = run_command(&["run", "--", "(5.0 ± 0.1 m) * (2.0 ± 0.05 m)"]);
+    assert!(stdout.contains("10.00 ± 0.32 m²"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "(5 +/- 0.1 m) to cm"]);
+    assert!(stdout.contains("± 10.00 cm"), "got {}", stdout);
+}
+
+#[test]
+fn test_temperature_differences() {
+    let (stdout, _) = run_command(&["run", "--", "10 °C difference in °F"]);
+    assert!(stdout.contains("18.00 °F difference"), "got {}", stdout);
+
+    let (stdout, _) = run_command(&["run", "--", "20 C - 50 F"]);
+    assert!(stdout.contains("10.00 C difference"), "got {}", stdout);
+
+    let (stdout, stderr) = run_command(&["run", "--", "20 C + 5 C"]);
+    let output = format!("{}{}", stdout, stderr);
+    assert!(output.contains("Cannot add two temperature readings"));
+}
+
 #[test]
 fn test_percentage_edge_cases() {
     // 0% of 100 = 0
@@ -841,7 +974,7 @@ fn test_percentage_small_numbers() {
 
     // 10% of 0.01 = 0.001
     let (stdout, _) = run_command(&["run", "--", "10% of 0.01"]);
-    assert!(stdout.contains("0.001") || stdout.contains("0.00"), "got {}", stdout);
+    assert!(stdout.contains("1e-3"), "got {}", stdout);
 
     // (25% of 0.8) = 0.2
     let (stdout, _) = run_command(&["run", "--", "(25% of 0.8)"]);
@@ -1182,11 +1315,11 @@ fn test_tui_display_doesnt_modify_history() {
     let mut state = numby::models::AppState::builder(&config).build();
     *state.history.write().unwrap() = vec![
         numby::models::HistoryEntry {
-            value: 40.0,
+            value: 40.0.into(),
             unit: None,
         },
         numby::models::HistoryEntry {
-            value: 50.0,
+            value: 50.0.into(),
             unit: None,
         },
     ];
@@ -1200,7 +1333,7 @@ fn test_tui_display_doesnt_modify_history() {
         .read()
         .unwrap()
         .iter()
-        .map(|h| h.value)
+        .map(|h| h.value.to_f64())
         .collect();
     assert_eq!(values, vec![40.0, 50.0]);
 
@@ -1213,13 +1346,13 @@ fn test_tui_display_doesnt_modify_history() {
         .read()
         .unwrap()
         .iter()
-        .map(|h| h.value)
+        .map(|h| h.value.to_f64())
         .collect();
     assert_eq!(values, vec![40.0, 50.0]);
 
     // Now simulate actual execution of regular expression (should add to history)
     let result = registry.evaluate("40 + 50", &mut state);
-    assert_eq!(result, Some(("90.00".to_string(), true)));
+    assert_eq!(result, Ok(("90.00".to_string(), true)));
     // History should now have 3 items
     assert_eq!(state.history.read().unwrap().len(), 3);
     let values: Vec<f64> = state
@@ -1227,7 +1360,7 @@ fn test_tui_display_doesnt_modify_history() {
         .read()
         .unwrap()
         .iter()
-        .map(|h| h.value)
+        .map(|h| h.value.to_f64())
         .collect();
     assert_eq!(values, vec![40.0, 50.0, 90.0]);
 
@@ -1235,7 +1368,7 @@ fn test_tui_display_doesnt_modify_history() {
 
     // Now simulate actual execution of sum (should not add sum result to history)
     let result2 = registry.evaluate("sum", &mut state);
-    assert_eq!(result2, Some(("180".to_string(), true)));
+    assert_eq!(result2, Ok(("180".to_string(), true)));
     // History should still have 3 items
     assert_eq!(state.history.read().unwrap().len(), 3);
     let values: Vec<f64> = state
@@ -1243,7 +1376,7 @@ fn test_tui_display_doesnt_modify_history() {
         .read()
         .unwrap()
         .iter()
-        .map(|h| h.value)
+        .map(|h| h.value.to_f64())
         .collect();
     assert_eq!(values, vec![40.0, 50.0, 90.0]);
 }
@@ -1662,7 +1795,7 @@ fn test_no_space_units() {
 
     // 1_000_000USD
     let (stdout, _) = run_command(&["run", "--", "1_000_000USD"]);
-    assert!(stdout.contains("1.0M USD") || stdout.contains("1M USD"));
+    assert!(stdout.contains("1000000.00 USD"));
 }
 
 // Inverse trigonometric functions
@@ -1825,6 +1958,124 @@ fn test_speed_conversions() {
     assert!(stdout.contains("96") || stdout.contains("97"));
 }
 
+// Compound quantities
+#[test]
+fn test_compound_quantities() {
+    let (stdout, _) = run_command(&["run", "--", "5 ft 3 in to cm"]);
+    assert!(stdout.contains("160") && stdout.contains("cm"));
+
+    let (stdout, _) = run_command(&["run", "--", "1.8 m to ft and in"]);
+    assert!(stdout.contains("5 ft 10.87 in"));
+
+    let (stdout, _) = run_command(&["run", "--", "5000 s to h min s"]);
+    assert!(stdout.contains("1 h 23 min 20 s"));
+}
+
+// Unit algebra
+#[test]
+fn test_unit_algebra_dimensions() {
+    let (stdout, _) = run_command(&["run", "--", "10 m * 5 m"]);
+    assert!(stdout.contains("50") && stdout.contains("m²"));
+
+    let (stdout, _) = run_command(&["run", "--", "100 km / 2 h"]);
+    assert!(stdout.contains("50") && stdout.contains("km/h"));
+
+    let (stdout, _) = run_command(&["run", "--", "100 km / 2 h to mph"]);
+    assert!(stdout.contains("31.07") && stdout.contains("mph"));
+
+    let (stdout, _) = run_command(&["run", "--", "3 $/kg * 10 kg"]);
+    assert!(stdout.contains("30") && stdout.contains("USD"));
+}
+
+// Mixed numbers and fraction conversion targets
+#[test]
+fn test_fractions() {
+    let (stdout, _) = run_command(&["run", "--", "2 3/4 cups * 2"]);
+    assert!(stdout.contains("5.50") && stdout.contains("cups"));
+
+    let (stdout, _) = run_command(&["run", "--", "0.375 to fraction"]);
+    assert!(stdout.contains("3/8"));
+}
+
+// Programmer mode: radix literals, bitwise operators and base targets
+#[test]
+fn test_programmer_mode() {
+    let (stdout, _) = run_command(&["run", "--", "0xFFFF"]);
+    assert!(stdout.contains("65535"));
+
+    let (stdout, _) = run_command(&["run", "--", "0o755 & 0o700 to octal"]);
+    assert!(stdout.contains("0o700"));
+
+    let (stdout, _) = run_command(&["run", "--", "1 << 4 | 0b11 in binary"]);
+    assert!(stdout.contains("0b10011"));
+}
+
+// Functions defined on one line are callable from later lines
+#[test]
+fn test_user_defined_functions() {
+    let config = numby::config::Config::default();
+    let registry =
+        numby::evaluator::AgentRegistry::new(&config).expect("Failed to initialize agent registry");
+    let mut state = numby::models::AppState::builder(&config).build();
+
+    let result = registry.evaluate("tax(amount) = amount * 0.08", &mut state);
+    assert_eq!(result, Ok(("tax(amount)".to_string(), false)));
+
+    let (result, _) = registry.evaluate("tax(250 USD)", &mut state).unwrap();
+    assert!(result.contains("20") && result.contains("USD"), "{}", result);
+
+    registry.evaluate("speed(d, t) = d / t", &mut state).unwrap();
+    let (result, _) = registry
+        .evaluate("speed(100 km, 2 h) to mph", &mut state)
+        .unwrap();
+    assert!(result.contains("31.07") && result.contains("mph"), "{}", result);
+}
+
+// Lists, ranges and statistics over them
+#[test]
+fn test_lists_and_statistics() {
+    let (stdout, _) = run_command(&["run", "--", "mean [1, 2, 3, 4]"]);
+    assert!(stdout.contains("2.50"));
+
+    let (stdout, _) = run_command(&["run", "--", "[5 km, 3 miles] to km"]);
+    assert!(stdout.contains("[5.00 km, 4.83 km]"));
+
+    let (stdout, _) = run_command(&["run", "--", "percentile(1..101, 90)"]);
+    assert!(stdout.contains("91"));
+}
+
+#[test]
+fn test_financial_functions_and_phrases() {
+    let (stdout, _) = run_command(&["run", "--", "irr(-1000, 300, 400, 500)"]);
+    assert!(stdout.contains("8.90%"));
+
+    let (stdout, _) = run_command(&["run", "--", "$5000 at 4% for 10 years compounded monthly"]);
+    assert!(stdout.contains("7454.16 USD"));
+
+    // Loans get an amortization schedule under the results table
+    let (stdout, _) = run_command(&[
+        "run",
+        "--",
+        "--format",
+        "table",
+        "mortgage $300k at 6.5% over 3 years",
+    ]);
+    assert!(stdout.contains("Principal") && stdout.contains("Balance"));
+    assert!(stdout.contains("110336.41"));
+}
+
+// Failed lines say why they failed
+#[test]
+fn test_error_messages_explain_failure() {
+    let (stdout, stderr) = run_command(&["run", "--", "5 fet"]);
+    let output = format!("{}{}", stdout, stderr);
+    assert!(output.contains("Unknown unit") && output.contains("fet"));
+
+    let (stdout, stderr) = run_command(&["run", "--", "5 kg to USD"]);
+    let output = format!("{}{}", stdout, stderr);
+    assert!(output.contains("Cannot convert") && output.contains("USD"));
+}
+
 // Sign function
 #[test]
 fn test_sign_function() {
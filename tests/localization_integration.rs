@@ -248,3 +248,38 @@ fn test_all_nine_locales() {
         );
     }
 }
+
+#[test]
+fn test_decimal_comma_locales() {
+    for locale in ["de", "fr", "ru"] {
+        let (stdout, _stderr, exit_code) = run_numby_with_locale(locale, "3,5 + 1,25");
+        assert_eq!(exit_code, 0, "Failed for locale: {}", locale);
+        assert!(stdout.contains("4,75"), "got {} for locale {}", stdout, locale);
+    }
+
+    let (stdout, _stderr, exit_code) = run_numby_with_locale("en-US", "3.5 + 1,250");
+    assert_eq!(exit_code, 0);
+    assert!(stdout.contains("1.3k"), "got {}", stdout);
+}
+
+#[test]
+fn test_words_in_locale_language() {
+    let cases = [
+        ("es", "mil doscientos treinta y cuatro"),
+        ("fr", "mille deux cent trente-quatre"),
+        ("de", "eintausendzweihundertvierunddreißig"),
+        ("ru", "одна тысяча двести тридцать четыре"),
+        ("ja", "千二百三十四"),
+        ("zh-CN", "一千二百三十四"),
+    ];
+    for (locale, expected) in cases {
+        let (stdout, _stderr, exit_code) = run_numby_with_locale(locale, "1234 to words");
+        assert_eq!(exit_code, 0, "Failed for locale: {}", locale);
+        assert!(
+            stdout.contains(expected),
+            "got {} for locale {}",
+            stdout,
+            locale
+        );
+    }
+}